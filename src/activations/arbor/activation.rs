@@ -1,11 +1,21 @@
+use super::diff::diff_nodes;
 use super::storage::{ArborConfig, ArborStorage};
-use super::types::{ArborEvent, Handle, NodeId, TreeId, TreeSkeleton};
+use super::types::{
+    ArborError, ArborEvent, ArborEventEnvelope, Handle, NodeId, SortOrder, TreeCondition, TreeId, TreeQuery, TreeSkeleton,
+};
 use crate::plexus::{HubContext, NoParent, PlexusStreamItem};
 use async_stream::stream;
 use futures::{Stream, StreamExt};
 use serde_json::Value;
 use std::marker::PhantomData;
 use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+/// How often the background indexer looks for external nodes to index
+const INDEX_INTERVAL: Duration = Duration::from_secs(5);
+
+/// External nodes the background indexer resolves per pass
+const INDEX_BATCH: usize = 100;
 
 /// Arbor activation - manages conversation trees
 ///
@@ -50,10 +60,24 @@ impl<P: HubContext> Arbor<P> {
     /// Inject parent context for resolving handles
     ///
     /// Called during hub construction (e.g., via `Arc::new_cyclic` for `DynamicHub`).
+    /// Also starts the background task that indexes external nodes for search
+    /// once their handles can be resolved.
     pub fn inject_parent(&self, parent: P) {
-        if self.hub.set(parent).is_err() {
+        if self.hub.set(parent.clone()).is_err() {
             tracing::warn!("Arbor parent context already set");
+            return;
         }
+        let storage = Arc::downgrade(&self.storage);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(INDEX_INTERVAL);
+            loop {
+                ticker.tick().await;
+                let Some(storage) = storage.upgrade() else { break };
+                if let Err(e) = index_pending_handles(&storage, &parent).await {
+                    tracing::warn!("Failed to index external nodes for search: {e}");
+                }
+            }
+        });
     }
 
     /// Check if parent context has been injected
@@ -148,6 +172,59 @@ impl<P: HubContext> Arbor<P> {
         }
     }
 
+    /// Query active trees by timestamps and metadata, one page at a time
+    #[plexus_macros::method(params(
+        conditions = "Conditions that must all hold: {path, op, value}. path is created_at, updated_at or a dotted metadata path; op is eq, ne, gt, gte, lt, lte, in, contains, exists or not_exists",
+        sort_by = "Field to sort by, same syntax as a condition path (default: created_at)",
+        order = "Sort order: asc or desc (default: desc)",
+        limit = "Page size, at least 1 (default: 100)",
+        offset = "Number of matches to skip (default: 0)"
+    ))]
+    async fn tree_query(
+        &self,
+        conditions: Vec<TreeCondition>,
+        sort_by: Option<String>,
+        order: Option<SortOrder>,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> impl Stream<Item = ArborEvent> + Send + 'static {
+        let storage = self.storage.clone();
+        stream! {
+            let query = TreeQuery {
+                conditions,
+                sort_by,
+                order: order.unwrap_or_default(),
+                limit: Some(limit.unwrap_or(100)),
+                offset: offset.unwrap_or(0),
+            };
+            match storage.tree_query(&query).await {
+                Ok(page) => yield ArborEvent::TreeQueryResults { tree_ids: page.tree_ids, next_offset: page.next_offset },
+                Err(e) => yield ArborEvent::Err { message: e.to_string() },
+            }
+        }
+    }
+
+    /// Subscribe to changes of a tree — reconnectable stream of sequenced events
+    ///
+    /// Replays the tree's logged changes after `after_seq` (exclusive) and then
+    /// tails live ones: `node_created`, `metadata_updated`, `tree_claimed` /
+    /// `tree_released` and the lifecycle transitions `tree_scheduled_deletion`,
+    /// `tree_reactivated` and `tree_archived`. On reconnect, pass the last
+    /// `seq` seen to resume without gaps or duplicates. If changes after
+    /// that `seq` were already pruned from the log, an `events_pruned` event
+    /// reports the gap first. The stream ends once the tree is archived.
+    #[plexus_macros::method(params(
+        tree_id = "UUID of the tree to watch",
+        after_seq = "Sequence number to resume from (0 or omit to start from beginning)"
+    ))]
+    async fn tree_subscribe(
+        &self,
+        tree_id: TreeId,
+        after_seq: Option<u64>,
+    ) -> impl Stream<Item = ArborEventEnvelope> + Send + 'static {
+        ArborStorage::subscribe_stream(self.storage.clone(), tree_id, after_seq)
+    }
+
     /// Update tree metadata
     #[plexus_macros::method(params(
         tree_id = "UUID of the tree to update",
@@ -289,9 +366,19 @@ impl<P: HubContext> Arbor<P> {
         metadata: Option<Value>,
     ) -> impl Stream<Item = ArborEvent> + Send + 'static {
         let storage = self.storage.clone();
+        let hub = self.hub.clone();
         stream! {
-            match storage.node_create_external(&tree_id, parent, handle, metadata).await {
-                Ok(node_id) => yield ArborEvent::NodeCreated { tree_id, node_id, parent },
+            match storage.node_create_external(&tree_id, parent, handle.clone(), metadata).await {
+                Ok(node_id) => {
+                    // Index the handle's content now if its activation can provide it;
+                    // otherwise the background indexer retries it later
+                    if let Some(hub) = hub.get() {
+                        if let Err(e) = index_handle(&storage, hub, &node_id, &handle).await {
+                            tracing::warn!("Failed to index external node {node_id}: {e}");
+                        }
+                    }
+                    yield ArborEvent::NodeCreated { tree_id, node_id, parent };
+                }
                 Err(e) => {
                     eprintln!("Error creating external node: {e}");
                     yield ArborEvent::TreeList { tree_ids: vec![] };
@@ -450,6 +537,143 @@ impl<P: HubContext> Arbor<P> {
         }
     }
 
+    /// Copy the path from the root down to a node into a new tree
+    ///
+    /// Use it to start a fresh session from a good conversation prefix.
+    #[plexus_macros::method(params(
+        tree_id = "UUID of the source tree",
+        node_id = "UUID of the last node to copy",
+        owner_id = "Owner of the new tree (default: 'system')",
+        metadata = "Optional metadata for the new tree"
+    ))]
+    async fn tree_copy(
+        &self,
+        tree_id: TreeId,
+        node_id: NodeId,
+        owner_id: Option<String>,
+        metadata: Option<Value>,
+    ) -> impl Stream<Item = ArborEvent> + Send + 'static {
+        let storage = self.storage.clone();
+        stream! {
+            let owner_id = owner_id.unwrap_or_else(|| "system".to_string());
+            match storage.tree_copy_path(&tree_id, &node_id, metadata, &owner_id).await {
+                Ok((new_tree_id, node_ids)) => yield ArborEvent::TreeCopied {
+                    source_tree_id: tree_id,
+                    tree_id: new_tree_id,
+                    node_ids,
+                },
+                Err(e) => yield ArborEvent::Err { message: e.to_string() },
+            }
+        }
+    }
+
+    /// Graft the path from a tree's root down to a node onto a node of another tree
+    #[plexus_macros::method(params(
+        tree_id = "UUID of the source tree",
+        node_id = "UUID of the last node to graft (the source root is not copied)",
+        target_tree_id = "UUID of the tree to graft onto",
+        target_parent = "UUID of the node to graft under"
+    ))]
+    async fn node_graft(
+        &self,
+        tree_id: TreeId,
+        node_id: NodeId,
+        target_tree_id: TreeId,
+        target_parent: NodeId,
+    ) -> impl Stream<Item = ArborEvent> + Send + 'static {
+        let storage = self.storage.clone();
+        stream! {
+            match storage.node_graft_path(&tree_id, &node_id, &target_tree_id, &target_parent).await {
+                Ok(node_ids) => yield ArborEvent::NodesCopied { tree_id: target_tree_id, node_ids },
+                Err(e) => yield ArborEvent::Err { message: e.to_string() },
+            }
+        }
+    }
+
+    /// Copy a range of nodes onto another branch
+    #[plexus_macros::method(params(
+        tree_id = "UUID of the source tree",
+        start_node = "UUID of the first node of the range",
+        end_node = "UUID of the last node of the range (start_node must be its ancestor)",
+        target_tree_id = "UUID of the tree to copy onto (may be the source tree)",
+        target_parent = "UUID of the node to copy under"
+    ))]
+    async fn node_cherry_pick(
+        &self,
+        tree_id: TreeId,
+        start_node: NodeId,
+        end_node: NodeId,
+        target_tree_id: TreeId,
+        target_parent: NodeId,
+    ) -> impl Stream<Item = ArborEvent> + Send + 'static {
+        let storage = self.storage.clone();
+        stream! {
+            match storage.node_cherry_pick(&tree_id, &start_node, &end_node, &target_tree_id, &target_parent).await {
+                Ok(node_ids) => yield ArborEvent::NodesCopied { tree_id: target_tree_id, node_ids },
+                Err(e) => yield ArborEvent::Err { message: e.to_string() },
+            }
+        }
+    }
+
+    /// Compare two branches: where their paths part ways and what follows
+    ///
+    /// Works within one tree (e.g. sessions forked from the same head) and
+    /// across trees, where nodes with the same content count as shared.
+    /// External nodes show resolved content when parent context is available.
+    #[plexus_macros::method(params(
+        tree_id = "UUID of the tree holding the left node",
+        node_id = "UUID of the left node",
+        other_node_id = "UUID of the right node",
+        other_tree_id = "UUID of the tree holding the right node (default: tree_id)",
+        width = "Width of the side-by-side rendering in characters (default: 120)"
+    ))]
+    async fn diff(
+        &self,
+        tree_id: TreeId,
+        node_id: NodeId,
+        other_node_id: NodeId,
+        other_tree_id: Option<TreeId>,
+        width: Option<usize>,
+    ) -> impl Stream<Item = ArborEvent> + Send + 'static {
+        let storage = self.storage.clone();
+        let hub = self.hub.clone();
+        stream! {
+            let other_tree_id = other_tree_id.unwrap_or(tree_id);
+            let left = (&tree_id, &node_id);
+            let right = (&other_tree_id, &other_node_id);
+            match diff_nodes(&storage, hub.get(), left, right, width.unwrap_or(120)).await {
+                Ok(diff) => yield ArborEvent::TreeDiff { diff },
+                Err(e) => yield ArborEvent::Err { message: e.to_string() },
+            }
+        }
+    }
+
+    /// Full-text search over node content, best match first
+    ///
+    /// Covers text nodes and, when parent context is available, external
+    /// nodes whose handles resolve to message content. External nodes are
+    /// indexed in the background, so a new one may take a few seconds to
+    /// show up.
+    #[plexus_macros::method(params(
+        query = "Search terms (FTS5 syntax: words, \"exact phrase\", OR, NOT, prefix*)",
+        tree_id = "Only search this tree (default: all active trees)",
+        limit = "Maximum number of hits (default: 20, at most 200)"
+    ))]
+    async fn search(
+        &self,
+        query: String,
+        tree_id: Option<TreeId>,
+        limit: Option<i64>,
+    ) -> impl Stream<Item = ArborEvent> + Send + 'static {
+        let storage = self.storage.clone();
+        stream! {
+            match storage.search(&query, tree_id.as_ref(), limit.unwrap_or(20)).await {
+                Ok(hits) => yield ArborEvent::SearchResults { query, hits },
+                Err(e) => yield ArborEvent::Err { message: e.to_string() },
+            }
+        }
+    }
+
     /// Render tree as text visualization
     ///
     /// If parent context is available, automatically resolves handles to show
@@ -517,6 +741,47 @@ async fn resolve_handle_to_string<P: HubContext>(parent: &P, handle: &Handle) ->
     }
 }
 
+/// Resolve a handle to the text to index for it: the resolved message
+/// `content`, or an empty string when the owning activation answered with
+/// nothing searchable. `None` when the activation couldn't be reached or
+/// answered with an error, so the node is retried later.
+pub(super) async fn resolve_handle_text<P: HubContext>(parent: &P, handle: &Handle) -> Option<String> {
+    let mut stream = parent.resolve_handle(handle).await.ok()?;
+    while let Some(item) = stream.next().await {
+        match item {
+            PlexusStreamItem::Data { content, .. } => {
+                return Some(content.get("content").and_then(Value::as_str).unwrap_or_default().to_string());
+            }
+            PlexusStreamItem::Error { .. } => return None,
+            PlexusStreamItem::Done { .. } => break,
+            _ => {}
+        }
+    }
+    Some(String::new())
+}
+
+/// Index the content of one external node's handle, or record that it
+/// couldn't be resolved so it is retried later.
+async fn index_handle<P: HubContext>(
+    storage: &ArborStorage,
+    parent: &P,
+    node_id: &NodeId,
+    handle: &Handle,
+) -> Result<(), ArborError> {
+    match resolve_handle_text(parent, handle).await {
+        Some(text) => storage.index_node_content(node_id, &text).await,
+        None => storage.index_node_failed(node_id).await,
+    }
+}
+
+/// Index the external nodes that are due, a batch at a time
+async fn index_pending_handles<P: HubContext>(storage: &ArborStorage, parent: &P) -> Result<(), ArborError> {
+    for (node_id, handle) in storage.search_pending_handles(INDEX_BATCH).await? {
+        index_handle(storage, parent, &node_id, &handle).await?;
+    }
+    Ok(())
+}
+
 /// Extract display content from resolved handle data
 fn extract_display_content(content: &Value) -> String {
     // Try common patterns for resolved content
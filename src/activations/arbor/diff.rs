@@ -0,0 +1,203 @@
+//! Branch comparison.
+//!
+//! Two nodes are compared by their root-to-node paths: the paths share nodes
+//! down to a common ancestor, and what follows on each side is the branch.
+//! Within one tree nodes are shared only when they are the same node, so
+//! sibling branches that happen to start with the same content still
+//! diverge at their parent. Across trees nodes are shared when they carry
+//! the same content, so a tree lines up with its copy made by an import.
+
+use super::activation::resolve_handle_text;
+use super::storage::ArborStorage;
+use super::types::{ArborError, DiffEntry, Node, NodeId, NodeType, TreeDiff, TreeId};
+use crate::plexus::HubContext;
+
+/// Narrowest column `render_side_by_side` will draw
+const MIN_COLUMN: usize = 10;
+
+/// Compare the path to `left` with the path to `right`, resolving external
+/// nodes through `hub` when given.
+pub(super) async fn diff_nodes<P: HubContext>(
+    storage: &ArborStorage,
+    hub: Option<&P>,
+    left: (&TreeId, &NodeId),
+    right: (&TreeId, &NodeId),
+    width: usize,
+) -> Result<TreeDiff, ArborError> {
+    let left_path = storage.context_get_path(left.0, left.1).await?;
+    let right_path = storage.context_get_path(right.0, right.1).await?;
+    let shared = shared_prefix_len(&left_path, &right_path, left.0 == right.0);
+
+    let left_entries = entries(&left_path[shared..], hub).await;
+    let right_entries = entries(&right_path[shared..], hub).await;
+    let render = render_side_by_side(&left_entries, &right_entries, width);
+
+    Ok(TreeDiff {
+        left_ancestor: shared.checked_sub(1).map(|i| left_path[i].id),
+        right_ancestor: shared.checked_sub(1).map(|i| right_path[i].id),
+        shared,
+        left: left_entries,
+        right: right_entries,
+        render,
+    })
+}
+
+/// Number of leading nodes the two paths share: the same nodes when both
+/// are in one tree, nodes with the same content otherwise.
+fn shared_prefix_len(left: &[Node], right: &[Node], same_tree: bool) -> usize {
+    left.iter()
+        .zip(right)
+        .take_while(|(l, r)| if same_tree { l.id == r.id } else { l.data == r.data })
+        .count()
+}
+
+async fn entries<P: HubContext>(nodes: &[Node], hub: Option<&P>) -> Vec<DiffEntry> {
+    let mut entries = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        let content = match &node.data {
+            NodeType::Text { content } => content.clone(),
+            NodeType::External { handle } => {
+                let resolved = match hub {
+                    Some(hub) => resolve_handle_text(hub, handle).await,
+                    None => None,
+                };
+                resolved.filter(|text| !text.is_empty()).unwrap_or_else(|| format!("[{handle}]"))
+            }
+        };
+        entries.push(DiffEntry { node_id: node.id, content });
+    }
+    entries
+}
+
+/// Two columns of `width` characters in total, one row per position below
+/// the common ancestor. The gutter marks each row: `=` same content, `|`
+/// different content, `<` left only, `>` right only.
+fn render_side_by_side(left: &[DiffEntry], right: &[DiffEntry], width: usize) -> String {
+    let column = (width.saturating_sub(3) / 2).max(MIN_COLUMN);
+    let rule = "-".repeat(column);
+    let mut out = format!("{:<column$}   right\n{rule}-+-{rule}\n", "left");
+
+    for i in 0..left.len().max(right.len()) {
+        let (l, r) = (left.get(i), right.get(i));
+        let marker = match (l, r) {
+            (Some(l), Some(r)) if l.content == r.content => '=',
+            (Some(_), Some(_)) => '|',
+            (Some(_), None) => '<',
+            _ => '>',
+        };
+        let l_lines = l.map(|e| wrap(&e.content, column)).unwrap_or_default();
+        let r_lines = r.map(|e| wrap(&e.content, column)).unwrap_or_default();
+        for j in 0..l_lines.len().max(r_lines.len()) {
+            let l_line = l_lines.get(j).map_or("", String::as_str);
+            let r_line = r_lines.get(j).map_or("", String::as_str);
+            let line = format!("{l_line:<column$} {marker} {r_line}");
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        if i + 1 < left.len().max(right.len()) {
+            out.push_str(&format!("{rule}-+-{rule}\n"));
+        }
+    }
+    out
+}
+
+/// Split `text` into lines of at most `width` characters.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let chars: Vec<char> = line.chars().collect();
+        if chars.is_empty() {
+            lines.push(String::new());
+        }
+        lines.extend(chars.chunks(width).map(|chunk| chunk.iter().collect::<String>()));
+    }
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activations::arbor::storage::tests::test_storage;
+    use crate::plexus::NoParent;
+
+    async fn text(storage: &ArborStorage, tree: TreeId, parent: NodeId, content: &str) -> NodeId {
+        storage.node_create_text(&tree, Some(parent), content.to_string(), None).await.unwrap()
+    }
+
+    #[test]
+    fn test_render_side_by_side() {
+        let entry = |content: &str| DiffEntry { node_id: NodeId::new(), content: content.to_string() };
+        let left = [entry("same"), entry("left take"), entry("only left\nsecond line")];
+        let right = [entry("same"), entry("right take")];
+
+        let render = render_side_by_side(&left, &right, 23);
+        assert_eq!(
+            render,
+            "\
+left         right
+-----------+-----------
+same       = same
+-----------+-----------
+left take  | right take
+-----------+-----------
+only left  <
+second lin <
+e          <
+"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_diff_branches_and_copies() {
+        let (_dir, storage) = test_storage().await;
+
+        let tree = storage.tree_create(None, "test").await.unwrap();
+        let root = storage.tree_get(&tree).await.unwrap().root;
+        let ticket = text(&storage, tree, root, "Fix the migration").await;
+        let first = text(&storage, tree, ticket, "Attempt A").await;
+        let second = text(&storage, tree, ticket, "Attempt B").await;
+        let second_done = text(&storage, tree, second, "Done").await;
+
+        let diff = diff_nodes(&storage, None::<&NoParent>, (&tree, &first), (&tree, &second_done), 80).await.unwrap();
+        assert_eq!(diff.shared, 2);
+        assert_eq!((diff.left_ancestor, diff.right_ancestor), (Some(ticket), Some(ticket)));
+        assert_eq!(diff.left.iter().map(|e| e.content.as_str()).collect::<Vec<_>>(), ["Attempt A"]);
+        assert_eq!(diff.right.iter().map(|e| e.node_id).collect::<Vec<_>>(), [second, second_done]);
+
+        // A copy in another tree lines up by content
+        let copy = storage.tree_create(None, "test").await.unwrap();
+        let copy_root = storage.tree_get(&copy).await.unwrap().root;
+        let copy_ticket = text(&storage, copy, copy_root, "Fix the migration").await;
+        let copy_attempt = text(&storage, copy, copy_ticket, "Attempt C").await;
+
+        let diff = diff_nodes(&storage, None::<&NoParent>, (&tree, &first), (&copy, &copy_attempt), 80).await.unwrap();
+        assert_eq!((diff.left_ancestor, diff.right_ancestor), (Some(ticket), Some(copy_ticket)));
+        assert!(diff.render.contains("Attempt A") && diff.render.contains("| Attempt C"), "{}", diff.render);
+    }
+
+    #[tokio::test]
+    async fn test_diff_keeps_identical_siblings_apart() {
+        let (_dir, storage) = test_storage().await;
+
+        // Two retries of the same prompt that went different ways
+        let tree = storage.tree_create(None, "test").await.unwrap();
+        let root = storage.tree_get(&tree).await.unwrap().root;
+        let first = text(&storage, tree, root, "Run the tests").await;
+        let first_reply = text(&storage, tree, first, "All green").await;
+        let second = text(&storage, tree, root, "Run the tests").await;
+        let second_reply = text(&storage, tree, second, "Two failures").await;
+
+        let diff = diff_nodes(&storage, None::<&NoParent>, (&tree, &first_reply), (&tree, &second_reply), 80)
+            .await
+            .unwrap();
+        assert_eq!(diff.shared, 1);
+        assert_eq!((diff.left_ancestor, diff.right_ancestor), (Some(root), Some(root)));
+        assert_eq!(diff.left.iter().map(|e| e.node_id).collect::<Vec<_>>(), [first, first_reply]);
+        assert_eq!(diff.right.iter().map(|e| e.node_id).collect::<Vec<_>>(), [second, second_reply]);
+        let first_row = diff.render.lines().nth(2).unwrap();
+        assert!(first_row.starts_with("Run the tests ") && first_row.ends_with("= Run the tests"), "{}", diff.render);
+    }
+}
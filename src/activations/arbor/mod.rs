@@ -1,5 +1,7 @@
 mod methods;
 mod activation;
+mod diff;
+mod query;
 mod storage;
 mod types;
 mod views;
@@ -8,8 +10,9 @@ pub use activation::{Arbor, ArborMethod};
 // Keep methods module for any helper types if needed
 pub use storage::{ArborConfig, ArborStorage};
 pub use types::{
-    ArborError, ArborEvent, ArborId, Node, NodeId, NodeType, ResourceRefs, ResourceState, Tree,
-    TreeId, TreeSkeleton,
+    ArborError, ArborEvent, ArborEventEnvelope, ArborId, DiffEntry, Node, NodeId, NodeType, ResourceRefs, ResourceState,
+    QueryOp, SearchHit, SortOrder, Tree, TreeCondition, TreeId, TreeQuery, TreeQueryPage,
+    TreeDiff, TreeSkeleton,
 };
 pub use views::{
     CollapseType, RangeContent, RangeHandle, RangeSpec, ResolveMode, TextRun,
@@ -0,0 +1,272 @@
+//! SQL for tree queries.
+//!
+//! Conditions on tree timestamps and metadata paths compile to a `WHERE`
+//! clause over `trees`, evaluated with `SQLite`'s JSON functions. Metadata
+//! paths are validated and inlined as literals so that expression indexes
+//! such as `json_extract(metadata, '$.type')` can serve them; values are
+//! always bound.
+
+use super::types::{ArborError, QueryOp, SortOrder, TreeCondition};
+use serde_json::Value;
+
+/// A value bound into a compiled query
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Bind {
+    Text(String),
+    Int(i64),
+    Real(f64),
+}
+
+/// Tree columns addressable by name; every other path is metadata
+const COLUMNS: [&str; 2] = ["created_at", "updated_at"];
+
+enum Field {
+    Column(&'static str),
+    /// JSON path into `trees.metadata`, e.g. `$.ticket."owner-id"`
+    Metadata(String),
+}
+
+impl Field {
+    fn parse(path: &str) -> Result<Self, ArborError> {
+        if let Some(column) = COLUMNS.iter().find(|c| **c == path) {
+            return Ok(Self::Column(column));
+        }
+        let mut json_path = "$".to_string();
+        if path.is_empty() {
+            return Ok(Self::Metadata(json_path));
+        }
+        for key in path.split('.') {
+            if key.is_empty() || !key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+                return Err(invalid(format!("bad path segment {key:?} in {path:?}")));
+            }
+            // Quote only when needed, so the path matches index expressions
+            if key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            {
+                json_path.push('.');
+                json_path.push_str(key);
+            } else {
+                json_path.push_str(&format!(".\"{key}\""));
+            }
+        }
+        Ok(Self::Metadata(json_path))
+    }
+
+    /// SQL expression for the field's value
+    fn value_sql(&self) -> String {
+        match self {
+            Self::Column(column) => (*column).to_string(),
+            Self::Metadata(path) => format!("json_extract(metadata, '{path}')"),
+        }
+    }
+}
+
+const fn invalid(message: String) -> ArborError {
+    ArborError::InvalidQuery { message }
+}
+
+fn scalar(value: &Value) -> Option<Bind> {
+    match value {
+        Value::String(s) => Some(Bind::Text(s.clone())),
+        Value::Bool(b) => Some(Bind::Int(i64::from(*b))),
+        Value::Number(n) => Some(n.as_i64().map_or_else(|| Bind::Real(n.as_f64().unwrap_or(f64::NAN)), Bind::Int)),
+        Value::Null | Value::Array(_) | Value::Object(_) => None,
+    }
+}
+
+/// Compile one condition into a SQL predicate, appending its binds.
+fn predicate(condition: &TreeCondition, binds: &mut Vec<Bind>) -> Result<String, ArborError> {
+    let field = Field::parse(&condition.path)?;
+    let expr = field.value_sql();
+    let op = condition.op;
+    let needs_value = || {
+        condition
+            .value
+            .as_ref()
+            .ok_or_else(|| invalid(format!("{op:?} on {:?} needs a value", condition.path)))
+    };
+    let needs_scalar = || {
+        needs_value().and_then(|v| {
+            scalar(v).ok_or_else(|| invalid(format!("{op:?} on {:?} needs a string, number or bool", condition.path)))
+        })
+    };
+
+    Ok(match op {
+        QueryOp::Eq | QueryOp::Ne => {
+            let (is, is_not) = if op == QueryOp::Eq { ("=", "0") } else { ("IS NOT", "1") };
+            match (needs_value()?, &field) {
+                (Value::Null, Field::Column(_)) => is_not.to_string(),
+                (Value::Null, Field::Metadata(path)) => format!("json_type(metadata, '{path}') {is} 'null'"),
+                (v @ (Value::Array(_) | Value::Object(_)), _) => {
+                    binds.push(Bind::Text(v.to_string()));
+                    format!("{expr} {is} json(?)")
+                }
+                (v, _) => {
+                    binds.push(scalar(v).unwrap_or(Bind::Text(v.to_string())));
+                    format!("{expr} {is} ?")
+                }
+            }
+        }
+        QueryOp::Gt | QueryOp::Gte | QueryOp::Lt | QueryOp::Lte => {
+            binds.push(needs_scalar()?);
+            let cmp = match op {
+                QueryOp::Gt => ">",
+                QueryOp::Gte => ">=",
+                QueryOp::Lt => "<",
+                _ => "<=",
+            };
+            format!("{expr} {cmp} ?")
+        }
+        QueryOp::In => {
+            let Value::Array(values) = needs_value()? else {
+                return Err(invalid(format!("In on {:?} needs an array", condition.path)));
+            };
+            if values.is_empty() {
+                return Ok("0".to_string());
+            }
+            for v in values {
+                binds.push(scalar(v).ok_or_else(|| invalid(format!("In on {:?} takes scalar values", condition.path)))?);
+            }
+            format!("{expr} IN ({})", vec!["?"; values.len()].join(", "))
+        }
+        QueryOp::Contains => {
+            let Field::Metadata(path) = &field else {
+                return Err(invalid(format!("Contains does not apply to {:?}", condition.path)));
+            };
+            let value = needs_scalar()?;
+            binds.push(value.clone());
+            binds.push(value);
+            format!(
+                "(CASE json_type(metadata, '{path}') \
+                 WHEN 'array' THEN EXISTS (SELECT 1 FROM json_each(metadata, '{path}') WHERE value = ?) \
+                 WHEN 'text' THEN instr({expr}, ?) > 0 ELSE 0 END)"
+            )
+        }
+        QueryOp::Exists | QueryOp::NotExists => {
+            let exists = op == QueryOp::Exists;
+            match &field {
+                Field::Column(_) => if exists { "1" } else { "0" }.to_string(),
+                Field::Metadata(path) => {
+                    format!("json_type(metadata, '{path}') IS {}NULL", if exists { "NOT " } else { "" })
+                }
+            }
+        }
+    })
+}
+
+/// `WHERE` predicates (joined with `AND`) and `ORDER BY` clause for a
+/// query, with binds in the order they appear.
+pub(super) fn compile(
+    conditions: &[TreeCondition],
+    sort_by: Option<&str>,
+    order: SortOrder,
+) -> Result<(String, String, Vec<Bind>), ArborError> {
+    let mut binds = Vec::new();
+    let predicates = conditions
+        .iter()
+        .map(|c| predicate(c, &mut binds))
+        .collect::<Result<Vec<_>, _>>()?;
+    let where_sql = if predicates.is_empty() { "1".to_string() } else { predicates.join(" AND ") };
+
+    let sort = Field::parse(sort_by.unwrap_or("created_at"))?.value_sql();
+    let direction = match order {
+        SortOrder::Asc => "ASC",
+        SortOrder::Desc => "DESC",
+    };
+    // `id` keeps pages stable when sort values tie
+    let order_sql = format!("{sort} {direction}, id {direction}");
+
+    Ok((where_sql, order_sql, binds))
+}
+
+/// Conditions equivalent to an exact-match metadata filter: every leaf of
+/// `filter` must equal the value at the same path (arrays compare whole).
+pub(super) fn filter_conditions(filter: &Value) -> Vec<TreeCondition> {
+    fn walk(prefix: &str, value: &Value, out: &mut Vec<TreeCondition>) {
+        match value {
+            Value::Object(map) if !map.is_empty() => {
+                for (key, v) in map {
+                    let path = if prefix.is_empty() { key.clone() } else { format!("{prefix}.{key}") };
+                    walk(&path, v, out);
+                }
+            }
+            Value::Object(_) => {
+                out.push(TreeCondition { path: prefix.to_string(), op: QueryOp::Exists, value: None });
+            }
+            v => out.push(TreeCondition { path: prefix.to_string(), op: QueryOp::Eq, value: Some(v.clone()) }),
+        }
+    }
+
+    let mut conditions = Vec::new();
+    walk("", filter, &mut conditions);
+    conditions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn cond(path: &str, op: QueryOp, value: Option<Value>) -> TreeCondition {
+        TreeCondition { path: path.to_string(), op, value }
+    }
+
+    #[test]
+    fn test_compile_paths_and_binds() {
+        let (where_sql, order_sql, binds) = compile(
+            &[
+                cond("type", QueryOp::Eq, Some(json!("orcha_monitor"))),
+                cond("ticket.owner-id", QueryOp::In, Some(json!(["a", "b"]))),
+                cond("created_at", QueryOp::Gt, Some(json!(100))),
+                cond("archived", QueryOp::NotExists, None),
+            ],
+            Some("priority"),
+            SortOrder::Asc,
+        )
+        .unwrap();
+
+        assert_eq!(
+            where_sql,
+            "json_extract(metadata, '$.type') = ? \
+             AND json_extract(metadata, '$.ticket.\"owner-id\"') IN (?, ?) \
+             AND created_at > ? \
+             AND json_type(metadata, '$.archived') IS NULL"
+        );
+        assert_eq!(order_sql, "json_extract(metadata, '$.priority') ASC, id ASC");
+        assert_eq!(
+            binds,
+            [Bind::Text("orcha_monitor".into()), Bind::Text("a".into()), Bind::Text("b".into()), Bind::Int(100)]
+        );
+    }
+
+    #[test]
+    fn test_compile_rejects_bad_conditions() {
+        let bad = [
+            cond("name') OR 1 --", QueryOp::Exists, None),
+            cond("a..b", QueryOp::Exists, None),
+            cond("tag", QueryOp::Eq, None),
+            cond("tag", QueryOp::Gt, Some(json!({"a": 1}))),
+            cond("tag", QueryOp::In, Some(json!("a"))),
+            cond("created_at", QueryOp::Contains, Some(json!("x"))),
+        ];
+        for condition in bad {
+            assert!(
+                matches!(compile(std::slice::from_ref(&condition), None, SortOrder::Desc), Err(ArborError::InvalidQuery { .. })),
+                "{condition:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_filter_conditions() {
+        let conditions = filter_conditions(&json!({"type": "orcha_monitor", "ticket": {"id": "T01", "tags": ["a"]}}));
+        assert_eq!(
+            conditions,
+            [
+                cond("ticket.id", QueryOp::Eq, Some(json!("T01"))),
+                cond("ticket.tags", QueryOp::Eq, Some(json!(["a"]))),
+                cond("type", QueryOp::Eq, Some(json!("orcha_monitor"))),
+            ]
+        );
+    }
+}
@@ -1,14 +1,20 @@
 use super::types::{
-    ArborError, ArborId, Node, NodeId, NodeType, ResourceRefs, ResourceState, Tree, TreeId, Handle,
+    ArborError, ArborEvent, ArborEventEnvelope, ArborId, Node, NodeId, NodeType, ResourceRefs,
+    ResourceState, SearchHit, Tree, TreeId, TreeQuery, TreeQueryPage, Handle,
 };
+use super::query::{compile, filter_conditions, Bind};
 use crate::activations::storage::init_sqlite_pool;
 use crate::activation_db_path_from_module;
+use async_stream::stream;
+use futures::Stream;
 use serde_json::Value;
-use sqlx::{sqlite::SqlitePool, Row};
+use sqlx::{sqlite::SqlitePool, Row, Sqlite, SqliteConnection};
 use uuid::Uuid;
 use std::collections::HashMap;
 use std::path::PathBuf;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::Notify;
 
 /// Configuration for Arbor storage
 #[derive(Debug, Clone)]
@@ -27,6 +33,9 @@ pub struct ArborConfig {
 
     /// Cleanup task interval (seconds)
     pub cleanup_interval: i64, // Default: 1 hour = 3600
+
+    /// How long logged tree changes are kept for `tree_subscribe` replay (seconds)
+    pub event_retention: i64, // Default: 7 days = 604800
 }
 
 impl Default for ArborConfig {
@@ -37,10 +46,19 @@ impl Default for ArborConfig {
             db_path: activation_db_path_from_module!("arbor.db"),
             auto_cleanup: true,
             cleanup_interval: 3600,             // 1 hour
+            event_retention: 604_800,           // 7 days
         }
     }
 }
 
+/// Most hits `search` returns
+const MAX_SEARCH_HITS: i64 = 200;
+
+/// Delay before retrying a handle that failed to resolve for indexing; it
+/// doubles with each failure up to `INDEX_RETRY_MAX_SECS`
+const INDEX_RETRY_BASE_SECS: i64 = 60;
+const INDEX_RETRY_MAX_SECS: i64 = 86_400;
+
 /// SQLite-based storage for Arbor tree structures.
 ///
 /// # Usage Pattern: Direct Injection
@@ -69,6 +87,12 @@ pub struct ArborStorage {
     pool: SqlitePool,
     #[allow(dead_code)]
     config: ArborConfig,
+    /// Per-tree Notify — wakes `tree_subscribe` streams when changes are logged.
+    /// An entry lives while the tree has subscribers.
+    tree_notifiers: RwLock<HashMap<TreeId, Arc<Notify>>>,
+    /// Held weakly by the background pruning task, which stops once the
+    /// storage is dropped
+    alive: Arc<()>,
 }
 
 impl ArborStorage {
@@ -76,12 +100,36 @@ impl ArborStorage {
     pub async fn new(config: ArborConfig) -> Result<Self, ArborError> {
         let pool = init_sqlite_pool(config.db_path.clone()).await?;
 
-        let storage = Self { pool, config };
+        let storage = Self { pool, config, tree_notifiers: RwLock::new(HashMap::new()), alive: Arc::new(()) };
         storage.run_migrations().await?;
+        if storage.config.auto_cleanup {
+            storage.start_event_pruning();
+        }
 
         Ok(storage)
     }
 
+    /// Spawn the periodic pruning of `tree_events` past `event_retention`,
+    /// every `cleanup_interval`.
+    fn start_event_pruning(&self) {
+        let pool = self.pool.clone();
+        let alive = Arc::downgrade(&self.alive);
+        let retention = self.config.event_retention;
+        let interval = Duration::from_secs(u64::try_from(self.config.cleanup_interval).unwrap_or(0).max(1));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if alive.upgrade().is_none() {
+                    break;
+                }
+                if let Err(e) = prune_tree_events(&pool, current_timestamp() - retention).await {
+                    tracing::warn!("Failed to prune tree events: {e}");
+                }
+            }
+        });
+    }
+
     /// Run database migrations
     async fn run_migrations(&self) -> Result<(), ArborError> {
         sqlx::query(
@@ -144,21 +192,93 @@ impl ArborStorage {
                 FOREIGN KEY (child_id) REFERENCES nodes(id) ON DELETE CASCADE
             );
 
+            -- Change log for tree_subscribe; seq is the resume cursor.
+            CREATE TABLE IF NOT EXISTS tree_events (
+                seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                tree_id TEXT NOT NULL,
+                event TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+
+            -- Highest seq pruned from each tree's change log
+            CREATE TABLE IF NOT EXISTS tree_event_floors (
+                tree_id TEXT PRIMARY KEY,
+                pruned_through INTEGER NOT NULL
+            );
+
             CREATE INDEX IF NOT EXISTS idx_trees_state ON trees(state);
             CREATE INDEX IF NOT EXISTS idx_trees_scheduled ON trees(scheduled_deletion_at) WHERE state = 'scheduled_delete';
             CREATE INDEX IF NOT EXISTS idx_trees_archived ON trees(archived_at) WHERE state = 'archived';
+            CREATE INDEX IF NOT EXISTS idx_trees_created ON trees(created_at);
+            CREATE INDEX IF NOT EXISTS idx_trees_meta_type ON trees(json_extract(metadata, '$.type'));
             CREATE INDEX IF NOT EXISTS idx_nodes_tree ON nodes(tree_id);
             CREATE INDEX IF NOT EXISTS idx_nodes_parent ON nodes(parent_id);
             CREATE INDEX IF NOT EXISTS idx_nodes_state ON nodes(state);
             CREATE INDEX IF NOT EXISTS idx_nodes_scheduled ON nodes(scheduled_deletion_at) WHERE state = 'scheduled_delete';
             CREATE INDEX IF NOT EXISTS idx_node_children_parent ON node_children(parent_id);
             CREATE INDEX IF NOT EXISTS idx_node_children_child ON node_children(child_id);
+            CREATE INDEX IF NOT EXISTS idx_tree_events_tree ON tree_events(tree_id, seq);
+            CREATE INDEX IF NOT EXISTS idx_tree_events_created ON tree_events(created_at);
             ",
         )
         .execute(&self.pool)
         .await
         .map_err(|e| ArborError::InitError { detail: format!("Failed to run migrations: {e}") })?;
 
+        self.migrate_search_index().await
+    }
+
+    /// Create the full-text index, backfilling it from existing nodes the
+    /// first time.
+    ///
+    /// `node_search_docs` has one row per searchable node and its `doc_id` is
+    /// the `node_search` rowid. Text nodes are indexed with their content;
+    /// external nodes start pending and are indexed once their handles are
+    /// resolved (see `index_node_content`), with failed attempts retried
+    /// after a growing delay. Deleting a node deletes its row, and a trigger
+    /// removes the indexed text with it.
+    async fn migrate_search_index(&self) -> Result<(), ArborError> {
+        let exists = sqlx::query("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'node_search_docs'")
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| ArborError::InitError { detail: format!("Failed to check search index: {e}") })?
+            .is_some();
+        if exists {
+            return Ok(());
+        }
+
+        sqlx::query(
+            r"
+            DROP TABLE IF EXISTS node_search;
+
+            CREATE VIRTUAL TABLE node_search USING fts5(content, tokenize = 'porter unicode61');
+
+            CREATE TABLE node_search_docs (
+                doc_id INTEGER PRIMARY KEY,
+                node_id TEXT NOT NULL UNIQUE,
+                indexed INTEGER NOT NULL DEFAULT 0,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                retry_at INTEGER NOT NULL DEFAULT 0,
+                FOREIGN KEY (node_id) REFERENCES nodes(id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX idx_node_search_docs_pending ON node_search_docs(retry_at) WHERE indexed = 0;
+
+            CREATE TRIGGER node_search_docs_delete AFTER DELETE ON node_search_docs BEGIN
+                DELETE FROM node_search WHERE rowid = old.doc_id;
+            END;
+
+            INSERT INTO node_search_docs (node_id, indexed)
+            SELECT id, node_type = 'text' FROM nodes WHERE node_type = 'external' OR content IS NOT NULL;
+
+            INSERT INTO node_search (rowid, content)
+            SELECT d.doc_id, n.content FROM node_search_docs d JOIN nodes n ON n.id = d.node_id WHERE d.indexed = 1;
+            ",
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ArborError::InitError { detail: format!("Failed to create search index: {e}") })?;
+
         Ok(())
     }
 
@@ -183,49 +303,9 @@ impl ArborStorage {
         owner_id: &str,
     ) -> Result<TreeId, ArborError> {
         let tree_id = tree_id.unwrap_or_default();
-        let root_id = NodeId::new();
-        let now = current_timestamp();
 
         let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
-
-        // Create tree
-        let metadata_json = metadata.map(|m| serde_json::to_string(&m).unwrap());
-        sqlx::query(
-            "INSERT INTO trees (id, root_node_id, ref_count, state, created_at, updated_at, metadata)
-             VALUES (?, ?, 1, 'active', ?, ?, ?)",
-        )
-        .bind(tree_id.to_string())
-        .bind(root_id.to_string())
-        .bind(now)
-        .bind(now)
-        .bind(metadata_json)
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| format!("Failed to create tree: {e}"))?;
-
-        // Create tree ref for owner
-        sqlx::query(
-            "INSERT INTO tree_refs (tree_id, owner_id, count, claimed_at) VALUES (?, ?, 1, ?)",
-        )
-        .bind(tree_id.to_string())
-        .bind(owner_id)
-        .bind(now)
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| format!("Failed to create tree ref: {e}"))?;
-
-        // Create root node (empty text node)
-        sqlx::query(
-            "INSERT INTO nodes (id, tree_id, parent_id, ref_count, state, node_type, content, created_at)
-             VALUES (?, ?, NULL, 1, 'active', 'text', '', ?)",
-        )
-        .bind(root_id.to_string())
-        .bind(tree_id.to_string())
-        .bind(now)
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| format!("Failed to create root node: {e}"))?;
-
+        insert_tree(&mut tx, &tree_id, metadata, owner_id).await?;
         tx.commit().await.map_err(|e| e.to_string())?;
 
         Ok(tree_id)
@@ -394,32 +474,6 @@ impl ArborStorage {
         children
     }
 
-    /// Add a child to a parent in the `node_children` table
-    async fn add_child_to_parent(&self, parent_id: &NodeId, child_id: &NodeId) -> Result<(), ArborError> {
-        // Get next position for this parent
-        let row = sqlx::query(
-            "SELECT COALESCE(MAX(position), -1) + 1 as next_pos FROM node_children WHERE parent_id = ?",
-        )
-        .bind(parent_id.to_string())
-        .fetch_one(&self.pool)
-        .await
-        .map_err(|e| format!("Failed to get next position: {e}"))?;
-
-        let next_pos: i64 = row.get("next_pos");
-
-        sqlx::query(
-            "INSERT INTO node_children (parent_id, child_id, position) VALUES (?, ?, ?)",
-        )
-        .bind(parent_id.to_string())
-        .bind(child_id.to_string())
-        .bind(next_pos)
-        .execute(&self.pool)
-        .await
-        .map_err(|e| format!("Failed to add child to parent: {e}"))?;
-
-        Ok(())
-    }
-
     /// Get reference information for a tree
     async fn get_tree_refs(&self, tree_id: &TreeId) -> Result<ResourceRefs, ArborError> {
         let rows = sqlx::query(
@@ -548,17 +602,24 @@ impl ArborStorage {
         let now = current_timestamp();
         let metadata_json = serde_json::to_string(&metadata)
             .map_err(|e| format!("Failed to serialize metadata: {e}"))?;
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
 
-        sqlx::query(
+        let updated = sqlx::query(
             "UPDATE trees SET metadata = ?, updated_at = ? WHERE id = ? AND state = 'active'",
         )
         .bind(metadata_json)
         .bind(now)
         .bind(tree_id.to_string())
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await
         .map_err(|e| format!("Failed to update tree metadata: {e}"))?;
 
+        if updated.rows_affected() > 0 {
+            log_event(&mut *tx, tree_id, &ArborEvent::MetadataUpdated { tree_id: *tree_id, metadata }).await?;
+        }
+
+        tx.commit().await.map_err(|e| e.to_string())?;
+        self.notify_tree(tree_id);
         Ok(())
     }
 
@@ -599,6 +660,8 @@ impl ArborStorage {
             .execute(&mut *tx)
             .await
             .map_err(|e| format!("Failed to reactivate tree: {e}"))?;
+
+            log_event(&mut *tx, tree_id, &ArborEvent::TreeReactivated { tree_id: *tree_id }).await?;
         }
 
         // Update or insert tree_ref
@@ -637,7 +700,11 @@ impl ArborStorage {
 
         let new_count: i64 = new_count_row.get("ref_count");
 
+        let claimed = ArborEvent::TreeClaimed { tree_id: *tree_id, owner_id: owner_id.to_string(), new_count };
+        log_event(&mut *tx, tree_id, &claimed).await?;
+
         tx.commit().await.map_err(|e| e.to_string())?;
+        self.notify_tree(tree_id);
         Ok(new_count)
     }
 
@@ -712,6 +779,9 @@ impl ArborStorage {
             .map_err(|e| format!("Failed to fetch tree: {e}"))?;
 
         let ref_count: i64 = tree_row.get("ref_count");
+        let released = ArborEvent::TreeReleased { tree_id: *tree_id, owner_id: owner_id.to_string(), new_count: ref_count };
+        log_event(&mut *tx, tree_id, &released).await?;
+
         if ref_count == 0 {
             sqlx::query(
                 "UPDATE trees SET state = 'scheduled_delete', scheduled_deletion_at = ?, updated_at = ? WHERE id = ?",
@@ -722,9 +792,13 @@ impl ArborStorage {
             .execute(&mut *tx)
             .await
             .map_err(|e| format!("Failed to schedule tree deletion: {e}"))?;
+
+            log_event(&mut *tx, tree_id, &ArborEvent::TreeScheduledDeletion { tree_id: *tree_id, scheduled_at: now })
+                .await?;
         }
 
         tx.commit().await.map_err(|e| e.to_string())?;
+        self.notify_tree(tree_id);
         Ok(ref_count)
     }
 
@@ -741,6 +815,8 @@ impl ArborStorage {
 
         let metadata_json = metadata.map(|m| serde_json::to_string(&m).unwrap());
 
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+
         sqlx::query(
             "INSERT INTO nodes (id, tree_id, parent_id, ref_count, state, node_type, content, metadata, created_at)
              VALUES (?, ?, ?, 1, 'active', 'text', ?, ?, ?)",
@@ -751,15 +827,21 @@ impl ArborStorage {
         .bind(&content)
         .bind(metadata_json)
         .bind(now)
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await
         .map_err(|e| format!("Failed to create text node: {e}"))?;
 
+        index_text(&mut tx, &node_id, &content).await?;
+
         // Add to node_children table if parent is specified
         if let Some(parent_id) = parent {
-            self.add_child_to_parent(&parent_id, &node_id).await?;
+            add_child(&mut *tx, &parent_id, &node_id).await?;
         }
 
+        log_event(&mut *tx, tree_id, &ArborEvent::NodeCreated { tree_id: *tree_id, node_id, parent }).await?;
+        tx.commit().await.map_err(|e| e.to_string())?;
+
+        self.notify_tree(tree_id);
         Ok(node_id)
     }
 
@@ -777,6 +859,8 @@ impl ArborStorage {
         let metadata_json = metadata.map(|m| serde_json::to_string(&m).unwrap());
         let meta_json = serde_json::to_string(&handle.meta).unwrap();
 
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+
         sqlx::query(
             "INSERT INTO nodes (id, tree_id, parent_id, ref_count, state, node_type, handle_plugin_id, handle_version, handle_method, handle_meta, metadata, created_at)
              VALUES (?, ?, ?, 1, 'active', 'external', ?, ?, ?, ?, ?, ?)",
@@ -790,15 +874,22 @@ impl ArborStorage {
         .bind(&meta_json)
         .bind(metadata_json)
         .bind(now)
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await
         .map_err(|e| format!("Failed to create external node: {e}"))?;
 
+        // Indexed once the handle resolves; see `index_node_content`
+        add_pending_doc(&mut *tx, &node_id).await?;
+
         // Add to node_children table if parent is specified
         if let Some(parent_id) = parent {
-            self.add_child_to_parent(&parent_id, &node_id).await?;
+            add_child(&mut *tx, &parent_id, &node_id).await?;
         }
 
+        log_event(&mut *tx, tree_id, &ArborEvent::NodeCreated { tree_id: *tree_id, node_id, parent }).await?;
+        tx.commit().await.map_err(|e| e.to_string())?;
+
+        self.notify_tree(tree_id);
         Ok(node_id)
     }
 
@@ -816,6 +907,8 @@ impl ArborStorage {
         let metadata_json = metadata.map(|m| serde_json::to_string(&m).unwrap());
         let meta_json = serde_json::to_string(&handle.meta).unwrap();
 
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+
         sqlx::query(
             "INSERT INTO nodes (id, tree_id, parent_id, ref_count, state, scheduled_deletion_at, node_type, handle_plugin_id, handle_version, handle_method, handle_meta, metadata, created_at)
              VALUES (?, ?, ?, 0, 'scheduled_delete', ?, 'external', ?, ?, ?, ?, ?, ?)",
@@ -830,15 +923,19 @@ impl ArborStorage {
         .bind(&meta_json)
         .bind(metadata_json)
         .bind(now)
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await
         .map_err(|e| format!("Failed to create ephemeral external node: {e}"))?;
 
         // Add to node_children table if parent is specified
         if let Some(parent_id) = parent {
-            self.add_child_to_parent(&parent_id, &node_id).await?;
+            add_child(&mut *tx, &parent_id, &node_id).await?;
         }
 
+        log_event(&mut *tx, tree_id, &ArborEvent::NodeCreated { tree_id: *tree_id, node_id, parent }).await?;
+        tx.commit().await.map_err(|e| e.to_string())?;
+
+        self.notify_tree(tree_id);
         Ok(node_id)
     }
 
@@ -1002,71 +1099,737 @@ impl ArborStorage {
         let now = current_timestamp();
         let seven_days_ago = now - (7 * 24 * 60 * 60);
 
-        let result = sqlx::query(
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+
+        let rows = sqlx::query(
             "UPDATE trees
              SET state = 'archived', archived_at = ?, updated_at = ?
-             WHERE state = 'scheduled_delete' AND scheduled_deletion_at < ?",
+             WHERE state = 'scheduled_delete' AND scheduled_deletion_at < ?
+             RETURNING id",
         )
         .bind(now)
         .bind(now)
         .bind(seven_days_ago)
-        .execute(&self.pool)
+        .fetch_all(&mut *tx)
         .await
         .map_err(|e| format!("Failed to archive trees: {e}"))?;
 
-        Ok(result.rows_affected() as usize)
+        let mut archived = Vec::with_capacity(rows.len());
+        for row in rows {
+            let id_str: String = row.get("id");
+            let tree_id = ArborId::parse_str(&id_str).map_err(|e| format!("Invalid tree ID: {e}"))?;
+            log_event(&mut *tx, &tree_id, &ArborEvent::TreeArchived { tree_id, archived_at: now }).await?;
+            archived.push(tree_id);
+        }
+
+        tx.commit().await.map_err(|e| e.to_string())?;
+        for tree_id in &archived {
+            self.notify_tree(tree_id);
+            self.forget_tree_notifier(tree_id);
+        }
+        Ok(archived.len())
     }
 
-    /// Query trees by metadata filter (e.g., {"type": "`orcha_monitor`"})
-    pub async fn tree_query_by_metadata(&self, filter: &Value) -> Result<Vec<TreeId>, ArborError> {
+    // ========================================================================
+    // Change Log
+    // ========================================================================
+
+    /// Wake the `tree_subscribe` streams for a tree.
+    fn notify_tree(&self, tree_id: &TreeId) {
+        let notifiers = self.tree_notifiers.read().unwrap_or_else(PoisonError::into_inner);
+        if let Some(notifier) = notifiers.get(tree_id) {
+            notifier.notify_waiters();
+        }
+    }
+
+    fn tree_notifier(&self, tree_id: &TreeId) -> Arc<Notify> {
+        let mut notifiers = self.tree_notifiers.write().unwrap_or_else(PoisonError::into_inner);
+        notifiers.entry(*tree_id).or_insert_with(|| Arc::new(Notify::new())).clone()
+    }
+
+    /// Drop the notifier of a tree once its last subscriber (holding
+    /// `notifier`) goes away.
+    fn release_tree_notifier(&self, tree_id: &TreeId, notifier: &Arc<Notify>) {
+        let mut notifiers = self.tree_notifiers.write().unwrap_or_else(PoisonError::into_inner);
+        let last = notifiers.get(tree_id).is_some_and(|n| Arc::ptr_eq(n, notifier) && Arc::strong_count(n) == 2);
+        if last {
+            notifiers.remove(tree_id);
+        }
+    }
+
+    /// Drop the notifier of a tree that has gone away; its subscribers have
+    /// been woken to read the final event and keep their own handle.
+    fn forget_tree_notifier(&self, tree_id: &TreeId) {
+        self.tree_notifiers.write().unwrap_or_else(PoisonError::into_inner).remove(tree_id);
+    }
+
+    /// Highest seq pruned from a tree's change log (0 when none was).
+    async fn tree_events_floor(&self, tree_id: &TreeId) -> Result<u64, ArborError> {
+        let floor: Option<i64> = sqlx::query_scalar("SELECT pruned_through FROM tree_event_floors WHERE tree_id = ?")
+            .bind(tree_id.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch tree event floor: {e}"))?;
+        Ok(floor.map_or(0, |seq| seq as u64))
+    }
+
+    /// When the tree was archived, or `None` while it is not.
+    async fn tree_archived_at(&self, tree_id: &TreeId) -> Result<Option<i64>, ArborError> {
+        let archived_at: Option<Option<i64>> =
+            sqlx::query_scalar("SELECT archived_at FROM trees WHERE id = ? AND state = 'archived'")
+                .bind(tree_id.to_string())
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to fetch tree state: {e}"))?;
+        Ok(archived_at.map(|at| at.unwrap_or(0)))
+    }
+
+    /// Delete logged tree changes older than `event_retention`, remembering
+    /// per tree how far the log was pruned. Returns the number deleted.
+    pub async fn prune_tree_events(&self) -> Result<u64, ArborError> {
+        prune_tree_events(&self.pool, current_timestamp() - self.config.event_retention).await
+    }
+
+    /// Read the changes logged for a tree with seq > `after_seq`, in order.
+    pub async fn tree_events_after(
+        &self,
+        tree_id: &TreeId,
+        after_seq: u64,
+    ) -> Result<Vec<ArborEventEnvelope>, ArborError> {
+        let rows = sqlx::query("SELECT seq, event FROM tree_events WHERE tree_id = ? AND seq > ? ORDER BY seq")
+            .bind(tree_id.to_string())
+            .bind(i64::try_from(after_seq).unwrap_or(i64::MAX))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch tree events: {e}"))?;
+
+        rows.into_iter()
+            .map(|row| {
+                let seq: i64 = row.get("seq");
+                let event_json: String = row.get("event");
+                let event = serde_json::from_str(&event_json)
+                    .map_err(|e| format!("Failed to deserialize tree event: {e}"))?;
+                Ok(ArborEventEnvelope { seq: seq as u64, event })
+            })
+            .collect()
+    }
+
+    /// Replay the changes logged for a tree after `after_seq` (from the
+    /// beginning when omitted), then tail new ones as they happen. The stream
+    /// ends once the tree is archived.
+    ///
+    /// When changes after the cursor have been pruned from the log, an
+    /// `EventsPruned` envelope reports the gap before the remaining changes.
+    pub fn subscribe_stream(
+        storage: Arc<Self>,
+        tree_id: TreeId,
+        after_seq: Option<u64>,
+    ) -> impl Stream<Item = ArborEventEnvelope> + Send + 'static {
+        stream! {
+            let exists = sqlx::query("SELECT 1 FROM trees WHERE id = ?")
+                .bind(tree_id.to_string())
+                .fetch_optional(&storage.pool)
+                .await;
+            if !matches!(exists, Ok(Some(_))) {
+                let message = ArborError::TreeNotFound { tree_id: tree_id.to_string() }.to_string();
+                yield ArborEventEnvelope { seq: 0, event: ArborEvent::Err { message } };
+                return;
+            }
+
+            let notifier = NotifierGuard { storage: storage.clone(), tree_id, notifier: storage.tree_notifier(&tree_id) };
+            let mut cursor = after_seq.unwrap_or(0);
+            loop {
+                // Register for wake-ups before reading so no change slips between
+                let notified = notifier.notifier.notified();
+                tokio::pin!(notified);
+                notified.as_mut().enable();
+
+                match storage.tree_events_floor(&tree_id).await {
+                    Ok(floor) if floor > cursor => {
+                        cursor = floor;
+                        yield ArborEventEnvelope { seq: floor, event: ArborEvent::EventsPruned { tree_id, through_seq: floor } };
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        yield ArborEventEnvelope { seq: cursor, event: ArborEvent::Err { message: e.to_string() } };
+                        return;
+                    }
+                }
+
+                // Read the state first: an archived tree's TreeArchived is then
+                // either among the events below or already pruned
+                let archived_at = match storage.tree_archived_at(&tree_id).await {
+                    Ok(archived_at) => archived_at,
+                    Err(e) => {
+                        yield ArborEventEnvelope { seq: cursor, event: ArborEvent::Err { message: e.to_string() } };
+                        return;
+                    }
+                };
+                let events = match storage.tree_events_after(&tree_id, cursor).await {
+                    Ok(events) => events,
+                    Err(e) => {
+                        yield ArborEventEnvelope { seq: cursor, event: ArborEvent::Err { message: e.to_string() } };
+                        return;
+                    }
+                };
+                for envelope in events {
+                    cursor = envelope.seq;
+                    let archived = matches!(envelope.event, ArborEvent::TreeArchived { .. });
+                    yield envelope;
+                    if archived {
+                        return;
+                    }
+                }
+                if let Some(archived_at) = archived_at {
+                    yield ArborEventEnvelope { seq: cursor, event: ArborEvent::TreeArchived { tree_id, archived_at } };
+                    return;
+                }
+
+                notified.await;
+            }
+        }
+    }
+
+    // ========================================================================
+    // Structural Editing
+    // ========================================================================
+
+    /// Copy the path from the root of `tree_id` down to `node_id` into a new
+    /// tree owned by `owner_id`. Returns the new tree and the copied nodes
+    /// below its root, top down.
+    pub async fn tree_copy_path(
+        &self,
+        tree_id: &TreeId,
+        node_id: &NodeId,
+        metadata: Option<Value>,
+        owner_id: &str,
+    ) -> Result<(TreeId, Vec<NodeId>), ArborError> {
+        let path = self.node_get_path(tree_id, node_id).await?;
+        let new_tree_id = TreeId::new();
+
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+        let root = insert_tree(&mut tx, &new_tree_id, metadata, owner_id).await?;
+        let copies = copy_nodes(&mut tx, tree_id, &path[1..], &new_tree_id, &root).await?;
+        tx.commit().await.map_err(|e| e.to_string())?;
+
+        self.notify_tree(&new_tree_id);
+        Ok((new_tree_id, copies))
+    }
+
+    /// Graft the path from the root of `source_tree` down to `source_node`
+    /// (without the root) onto `target_parent` in `target_tree`. Returns the
+    /// copied nodes, top down.
+    pub async fn node_graft_path(
+        &self,
+        source_tree: &TreeId,
+        source_node: &NodeId,
+        target_tree: &TreeId,
+        target_parent: &NodeId,
+    ) -> Result<Vec<NodeId>, ArborError> {
+        let path = self.node_get_path(source_tree, source_node).await?;
+        self.copy_chain(source_tree, &path[1..], target_tree, target_parent).await
+    }
+
+    /// Copy the nodes from `start` down to `end` (inclusive; `start` must be
+    /// an ancestor of `end`) onto `target_parent` in `target_tree`. Returns
+    /// the copied nodes, top down.
+    pub async fn node_cherry_pick(
+        &self,
+        source_tree: &TreeId,
+        start: &NodeId,
+        end: &NodeId,
+        target_tree: &TreeId,
+        target_parent: &NodeId,
+    ) -> Result<Vec<NodeId>, ArborError> {
+        let path = self.node_get_path(source_tree, end).await?;
+        let Some(from) = path.iter().position(|id| id == start) else {
+            return Err(ArborError::InvalidState { message: format!("Node {start} is not an ancestor of {end}") });
+        };
+        self.copy_chain(source_tree, &path[from..], target_tree, target_parent).await
+    }
+
+    /// Copy `nodes` of `source_tree` as a chain under `target_parent` in one
+    /// transaction; see `copy_nodes`.
+    async fn copy_chain(
+        &self,
+        source_tree: &TreeId,
+        nodes: &[NodeId],
+        target_tree: &TreeId,
+        target_parent: &NodeId,
+    ) -> Result<Vec<NodeId>, ArborError> {
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+        let copies = copy_nodes(&mut tx, source_tree, nodes, target_tree, target_parent).await?;
+        tx.commit().await.map_err(|e| e.to_string())?;
+
+        self.notify_tree(target_tree);
+        Ok(copies)
+    }
+
+    // ========================================================================
+    // Search
+    // ========================================================================
+
+    /// Index the resolved content of an external node's handle.
+    ///
+    /// Text nodes are indexed on creation. External nodes are indexed with
+    /// whatever their handle resolves to, which only the owning activation
+    /// can provide; pass an empty string to record that there was nothing to
+    /// index so the handle isn't resolved again. A node that is already
+    /// indexed is left as it is.
+    pub async fn index_node_content(&self, node_id: &NodeId, content: &str) -> Result<(), ArborError> {
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+
+        let doc = sqlx::query(
+            "INSERT INTO node_search_docs (node_id, indexed) VALUES (?, 1)
+             ON CONFLICT (node_id) DO UPDATE SET indexed = 1 WHERE indexed = 0
+             RETURNING doc_id",
+        )
+        .bind(node_id.to_string())
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| ArborError::StorageError { operation: "index_node".to_string(), detail: e.to_string() })?;
+
+        if let Some(doc) = doc {
+            sqlx::query("INSERT INTO node_search (rowid, content) VALUES (?, ?)")
+                .bind(doc.get::<i64, _>("doc_id"))
+                .bind(content)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| ArborError::StorageError { operation: "index_node".to_string(), detail: e.to_string() })?;
+        }
+
+        tx.commit().await.map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Record that an external node's handle could not be resolved, so it is
+    /// retried after a delay that doubles with each failure, up to a day.
+    pub async fn index_node_failed(&self, node_id: &NodeId) -> Result<(), ArborError> {
+        sqlx::query(
+            "UPDATE node_search_docs
+             SET attempts = attempts + 1, retry_at = ?1 + MIN(?2 << MIN(attempts, 16), ?3)
+             WHERE node_id = ?4 AND indexed = 0",
+        )
+        .bind(current_timestamp())
+        .bind(INDEX_RETRY_BASE_SECS)
+        .bind(INDEX_RETRY_MAX_SECS)
+        .bind(node_id.to_string())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ArborError::StorageError { operation: "index_node_failed".to_string(), detail: e.to_string() })?;
+
+        Ok(())
+    }
+
+    /// Up to `limit` external nodes in active trees whose handle content is
+    /// not indexed yet and is due for another attempt, longest waiting first.
+    pub async fn search_pending_handles(&self, limit: usize) -> Result<Vec<(NodeId, Handle)>, ArborError> {
         let rows = sqlx::query(
-            "SELECT id, metadata FROM trees WHERE state = 'active'"
+            "SELECT n.id, n.handle_plugin_id, n.handle_version, n.handle_method, n.handle_meta
+             FROM node_search_docs d
+             JOIN nodes n ON n.id = d.node_id
+             JOIN trees t ON t.id = n.tree_id
+             WHERE d.indexed = 0 AND d.retry_at <= ? AND t.state = 'active'
+             ORDER BY d.retry_at
+             LIMIT ?",
         )
+        .bind(current_timestamp())
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
         .fetch_all(&self.pool)
         .await
-        .map_err(|e| format!("Failed to query trees: {e}"))?;
+        .map_err(|e| ArborError::StorageError { operation: "pending_handles".to_string(), detail: e.to_string() })?;
 
-        let mut matching_trees = Vec::new();
+        rows.iter()
+            .map(|row| {
+                let node_id: String = row.get("id");
+                let plugin_id_str: String = row.get("handle_plugin_id");
+                let plugin_id = Uuid::parse_str(&plugin_id_str)
+                    .map_err(|e| format!("Invalid handle plugin_id: {e}"))?;
+                let meta_json: Option<String> = row.get("handle_meta");
+                let meta: Vec<String> = meta_json
+                    .and_then(|s| serde_json::from_str(&s).ok())
+                    .unwrap_or_default();
+                let handle = Handle::new(plugin_id, row.get::<String, _>("handle_version"), row.get::<String, _>("handle_method"))
+                    .with_meta(meta);
+                Ok((ArborId::parse_str(&node_id).map_err(|e| format!("Invalid node ID: {e}"))?, handle))
+            })
+            .collect()
+    }
 
+    /// Full-text search over node content in active trees, best match first.
+    ///
+    /// `query` uses FTS5 syntax: bare words must all match, `"..."` matches a
+    /// phrase, `OR`/`NOT` combine terms and `word*` matches a prefix. Each hit
+    /// carries a snippet with matches wrapped in `[` `]` and the node-id path
+    /// from the root to the node. At most `limit` hits are returned, capped
+    /// at `MAX_SEARCH_HITS`.
+    pub async fn search(
+        &self,
+        query: &str,
+        tree_id: Option<&TreeId>,
+        limit: i64,
+    ) -> Result<Vec<SearchHit>, ArborError> {
+        let rows = sqlx::query(
+            "SELECT n.tree_id, n.id AS node_id,
+                    snippet(node_search, 0, '[', ']', '…', 16) AS snippet,
+                    bm25(node_search) AS rank
+             FROM node_search
+             JOIN node_search_docs d ON d.doc_id = node_search.rowid
+             JOIN nodes n ON n.id = d.node_id
+             JOIN trees t ON t.id = n.tree_id
+             WHERE node_search MATCH ?1 AND t.state = 'active'
+             AND (?2 IS NULL OR n.tree_id = ?2)
+             ORDER BY rank
+             LIMIT ?3",
+        )
+        .bind(query)
+        .bind(tree_id.map(ToString::to_string))
+        .bind(limit.clamp(0, MAX_SEARCH_HITS))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ArborError::StorageError { operation: "search".to_string(), detail: e.to_string() })?;
+
+        let mut hits = Vec::with_capacity(rows.len());
         for row in rows {
-            let tree_id_str: String = row.get("id");
-            let metadata_str: Option<String> = row.get("metadata");
-
-            if let Some(metadata_json) = metadata_str {
-                if let Ok(metadata) = serde_json::from_str::<Value>(&metadata_json) {
-                    // Check if metadata matches filter
-                    if metadata_matches(filter, &metadata) {
-                        if let Ok(tree_id) = ArborId::parse_str(&tree_id_str) {
-                            matching_trees.push(tree_id);
-                        }
-                    }
-                }
-            }
+            let tree_id_str: String = row.get("tree_id");
+            let node_id_str: String = row.get("node_id");
+            let tree_id = ArborId::parse_str(&tree_id_str).map_err(|e| format!("Invalid tree ID: {e}"))?;
+            let node_id = ArborId::parse_str(&node_id_str).map_err(|e| format!("Invalid node ID: {e}"))?;
+            let path = self.node_get_path(&tree_id, &node_id).await?;
+            hits.push(SearchHit {
+                tree_id,
+                node_id,
+                snippet: row.get("snippet"),
+                // bm25 is lower-is-better; flip it so higher scores rank higher
+                score: -row.get::<f64, _>("rank"),
+                path,
+            });
         }
 
-        Ok(matching_trees)
+        Ok(hits)
     }
-}
 
-/// Helper to check if metadata matches a filter
-fn metadata_matches(filter: &Value, metadata: &Value) -> bool {
-    match (filter, metadata) {
-        (Value::Object(filter_obj), Value::Object(meta_obj)) => {
-            // Check if all filter keys exist in metadata with matching values
-            filter_obj.iter().all(|(key, filter_value)| {
-                meta_obj.get(key).is_some_and(|meta_value| {
-                    metadata_matches(filter_value, meta_value)
-                })
+    /// Query trees by metadata filter (e.g., {"type": "`orcha_monitor`"})
+    ///
+    /// Every leaf of `filter` must equal the value at the same metadata path;
+    /// see `tree_query` for richer conditions.
+    pub async fn tree_query_by_metadata(&self, filter: &Value) -> Result<Vec<TreeId>, ArborError> {
+        let query = TreeQuery { conditions: filter_conditions(filter), ..TreeQuery::default() };
+        Ok(self.tree_query(&query).await?.tree_ids)
+    }
+
+    /// Query active trees by conditions on their timestamps and metadata,
+    /// one page at a time. Filtering, sorting and paging all run in `SQLite`.
+    pub async fn tree_query(&self, query: &TreeQuery) -> Result<TreeQueryPage, ArborError> {
+        if let Some(limit) = query.limit.filter(|n| *n < 1) {
+            return Err(ArborError::InvalidQuery { message: format!("limit must be at least 1, got {limit}") });
+        }
+        let (where_sql, order_sql, binds) = compile(&query.conditions, query.sort_by.as_deref(), query.order)?;
+        let sql = format!(
+            "SELECT id FROM trees WHERE state = 'active' AND {where_sql} ORDER BY {order_sql} LIMIT ? OFFSET ?"
+        );
+
+        let mut q = sqlx::query(&sql);
+        for bind in binds {
+            q = match bind {
+                Bind::Text(s) => q.bind(s),
+                Bind::Int(n) => q.bind(n),
+                Bind::Real(x) => q.bind(x),
+            };
+        }
+        let page_size = query.limit;
+        let offset = query.offset.max(0);
+        // One extra row tells us whether there is another page
+        let limit = page_size.map_or(-1, |n| n.saturating_add(1));
+        let rows = q
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| ArborError::StorageError { operation: "tree_query".to_string(), detail: e.to_string() })?;
+
+        let mut tree_ids = rows
+            .iter()
+            .map(|row| {
+                let id_str: String = row.get("id");
+                ArborId::parse_str(&id_str).map_err(|e| format!("Invalid tree ID: {e}").into())
             })
+            .collect::<Result<Vec<TreeId>, ArborError>>()?;
+
+        let mut next_offset = None;
+        if let Some(n) = page_size {
+            let len = usize::try_from(n).unwrap_or(usize::MAX);
+            if tree_ids.len() > len {
+                tree_ids.truncate(len);
+                next_offset = Some(offset + n);
+            }
         }
-        (Value::Array(filter_arr), Value::Array(meta_arr)) => {
-            // Arrays must match exactly (for simplicity)
-            filter_arr == meta_arr
+
+        Ok(TreeQueryPage { tree_ids, next_offset })
+    }
+}
+
+/// Insert a tree with an empty root node, owned by `owner_id`, and log its
+/// creation. Returns the root node.
+async fn insert_tree(
+    conn: &mut SqliteConnection,
+    tree_id: &TreeId,
+    metadata: Option<Value>,
+    owner_id: &str,
+) -> Result<NodeId, ArborError> {
+    let root_id = NodeId::new();
+    let now = current_timestamp();
+
+    // Create tree
+    let metadata_json = metadata.map(|m| serde_json::to_string(&m).unwrap());
+    sqlx::query(
+        "INSERT INTO trees (id, root_node_id, ref_count, state, created_at, updated_at, metadata)
+         VALUES (?, ?, 1, 'active', ?, ?, ?)",
+    )
+    .bind(tree_id.to_string())
+    .bind(root_id.to_string())
+    .bind(now)
+    .bind(now)
+    .bind(metadata_json)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to create tree: {e}"))?;
+
+    // Create tree ref for owner
+    sqlx::query(
+        "INSERT INTO tree_refs (tree_id, owner_id, count, claimed_at) VALUES (?, ?, 1, ?)",
+    )
+    .bind(tree_id.to_string())
+    .bind(owner_id)
+    .bind(now)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to create tree ref: {e}"))?;
+
+    // Create root node (empty text node)
+    sqlx::query(
+        "INSERT INTO nodes (id, tree_id, parent_id, ref_count, state, node_type, content, created_at)
+         VALUES (?, ?, NULL, 1, 'active', 'text', '', ?)",
+    )
+    .bind(root_id.to_string())
+    .bind(tree_id.to_string())
+    .bind(now)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to create root node: {e}"))?;
+
+    log_event(&mut *conn, tree_id, &ArborEvent::TreeCreated { tree_id: *tree_id }).await?;
+    Ok(root_id)
+}
+
+/// Copy `nodes` of `source_tree` as a chain under `target_parent`, each
+/// under the copy of the one before, and bump the target tree's
+/// `updated_at`. Copies keep their content, handle, metadata, `ref_count`
+/// and `node_refs` owners and are indexed for search like their sources, but
+/// are created now as active nodes with no lifecycle timestamps.
+async fn copy_nodes(
+    conn: &mut SqliteConnection,
+    source_tree: &TreeId,
+    nodes: &[NodeId],
+    target_tree: &TreeId,
+    target_parent: &NodeId,
+) -> Result<Vec<NodeId>, ArborError> {
+    sqlx::query("SELECT 1 FROM nodes WHERE id = ? AND tree_id = ?")
+        .bind(target_parent.to_string())
+        .bind(target_tree.to_string())
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to fetch target parent: {e}"))?
+        .ok_or_else(|| ArborError::NodeNotFound {
+            node_id: target_parent.to_string(),
+            tree_id: target_tree.to_string(),
+        })?;
+
+    let mut parent = *target_parent;
+    let mut copies = Vec::with_capacity(nodes.len());
+    for source in nodes {
+        let copy = NodeId::new();
+        let inserted = sqlx::query(
+            "INSERT INTO nodes (id, tree_id, parent_id, ref_count, state, node_type, content, handle_plugin_id, handle_version, handle_method, handle_meta, created_at, metadata)
+             SELECT ?, ?, ?, ref_count, 'active', node_type, content, handle_plugin_id, handle_version, handle_method, handle_meta, ?, metadata
+             FROM nodes WHERE id = ? AND tree_id = ?",
+        )
+        .bind(copy.to_string())
+        .bind(target_tree.to_string())
+        .bind(parent.to_string())
+        .bind(current_timestamp())
+        .bind(source.to_string())
+        .bind(source_tree.to_string())
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to copy node: {e}"))?;
+        if inserted.rows_affected() == 0 {
+            return Err(ArborError::NodeNotFound { node_id: source.to_string(), tree_id: source_tree.to_string() });
         }
-        (filter_val, meta_val) => filter_val == meta_val,
+
+        sqlx::query(
+            "INSERT INTO node_refs (node_id, owner_id, count, claimed_at)
+             SELECT ?, owner_id, count, claimed_at FROM node_refs WHERE node_id = ?",
+        )
+        .bind(copy.to_string())
+        .bind(source.to_string())
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to copy node refs: {e}"))?;
+
+        copy_search_doc(&mut *conn, source, &copy).await?;
+
+        add_child(&mut *conn, &parent, &copy).await?;
+
+        let created = ArborEvent::NodeCreated { tree_id: *target_tree, node_id: copy, parent: Some(parent) };
+        log_event(&mut *conn, target_tree, &created).await?;
+
+        copies.push(copy);
+        parent = copy;
+    }
+
+    sqlx::query("UPDATE trees SET updated_at = ? WHERE id = ?")
+        .bind(current_timestamp())
+        .bind(target_tree.to_string())
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to update tree: {e}"))?;
+
+    Ok(copies)
+}
+
+/// Append `child` to the children of `parent`.
+async fn add_child<'e, E>(executor: E, parent: &NodeId, child: &NodeId) -> Result<(), ArborError>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
+    sqlx::query(
+        "INSERT INTO node_children (parent_id, child_id, position)
+         SELECT ?, ?, COALESCE(MAX(position), -1) + 1 FROM node_children WHERE parent_id = ?",
+    )
+    .bind(parent.to_string())
+    .bind(child.to_string())
+    .bind(parent.to_string())
+    .execute(executor)
+    .await
+    .map_err(|e| format!("Failed to add child to parent: {e}"))?;
+    Ok(())
+}
+
+/// Index a text node's content for search.
+async fn index_text(conn: &mut SqliteConnection, node_id: &NodeId, content: &str) -> Result<(), ArborError> {
+    let doc_id: i64 = sqlx::query_scalar("INSERT INTO node_search_docs (node_id, indexed) VALUES (?, 1) RETURNING doc_id")
+        .bind(node_id.to_string())
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to index node: {e}"))?;
+    sqlx::query("INSERT INTO node_search (rowid, content) VALUES (?, ?)")
+        .bind(doc_id)
+        .bind(content)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to index node: {e}"))?;
+    Ok(())
+}
+
+/// Queue an external node for indexing once its handle is resolved.
+async fn add_pending_doc<'e, E>(executor: E, node_id: &NodeId) -> Result<(), ArborError>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
+    sqlx::query("INSERT INTO node_search_docs (node_id) VALUES (?)")
+        .bind(node_id.to_string())
+        .execute(executor)
+        .await
+        .map_err(|e| format!("Failed to queue node for indexing: {e}"))?;
+    Ok(())
+}
+
+/// Give `copy` the search state of `source`: its indexed text, or its place
+/// in the indexing queue.
+async fn copy_search_doc(conn: &mut SqliteConnection, source: &NodeId, copy: &NodeId) -> Result<(), ArborError> {
+    let doc_id: Option<i64> = sqlx::query_scalar(
+        "INSERT INTO node_search_docs (node_id, indexed, attempts, retry_at)
+         SELECT ?, indexed, attempts, retry_at FROM node_search_docs WHERE node_id = ?
+         RETURNING doc_id",
+    )
+    .bind(copy.to_string())
+    .bind(source.to_string())
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to index copied node: {e}"))?;
+
+    if let Some(doc_id) = doc_id {
+        sqlx::query(
+            "INSERT INTO node_search (rowid, content)
+             SELECT ?, content FROM node_search
+             WHERE rowid = (SELECT doc_id FROM node_search_docs WHERE node_id = ?)",
+        )
+        .bind(doc_id)
+        .bind(source.to_string())
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to index copied node: {e}"))?;
+    }
+    Ok(())
+}
+
+/// A subscriber's handle on its tree's notifier; releases the map entry
+/// when the last subscriber's stream is dropped.
+struct NotifierGuard {
+    storage: Arc<ArborStorage>,
+    tree_id: TreeId,
+    notifier: Arc<Notify>,
+}
+
+impl Drop for NotifierGuard {
+    fn drop(&mut self) {
+        self.storage.release_tree_notifier(&self.tree_id, &self.notifier);
     }
 }
 
+/// Delete logged tree changes created before `cutoff`, raising each tree's
+/// floor to the highest seq deleted.
+async fn prune_tree_events(pool: &SqlitePool, cutoff: i64) -> Result<u64, ArborError> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    sqlx::query(
+        "INSERT INTO tree_event_floors (tree_id, pruned_through)
+         SELECT tree_id, MAX(seq) FROM tree_events WHERE created_at < ? GROUP BY tree_id
+         ON CONFLICT (tree_id) DO UPDATE SET pruned_through = MAX(pruned_through, excluded.pruned_through)",
+    )
+    .bind(cutoff)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to record pruned tree events: {e}"))?;
+
+    let deleted = sqlx::query("DELETE FROM tree_events WHERE created_at < ?")
+        .bind(cutoff)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to prune tree events: {e}"))?
+        .rows_affected();
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(deleted)
+}
+
+/// Append `event` to the change log of `tree_id`.
+async fn log_event<'e, E>(executor: E, tree_id: &TreeId, event: &ArborEvent) -> Result<(), ArborError>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
+    let event_json = serde_json::to_string(event).map_err(|e| format!("Failed to serialize event: {e}"))?;
+    sqlx::query("INSERT INTO tree_events (tree_id, event, created_at) VALUES (?, ?, ?)")
+        .bind(tree_id.to_string())
+        .bind(event_json)
+        .bind(current_timestamp())
+        .execute(executor)
+        .await
+        .map_err(|e| format!("Failed to log tree event: {e}"))?;
+    Ok(())
+}
+
 /// Get current Unix timestamp in seconds
 fn current_timestamp() -> i64 {
     SystemTime::now()
@@ -1074,3 +1837,449 @@ fn current_timestamp() -> i64 {
         .unwrap()
         .as_secs() as i64
 }
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use crate::activations::arbor::{QueryOp, SortOrder, TreeCondition};
+    use tempfile::{tempdir, TempDir};
+
+    /// Storage on a fresh database; keep the `TempDir` alive while it's used.
+    pub(crate) async fn test_storage() -> (TempDir, ArborStorage) {
+        let dir = tempdir().unwrap();
+        let config = ArborConfig { db_path: dir.path().join("arbor.db"), auto_cleanup: false, ..ArborConfig::default() };
+        let storage = ArborStorage::new(config).await.unwrap();
+        (dir, storage)
+    }
+
+    #[tokio::test]
+    async fn test_search_ranks_text_and_indexed_handles() {
+        let (_dir, storage) = test_storage().await;
+
+        let tree = storage.tree_create(None, "test").await.unwrap();
+        let root = storage.tree_get(&tree).await.unwrap().root;
+        let question = storage
+            .node_create_text(&tree, Some(root), "Why does the migration fail?".into(), None)
+            .await
+            .unwrap();
+        let answer = storage
+            .node_create_text(&tree, Some(question), "The migration bug: migrations run twice.".into(), None)
+            .await
+            .unwrap();
+        let other = storage.tree_create(None, "test").await.unwrap();
+        let other_root = storage.tree_get(&other).await.unwrap().root;
+        storage.node_create_text(&other, Some(other_root), "Unrelated chatter".into(), None).await.unwrap();
+
+        let hits = storage.search("migration bug", None, 10).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].node_id, answer);
+        assert_eq!(hits[0].path, vec![root, question, answer]);
+        assert!(hits[0].snippet.contains("[migration] [bug]"), "{}", hits[0].snippet);
+
+        let hits = storage.search("migrate", None, 10).await.unwrap();
+        assert_eq!(hits.len(), 2, "porter stemming matches migration/migrations");
+        assert!(storage.search("migration", Some(&other), 10).await.unwrap().is_empty());
+        assert!(storage.search("\"unbalanced", None, 10).await.is_err());
+
+        let handle = Handle::new(Uuid::new_v4(), "1.0.0", "chat").with_meta(vec!["msg-1".into()]);
+        let external = storage.node_create_external(&other, Some(other_root), handle, None).await.unwrap();
+        let pending = storage.search_pending_handles(10).await.unwrap();
+        assert_eq!(pending.iter().map(|(node, _)| *node).collect::<Vec<_>>(), vec![external]);
+
+        storage.index_node_content(&external, "Resolved handle about the rollout").await.unwrap();
+        storage.index_node_content(&external, "Resolved again").await.unwrap();
+        assert!(storage.search_pending_handles(10).await.unwrap().is_empty());
+        let hits = storage.search("rollout", None, 10).await.unwrap();
+        assert_eq!((hits[0].tree_id, hits[0].node_id), (other, external));
+        assert!(storage.search("again", None, 10).await.unwrap().is_empty(), "indexed nodes stay as they are");
+    }
+
+    #[tokio::test]
+    async fn test_search_clamps_limit() {
+        let (_dir, storage) = test_storage().await;
+
+        let tree = storage.tree_create(None, "test").await.unwrap();
+        let root = storage.tree_get(&tree).await.unwrap().root;
+        for i in 0..=MAX_SEARCH_HITS {
+            storage.node_create_text(&tree, Some(root), format!("note {i}"), None).await.unwrap();
+        }
+
+        assert_eq!(storage.search("note", None, 3).await.unwrap().len(), 3);
+        assert!(storage.search("note", None, -1).await.unwrap().is_empty());
+        assert_eq!(storage.search("note", None, i64::MAX).await.unwrap().len(), MAX_SEARCH_HITS as usize);
+    }
+
+    #[tokio::test]
+    async fn test_unresolved_handles_back_off() {
+        let (_dir, storage) = test_storage().await;
+        let retry_at = |node: NodeId| {
+            let storage = &storage;
+            async move {
+                sqlx::query_as::<_, (i64, i64)>("SELECT attempts, retry_at FROM node_search_docs WHERE node_id = ?")
+                    .bind(node.to_string())
+                    .fetch_one(&storage.pool)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let tree = storage.tree_create(None, "test").await.unwrap();
+        let root = storage.tree_get(&tree).await.unwrap().root;
+        let handle = Handle::new(Uuid::new_v4(), "1.0.0", "chat").with_meta(vec!["msg-1".into()]);
+        let external = storage.node_create_external(&tree, Some(root), handle, None).await.unwrap();
+
+        // A failed resolution takes the node out of the queue for a while
+        let before = current_timestamp();
+        storage.index_node_failed(&external).await.unwrap();
+        assert!(storage.search_pending_handles(10).await.unwrap().is_empty());
+        let (attempts, first_retry) = retry_at(external).await;
+        assert_eq!(attempts, 1);
+        assert!(first_retry >= before + INDEX_RETRY_BASE_SECS);
+
+        // ... and each further failure doubles the wait, up to the cap
+        sqlx::query("UPDATE node_search_docs SET retry_at = 0").execute(&storage.pool).await.unwrap();
+        assert_eq!(storage.search_pending_handles(10).await.unwrap().len(), 1);
+        storage.index_node_failed(&external).await.unwrap();
+        let (attempts, second_retry) = retry_at(external).await;
+        assert_eq!(attempts, 2);
+        assert!(second_retry >= before + 2 * INDEX_RETRY_BASE_SECS);
+        sqlx::query("UPDATE node_search_docs SET attempts = 40").execute(&storage.pool).await.unwrap();
+        storage.index_node_failed(&external).await.unwrap();
+        assert!(retry_at(external).await.1 <= current_timestamp() + INDEX_RETRY_MAX_SECS);
+    }
+
+    #[tokio::test]
+    async fn test_search_index_follows_deleted_nodes_and_trees() {
+        let (_dir, storage) = test_storage().await;
+        let indexed = || async {
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM node_search").fetch_one(&storage.pool).await.unwrap()
+        };
+
+        let tree = storage.tree_create(None, "test").await.unwrap();
+        let root = storage.tree_get(&tree).await.unwrap().root;
+        let first = storage.node_create_text(&tree, Some(root), "first draft".into(), None).await.unwrap();
+        storage.node_create_text(&tree, Some(first), "second draft".into(), None).await.unwrap();
+        let other = storage.tree_create(None, "test").await.unwrap();
+        let other_root = storage.tree_get(&other).await.unwrap().root;
+        storage.node_create_text(&other, Some(other_root), "other draft".into(), None).await.unwrap();
+        assert_eq!(indexed().await, 3);
+
+        sqlx::query("DELETE FROM nodes WHERE id = ?").bind(first.to_string()).execute(&storage.pool).await.unwrap();
+        assert_eq!(indexed().await, 2);
+
+        sqlx::query("DELETE FROM trees WHERE id = ?").bind(tree.to_string()).execute(&storage.pool).await.unwrap();
+        assert_eq!(indexed().await, 1);
+        let hits = storage.search("draft", None, 10).await.unwrap();
+        assert_eq!(hits.iter().map(|h| h.tree_id).collect::<Vec<_>>(), [other]);
+    }
+
+    #[tokio::test]
+    async fn test_tree_query_filters_sorts_and_pages() {
+        let (_dir, storage) = test_storage().await;
+
+        let mut trees = Vec::new();
+        for (owner, priority, tags) in [("a", 1, vec!["db"]), ("b", 3, vec!["db", "urgent"]), ("c", 2, vec![])] {
+            let metadata = serde_json::json!({"type": "orcha_monitor", "owner": owner, "priority": priority, "tags": tags});
+            trees.push(storage.tree_create(Some(metadata), "test").await.unwrap());
+        }
+        storage.tree_create(Some(serde_json::json!({"type": "other"})), "test").await.unwrap();
+
+        let cond = |path: &str, op, value| TreeCondition { path: path.into(), op, value };
+        let run = |conditions, limit, offset| {
+            let query = TreeQuery {
+                conditions,
+                sort_by: Some("priority".into()),
+                order: SortOrder::Asc,
+                limit,
+                offset,
+            };
+            let storage = &storage;
+            async move { storage.tree_query(&query).await.unwrap() }
+        };
+
+        let page = run(vec![cond("owner", QueryOp::In, Some(serde_json::json!(["a", "b"])))], None, 0).await;
+        assert_eq!(page.tree_ids, vec![trees[0], trees[1]]);
+        let page = run(vec![cond("tags", QueryOp::Contains, Some(serde_json::json!("urgent")))], None, 0).await;
+        assert_eq!(page.tree_ids, vec![trees[1]]);
+        let page = run(vec![cond("priority", QueryOp::Gte, Some(serde_json::json!(2)))], None, 0).await;
+        assert_eq!(page.tree_ids, vec![trees[2], trees[1]]);
+        let page = run(vec![cond("priority", QueryOp::NotExists, None)], None, 0).await;
+        assert_eq!(page.tree_ids.len(), 1);
+
+        let first = run(vec![cond("type", QueryOp::Eq, Some(serde_json::json!("orcha_monitor")))], Some(2), 0).await;
+        assert_eq!((first.tree_ids.clone(), first.next_offset), (vec![trees[0], trees[2]], Some(2)));
+        let second = run(vec![cond("type", QueryOp::Eq, Some(serde_json::json!("orcha_monitor")))], Some(2), 2).await;
+        assert_eq!((second.tree_ids, second.next_offset), (vec![trees[1]], None));
+        for limit in [0, -1] {
+            let query = TreeQuery { limit: Some(limit), ..TreeQuery::default() };
+            assert!(matches!(storage.tree_query(&query).await, Err(ArborError::InvalidQuery { .. })), "{limit}");
+        }
+
+        let mut monitors = storage.tree_query_by_metadata(&serde_json::json!({"type": "orcha_monitor"})).await.unwrap();
+        monitors.sort_by_key(ToString::to_string);
+        trees.sort_by_key(ToString::to_string);
+        assert_eq!(monitors, trees);
+    }
+
+    #[tokio::test]
+    async fn test_copy_graft_and_cherry_pick_preserve_nodes() {
+        let (_dir, storage) = test_storage().await;
+
+        let source = storage.tree_create(None, "test").await.unwrap();
+        let root = storage.tree_get(&source).await.unwrap().root;
+        let prompt = storage.node_create_text(&source, Some(root), "Design the schema".into(), None).await.unwrap();
+        let handle = Handle::new(Uuid::new_v4(), "1.0.0", "chat").with_meta(vec!["msg-1".into()]);
+        let reply = storage
+            .node_create_external(&source, Some(prompt), handle.clone(), Some(serde_json::json!({"role": "assistant"})))
+            .await
+            .unwrap();
+        let follow_up = storage.node_create_text(&source, Some(reply), "Now the API".into(), None).await.unwrap();
+        sqlx::query("INSERT INTO node_refs (node_id, owner_id, count, claimed_at) VALUES (?, 'cone', 2, 0)")
+            .bind(reply.to_string())
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE nodes SET ref_count = 2, state = 'scheduled_delete', scheduled_deletion_at = 5, created_at = 0 WHERE id = ?")
+            .bind(reply.to_string())
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+
+        // Copy the conversation prefix into a fresh tree
+        let (copy_tree, copies) = storage.tree_copy_path(&source, &reply, None, "other").await.unwrap();
+        let copied = storage.tree_get(&copy_tree).await.unwrap();
+        assert_eq!(copies.len(), 2);
+        assert_eq!(storage.node_get_path(&copy_tree, &copies[1]).await.unwrap(), [vec![copied.root], copies.clone()].concat());
+        let copied_reply = &copied.nodes[&copies[1]];
+        assert_eq!(copied_reply.data, NodeType::External { handle });
+        assert_eq!(copied_reply.metadata, Some(serde_json::json!({"role": "assistant"})));
+        // Copies keep their owners and counts but are created now as active nodes
+        assert_eq!(copied_reply.refs.as_ref().unwrap().owners.get("cone"), Some(&2));
+        assert_eq!(copied_reply.state, Some(ResourceState::Active));
+        assert_eq!(copied_reply.scheduled_deletion_at, None);
+        assert!(copied_reply.created_at > 0);
+        let ref_count: i64 = sqlx::query_scalar("SELECT ref_count FROM nodes WHERE id = ?")
+            .bind(copies[1].to_string())
+            .fetch_one(&storage.pool)
+            .await
+            .unwrap();
+        assert_eq!(ref_count, 2);
+        let source_reply = &storage.tree_get(&source).await.unwrap().nodes[&reply];
+        assert_eq!(source_reply.refs.as_ref().unwrap().owners.get("cone"), Some(&2));
+        let hits = storage.search("schema", Some(&copy_tree), 10).await.unwrap();
+        assert_eq!(hits.iter().map(|h| h.node_id).collect::<Vec<_>>(), [copies[0]]);
+
+        // Graft the whole source path under the copy's head
+        sqlx::query("UPDATE trees SET updated_at = 0 WHERE id = ?")
+            .bind(copy_tree.to_string())
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+        let grafted = storage.node_graft_path(&source, &follow_up, &copy_tree, &copies[1]).await.unwrap();
+        assert_eq!(grafted.len(), 3);
+        assert!(storage.tree_get(&copy_tree).await.unwrap().updated_at > 0);
+        assert_eq!(storage.node_get_children(&copy_tree, &copies[1]).await.unwrap(), [grafted[0]]);
+
+        // Cherry-pick just the last two nodes onto the source root
+        let picked = storage.node_cherry_pick(&source, &reply, &follow_up, &source, &root).await.unwrap();
+        assert_eq!(storage.node_get_path(&source, &picked[1]).await.unwrap(), [root, picked[0], picked[1]]);
+        assert!(matches!(
+            storage.node_cherry_pick(&source, &follow_up, &reply, &source, &root).await,
+            Err(ArborError::InvalidState { .. })
+        ));
+        assert!(matches!(
+            storage.node_graft_path(&source, &reply, &copy_tree, &prompt).await,
+            Err(ArborError::NodeNotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_failed_copy_leaves_nothing_behind() {
+        let (_dir, storage) = test_storage().await;
+        let count = |table: &'static str| {
+            let storage = &storage;
+            async move {
+                sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) FROM {table}"))
+                    .fetch_one(&storage.pool)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let source = storage.tree_create(None, "test").await.unwrap();
+        let root = storage.tree_get(&source).await.unwrap().root;
+        let prompt = storage.node_create_text(&source, Some(root), "Design the schema".into(), None).await.unwrap();
+        let reply = storage.node_create_text(&source, Some(prompt), "Use two tables".into(), None).await.unwrap();
+
+        // Fail on the second node, after the tree and the first copy are written
+        sqlx::query(
+            "CREATE TRIGGER fail_copy BEFORE INSERT ON nodes WHEN NEW.content = 'Use two tables'
+             BEGIN SELECT RAISE(ABORT, 'copy failed'); END",
+        )
+        .execute(&storage.pool)
+        .await
+        .unwrap();
+        let before = (count("trees").await, count("nodes").await, count("tree_events").await, count("node_search").await);
+
+        assert!(storage.tree_copy_path(&source, &reply, None, "other").await.is_err());
+        assert!(storage.node_graft_path(&source, &reply, &source, &root).await.is_err());
+        let after = (count("trees").await, count("nodes").await, count("tree_events").await, count("node_search").await);
+        assert_eq!(after, before);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_replays_then_tails_from_cursor() {
+        use futures::StreamExt;
+        use std::time::Duration;
+
+        async fn next(events: &mut (impl Stream<Item = ArborEventEnvelope> + Unpin)) -> ArborEventEnvelope {
+            tokio::time::timeout(Duration::from_secs(5), events.next()).await.unwrap().unwrap()
+        }
+
+        let (_dir, storage) = test_storage().await;
+        let storage = Arc::new(storage);
+
+        let tree = storage.tree_create(None, "test").await.unwrap();
+        let root = storage.tree_get(&tree).await.unwrap().root;
+        let first = storage.node_create_text(&tree, Some(root), "one".into(), None).await.unwrap();
+        storage.tree_update_metadata(&tree, serde_json::json!({"name": "demo"})).await.unwrap();
+
+        let mut events = Box::pin(ArborStorage::subscribe_stream(storage.clone(), tree, None));
+        assert!(matches!(next(&mut events).await.event, ArborEvent::TreeCreated { .. }));
+        let created = next(&mut events).await;
+        assert!(matches!(created.event, ArborEvent::NodeCreated { node_id, .. } if node_id == first));
+        let updated = next(&mut events).await;
+        assert!(matches!(updated.event, ArborEvent::MetadataUpdated { .. }));
+
+        // Live changes arrive as they happen
+        let second = storage.node_create_text(&tree, Some(first), "two".into(), None).await.unwrap();
+        let live = next(&mut events).await;
+        assert!(matches!(live.event, ArborEvent::NodeCreated { node_id, .. } if node_id == second));
+        storage.tree_release(&tree, "test", 1).await.unwrap();
+        assert!(matches!(next(&mut events).await.event, ArborEvent::TreeReleased { new_count: 0, .. }));
+        assert!(matches!(next(&mut events).await.event, ArborEvent::TreeScheduledDeletion { .. }));
+
+        // A reconnecting client resumes right after its cursor
+        let resumed: Vec<_> = ArborStorage::subscribe_stream(storage.clone(), tree, Some(updated.seq))
+            .take(3)
+            .collect()
+            .await;
+        assert_eq!(resumed[0].seq, live.seq);
+        assert!(matches!(resumed[2].event, ArborEvent::TreeScheduledDeletion { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_reports_pruned_gap() {
+        use futures::StreamExt;
+
+        let (_dir, storage) = test_storage().await;
+        let storage = Arc::new(storage);
+
+        let tree = storage.tree_create(None, "test").await.unwrap();
+        let root = storage.tree_get(&tree).await.unwrap().root;
+        let first = storage.node_create_text(&tree, Some(root), "one".into(), None).await.unwrap();
+        let second = storage.node_create_text(&tree, Some(first), "two".into(), None).await.unwrap();
+        let events = storage.tree_events_after(&tree, 0).await.unwrap();
+        assert_eq!(events.len(), 3);
+
+        // Age the first two changes past the retention window and prune them
+        sqlx::query("UPDATE tree_events SET created_at = 0 WHERE seq <= ?")
+            .bind(events[1].seq as i64)
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+        assert_eq!(storage.prune_tree_events().await.unwrap(), 2);
+
+        // A cursor inside the pruned range gets the gap, then what is left
+        for cursor in [None, Some(events[0].seq)] {
+            let resumed: Vec<_> = ArborStorage::subscribe_stream(storage.clone(), tree, cursor).take(2).collect().await;
+            assert!(matches!(
+                resumed[0].event,
+                ArborEvent::EventsPruned { tree_id, through_seq } if tree_id == tree && through_seq == events[1].seq
+            ));
+            assert_eq!(resumed[0].seq, events[1].seq);
+            assert!(matches!(resumed[1].event, ArborEvent::NodeCreated { node_id, .. } if node_id == second));
+        }
+
+        // A cursor past the pruned range resumes with no gap
+        let resumed: Vec<_> = ArborStorage::subscribe_stream(storage.clone(), tree, Some(events[1].seq)).take(1).collect().await;
+        assert_eq!(resumed[0].seq, events[2].seq);
+
+        // Pruning again never lowers the floor
+        assert_eq!(storage.prune_tree_events().await.unwrap(), 0);
+        assert_eq!(storage.tree_events_floor(&tree).await.unwrap(), events[1].seq);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_ends_on_pruned_archive() {
+        use futures::StreamExt;
+
+        let (_dir, storage) = test_storage().await;
+        let storage = Arc::new(storage);
+
+        let tree = storage.tree_create(None, "test").await.unwrap();
+        storage.tree_release(&tree, "test", 1).await.unwrap();
+        sqlx::query("UPDATE trees SET scheduled_deletion_at = 0").execute(&storage.pool).await.unwrap();
+        assert_eq!(storage.cleanup_scheduled_trees().await.unwrap(), 1);
+
+        // Prune the whole log, TreeArchived included
+        sqlx::query("UPDATE tree_events SET created_at = 0").execute(&storage.pool).await.unwrap();
+        assert!(storage.prune_tree_events().await.unwrap() > 0);
+        let floor = storage.tree_events_floor(&tree).await.unwrap();
+
+        // Both a fresh subscriber and one past the gap still see the tree end
+        for cursor in [None, Some(floor)] {
+            let events: Vec<_> = ArborStorage::subscribe_stream(storage.clone(), tree, cursor).collect().await;
+            assert!(matches!(
+                events.last().unwrap().event,
+                ArborEvent::TreeArchived { tree_id, .. } if tree_id == tree
+            ));
+            assert_eq!(events.len(), if cursor.is_none() { 2 } else { 1 });
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tree_notifiers_are_released() {
+        use futures::StreamExt;
+
+        let (_dir, storage) = test_storage().await;
+        let storage = Arc::new(storage);
+        let watched = |storage: &ArborStorage| storage.tree_notifiers.read().unwrap().len();
+
+        let tree = storage.tree_create(None, "test").await.unwrap();
+        let mut first = Box::pin(ArborStorage::subscribe_stream(storage.clone(), tree, None));
+        let mut second = Box::pin(ArborStorage::subscribe_stream(storage.clone(), tree, None));
+        first.next().await.unwrap();
+        second.next().await.unwrap();
+        assert_eq!(watched(&storage), 1);
+
+        drop(first);
+        assert_eq!(watched(&storage), 1, "still watched by the second subscriber");
+        drop(second);
+        assert_eq!(watched(&storage), 0);
+
+        // Archiving a tree drops its notifier even while a stream holds on
+        let mut events = Box::pin(ArborStorage::subscribe_stream(storage.clone(), tree, None));
+        events.next().await.unwrap();
+        storage.tree_release(&tree, "test", 1).await.unwrap();
+        sqlx::query("UPDATE trees SET scheduled_deletion_at = 0").execute(&storage.pool).await.unwrap();
+        assert_eq!(storage.cleanup_scheduled_trees().await.unwrap(), 1);
+        assert_eq!(watched(&storage), 0);
+        let rest: Vec<_> = events.collect().await;
+        assert!(matches!(rest.last().unwrap().event, ArborEvent::TreeArchived { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_failed_node_create_logs_nothing() {
+        let (_dir, storage) = test_storage().await;
+
+        let tree = storage.tree_create(None, "test").await.unwrap();
+        let before = storage.tree_events_after(&tree, 0).await.unwrap().len();
+
+        // The missing parent fails the child link after the node row is written
+        assert!(storage.node_create_text(&tree, Some(NodeId::new()), "orphan".into(), None).await.is_err());
+        assert_eq!(storage.tree_events_after(&tree, 0).await.unwrap().len(), before);
+        assert_eq!(storage.tree_get(&tree).await.unwrap().nodes.len(), 1);
+        assert!(storage.search("orphan", None, 10).await.unwrap().is_empty());
+    }
+}
@@ -406,6 +406,103 @@ impl From<&Tree> for TreeSkeleton {
     }
 }
 
+/// A node matched by a full-text search
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct SearchHit {
+    pub tree_id: TreeId,
+    pub node_id: NodeId,
+    /// Excerpt around the match, with matched terms wrapped in `[` `]`
+    pub snippet: String,
+    /// Relevance (negated BM25); higher is a better match
+    pub score: f64,
+    /// Node IDs from the tree root down to the matched node
+    pub path: Vec<NodeId>,
+}
+
+/// A node on one side of a branch comparison
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct DiffEntry {
+    pub node_id: NodeId,
+    /// Text content, or what the node's handle resolves to
+    pub content: String,
+}
+
+/// Comparison of two branches, from where they part ways
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct TreeDiff {
+    /// Last node the left path shares with the right one
+    pub left_ancestor: Option<NodeId>,
+    /// Its counterpart on the right path (the same node within one tree)
+    pub right_ancestor: Option<NodeId>,
+    /// Number of shared nodes from the root down to the ancestor
+    pub shared: usize,
+    /// Left path below the ancestor, down to the left node
+    pub left: Vec<DiffEntry>,
+    /// Right path below the ancestor, down to the right node
+    pub right: Vec<DiffEntry>,
+    /// Side-by-side text rendering of `left` and `right`
+    pub render: String,
+}
+
+/// Comparison applied by a `TreeCondition`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryOp {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    /// Field equals one of the values in an array
+    In,
+    /// Array field has the value as an element, or string field has it as a substring
+    Contains,
+    Exists,
+    NotExists,
+}
+
+/// One condition of a tree query
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct TreeCondition {
+    /// `created_at` / `updated_at` (tree timestamps), otherwise a dotted
+    /// path into the tree metadata (e.g. `ticket.owner`)
+    pub path: String,
+    pub op: QueryOp,
+    /// Operand; unused by `exists` / `not_exists`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<serde_json::Value>,
+}
+
+/// Sort direction for tree queries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Query over active trees: all conditions must hold
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TreeQuery {
+    pub conditions: Vec<TreeCondition>,
+    /// Field to sort by, same syntax as `TreeCondition::path` (default: `created_at`)
+    pub sort_by: Option<String>,
+    pub order: SortOrder,
+    /// Page size (`None` for all matches)
+    pub limit: Option<i64>,
+    pub offset: i64,
+}
+
+/// One page of tree query results
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeQueryPage {
+    pub tree_ids: Vec<TreeId>,
+    /// Offset of the next page, if there are more matches
+    pub next_offset: Option<i64>,
+}
+
 // ============================================================================
 // Stream Events
 // ============================================================================
@@ -427,6 +524,9 @@ pub enum ArborEvent {
     #[serde(rename = "tree_list")]
     TreeList { tree_ids: Vec<TreeId> },
 
+    #[serde(rename = "metadata_updated")]
+    MetadataUpdated { tree_id: TreeId, metadata: serde_json::Value },
+
     // Reference counting events
     #[serde(rename = "tree_claimed")]
     TreeClaimed {
@@ -442,12 +542,21 @@ pub enum ArborEvent {
         new_count: i64,
     },
 
+    /// A tree scheduled for deletion was claimed again
+    #[serde(rename = "tree_reactivated")]
+    TreeReactivated { tree_id: TreeId },
+
     #[serde(rename = "tree_scheduled_deletion")]
     TreeScheduledDeletion { tree_id: TreeId, scheduled_at: i64 },
 
     #[serde(rename = "tree_archived")]
     TreeArchived { tree_id: TreeId, archived_at: i64 },
 
+    /// Logged changes up to `through_seq` were pruned before they could be
+    /// replayed; re-read the tree to catch up
+    #[serde(rename = "events_pruned")]
+    EventsPruned { tree_id: TreeId, through_seq: u64 },
+
     #[serde(rename = "tree_refs")]
     TreeRefs { tree_id: TreeId, refs: ResourceRefs },
 
@@ -566,6 +675,35 @@ pub enum ArborEvent {
     #[serde(rename = "tree_render")]
     TreeRender { tree_id: TreeId, render: String },
 
+    #[serde(rename = "tree_query_results")]
+    TreeQueryResults {
+        tree_ids: Vec<TreeId>,
+        next_offset: Option<i64>,
+    },
+
+    // Structural editing
+    #[serde(rename = "tree_copied")]
+    TreeCopied {
+        source_tree_id: TreeId,
+        tree_id: TreeId,
+        /// Copies below the new root, top down; the last is the new head
+        node_ids: Vec<NodeId>,
+    },
+
+    #[serde(rename = "nodes_copied")]
+    NodesCopied {
+        tree_id: TreeId,
+        /// Copies in the target tree, top down; the last is the new head
+        node_ids: Vec<NodeId>,
+    },
+
+    #[serde(rename = "tree_diff")]
+    TreeDiff { diff: TreeDiff },
+
+    // Search
+    #[serde(rename = "search_results")]
+    SearchResults { query: String, hits: Vec<SearchHit> },
+
     // View operations
     #[serde(rename = "view_created")]
     ViewCreated {
@@ -605,6 +743,18 @@ pub enum ArborEvent {
     Err { message: String },
 }
 
+/// A tree change paired with its durable sequence number.
+///
+/// Callers should keep the last `seq` they processed and pass it as
+/// `after_seq` to `tree_subscribe` on reconnect to pick up exactly where
+/// they left off.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ArborEventEnvelope {
+    /// Monotonically increasing sequence number assigned when the change was logged
+    pub seq: u64,
+    pub event: ArborEvent,
+}
+
 // ============================================================================
 // Error Types
 // ============================================================================
@@ -622,6 +772,8 @@ pub enum ArborError {
     InvalidState { message: String },
     #[error("Initialization error: {detail}")]
     InitError { detail: String },
+    #[error("Invalid query: {message}")]
+    InvalidQuery { message: String },
 }
 
 impl From<String> for ArborError {
@@ -1,17 +1,33 @@
 use super::executor::BashExecutor;
-use super::types::BashEvent;
+use super::policy::ExecPolicy;
+use super::sessions::PtySessions;
+use super::types::{BashEvent, BashOptions, PtySessionEvent, PtySessionOptions, SessionError};
+use async_stream::stream;
 use futures::Stream;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
 
 /// Bash activation - execute shell commands and stream output
 #[derive(Clone)]
 pub struct Bash {
     executor: BashExecutor,
+    sessions: PtySessions,
 }
 
 impl Bash {
-    pub const fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             executor: BashExecutor::new(),
+            sessions: PtySessions::new(),
+        }
+    }
+
+    /// A Bash activation whose commands and sessions are held to `policy`
+    pub fn with_policy(policy: Arc<ExecPolicy>) -> Self {
+        Self {
+            executor: BashExecutor::with_policy(policy.clone()),
+            sessions: PtySessions::with_policy(policy),
         }
     }
 
@@ -45,9 +61,250 @@ version = "1.0.0",
 description = "Execute bash commands and stream output")]
 impl Bash {
     /// Execute a bash command and stream stdout, stderr, and exit code
+    #[plexus_macros::method(params(
+        command = "Command line passed to `bash -c`",
+        cwd = "Working directory (default: the server's)",
+        env = "Environment variables to set for the command",
+        clear_env = "Start from an empty environment instead of the server's (default: false)",
+        stdin = "Text written to the command's stdin",
+        timeout_secs = "Kill the command's process group after this many seconds",
+        max_output_lines = "Stop streaming after this many stdout + stderr lines (default: unlimited)",
+        max_output_bytes = "Stop streaming after this many bytes of stdout + stderr (default: unlimited)"
+    ))]
+    async fn execute(
+        &self,
+        command: String,
+        cwd: Option<String>,
+        env: Option<HashMap<String, String>>,
+        clear_env: Option<bool>,
+        stdin: Option<String>,
+        timeout_secs: Option<u64>,
+        max_output_lines: Option<u64>,
+        max_output_bytes: Option<u64>,
+    ) -> impl Stream<Item = BashEvent> + Send + 'static {
+        let options = BashOptions {
+            cwd,
+            env: env.unwrap_or_default(),
+            clear_env: clear_env.unwrap_or(false),
+            stdin,
+            timeout_secs,
+            max_output_lines,
+            max_output_bytes,
+        };
+        self.executor.execute_with(&command, options).await
+    }
+
+    /// Open an interactive shell on a pseudo-terminal.
+    ///
+    /// Shell state (cwd, exported variables, activated virtualenvs) persists
+    /// across `write` calls until the session is closed. The exec policy can
+    /// refuse sessions outright or confine their working directory.
+    #[plexus_macros::method(params(
+        shell = "Shell program to run (default: bash)",
+        args = "Arguments passed to the shell",
+        cwd = "Working directory the shell starts in (default: the server's)",
+        env = "Environment variables to set for the shell",
+        clear_env = "Start from an empty environment instead of the server's (default: false)",
+        cols = "Terminal width in columns (default: 80)",
+        rows = "Terminal height in rows (default: 24)"
+    ))]
+    async fn open(
+        &self,
+        shell: Option<String>,
+        args: Option<Vec<String>>,
+        cwd: Option<String>,
+        env: Option<HashMap<String, String>>,
+        clear_env: Option<bool>,
+        cols: Option<u16>,
+        rows: Option<u16>,
+    ) -> impl Stream<Item = PtySessionEvent> + Send + 'static {
+        let defaults = PtySessionOptions::default();
+        let options = PtySessionOptions {
+            shell: shell.unwrap_or(defaults.shell),
+            args: args.unwrap_or_default(),
+            cwd,
+            env: env.unwrap_or_default(),
+            clear_env: clear_env.unwrap_or(false),
+            cols: cols.unwrap_or(defaults.cols),
+            rows: rows.unwrap_or(defaults.rows),
+        };
+        let result = self.sessions.open(options).await;
+
+        stream! {
+            match result {
+                Ok(info) => yield PtySessionEvent::Session { info },
+                Err(SessionError::Policy(violation)) => yield PtySessionEvent::PolicyViolation { violation },
+                Err(e) => yield PtySessionEvent::Error { message: e.to_string() },
+            }
+        }
+    }
+
+    /// List open interactive sessions
     #[plexus_macros::method]
-    async fn execute(&self, command: String) -> impl Stream<Item = BashEvent> + Send + 'static {
-        self.executor.execute(&command).await
+    async fn sessions(&self) -> impl Stream<Item = PtySessionEvent> + Send + 'static {
+        let infos = self.sessions.list().await;
+        futures::stream::iter(infos.into_iter().map(|info| PtySessionEvent::Session { info }))
+    }
+
+    /// Send input to an interactive session
+    #[plexus_macros::method(params(
+        session_id = "Session returned by open",
+        data = "Raw input; include a trailing newline to run a command"
+    ))]
+    async fn write(&self, session_id: Uuid, data: String) -> impl Stream<Item = PtySessionEvent> + Send + 'static {
+        PtySessionActivation::new(session_id, self.sessions.clone()).write(data).await
+    }
+
+    /// Read an interactive session's buffered output and return
+    #[plexus_macros::method(params(
+        session_id = "Session returned by open",
+        since = "Byte offset to read from (default: 0, the oldest buffered output)"
+    ))]
+    async fn read(&self, session_id: Uuid, since: Option<u64>) -> impl Stream<Item = PtySessionEvent> + Send + 'static {
+        self.sessions.read(session_id, since.unwrap_or(0), false)
+    }
+
+    /// Stream an interactive session's output until the shell exits
+    #[plexus_macros::method(streaming, params(
+        session_id = "Session returned by open",
+        since = "Byte offset to start from (default: 0, the oldest buffered output)"
+    ))]
+    async fn subscribe(&self, session_id: Uuid, since: Option<u64>) -> impl Stream<Item = PtySessionEvent> + Send + 'static {
+        self.sessions.read(session_id, since.unwrap_or(0), true)
+    }
+
+    /// Change an interactive session's terminal size
+    #[plexus_macros::method(params(
+        session_id = "Session returned by open",
+        cols = "Terminal width in columns",
+        rows = "Terminal height in rows"
+    ))]
+    async fn resize(&self, session_id: Uuid, cols: u16, rows: u16) -> impl Stream<Item = PtySessionEvent> + Send + 'static {
+        PtySessionActivation::new(session_id, self.sessions.clone()).resize(cols, rows).await
+    }
+
+    /// Kill an interactive session's shell and forget the session
+    #[plexus_macros::method(params(session_id = "Session returned by open"))]
+    async fn close(&self, session_id: Uuid) -> impl Stream<Item = PtySessionEvent> + Send + 'static {
+        PtySessionActivation::new(session_id, self.sessions.clone()).close().await
+    }
+
+    /// Look up an interactive session by its ID and return a typed
+    /// per-session namespace.
+    ///
+    /// `bash.session <id>.write(…)` routes through this gate. Resolution
+    /// fails with `None` if the ID doesn't parse as a UUID or no session
+    /// with that ID is open.
+    #[plexus_macros::child(list = "session_ids")]
+    async fn session(&self, id: &str) -> Option<PtySessionActivation> {
+        let session_id = Uuid::parse_str(id).ok()?;
+        self.sessions
+            .contains(&session_id)
+            .await
+            .then(|| PtySessionActivation::new(session_id, self.sessions.clone()))
+    }
+
+    /// Enumerate session IDs for `ChildRouter::list_children` tab-completion.
+    async fn session_ids(&self) -> impl Stream<Item = String> + Send + '_ {
+        let ids = self.sessions.ids().await;
+        futures::stream::iter(ids.into_iter().map(|id| id.to_string()))
+    }
+}
+
+/// Per-session activation returned by the `bash.session` child gate.
+///
+/// Pins the session ID at construction so method arguments carry only the
+/// input, offset or size. Cheap to clone — the registry is shared.
+#[derive(Clone)]
+pub struct PtySessionActivation {
+    session_id: Uuid,
+    sessions: PtySessions,
+}
+
+impl PtySessionActivation {
+    /// Construct a `PtySessionActivation` bound to `session_id`.
+    ///
+    /// Constructed by `Bash::session(id)` after confirming the session is
+    /// open; methods on an unknown ID yield `Error` events.
+    pub const fn new(session_id: Uuid, sessions: PtySessions) -> Self {
+        Self { session_id, sessions }
+    }
+
+    /// The session this activation is bound to.
+    pub const fn session_id(&self) -> Uuid {
+        self.session_id
+    }
+}
+
+#[plexus_macros::activation(
+    namespace = "session",
+    version = "1.0.0",
+    description = "Interactive shell on a pseudo-terminal"
+)]
+impl PtySessionActivation {
+    /// Fetch this session's size, pid and exit status.
+    #[plexus_macros::method]
+    pub(super) async fn info(&self) -> impl Stream<Item = PtySessionEvent> + Send + 'static {
+        let result = self.sessions.info(&self.session_id).await;
+        stream! {
+            match result {
+                Ok(info) => yield PtySessionEvent::Session { info },
+                Err(e) => yield PtySessionEvent::Error { message: e.to_string() },
+            }
+        }
+    }
+
+    /// Send input to the shell.
+    #[plexus_macros::method(params(data = "Raw input; include a trailing newline to run a command"))]
+    pub(super) async fn write(&self, data: String) -> impl Stream<Item = PtySessionEvent> + Send + 'static {
+        let result = self.sessions.write(&self.session_id, data).await;
+        stream! {
+            match result {
+                Ok(bytes) => yield PtySessionEvent::Written { bytes },
+                Err(e) => yield PtySessionEvent::Error { message: e.to_string() },
+            }
+        }
+    }
+
+    /// Read buffered output and return.
+    #[plexus_macros::method(params(
+        since = "Byte offset to read from (default: 0, the oldest buffered output)"
+    ))]
+    pub(super) async fn read(&self, since: Option<u64>) -> impl Stream<Item = PtySessionEvent> + Send + 'static {
+        self.sessions.read(self.session_id, since.unwrap_or(0), false)
+    }
+
+    /// Stream output until the shell exits.
+    #[plexus_macros::method(streaming, params(
+        since = "Byte offset to start from (default: 0, the oldest buffered output)"
+    ))]
+    pub(super) async fn subscribe(&self, since: Option<u64>) -> impl Stream<Item = PtySessionEvent> + Send + 'static {
+        self.sessions.read(self.session_id, since.unwrap_or(0), true)
+    }
+
+    /// Change the terminal size.
+    #[plexus_macros::method(params(cols = "Terminal width in columns", rows = "Terminal height in rows"))]
+    pub(super) async fn resize(&self, cols: u16, rows: u16) -> impl Stream<Item = PtySessionEvent> + Send + 'static {
+        let result = self.sessions.resize(&self.session_id, cols, rows).await;
+        stream! {
+            match result {
+                Ok(info) => yield PtySessionEvent::Session { info },
+                Err(e) => yield PtySessionEvent::Error { message: e.to_string() },
+            }
+        }
+    }
+
+    /// Kill the shell and forget the session.
+    #[plexus_macros::method]
+    pub(super) async fn close(&self) -> impl Stream<Item = PtySessionEvent> + Send + 'static {
+        let session_id = self.session_id;
+        let result = self.sessions.close(&session_id).await;
+        stream! {
+            match result {
+                Ok(()) => yield PtySessionEvent::Closed { session_id },
+                Err(e) => yield PtySessionEvent::Error { message: e.to_string() },
+            }
+        }
     }
 }
 
@@ -91,9 +348,9 @@ mod tests {
 
         assert_eq!(schema.namespace, "bash");
         assert_eq!(schema.version, "1.0.0");
-        // 2 methods: user-defined "execute" + auto-generated "schema"
-        assert_eq!(schema.methods.len(), 2);
-        assert!(schema.is_leaf(), "bash should be a leaf plugin");
+        for name in ["execute", "open", "sessions", "write", "read", "subscribe", "resize", "close", "session", "schema"] {
+            assert!(schema.methods.iter().any(|m| m.name == name), "missing method {name}");
+        }
 
         let execute = schema.methods.iter().find(|m| m.name == "execute")
             .expect("should have an 'execute' method");
@@ -110,4 +367,23 @@ mod tests {
         assert!(returns_json.contains("stderr") || returns_json.contains("Stderr"));
         assert!(returns_json.contains("exit") || returns_json.contains("Exit"));
     }
+
+    #[tokio::test]
+    async fn test_session_child_gate() {
+        use crate::plexus::{ChildRouter, MethodRole};
+
+        let bash = Bash::new();
+        let schema = bash.plugin_schema();
+        let session = schema.methods.iter().find(|m| m.name == "session").expect("session gate");
+        assert!(matches!(
+            &session.role,
+            MethodRole::DynamicChild { list_method: Some(list), .. } if list == "session_ids"
+        ));
+
+        let info = bash.sessions.open(PtySessionOptions::default()).await.unwrap();
+        assert!(bash.get_child(&info.session_id.to_string()).await.is_some());
+        assert!(bash.get_child(&Uuid::new_v4().to_string()).await.is_none());
+        assert!(bash.get_child("not-a-uuid").await.is_none());
+        bash.sessions.close(&info.session_id).await.unwrap();
+    }
 }
@@ -1,19 +1,114 @@
-use super::types::{BashOutput, ExecutorError};
+use super::policy::ExecPolicy;
+use super::types::{BashOptions, BashOutput, ExecutorError};
 use async_stream::stream;
 use futures::Stream;
 use std::pin::Pin;
+use std::process::Stdio;
 use std::sync::Arc;
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command;
-use tokio::sync::Mutex;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::time::{Duration, Instant};
 
 /// Core bash executor - can be used programmatically without RPC
+///
+/// Every command is checked against the executor's `ExecPolicy` before it is
+/// spawned, and runs under the policy's resource limits.
 #[derive(Clone)]
-pub struct BashExecutor;
+pub struct BashExecutor {
+    policy: Arc<ExecPolicy>,
+}
+
+/// SIGKILL every process in the group led by `child`.
+///
+/// Commands are spawned as their own process group, so the group id is the
+/// child's pid. `kill(1)` does the signalling to keep this module free of
+/// `unsafe`; if it can't be run, only the direct child is killed.
+async fn kill_process_group(child: &mut Child) {
+    let Some(pid) = child.id() else { return };
+    let group = format!("-{pid}");
+    let killed = Command::new("kill")
+        .args(["-KILL", "--", &group])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .is_ok_and(|status| status.success());
+    if !killed {
+        let _ = child.start_kill();
+    }
+}
+
+/// Which of the child's output pipes a line came from.
+#[derive(Clone, Copy)]
+enum Pipe {
+    Stdout,
+    Stderr,
+}
+
+/// Line and byte budget shared by stdout and stderr.
+///
+/// Once a line doesn't fit, it and every later line are dropped and counted,
+/// so what was streamed is always a prefix of the real output.
+struct OutputBudget {
+    max_lines: Option<u64>,
+    max_bytes: Option<u64>,
+    lines: u64,
+    bytes: u64,
+    dropped_lines: u64,
+    dropped_bytes: u64,
+}
+
+impl OutputBudget {
+    const fn new(max_lines: Option<u64>, max_bytes: Option<u64>) -> Self {
+        Self {
+            max_lines,
+            max_bytes,
+            lines: 0,
+            bytes: 0,
+            dropped_lines: 0,
+            dropped_bytes: 0,
+        }
+    }
+
+    /// Charge `line` to the budget; false means it should be dropped.
+    fn admit(&mut self, line: &str) -> bool {
+        let len = line.len() as u64;
+        let fits = self.dropped_lines == 0
+            && self.max_lines.is_none_or(|max| self.lines < max)
+            && self.max_bytes.is_none_or(|max| self.bytes + len <= max);
+        if fits {
+            self.lines += 1;
+            self.bytes += len;
+        } else {
+            self.dropped_lines += 1;
+            self.dropped_bytes += len;
+        }
+        fits
+    }
+}
+
+/// Sleep until `deadline`, or forever when there is none.
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
 
 impl BashExecutor {
-    pub const fn new() -> Self {
-        Self
+    /// An executor with the permissive policy
+    pub fn new() -> Self {
+        Self::with_policy(Arc::new(ExecPolicy::permissive()))
+    }
+
+    /// An executor that enforces `policy`
+    pub const fn with_policy(policy: Arc<ExecPolicy>) -> Self {
+        Self { policy }
+    }
+
+    pub fn policy(&self) -> &ExecPolicy {
+        &self.policy
     }
 
     /// Execute a bash command and stream the output
@@ -23,18 +118,56 @@ impl BashExecutor {
     pub async fn execute(
         &self,
         command: &str,
+    ) -> Pin<Box<dyn Stream<Item = BashOutput> + Send + 'static>> {
+        self.execute_with(command, BashOptions::default()).await
+    }
+
+    /// Execute a bash command with a working directory, environment, stdin
+    /// and time limit.
+    ///
+    /// A command the policy rejects yields a single `PolicyViolation` and is
+    /// never spawned.
+    ///
+    /// Stdout and stderr lines are yielded in the order they arrive. Lines past
+    /// the output budget are dropped and reported by a single `Truncated`. On
+    /// timeout the whole process group is killed, `TimedOut` is yielded and
+    /// the stream still ends with the `Exit` of the reaped process.
+    pub async fn execute_with(
+        &self,
+        command: &str,
+        options: BashOptions,
     ) -> Pin<Box<dyn Stream<Item = BashOutput> + Send + 'static>> {
         let command = command.to_string();
+        let policy = self.policy.clone();
 
         Box::pin(stream! {
+            let cwd = match policy.check(&command, options.cwd.as_deref()) {
+                Ok(cwd) => cwd,
+                Err(violation) => {
+                    tracing::warn!(%violation, command = %command, "Bash command rejected by exec policy");
+                    yield BashOutput::PolicyViolation { violation };
+                    return;
+                }
+            };
+
+            let (program, args) = policy.wrap("bash", &["-c".to_string(), command.clone()]);
+            let mut cmd = Command::new(program);
+            cmd.args(&args)
+                .stdin(if options.stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .process_group(0)
+                .kill_on_drop(true);
+            if let Some(cwd) = &cwd {
+                cmd.current_dir(cwd);
+            }
+            if options.clear_env {
+                cmd.env_clear();
+            }
+            cmd.envs(&options.env);
+
             // Spawn the bash process
-            let mut child = match Command::new("bash")
-                .arg("-c")
-                .arg(&command)
-                .stdout(std::process::Stdio::piped())
-                .stderr(std::process::Stdio::piped())
-                .spawn()
-            {
+            let mut child = match cmd.spawn() {
                 Ok(child) => child,
                 Err(e) => {
                     let err = ExecutorError::SpawnFailed {
@@ -46,6 +179,16 @@ impl BashExecutor {
                     return;
                 }
             };
+            let deadline = options
+                .timeout_secs
+                .map(|secs| Instant::now() + Duration::from_secs(secs));
+
+            // Feed stdin from a task so a command that doesn't read it can't block us
+            if let (Some(input), Some(mut child_stdin)) = (options.stdin, child.stdin.take()) {
+                tokio::spawn(async move {
+                    let _ = child_stdin.write_all(input.as_bytes()).await;
+                });
+            }
 
             // Get stdout and stderr handles
             let stdout = if let Some(s) = child.stdout.take() { s } else {
@@ -67,44 +210,73 @@ impl BashExecutor {
                 return;
             };
 
-            // Capture stderr in background task to prevent pipe buffer blocking
-            let stderr_buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
-            let stderr_buf = stderr_buffer.clone();
-            let stderr_task = tokio::spawn(async move {
-                let mut stderr_reader = BufReader::new(stderr).lines();
-                while let Ok(Some(line)) = stderr_reader.next_line().await {
-                    let mut buf = stderr_buf.lock().await;
-                    if buf.len() < 100 {
-                        buf.push(line);
+            // Read both pipes in one loop so lines come out in arrival order and
+            // neither pipe can fill up and block the command. Once the deadline
+            // passes the group is killed and whatever is still buffered is drained.
+            let mut budget = OutputBudget::new(options.max_output_lines, options.max_output_bytes);
+            let mut timed_out = false;
+            let mut stdout_reader = BufReader::new(stdout).lines();
+            let mut stderr_reader = BufReader::new(stderr).lines();
+            let (mut stdout_open, mut stderr_open) = (true, true);
+            while stdout_open || stderr_open {
+                let next = tokio::select! {
+                    line = stdout_reader.next_line(), if stdout_open => Some((Pipe::Stdout, line)),
+                    line = stderr_reader.next_line(), if stderr_open => Some((Pipe::Stderr, line)),
+                    () = sleep_until(deadline), if !timed_out => None,
+                };
+                match next {
+                    Some((pipe, Ok(Some(line)))) => {
+                        if budget.admit(&line) {
+                            let at_ms = chrono::Utc::now().timestamp_millis();
+                            yield match pipe {
+                                Pipe::Stdout => BashOutput::Stdout { line, at_ms },
+                                Pipe::Stderr => BashOutput::Stderr { line, at_ms },
+                            };
+                        }
+                    }
+                    Some((Pipe::Stdout, _)) => stdout_open = false,
+                    Some((Pipe::Stderr, _)) => stderr_open = false,
+                    None => {
+                        timed_out = true;
+                        kill_process_group(&mut child).await;
                     }
                 }
-            });
-
-            // Stream stdout lines
-            let mut stdout_reader = BufReader::new(stdout).lines();
-            while let Ok(Some(line)) = stdout_reader.next_line().await {
-                yield BashOutput::Stdout { line };
             }
 
-            // Wait for stderr task to finish before reading the buffer
-            let _ = stderr_task.await;
+            // The command may have closed its pipes and kept running
+            let status = if timed_out {
+                child.wait().await
+            } else {
+                tokio::select! {
+                    status = child.wait() => status,
+                    () = sleep_until(deadline) => {
+                        timed_out = true;
+                        kill_process_group(&mut child).await;
+                        child.wait().await
+                    }
+                }
+            };
 
-            // Yield captured stderr lines
-            let stderr_lines = stderr_buffer.lock().await;
-            if !stderr_lines.is_empty() {
+            if budget.dropped_lines > 0 {
                 tracing::debug!(
-                    stderr_line_count = stderr_lines.len(),
+                    dropped_lines = budget.dropped_lines,
+                    dropped_bytes = budget.dropped_bytes,
                     command = %command,
-                    "Bash process produced stderr output"
+                    "Bash output truncated"
                 );
-                for line in stderr_lines.iter() {
-                    yield BashOutput::Stderr { line: line.clone() };
-                }
+                yield BashOutput::Truncated {
+                    lines: budget.dropped_lines,
+                    bytes: budget.dropped_bytes,
+                };
             }
-            drop(stderr_lines);
 
-            // Wait for process to complete and get exit code
-            match child.wait().await {
+            if let (true, Some(timeout_secs)) = (timed_out, options.timeout_secs) {
+                tracing::warn!(timeout_secs, command = %command, "Bash process timed out");
+                yield BashOutput::TimedOut { timeout_secs };
+            }
+
+            // Report the exit code of the reaped process
+            match status {
                 Ok(status) => {
                     let code = status.code().unwrap_or(-1);
                     tracing::debug!(exit_code = code, command = %command, "Bash process exited");
@@ -136,6 +308,13 @@ impl BashExecutor {
 
         results
     }
+
+    /// Like `execute_collect`, with options
+    pub async fn execute_collect_with(&self, command: &str, options: BashOptions) -> Vec<BashOutput> {
+        use futures::StreamExt;
+
+        self.execute_with(command, options).await.collect().await
+    }
 }
 
 impl Default for BashExecutor {
@@ -147,6 +326,7 @@ impl Default for BashExecutor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
 
     #[tokio::test]
     async fn test_execute_simple_command() {
@@ -158,7 +338,7 @@ mod tests {
 
         // Check for stdout
         match &outputs[0] {
-            BashOutput::Stdout { line } => assert_eq!(line, "hello world"),
+            BashOutput::Stdout { line, .. } => assert_eq!(line, "hello world"),
             _ => panic!("Expected stdout"),
         }
 
@@ -195,4 +375,195 @@ mod tests {
             _ => panic!("Expected exit"),
         }
     }
+
+    #[tokio::test]
+    async fn test_execute_cwd_env_and_stdin() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = BashOptions {
+            cwd: Some(dir.path().to_string_lossy().into_owned()),
+            env: HashMap::from([("GREETING".to_string(), "hi".to_string())]),
+            stdin: Some("from stdin\n".to_string()),
+            ..Default::default()
+        };
+        let outputs = BashExecutor::new()
+            .execute_collect_with("pwd; echo \"$GREETING\"; cat", options)
+            .await;
+
+        let stdout: Vec<&str> = outputs
+            .iter()
+            .filter_map(|o| match o {
+                BashOutput::Stdout { line, .. } => Some(line.as_str()),
+                _ => None,
+            })
+            .collect();
+        let cwd = dir.path().canonicalize().unwrap();
+        assert_eq!(stdout, vec![cwd.to_str().unwrap(), "hi", "from stdin"]);
+        assert!(matches!(outputs.last(), Some(BashOutput::Exit { code: 0 })));
+    }
+
+    #[tokio::test]
+    async fn test_execute_clear_env() {
+        let options = BashOptions {
+            clear_env: true,
+            env: HashMap::from([("ONLY".to_string(), "1".to_string())]),
+            ..Default::default()
+        };
+        let outputs = BashExecutor::new()
+            .execute_collect_with("echo \"${HOME-unset} $ONLY\"", options)
+            .await;
+        assert!(matches!(&outputs[0], BashOutput::Stdout { line, .. } if line == "unset 1"), "{outputs:?}");
+    }
+
+    #[tokio::test]
+    async fn test_execute_timeout_kills_process_group() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("survived");
+        let options = BashOptions {
+            timeout_secs: Some(1),
+            ..Default::default()
+        };
+        // The background sleeper shares the group and must die with the shell
+        let command = format!("(sleep 2 && touch {}) & echo started; sleep 30", marker.display());
+
+        let started = std::time::Instant::now();
+        let outputs = BashExecutor::new().execute_collect_with(&command, options).await;
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+
+        assert!(matches!(&outputs[0], BashOutput::Stdout { line, .. } if line == "started"));
+        let n = outputs.len();
+        assert!(matches!(outputs[n - 2], BashOutput::TimedOut { timeout_secs: 1 }), "{outputs:?}");
+        assert!(matches!(outputs[n - 1], BashOutput::Exit { code: -1 }));
+
+        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        assert!(!marker.exists(), "background process outlived the timeout");
+    }
+
+    #[tokio::test]
+    async fn test_execute_exits_before_timeout() {
+        let options = BashOptions {
+            timeout_secs: Some(30),
+            ..Default::default()
+        };
+        let outputs = BashExecutor::new().execute_collect_with("exit 3", options).await;
+        assert!(!outputs.iter().any(|o| matches!(o, BashOutput::TimedOut { .. })));
+        assert!(matches!(outputs.last(), Some(BashOutput::Exit { code: 3 })));
+    }
+
+    #[tokio::test]
+    async fn test_execute_interleaves_stdout_and_stderr() {
+        // Far more stderr than the old 100-line cap, interleaved with stdout
+        let outputs = BashExecutor::new()
+            .execute_collect("echo out1; sleep 0.2; echo err1 >&2; sleep 0.2; echo out2; seq 2 150 >&2")
+            .await;
+
+        let lines: Vec<&str> = outputs
+            .iter()
+            .filter_map(|o| match o {
+                BashOutput::Stdout { line, .. } | BashOutput::Stderr { line, .. } => Some(line.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(lines.len(), 152);
+        assert_eq!(&lines[..3], ["out1", "err1", "out2"]);
+        let stderr_count = outputs.iter().filter(|o| matches!(o, BashOutput::Stderr { .. })).count();
+        assert_eq!(stderr_count, 150);
+
+        let stamps: Vec<i64> = outputs
+            .iter()
+            .filter_map(|o| match o {
+                BashOutput::Stdout { at_ms, .. } | BashOutput::Stderr { at_ms, .. } => Some(*at_ms),
+                _ => None,
+            })
+            .collect();
+        assert!(stamps[0] > 0);
+        assert!(stamps.windows(2).all(|w| w[0] <= w[1]));
+        assert!(!outputs.iter().any(|o| matches!(o, BashOutput::Truncated { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_execute_output_budget_reports_truncation() {
+        let options = BashOptions {
+            max_output_lines: Some(3),
+            ..Default::default()
+        };
+        let outputs = BashExecutor::new()
+            .execute_collect_with("seq 1 10", options)
+            .await;
+
+        assert_eq!(outputs.len(), 5, "{outputs:?}");
+        assert!(matches!(&outputs[2], BashOutput::Stdout { line, .. } if line == "3"));
+        assert!(matches!(outputs[3], BashOutput::Truncated { lines: 7, bytes: 8 }));
+        assert!(matches!(outputs[4], BashOutput::Exit { code: 0 }));
+
+        let options = BashOptions {
+            max_output_bytes: Some(5),
+            ..Default::default()
+        };
+        let outputs = BashExecutor::new()
+            .execute_collect_with("echo abc; echo defg; echo h", options)
+            .await;
+        assert!(matches!(&outputs[0], BashOutput::Stdout { line, .. } if line == "abc"));
+        assert!(matches!(outputs[1], BashOutput::Truncated { lines: 2, bytes: 5 }), "{outputs:?}");
+    }
+
+    #[tokio::test]
+    async fn test_policy_violation_is_not_executed() {
+        use crate::activations::bash::{ExecPolicyConfig, PolicyViolation};
+
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("ran");
+        let policy = ExecPolicy::new(ExecPolicyConfig {
+            deny: vec![r"\btouch\b".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+        let executor = BashExecutor::with_policy(Arc::new(policy));
+
+        let outputs = executor
+            .execute_collect(&format!("touch {}", marker.display()))
+            .await;
+        assert!(matches!(
+            outputs.as_slice(),
+            [BashOutput::PolicyViolation { violation: PolicyViolation::CommandDenied { .. } }]
+        ), "{outputs:?}");
+        assert!(!marker.exists());
+    }
+
+    #[tokio::test]
+    async fn test_policy_limits_and_cwd_root() {
+        use crate::activations::bash::{ExecPolicyConfig, ResourceLimits};
+
+        let root = tempfile::tempdir().unwrap();
+        let policy = ExecPolicy::new(ExecPolicyConfig {
+            cwd_root: Some(root.path().to_path_buf()),
+            limits: ResourceLimits {
+                open_files: Some(64),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+        let executor = BashExecutor::with_policy(Arc::new(policy));
+
+        // The limit is hard, so the command can't raise it back
+        let outputs = executor
+            .execute_collect("pwd; ulimit -n; ulimit -n 4096 2>/dev/null || echo capped")
+            .await;
+        let stdout: Vec<&str> = outputs
+            .iter()
+            .filter_map(|o| match o {
+                BashOutput::Stdout { line, .. } => Some(line.as_str()),
+                _ => None,
+            })
+            .collect();
+        let root_path = root.path().canonicalize().unwrap();
+        assert_eq!(stdout, vec![root_path.to_str().unwrap(), "64", "capped"]);
+
+        let options = BashOptions {
+            cwd: Some("/".to_string()),
+            ..Default::default()
+        };
+        let outputs = executor.execute_collect_with("true", options).await;
+        assert!(matches!(outputs.as_slice(), [BashOutput::PolicyViolation { .. }]), "{outputs:?}");
+    }
 }
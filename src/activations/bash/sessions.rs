@@ -0,0 +1,643 @@
+use super::policy::{ExecPolicy, PolicyViolation};
+use super::types::{PtySessionEvent, PtySessionInfo, PtySessionOptions, SessionError};
+use async_stream::stream;
+use futures::Stream;
+use portable_pty::{native_pty_system, ChildKiller, CommandBuilder, MasterPty, PtySize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex as StdMutex, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, RwLock};
+use uuid::Uuid;
+
+/// Output kept per session for `read` / late subscribers. Older output is
+/// discarded once a session produces more than this.
+const SCROLLBACK_BYTES: usize = 1 << 20;
+
+/// How long a session whose shell exited stays registered, so clients can
+/// still read its last output and exit code.
+const EXITED_GRACE: Duration = Duration::from_mins(1);
+
+/// How often idle and exited sessions are swept.
+const REAP_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Buffered terminal output, addressed by absolute byte offset.
+///
+/// `base` is the offset of the first byte still held in `text`; everything
+/// before it has been dropped from the scrollback.
+#[derive(Default)]
+struct Scrollback {
+    base: u64,
+    text: String,
+}
+
+impl Scrollback {
+    const fn end(&self) -> u64 {
+        self.base + self.text.len() as u64
+    }
+
+    fn push(&mut self, chunk: &str) {
+        self.text.push_str(chunk);
+        if self.text.len() > SCROLLBACK_BYTES {
+            let mut cut = self.text.len() - SCROLLBACK_BYTES;
+            while !self.text.is_char_boundary(cut) {
+                cut += 1;
+            }
+            self.text.drain(..cut);
+            self.base += cut as u64;
+        }
+    }
+
+    /// Output from `offset` onwards, with the offset it actually starts at.
+    fn since(&self, offset: u64) -> (u64, &str) {
+        let start = offset.clamp(self.base, self.end());
+        let mut idx = (start - self.base) as usize;
+        while !self.text.is_char_boundary(idx) {
+            idx += 1;
+        }
+        (self.base + idx as u64, &self.text[idx..])
+    }
+}
+
+/// A shell running on a pseudo-terminal.
+///
+/// A blocking reader task drains the PTY into the scrollback and, on EOF,
+/// reaps the shell and publishes its exit code. Dropping the last handle
+/// kills the shell.
+///
+/// `last_active` is bumped by input, output and the shell's exit; the
+/// registry closes sessions that sit idle too long.
+struct PtySession {
+    id: Uuid,
+    pid: Option<u32>,
+    master: StdMutex<Box<dyn MasterPty + Send>>,
+    writer: Arc<StdMutex<Box<dyn Write + Send>>>,
+    killer: StdMutex<Box<dyn ChildKiller + Send + Sync>>,
+    size: StdMutex<(u16, u16)>,
+    scrollback: Arc<StdMutex<Scrollback>>,
+    last_active: Arc<StdMutex<Instant>>,
+    /// Bumped after every scrollback append so readers can wait for output
+    written: watch::Receiver<u64>,
+    /// `Some(code)` once the shell has exited
+    exited: watch::Receiver<Option<i32>>,
+}
+
+impl PtySession {
+    fn info(&self) -> PtySessionInfo {
+        let (cols, rows) = *self.size.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        PtySessionInfo {
+            session_id: self.id,
+            pid: self.pid,
+            cols,
+            rows,
+            exit_code: *self.exited.borrow(),
+        }
+    }
+
+    fn idle_for(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(*self.last_active.lock().unwrap_or_else(std::sync::PoisonError::into_inner))
+    }
+
+    /// Whether the registry should drop this session at `now`.
+    fn expired(&self, now: Instant, idle_limit: Option<Duration>) -> bool {
+        let idle = self.idle_for(now);
+        if self.exited.borrow().is_some() {
+            idle >= EXITED_GRACE
+        } else {
+            idle_limit.is_some_and(|limit| idle >= limit)
+        }
+    }
+}
+
+impl Drop for PtySession {
+    fn drop(&mut self) {
+        if self.exited.borrow().is_none() {
+            let mut killer = self.killer.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+            let _ = killer.kill();
+        }
+    }
+}
+
+type SessionMap = RwLock<HashMap<Uuid, Arc<PtySession>>>;
+
+/// Registry of interactive PTY shells, keyed by session id.
+///
+/// The policy's `max_sessions` caps how many shells run at once. A sweep
+/// started with the first shell closes shells idle for longer than
+/// `session_idle_secs` and forgets exited ones after a short grace period.
+///
+/// Cheap to clone — all clones share the same sessions and policy.
+#[derive(Clone, Default)]
+pub struct PtySessions {
+    sessions: Arc<SessionMap>,
+    policy: Arc<ExecPolicy>,
+    reaper_started: Arc<AtomicBool>,
+}
+
+impl PtySessions {
+    /// A registry with the permissive policy
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry whose shells are started under `policy`
+    pub fn with_policy(policy: Arc<ExecPolicy>) -> Self {
+        Self {
+            sessions: Arc::default(),
+            policy,
+            reaper_started: Arc::default(),
+        }
+    }
+
+    fn idle_limit(&self) -> Option<Duration> {
+        let secs = self.policy.config().session_idle_secs;
+        (secs > 0).then(|| Duration::from_secs(secs))
+    }
+
+    /// Start a shell on a fresh pseudo-terminal and register it.
+    pub async fn open(&self, options: PtySessionOptions) -> Result<PtySessionInfo, SessionError> {
+        let cwd = self.policy.check_session(options.cwd.as_deref())?;
+        // Held until the new session is registered, so concurrent opens
+        // can't both squeeze under the limit.
+        let mut sessions = self.sessions.write().await;
+        let idle_limit = self.idle_limit();
+        sessions.retain(|_, session| !session.expired(Instant::now(), idle_limit));
+        let max = self.policy.config().max_sessions;
+        if sessions.values().filter(|s| s.exited.borrow().is_none()).count() >= max {
+            return Err(PolicyViolation::TooManySessions { max }.into());
+        }
+
+        let size = PtySize {
+            rows: options.rows,
+            cols: options.cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        };
+        let pair = native_pty_system()
+            .openpty(size)
+            .map_err(|e| SessionError::Pty(e.to_string()))?;
+
+        let (program, args) = self.policy.wrap(&options.shell, &options.args);
+        let mut cmd = CommandBuilder::new(program);
+        cmd.args(&args);
+        if let Some(cwd) = &cwd {
+            cmd.cwd(cwd);
+        }
+        if options.clear_env {
+            cmd.env_clear();
+        }
+        if !options.env.contains_key("TERM") {
+            cmd.env("TERM", "xterm-256color");
+        }
+        for (key, value) in &options.env {
+            cmd.env(key, value);
+        }
+
+        let mut child = pair
+            .slave
+            .spawn_command(cmd)
+            .map_err(|e| SessionError::Spawn(e.to_string()))?;
+        // The shell holds the only slave fd now, so its exit shows up as EOF
+        drop(pair.slave);
+
+        let reader = pair
+            .master
+            .try_clone_reader()
+            .map_err(|e| SessionError::Pty(e.to_string()))?;
+        let writer = pair
+            .master
+            .take_writer()
+            .map_err(|e| SessionError::Pty(e.to_string()))?;
+
+        let id = Uuid::new_v4();
+        let pid = child.process_id();
+        let killer = child.clone_killer();
+        let scrollback = Arc::new(StdMutex::new(Scrollback::default()));
+        let last_active = Arc::new(StdMutex::new(Instant::now()));
+        let (written_tx, written) = watch::channel(0);
+        let (exited_tx, exited) = watch::channel(None);
+
+        let task_scrollback = scrollback.clone();
+        let task_last_active = last_active.clone();
+        tokio::task::spawn_blocking(move || {
+            pump_output(reader, &task_scrollback, &task_last_active, &written_tx);
+            let code = child
+                .wait()
+                .map_or(-1, |status| i32::try_from(status.exit_code()).unwrap_or(-1));
+            tracing::debug!(session_id = %id, exit_code = code, "PTY shell exited");
+            touch(&task_last_active);
+            let _ = exited_tx.send(Some(code));
+        });
+
+        let session = Arc::new(PtySession {
+            id,
+            pid,
+            master: StdMutex::new(pair.master),
+            writer: Arc::new(StdMutex::new(writer)),
+            killer: StdMutex::new(killer),
+            size: StdMutex::new((options.cols, options.rows)),
+            scrollback,
+            last_active,
+            written,
+            exited,
+        });
+        let info = session.info();
+        sessions.insert(id, session);
+        drop(sessions);
+        tracing::info!(session_id = %id, ?pid, shell = %options.shell, "Opened PTY session");
+        self.start_reaper();
+        Ok(info)
+    }
+
+    /// Spawn the periodic sweep, once per registry. It holds the sessions
+    /// weakly and stops when the registry is dropped.
+    fn start_reaper(&self) {
+        if self.reaper_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let sessions = Arc::downgrade(&self.sessions);
+        let idle_limit = self.idle_limit();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(REAP_INTERVAL);
+            loop {
+                ticker.tick().await;
+                if !reap(&sessions, Instant::now(), idle_limit).await {
+                    break;
+                }
+            }
+        });
+    }
+
+    async fn get(&self, id: &Uuid) -> Result<Arc<PtySession>, SessionError> {
+        self.sessions
+            .read()
+            .await
+            .get(id)
+            .cloned()
+            .ok_or(SessionError::NotFound(*id))
+    }
+
+    /// Whether a session with this id is registered.
+    pub async fn contains(&self, id: &Uuid) -> bool {
+        self.sessions.read().await.contains_key(id)
+    }
+
+    /// Ids of every registered session, including shells that have exited
+    /// but haven't been closed.
+    pub async fn ids(&self) -> Vec<Uuid> {
+        self.sessions.read().await.keys().copied().collect()
+    }
+
+    /// Snapshot of every registered session.
+    pub async fn list(&self) -> Vec<PtySessionInfo> {
+        self.sessions.read().await.values().map(|s| s.info()).collect()
+    }
+
+    /// Snapshot of one session.
+    pub async fn info(&self, id: &Uuid) -> Result<PtySessionInfo, SessionError> {
+        Ok(self.get(id).await?.info())
+    }
+
+    /// Send raw input (keystrokes, including any trailing newline) to the shell.
+    pub async fn write(&self, id: &Uuid, data: String) -> Result<usize, SessionError> {
+        let session = self.get(id).await?;
+        let exit_code = *session.exited.borrow();
+        if let Some(code) = exit_code {
+            return Err(SessionError::Exited { id: *id, code });
+        }
+        touch(&session.last_active);
+        let writer = session.writer.clone();
+        let len = data.len();
+        // A shell that isn't reading can block a PTY write; keep it off the runtime
+        tokio::task::spawn_blocking(move || {
+            let mut writer = writer.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+            writer.write_all(data.as_bytes())?;
+            writer.flush()
+        })
+        .await
+        .map_err(|e| SessionError::Io(e.to_string()))?
+        .map_err(|e| SessionError::Io(e.to_string()))?;
+        Ok(len)
+    }
+
+    /// Change the terminal size; the shell receives `SIGWINCH`.
+    pub async fn resize(&self, id: &Uuid, cols: u16, rows: u16) -> Result<PtySessionInfo, SessionError> {
+        let session = self.get(id).await?;
+        session
+            .master
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .resize(PtySize {
+                rows,
+                cols,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .map_err(|e| SessionError::Pty(e.to_string()))?;
+        *session.size.lock().unwrap_or_else(std::sync::PoisonError::into_inner) = (cols, rows);
+        Ok(session.info())
+    }
+
+    /// Kill the shell (if it is still running) and forget the session.
+    pub async fn close(&self, id: &Uuid) -> Result<(), SessionError> {
+        let session = self
+            .sessions
+            .write()
+            .await
+            .remove(id)
+            .ok_or(SessionError::NotFound(*id))?;
+        tracing::info!(session_id = %id, "Closed PTY session");
+        // Dropping the last handle kills the shell; in-flight readers keep
+        // their own handle and see the exit once it lands.
+        drop(session);
+        Ok(())
+    }
+
+    /// Stream buffered output from byte offset `since`.
+    ///
+    /// With `follow`, keeps streaming new output until the shell exits and
+    /// then yields `Exited`; without it, stops at the end of the buffer (and
+    /// still reports `Exited` if the shell is already gone).
+    pub fn read(
+        &self,
+        id: Uuid,
+        since: u64,
+        follow: bool,
+    ) -> impl Stream<Item = PtySessionEvent> + Send + 'static {
+        let sessions = self.clone();
+        stream! {
+            let session = match sessions.get(&id).await {
+                Ok(session) => session,
+                Err(e) => {
+                    yield PtySessionEvent::Error { message: e.to_string() };
+                    return;
+                }
+            };
+            let mut written = session.written.clone();
+            let mut exited = session.exited.clone();
+            let mut offset = since;
+            loop {
+                // Mark the current output as seen before copying it out, so a
+                // write that lands in between still wakes the next wait.
+                written.borrow_and_update();
+                let exit_code = *exited.borrow_and_update();
+                let chunk = {
+                    let scrollback = session.scrollback.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+                    let (start, text) = scrollback.since(offset);
+                    offset = scrollback.end();
+                    (!text.is_empty()).then(|| (start, text.to_string()))
+                };
+                if let Some((start, data)) = chunk {
+                    yield PtySessionEvent::Output { offset: start, data };
+                }
+                if let Some(code) = exit_code {
+                    yield PtySessionEvent::Exited { code };
+                    return;
+                }
+                if !follow {
+                    return;
+                }
+                tokio::select! {
+                    _ = written.changed() => {}
+                    _ = exited.changed() => {}
+                }
+            }
+        }
+    }
+}
+
+/// Close the sessions in `sessions` that have expired at `now`. Returns
+/// `false` once the registry itself is gone.
+async fn reap(sessions: &Weak<SessionMap>, now: Instant, idle_limit: Option<Duration>) -> bool {
+    let Some(sessions) = sessions.upgrade() else {
+        return false;
+    };
+    let expired: Vec<Arc<PtySession>> = {
+        let mut sessions = sessions.write().await;
+        let ids: Vec<Uuid> = sessions
+            .iter()
+            .filter(|(_, session)| session.expired(now, idle_limit))
+            .map(|(id, _)| *id)
+            .collect();
+        ids.iter().filter_map(|id| sessions.remove(id)).collect()
+    };
+    for session in expired {
+        tracing::info!(session_id = %session.id, exit_code = ?*session.exited.borrow(), "Reaped PTY session");
+    }
+    true
+}
+
+fn touch(last_active: &StdMutex<Instant>) {
+    *last_active.lock().unwrap_or_else(std::sync::PoisonError::into_inner) = Instant::now();
+}
+
+/// Copy PTY output into the scrollback until EOF.
+///
+/// Linux reports a hung-up PTY as `EIO` rather than a zero-length read, so
+/// any read error ends the pump. Multi-byte UTF-8 sequences split across
+/// reads are carried over to the next read.
+fn pump_output(
+    mut reader: Box<dyn Read + Send>,
+    scrollback: &StdMutex<Scrollback>,
+    last_active: &StdMutex<Instant>,
+    written: &watch::Sender<u64>,
+) {
+    let mut buf = [0u8; 4096];
+    let mut pending: Vec<u8> = Vec::new();
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        pending.extend_from_slice(&buf[..n]);
+        let valid = match std::str::from_utf8(&pending) {
+            Ok(s) => s.len(),
+            // An incomplete trailing sequence waits for the next read;
+            // anything else is invalid and decoded lossily.
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => pending.len(),
+        };
+        if valid == 0 {
+            continue;
+        }
+        let text = String::from_utf8_lossy(&pending[..valid]).into_owned();
+        pending.drain(..valid);
+        touch(last_active);
+        let end = {
+            let mut scrollback = scrollback.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+            scrollback.push(&text);
+            scrollback.end()
+        };
+        let _ = written.send(end);
+    }
+    if !pending.is_empty() {
+        let text = String::from_utf8_lossy(&pending).into_owned();
+        let end = {
+            let mut scrollback = scrollback.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+            scrollback.push(&text);
+            scrollback.end()
+        };
+        let _ = written.send(end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    fn quiet_shell() -> PtySessionOptions {
+        PtySessionOptions {
+            args: vec!["--noprofile".to_string(), "--norc".to_string()],
+            ..Default::default()
+        }
+    }
+
+    /// Follow the session until `needle` shows up in its output.
+    async fn wait_for_output(sessions: &PtySessions, id: Uuid, needle: &str) -> String {
+        let mut out = String::new();
+        let mut stream = Box::pin(sessions.read(id, 0, true));
+        let found = tokio::time::timeout(Duration::from_secs(10), async {
+            while let Some(event) = stream.next().await {
+                if let PtySessionEvent::Output { data, .. } = event {
+                    out.push_str(&data);
+                    if out.contains(needle) {
+                        return true;
+                    }
+                }
+            }
+            false
+        })
+        .await
+        .unwrap_or(false);
+        assert!(found, "never saw {needle:?} in output: {out:?}");
+        out
+    }
+
+    #[tokio::test]
+    async fn test_shell_state_persists_between_writes() {
+        let sessions = PtySessions::new();
+        let info = sessions.open(quiet_shell()).await.unwrap();
+        let id = info.session_id;
+
+        sessions.write(&id, "cd /tmp && export GREETING=hello\n".to_string()).await.unwrap();
+        sessions.write(&id, "echo \"$PWD:$GREETING\"\n".to_string()).await.unwrap();
+        // The echoed input shows the unexpanded variables, so this only
+        // matches the command's actual output
+        wait_for_output(&sessions, id, "/tmp:hello").await;
+
+        sessions.close(&id).await.unwrap();
+        assert!(!sessions.contains(&id).await);
+    }
+
+    #[tokio::test]
+    async fn test_resize_and_exit() {
+        let sessions = PtySessions::new();
+        let id = sessions.open(quiet_shell()).await.unwrap().session_id;
+
+        let info = sessions.resize(&id, 132, 40).await.unwrap();
+        assert_eq!((info.cols, info.rows), (132, 40));
+        sessions.write(&id, "stty size\n".to_string()).await.unwrap();
+        wait_for_output(&sessions, id, "40 132").await;
+
+        sessions.write(&id, "exit 7\n".to_string()).await.unwrap();
+        let events: Vec<_> = tokio::time::timeout(Duration::from_secs(10), sessions.read(id, 0, true).collect())
+            .await
+            .unwrap();
+        assert!(matches!(events.last(), Some(PtySessionEvent::Exited { code: 7 })), "{events:?}");
+        assert!(matches!(
+            sessions.write(&id, "true\n".to_string()).await,
+            Err(SessionError::Exited { code: 7, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_session() {
+        let sessions = PtySessions::new();
+        let id = Uuid::new_v4();
+        assert!(matches!(sessions.close(&id).await, Err(SessionError::NotFound(_))));
+        let events: Vec<_> = sessions.read(id, 0, false).collect().await;
+        assert!(matches!(events.as_slice(), [PtySessionEvent::Error { .. }]));
+    }
+
+    #[tokio::test]
+    async fn test_policy_can_disable_sessions() {
+        let policy = ExecPolicy::new(crate::activations::bash::ExecPolicyConfig {
+            allow_sessions: false,
+            ..Default::default()
+        })
+        .unwrap();
+        let sessions = PtySessions::with_policy(Arc::new(policy));
+        assert!(matches!(
+            sessions.open(quiet_shell()).await,
+            Err(SessionError::Policy(crate::activations::bash::PolicyViolation::SessionsDisabled))
+        ));
+        assert!(sessions.ids().await.is_empty());
+    }
+
+    /// Ask the shell to exit and wait until it has.
+    async fn exit_shell(sessions: &PtySessions, id: Uuid) {
+        sessions.write(&id, "exit\n".to_string()).await.unwrap();
+        let events: Vec<_> = tokio::time::timeout(Duration::from_secs(10), sessions.read(id, 0, true).collect())
+            .await
+            .unwrap();
+        assert!(matches!(events.last(), Some(PtySessionEvent::Exited { .. })), "{events:?}");
+    }
+
+    #[tokio::test]
+    async fn test_max_sessions_counts_running_shells() {
+        let policy = ExecPolicy::new(crate::activations::bash::ExecPolicyConfig {
+            max_sessions: 1,
+            ..Default::default()
+        })
+        .unwrap();
+        let sessions = PtySessions::with_policy(Arc::new(policy));
+        let first = sessions.open(quiet_shell()).await.unwrap().session_id;
+        assert!(matches!(
+            sessions.open(quiet_shell()).await,
+            Err(SessionError::Policy(PolicyViolation::TooManySessions { max: 1 }))
+        ));
+
+        // A shell that exited no longer takes up a slot
+        exit_shell(&sessions, first).await;
+        let second = sessions.open(quiet_shell()).await.unwrap().session_id;
+        assert!(sessions.contains(&first).await && sessions.contains(&second).await);
+    }
+
+    #[tokio::test]
+    async fn test_reap_closes_idle_and_exited_sessions() {
+        let sessions = PtySessions::new();
+        let idle = sessions.open(quiet_shell()).await.unwrap().session_id;
+        let exited = sessions.open(quiet_shell()).await.unwrap().session_id;
+        exit_shell(&sessions, exited).await;
+
+        let registry = Arc::downgrade(&sessions.sessions);
+        let limit = Some(Duration::from_mins(10));
+        assert!(reap(&registry, Instant::now(), limit).await);
+        assert_eq!(sessions.ids().await.len(), 2);
+
+        // The exited shell goes after its grace period, the idle one later
+        reap(&registry, Instant::now() + EXITED_GRACE, limit).await;
+        assert_eq!(sessions.ids().await, [idle]);
+        reap(&registry, Instant::now() + Duration::from_mins(10), limit).await;
+        assert!(sessions.ids().await.is_empty());
+
+        drop(sessions);
+        assert!(!reap(&registry, Instant::now(), limit).await);
+    }
+
+    #[test]
+    fn test_scrollback_drops_oldest_output() {
+        let mut scrollback = Scrollback::default();
+        scrollback.push(&"a".repeat(SCROLLBACK_BYTES));
+        scrollback.push("bcd");
+        assert_eq!(scrollback.base, 3);
+        assert_eq!(scrollback.end(), SCROLLBACK_BYTES as u64 + 3);
+        let (start, text) = scrollback.since(0);
+        assert_eq!(start, 3);
+        assert!(text.ends_with("bcd"));
+        assert_eq!(scrollback.since(SCROLLBACK_BYTES as u64 + 1).1, "cd");
+    }
+}
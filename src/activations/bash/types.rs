@@ -1,21 +1,70 @@
+use super::policy::PolicyViolation;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use thiserror::Error;
+use uuid::Uuid;
 
 /// Stream events from bash command execution
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum BashEvent {
-    /// Standard output line
-    Stdout { line: String },
-    /// Standard error line
-    Stderr { line: String },
+    /// Standard output line, stamped with its arrival time (Unix millis)
+    Stdout {
+        line: String,
+        #[serde(default)]
+        at_ms: i64,
+    },
+    /// Standard error line, stamped with its arrival time (Unix millis)
+    Stderr {
+        line: String,
+        #[serde(default)]
+        at_ms: i64,
+    },
+    /// Lines dropped because the output budget was spent. Emitted once,
+    /// before `TimedOut` / `Exit`, and only if something was dropped.
+    Truncated { lines: u64, bytes: u64 },
+    /// The command ran past its `timeout_secs` and its process group was killed.
+    /// An `Exit` follows once the process has been reaped.
+    TimedOut { timeout_secs: u64 },
     /// Exit code when process completes
     Exit { code: i32 },
+    /// The exec policy refused the command; nothing was run
+    PolicyViolation { violation: PolicyViolation },
     /// Error from the executor itself (not the command)
     Error { message: String },
 }
 
+/// How a command is run: working directory, environment, stdin, time limit
+/// and output budget.
+///
+/// The default runs in the server's cwd with the server's environment, no
+/// stdin, no timeout and no cap on output.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct BashOptions {
+    /// Working directory for the command
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Variables set on top of the inherited (or cleared) environment
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Start from an empty environment instead of inheriting the server's
+    #[serde(default)]
+    pub clear_env: bool,
+    /// Written to the command's stdin, which is then closed
+    #[serde(default)]
+    pub stdin: Option<String>,
+    /// Kill the command's whole process group after this many seconds
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Stop streaming after this many stdout + stderr lines
+    #[serde(default)]
+    pub max_output_lines: Option<u64>,
+    /// Stop streaming after this many bytes of stdout + stderr
+    #[serde(default)]
+    pub max_output_bytes: Option<u64>,
+}
+
 // Keep the old name as an alias for backwards compatibility
 pub type BashOutput = BashEvent;
 
@@ -40,3 +89,96 @@ pub enum ExecutorError {
         source: std::io::Error,
     },
 }
+
+/// How an interactive PTY session's shell is started.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PtySessionOptions {
+    /// Shell program to run
+    pub shell: String,
+    /// Arguments passed to the shell
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Working directory the shell starts in
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Variables set on top of the inherited (or cleared) environment.
+    /// `TERM` defaults to `xterm-256color` unless set here.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Start from an empty environment instead of inheriting the server's
+    #[serde(default)]
+    pub clear_env: bool,
+    /// Terminal width in columns
+    pub cols: u16,
+    /// Terminal height in rows
+    pub rows: u16,
+}
+
+impl Default for PtySessionOptions {
+    fn default() -> Self {
+        Self {
+            shell: "bash".to_string(),
+            args: Vec::new(),
+            cwd: None,
+            env: HashMap::new(),
+            clear_env: false,
+            cols: 80,
+            rows: 24,
+        }
+    }
+}
+
+/// Snapshot of an interactive PTY session
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PtySessionInfo {
+    pub session_id: Uuid,
+    /// Process id of the shell
+    pub pid: Option<u32>,
+    pub cols: u16,
+    pub rows: u16,
+    /// Set once the shell has exited; the session stays readable until closed
+    pub exit_code: Option<i32>,
+}
+
+/// Stream events from interactive PTY session methods
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PtySessionEvent {
+    /// A session was opened, or its current state was requested
+    Session { info: PtySessionInfo },
+    /// Terminal output starting at byte `offset` of the session's output.
+    /// Pass the next offset as `since` to resume reading without gaps.
+    Output { offset: u64, data: String },
+    /// Input was written to the terminal
+    Written { bytes: usize },
+    /// The shell exited; no more output will follow
+    Exited { code: i32 },
+    /// The session was closed and its shell killed
+    Closed { session_id: Uuid },
+    /// The exec policy refused to open the session
+    PolicyViolation { violation: PolicyViolation },
+    /// The session doesn't exist or the operation failed
+    Error { message: String },
+}
+
+/// Typed errors from interactive PTY sessions
+#[derive(Debug, Error)]
+pub enum SessionError {
+    #[error("rejected by exec policy: {0}")]
+    Policy(#[from] PolicyViolation),
+
+    #[error("no bash session with id {0}")]
+    NotFound(Uuid),
+
+    #[error("bash session {id} has exited (code {code})")]
+    Exited { id: Uuid, code: i32 },
+
+    #[error("pseudo-terminal error: {0}")]
+    Pty(String),
+
+    #[error("failed to spawn shell: {0}")]
+    Spawn(String),
+
+    #[error("terminal I/O failed: {0}")]
+    Io(String),
+}
@@ -1418,12 +1418,8 @@ impl<P: HubContext> ClaudeCode<P> {
                                 current_tool_input.clear();
                             }
                         }
-                        StreamEventInner::MessageDelta { delta } => {
-                            // If stop_reason is tool_use with loopback, mark as awaiting
-                            if delta.stop_reason == Some("tool_use".to_string()) {
-                                // Check if we're in loopback mode (already marked above)
-                            }
-                        }
+                        // MessageDelta with stop_reason tool_use needs no handling here:
+                        // loopback mode was already marked when the tool_use block closed.
                         _ => {}
                     }
                 }
@@ -1853,6 +1849,7 @@ mod ir18_tests {
             archive_window: 2_592_000,
             auto_cleanup: false,
             cleanup_interval: 3600,
+            event_retention: 604_800,
         };
         let arbor = Arc::new(ArborStorage::new(arbor_config).await.unwrap());
 
@@ -1,4 +1,5 @@
 use super::types::{Model, RawClaudeEvent};
+use crate::activations::bash::ExecPolicy;
 use async_stream::stream;
 use futures::Stream;
 use serde_json::Value;
@@ -129,17 +130,27 @@ impl Default for LaunchConfig {
 #[derive(Clone)]
 pub struct ClaudeCodeExecutor {
     claude_path: String,
+    /// Resource limits and network isolation to launch under, if any
+    policy: Option<ExecPolicy>,
 }
 
 impl ClaudeCodeExecutor {
     pub fn new() -> Self {
         Self {
             claude_path: Self::find_claude_binary().unwrap_or_else(|| "claude".to_string()),
+            policy: None,
         }
     }
 
     pub const fn with_path(path: String) -> Self {
-        Self { claude_path: path }
+        Self { claude_path: path, policy: None }
+    }
+
+    /// Launch under `policy`'s resource limits and network isolation.
+    #[must_use]
+    pub fn with_policy(mut self, policy: ExecPolicy) -> Self {
+        self.policy = Some(policy);
+        self
     }
 
     /// Discover the Claude binary location
@@ -255,6 +266,7 @@ impl ClaudeCodeExecutor {
     ) -> Pin<Box<dyn Stream<Item = RawClaudeEvent> + Send + 'static>> {
         let mut args = self.build_args(&config);
         let claude_path = self.claude_path.clone();
+        let policy = self.policy.clone();
         let working_dir = config.working_dir.clone();
         let loopback_enabled = config.loopback_enabled;
         let loopback_session_id = config.loopback_session_id.clone();
@@ -403,8 +415,13 @@ impl ClaudeCodeExecutor {
             // Emit the launch command as an event (captured in arbor for debugging)
             yield RawClaudeEvent::LaunchCommand { command: shell_cmd.clone() };
 
-            let mut cmd = Command::new("bash");
-            cmd.args(["-c", &shell_cmd])
+            let shell_args = ["-c".to_string(), shell_cmd.clone()];
+            let (program, program_args) = match &policy {
+                Some(policy) => policy.wrap("bash", &shell_args),
+                None => ("bash".to_string(), shell_args.to_vec()),
+            };
+            let mut cmd = Command::new(program);
+            cmd.args(program_args)
                 .current_dir(&working_dir)
                 .stdout(Stdio::piped())
                 .stderr(Stdio::piped())
@@ -1291,6 +1291,7 @@ mod render_tests {
             archive_window: 2_592_000,
             auto_cleanup: false, // Disable for tests
             cleanup_interval: 3600,
+            event_retention: 604_800,
         };
         let arbor = Arc::new(ArborStorage::new(arbor_config).await.unwrap());
 
@@ -1,7 +1,15 @@
 use super::storage::{LoopbackStorage, LoopbackStorageConfig};
-use super::types::{ApprovalStatus, ApprovalId, RespondResult, PendingResult, WaitForApprovalResult, ConfigureResult};
+use super::types::{
+    ApprovalDecision, ApprovalFilter, ApprovalId, ApprovalRequest, ApprovalSettings, ApprovalStatus, ConfigureResult,
+    DecidedBy, DecisionsResult, GetApprovalResult, HistoryResult, LoopbackError, PendingResult, PoliciesResult, PolicyAction, PolicyResult, PolicyRuleId, RemovePolicyResult,
+    RespondResult, SessionSettingsResult, StatsResult, SubscribeEvent, WaitForApprovalResult, UNAUTHENTICATED_RESPONDER,
+};
 use async_stream::stream;
 use futures::Stream;
+use plexus_core::plexus::AuthContext;
+use plexus_core::plexus::bidirectional::{
+    bidir_error_message, BidirError, StandardBidirChannel, StandardRequest, StandardResponse,
+};
 use serde_json::{json, Value};
 use std::sync::Arc;
 use std::time::Duration;
@@ -44,7 +52,10 @@ impl ClaudeCodeLoopback {
     /// Permission prompt handler - blocks until parent approves/denies
     ///
     /// This is called by Claude Code CLI via --permission-prompt-tool.
-    /// It blocks (polls) until the parent calls `loopback.respond()`.
+    /// Policy rules (`add_policy`) are checked first; an `allow` or `deny`
+    /// match answers at once. Otherwise it blocks until the parent calls `loopback.respond()` (or a client
+    /// subscribed via `loopback.subscribe()` answers), and applies the
+    /// session's default decision once its timeout runs out.
     ///
     /// Returns a JSON string (not object) because Claude Code expects the MCP response
     /// to have the permission JSON already stringified in content[0].text.
@@ -88,6 +99,25 @@ impl ClaudeCodeLoopback {
             // DEBUG: Log the lookup result
             tracing::debug!("[LOOPBACK] permit: tool_use_id={} mapped to session_id={}", tool_use_id, session_id);
 
+            // Policy rules settle the call without asking anyone
+            match storage.check_policies(&session_id, &tool_name, &input).await {
+                Ok(Some(rule)) if rule.action != PolicyAction::Ask => {
+                    if let Err(e) = storage.record_decision(&session_id, &tool_name, &tool_use_id, &input, &rule).await {
+                        tracing::warn!("[LOOPBACK] failed to record policy decision: {}", e);
+                    }
+                    let response = if rule.action == PolicyAction::Allow {
+                        json!({ "behavior": "allow", "updatedInput": input.clone() })
+                    } else {
+                        let reason = rule.description.as_deref().map(|d| format!(": {d}")).unwrap_or_default();
+                        json!({ "behavior": "deny", "message": format!("Denied by policy rule {}{reason}", rule.id) })
+                    };
+                    yield response.to_string();
+                    return;
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("[LOOPBACK] policy check failed, asking instead: {}", e),
+            }
+
             // Create approval request
             let approval = match storage.create_approval(
                 &session_id,
@@ -108,89 +138,103 @@ impl ClaudeCodeLoopback {
             };
 
             let approval_id = approval.id;
-            let timeout_secs = 300u64; // 5 minute timeout
-            let poll_interval = Duration::from_secs(1);
-            let start = std::time::Instant::now();
+            let settings = storage.session_settings(&session_id);
 
-            // Blocking poll loop - like HumanLayer's hlyr
-            loop {
-                // Check timeout
-                if start.elapsed().as_secs() > timeout_secs {
-                    let _ = storage.resolve_approval(&approval_id, false, Some("Timed out".to_string())).await;
+            // Wait for `respond` (or a pushed confirm prompt) to resolve it
+            let current = match storage
+                .wait_for_resolution(&approval_id, Some(Duration::from_secs(settings.timeout_secs)))
+                .await
+            {
+                Ok(current) => current,
+                Err(e) => {
                     let response = json!({
                         "behavior": "deny",
-                        "message": "Approval request timed out"
+                        "message": format!("Failed to check approval: {}", e)
                     });
                     yield response.to_string();
                     return;
                 }
+            };
 
-                // Poll for resolution
-                match storage.get_approval(&approval_id).await {
-                    Ok(current) => {
-                        match current.status {
-                            ApprovalStatus::Approved => {
-                                // Return allow response as JSON string
-                                // Claude Code expects: {"behavior": "allow", "updatedInput": {...}}
-                                let response = json!({
-                                    "behavior": "allow",
-                                    "updatedInput": input.clone()
-                                });
-                                yield response.to_string();
-                                return;
-                            }
-                            ApprovalStatus::Denied => {
-                                let response = json!({
-                                    "behavior": "deny",
-                                    "message": current.response_message.unwrap_or_else(|| "Denied by parent".to_string())
-                                });
-                                yield response.to_string();
-                                return;
-                            }
-                            ApprovalStatus::TimedOut => {
-                                let response = json!({
-                                    "behavior": "deny",
-                                    "message": "Approval timed out"
-                                });
-                                yield response.to_string();
-                                return;
-                            }
-                            ApprovalStatus::Pending => {
-                                // Continue polling
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        let response = json!({
-                            "behavior": "deny",
-                            "message": format!("Failed to check approval: {}", e)
-                        });
-                        yield response.to_string();
-                        return;
-                    }
+            let response = if current.status == ApprovalStatus::Pending {
+                // Nobody answered in time: apply the session's default decision,
+                // unless an answer lands first
+                let approve = settings.default_decision.approves();
+                let note = format!("Timed out; default decision: {}", if approve { "approve" } else { "deny" });
+                match storage.time_out_approval(&approval_id, approve, Some(note)).await {
+                    Ok(()) if approve => json!({ "behavior": "allow", "updatedInput": input.clone() }),
+                    Ok(()) => json!({ "behavior": "deny", "message": "Approval request timed out" }),
+                    Err(LoopbackError::AlreadyResolved { .. }) => match storage.get_approval(&approval_id).await {
+                        Ok(current) => permit_response(current, &input),
+                        Err(e) => json!({ "behavior": "deny", "message": format!("Failed to check approval: {}", e) }),
+                    },
+                    Err(e) => json!({ "behavior": "deny", "message": format!("Failed to resolve approval: {}", e) }),
                 }
-
-                sleep(poll_interval).await;
-            }
+            } else {
+                permit_response(current, &input)
+            };
+            yield response.to_string();
         }
     }
 
     /// Respond to a pending approval request
+    ///
+    /// The caller is unauthenticated, so the history records the responder as
+    /// `unauthenticated`; use `respond_authenticated` to be named instead.
+    ///
+    /// An approver can pass `updated_input` to approve a tightened version of
+    /// the call (say, a narrower bash command); the tool then runs with it,
+    /// and the changed fields are kept in the approval's `input_diff`.
     #[plexus_macros::method(params(
         approval_id = "ID of the approval request",
         approve = "Whether to approve (true) or deny (false)",
-        message = "Optional message/reason"
+        message = "Optional message/reason",
+        updated_input = "Replacement tool input to approve instead of the requested one: a JSON object (or a string holding one)"
     ))]
     async fn respond(
         &self,
         approval_id: ApprovalId,
         approve: bool,
         message: Option<String>,
+        updated_input: Option<Value>,
+    ) -> impl Stream<Item = RespondResult> + Send + 'static {
+        let storage = self.storage.clone();
+
+        stream! {
+            match resolve(&storage, &approval_id, approve, message, UNAUTHENTICATED_RESPONDER, updated_input).await {
+                Ok(()) => {
+                    yield RespondResult::Ok { approval_id };
+                }
+                Err(e) => {
+                    yield RespondResult::Err { message: e.to_string() };
+                }
+            }
+        }
+    }
+
+    /// Respond to a pending approval request as the authenticated caller
+    ///
+    /// Same as `respond`, but the responder recorded in the history is the
+    /// user id from the connection's auth context. Requires authentication.
+    #[plexus_macros::method(params(
+        approval_id = "ID of the approval request",
+        approve = "Whether to approve (true) or deny (false)",
+        message = "Optional message/reason",
+        updated_input = "Replacement tool input to approve instead of the requested one: a JSON object (or a string holding one)"
+    ))]
+    async fn respond_authenticated(
+        &self,
+        auth: &AuthContext,
+        approval_id: ApprovalId,
+        approve: bool,
+        message: Option<String>,
+        updated_input: Option<Value>,
     ) -> impl Stream<Item = RespondResult> + Send + 'static {
         let storage = self.storage.clone();
+        let responder = auth.user_id.clone();
 
         stream! {
-            match storage.resolve_approval(&approval_id, approve, message).await {
+            match resolve(&storage, &approval_id, approve, message, &responder, updated_input).await {
                 Ok(()) => {
                     yield RespondResult::Ok { approval_id };
                 }
@@ -223,6 +267,173 @@ impl ClaudeCodeLoopback {
         }
     }
 
+    /// Add an auto-approval policy rule
+    ///
+    /// `permit` checks every tool call against the rules before asking
+    /// anyone: `deny` beats `ask`, which beats `allow`. Patterns are globs
+    /// (`*`, `?`); an `allow` rule never matches a `command` with shell
+    /// metacharacters (`;`, `&`, `|`, `>`, `$`, …) or a path with `..`.
+    #[plexus_macros::method(params(
+        action = "allow | deny | ask",
+        tool_name = "Glob over the tool name (default: every tool)",
+        input = "Globs over input fields, e.g. {\"command\": \"cargo test*\"} (default: none)",
+        scope = "Session or orcha graph id the rule is limited to (default: everywhere)",
+        description = "Why the rule exists; included in denial messages"
+    ))]
+    async fn add_policy(
+        &self,
+        action: PolicyAction,
+        tool_name: Option<String>,
+        input: Option<std::collections::BTreeMap<String, String>>,
+        scope: Option<String>,
+        description: Option<String>,
+    ) -> impl Stream<Item = PolicyResult> + Send + 'static {
+        let storage = self.storage.clone();
+
+        stream! {
+            match storage.add_policy(action, tool_name, input.unwrap_or_default(), scope, description).await {
+                Ok(rule) => yield PolicyResult::Ok { rule },
+                Err(e) => yield PolicyResult::Err { message: e.to_string() },
+            }
+        }
+    }
+
+    /// List policy rules
+    #[plexus_macros::method(params(
+        scope = "Only rules limited to this session or graph id (default: all rules)"
+    ))]
+    async fn list_policies(
+        &self,
+        scope: Option<String>,
+    ) -> impl Stream<Item = PoliciesResult> + Send + 'static {
+        let storage = self.storage.clone();
+
+        stream! {
+            match storage.list_policies(scope.as_deref()).await {
+                Ok(rules) => yield PoliciesResult::Ok { rules },
+                Err(e) => yield PoliciesResult::Err { message: e.to_string() },
+            }
+        }
+    }
+
+    /// Remove a policy rule
+    #[plexus_macros::method(params(
+        rule_id = "ID of the rule to remove"
+    ))]
+    async fn remove_policy(
+        &self,
+        rule_id: PolicyRuleId,
+    ) -> impl Stream<Item = RemovePolicyResult> + Send + 'static {
+        let storage = self.storage.clone();
+
+        stream! {
+            match storage.remove_policy(&rule_id).await {
+                Ok(()) => yield RemovePolicyResult::Ok { rule_id },
+                Err(e) => yield RemovePolicyResult::Err { message: e.to_string() },
+            }
+        }
+    }
+
+    /// Audit trail of tool calls settled by policy rules, newest first
+    #[plexus_macros::method(params(
+        session_id = "Optional session ID to filter by (includes its child sessions)",
+        limit = "Maximum number of decisions (default: 100)"
+    ))]
+    async fn decisions(
+        &self,
+        session_id: Option<String>,
+        limit: Option<usize>,
+    ) -> impl Stream<Item = DecisionsResult> + Send + 'static {
+        let storage = self.storage.clone();
+
+        stream! {
+            match storage.list_decisions(session_id.as_deref(), limit.unwrap_or(100)).await {
+                Ok(decisions) => yield DecisionsResult::Ok { decisions },
+                Err(e) => yield DecisionsResult::Err { message: e.to_string() },
+            }
+        }
+    }
+
+    /// Look up one approval request, resolved or not
+    #[plexus_macros::method(params(
+        approval_id = "ID of the approval request"
+    ))]
+    async fn get(
+        &self,
+        approval_id: ApprovalId,
+    ) -> impl Stream<Item = GetApprovalResult> + Send + 'static {
+        let storage = self.storage.clone();
+
+        stream! {
+            match storage.get_approval(&approval_id).await {
+                Ok(approval) => yield GetApprovalResult::Ok { approval },
+                Err(e) => yield GetApprovalResult::Err { message: e.to_string() },
+            }
+        }
+    }
+
+    /// Past and pending approval requests, newest first
+    ///
+    /// Resolved requests carry who resolved them, how long they waited and
+    /// the input the tool was allowed to run with.
+    #[plexus_macros::method(params(
+        session_id = "Only this session's requests",
+        graph_id = "Only requests from this graph / parent session and its child sessions",
+        tool_name = "Only requests for this tool",
+        status = "Only requests in this status: pending | approved | denied | timed_out",
+        since = "Only requests created at or after this unix timestamp (seconds)",
+        until = "Only requests created before this unix timestamp (seconds)",
+        limit = "Maximum number of requests (default: 100)"
+    ))]
+    async fn history(
+        &self,
+        session_id: Option<String>,
+        graph_id: Option<String>,
+        tool_name: Option<String>,
+        status: Option<ApprovalStatus>,
+        since: Option<i64>,
+        until: Option<i64>,
+        limit: Option<usize>,
+    ) -> impl Stream<Item = HistoryResult> + Send + 'static {
+        let storage = self.storage.clone();
+        let filter = ApprovalFilter { session_id, graph_id, tool_name, status, since, until };
+
+        stream! {
+            match storage.history(&filter, Some(limit.unwrap_or(100))).await {
+                Ok(approvals) => yield HistoryResult::Ok { approvals },
+                Err(e) => yield HistoryResult::Err { message: e.to_string() },
+            }
+        }
+    }
+
+    /// Approval analytics: outcome counts, wait times, the most-denied tools
+    /// and who resolves what, over the same filters as `history`
+    #[plexus_macros::method(params(
+        session_id = "Only this session's requests",
+        graph_id = "Only requests from this graph / parent session and its child sessions",
+        tool_name = "Only requests for this tool",
+        since = "Only requests created at or after this unix timestamp (seconds)",
+        until = "Only requests created before this unix timestamp (seconds)"
+    ))]
+    async fn stats(
+        &self,
+        session_id: Option<String>,
+        graph_id: Option<String>,
+        tool_name: Option<String>,
+        since: Option<i64>,
+        until: Option<i64>,
+    ) -> impl Stream<Item = StatsResult> + Send + 'static {
+        let storage = self.storage.clone();
+        let filter = ApprovalFilter { session_id, graph_id, tool_name, status: None, since, until };
+
+        stream! {
+            match storage.stats(&filter).await {
+                Ok(stats) => yield StatsResult::Ok { stats },
+                Err(e) => yield StatsResult::Err { message: e.to_string() },
+            }
+        }
+    }
+
     /// Wait for a new approval request to arrive for a session
     ///
     /// This method blocks until a new approval arrives or the timeout is reached.
@@ -294,6 +505,139 @@ impl ClaudeCodeLoopback {
         }
     }
 
+    /// Set how long approvals in a session wait for an answer and what is
+    /// decided when nobody answers. Child sessions inherit their parent's.
+    #[plexus_macros::method(params(
+        session_id = "Session ID the settings apply to",
+        timeout_secs = "Seconds an approval waits for an answer (default: 300)",
+        default_decision = "Decision applied on timeout: approve | deny (default: deny)"
+    ))]
+    async fn session_settings(
+        &self,
+        session_id: String,
+        timeout_secs: Option<u64>,
+        default_decision: Option<ApprovalDecision>,
+    ) -> impl Stream<Item = SessionSettingsResult> + Send + 'static {
+        let storage = self.storage.clone();
+
+        stream! {
+            let settings = updated_settings(&storage, &session_id, timeout_secs, default_decision);
+            yield SessionSettingsResult::Ok { session_id, settings };
+        }
+    }
+
+    /// Push a session's approvals to this client as confirm prompts
+    ///
+    /// Stays open while the client is connected. Every approval created for
+    /// the session (or one of its child sessions) — including those already
+    /// pending — is sent as a confirm request; the answer resolves it. A
+    /// prompt left unanswered for the session's timeout gets the session's
+    /// default decision. Requires a bidirectional transport.
+    #[plexus_macros::method(bidirectional, streaming, params(
+        session_id = "Session ID whose approvals to push",
+        timeout_secs = "Seconds to wait for each answer (default: the session's setting, 300)",
+        default_decision = "Decision applied on timeout: approve | deny (default: the session's setting, deny)"
+    ))]
+    async fn subscribe(
+        &self,
+        ctx: &Arc<StandardBidirChannel>,
+        session_id: String,
+        timeout_secs: Option<u64>,
+        default_decision: Option<ApprovalDecision>,
+    ) -> impl Stream<Item = SubscribeEvent> + Send + 'static {
+        let ctx = ctx.clone();
+        let storage = self.storage.clone();
+
+        stream! {
+            if !ctx.is_bidirectional() {
+                yield SubscribeEvent::Err {
+                    message: "subscribe needs a bidirectional transport; use wait_for_approval + respond instead".to_string(),
+                };
+                return;
+            }
+
+            // Listen before reading the backlog so nothing created in between is missed
+            let mut created = storage.subscribe_created();
+            let settings = updated_settings(&storage, &session_id, timeout_secs, default_decision);
+            yield SubscribeEvent::Subscribed { session_id: session_id.clone(), settings };
+
+            let mut queue: std::collections::VecDeque<ApprovalRequest> = match storage.list_pending(Some(&session_id)).await {
+                Ok(approvals) => approvals.into(),
+                Err(e) => {
+                    yield SubscribeEvent::Err { message: e.to_string() };
+                    return;
+                }
+            };
+
+            loop {
+                let approval = if let Some(approval) = queue.pop_front() {
+                    approval
+                } else {
+                    match created.recv().await {
+                        Ok(approval) if storage.in_session(&session_id, &approval.session_id) => approval,
+                        Ok(_) => continue,
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {
+                            // Fell behind: pick the backlog up from storage
+                            if let Ok(approvals) = storage.list_pending(Some(&session_id)).await {
+                                queue.extend(approvals);
+                            }
+                            continue;
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+                    }
+                };
+
+                // Skip anything settled while it waited in the queue
+                if !matches!(storage.get_approval(&approval.id).await, Ok(a) if a.status == ApprovalStatus::Pending) {
+                    continue;
+                }
+
+                let settings = storage.session_settings(&approval.session_id);
+                yield SubscribeEvent::Prompted { approval_id: approval.id, tool_name: approval.tool_name.clone() };
+
+                let request = StandardRequest::Confirm {
+                    message: confirm_message(&approval),
+                    default: Some(settings.default_decision.approves()),
+                };
+                let (approve, decided_by, message) =
+                    match ctx.request_with_timeout(request, Duration::from_secs(settings.timeout_secs)).await {
+                        Ok(StandardResponse::Confirmed { value: true }) => (true, DecidedBy::Client, None),
+                        Ok(StandardResponse::Confirmed { value: false }) => {
+                            (false, DecidedBy::Client, Some("Denied by client".to_string()))
+                        }
+                        Ok(_) | Err(BidirError::Cancelled) => {
+                            (false, DecidedBy::Client, Some("Dismissed by client".to_string()))
+                        }
+                        Err(BidirError::Timeout(_)) => {
+                            let approve = settings.default_decision.approves();
+                            let note = format!("Timed out; default decision: {}", if approve { "approve" } else { "deny" });
+                            (approve, DecidedBy::Default, Some(note))
+                        }
+                        Err(e) => {
+                            // The client went away; leave the approval for someone else
+                            yield SubscribeEvent::Err { message: bidir_error_message(&e) };
+                            return;
+                        }
+                    };
+
+                let resolved = match decided_by {
+                    DecidedBy::Client => {
+                        storage.resolve_approval_as(&approval.id, approve, message, Some("bidirectional-client")).await
+                    }
+                    DecidedBy::Default => storage.time_out_approval(&approval.id, approve, message).await,
+                };
+                match resolved {
+                    Ok(()) => yield SubscribeEvent::Resolved { approval_id: approval.id, approved: approve, decided_by },
+                    // Someone else answered while the prompt was open
+                    Err(LoopbackError::AlreadyResolved { .. }) => {
+                        yield SubscribeEvent::Superseded { approval_id: approval.id };
+                    }
+                    Err(e) => yield SubscribeEvent::Err { message: e.to_string() },
+                }
+            }
+        }
+    }
+
     /// Generate MCP configuration for a loopback session
     #[plexus_macros::method(params(
         session_id = "Session ID for correlation"
@@ -322,3 +666,261 @@ impl ClaudeCodeLoopback {
         }
     }
 }
+
+/// The `permit` answer for an approval that has been resolved.
+fn permit_response(approval: ApprovalRequest, input: &Value) -> Value {
+    match approval.status {
+        // Claude Code expects: {"behavior": "allow", "updatedInput": {...}},
+        // with the approver's edits if any
+        ApprovalStatus::Approved => json!({
+            "behavior": "allow",
+            "updatedInput": approval.updated_input.unwrap_or_else(|| input.clone())
+        }),
+        ApprovalStatus::Denied => json!({
+            "behavior": "deny",
+            "message": approval.response_message.unwrap_or_else(|| "Denied by parent".to_string())
+        }),
+        // Timed out with an approving default: the tool runs as requested
+        ApprovalStatus::TimedOut if approval.updated_input.is_some() => json!({
+            "behavior": "allow",
+            "updatedInput": approval.updated_input
+        }),
+        ApprovalStatus::TimedOut | ApprovalStatus::Pending => json!({
+            "behavior": "deny",
+            "message": "Approval timed out"
+        }),
+    }
+}
+
+/// Resolve an approval for `respond`, with the approver's replacement input if given.
+async fn resolve(
+    storage: &LoopbackStorage,
+    approval_id: &ApprovalId,
+    approve: bool,
+    message: Option<String>,
+    responder: &str,
+    updated_input: Option<Value>,
+) -> Result<(), LoopbackError> {
+    match updated_input {
+        Some(_) if !approve => Err(LoopbackError::InvalidInput {
+            detail: "updated_input only applies when approving".to_string(),
+        }),
+        Some(updated_input) => storage
+            .approve_with_input(approval_id, updated_input, message, Some(responder))
+            .await
+            .map(|_| ()),
+        None => storage.resolve_approval_as(approval_id, approve, message, Some(responder)).await,
+    }
+}
+
+/// Apply any overrides to a session's approval settings and return the result.
+fn updated_settings(
+    storage: &LoopbackStorage,
+    session_id: &str,
+    timeout_secs: Option<u64>,
+    default_decision: Option<ApprovalDecision>,
+) -> ApprovalSettings {
+    let mut settings = storage.session_settings(session_id);
+    if timeout_secs.is_none() && default_decision.is_none() {
+        return settings;
+    }
+    if let Some(timeout_secs) = timeout_secs {
+        settings.timeout_secs = timeout_secs;
+    }
+    if let Some(default_decision) = default_decision {
+        settings.default_decision = default_decision;
+    }
+    storage.set_session_settings(session_id, settings);
+    settings
+}
+
+/// The question put to a subscribed client for an approval.
+fn confirm_message(approval: &ApprovalRequest) -> String {
+    let detail = approval
+        .input
+        .get("command")
+        .or_else(|| approval.input.get("prompt"))
+        .or_else(|| approval.input.get("file_path"))
+        .and_then(Value::as_str)
+        .map_or_else(|| approval.input.to_string(), str::to_string);
+    let detail = if detail.chars().count() > 200 {
+        format!("{}…", detail.chars().take(200).collect::<String>())
+    } else {
+        detail
+    };
+    format!("[{}] Allow {}: {detail}?", approval.session_id, approval.tool_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use plexus_core::plexus::bidirectional::auto_respond_channel;
+    use tempfile::tempdir;
+
+    async fn loopback(dir: &tempfile::TempDir) -> ClaudeCodeLoopback {
+        ClaudeCodeLoopback::new(LoopbackStorageConfig { db_path: dir.path().join("loopback.db") })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_pushes_approvals_as_confirm_prompts() {
+        let dir = tempdir().unwrap();
+        let loopback = loopback(&dir).await;
+        let storage = loopback.storage();
+        let ctx = auto_respond_channel(|_: &StandardRequest| StandardResponse::Confirmed { value: true });
+
+        let pending = storage.create_approval("s", "Read", "t0", &json!({"file_path": "a.rs"})).await.unwrap();
+        let events = loopback.subscribe(&ctx, "s".into(), None, None).await;
+        futures::pin_mut!(events);
+
+        assert!(matches!(events.next().await, Some(SubscribeEvent::Subscribed { .. })));
+        assert!(matches!(events.next().await, Some(SubscribeEvent::Prompted { approval_id, .. }) if approval_id == pending.id));
+        assert!(matches!(
+            events.next().await,
+            Some(SubscribeEvent::Resolved { approved: true, decided_by: DecidedBy::Client, .. })
+        ));
+
+        // Approvals created while subscribed are pushed as they arrive
+        let fresh = storage.create_approval("s", "Bash", "t1", &json!({"command": "ls"})).await.unwrap();
+        storage.create_approval("elsewhere", "Bash", "t2", &json!({})).await.unwrap();
+        assert!(matches!(events.next().await, Some(SubscribeEvent::Prompted { approval_id, .. }) if approval_id == fresh.id));
+        assert!(matches!(events.next().await, Some(SubscribeEvent::Resolved { approved: true, .. })));
+        assert_eq!(storage.get_approval(&fresh.id).await.unwrap().status, ApprovalStatus::Approved);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_declined_prompt_denies() {
+        let dir = tempdir().unwrap();
+        let loopback = loopback(&dir).await;
+        let storage = loopback.storage();
+        let ctx = auto_respond_channel(|_: &StandardRequest| StandardResponse::Confirmed { value: false });
+
+        let approval = storage.create_approval("s", "Bash", "t0", &json!({"command": "rm -rf /"})).await.unwrap();
+        let events = loopback.subscribe(&ctx, "s".into(), None, None).await;
+        futures::pin_mut!(events);
+        let events: Vec<SubscribeEvent> = events.take(3).collect().await;
+
+        assert!(matches!(events.last(), Some(SubscribeEvent::Resolved { approved: false, .. })));
+        let resolved = storage.get_approval(&approval.id).await.unwrap();
+        assert_eq!(resolved.status, ApprovalStatus::Denied);
+        assert_eq!(resolved.response_message.as_deref(), Some("Denied by client"));
+    }
+
+    #[tokio::test]
+    async fn test_permit_applies_policy_rules_before_asking() {
+        let dir = tempdir().unwrap();
+        let loopback = loopback(&dir).await;
+        let storage = loopback.storage();
+        let patterns = |command: &str| [("command".to_string(), command.to_string())].into();
+        storage
+            .add_policy(PolicyAction::Allow, Some("Bash".into()), patterns("cargo *"), None, None)
+            .await
+            .unwrap();
+        storage
+            .add_policy(PolicyAction::Deny, Some("Bash".into()), patterns("rm *"), Some("s".into()), Some("no deletes".into()))
+            .await
+            .unwrap();
+
+        let permit = |command: &str| {
+            let loopback = loopback.clone();
+            let input = json!({ "command": command });
+            async move {
+                let responses: Vec<String> = loopback
+                    .permit("Bash".into(), "t".into(), input, Some(json!({"query.session_id": "s"})))
+                    .await
+                    .collect()
+                    .await;
+                serde_json::from_str::<Value>(&responses[0]).unwrap()
+            }
+        };
+
+        assert_eq!(permit("cargo test").await["behavior"], "allow");
+        let denied = permit("rm -rf target").await;
+        assert_eq!(denied["behavior"], "deny");
+        assert!(denied["message"].as_str().unwrap().ends_with(": no deletes"));
+
+        let decisions = storage.list_decisions(Some("s"), 10).await.unwrap();
+        let actions: Vec<PolicyAction> = decisions.iter().map(|d| d.action).collect();
+        assert_eq!(actions, [PolicyAction::Deny, PolicyAction::Allow]);
+        assert!(storage.list_pending(Some("s")).await.unwrap().is_empty(), "nothing was asked");
+    }
+
+    #[tokio::test]
+    async fn test_permit_returns_approver_edited_input() {
+        let dir = tempdir().unwrap();
+        let loopback = loopback(&dir).await;
+        let storage = loopback.storage();
+
+        let permit = {
+            let loopback = loopback.clone();
+            tokio::spawn(async move {
+                let input = json!({"command": "rm -rf target", "timeout": 60});
+                let responses: Vec<String> = loopback
+                    .permit("Bash".into(), "t0".into(), input, Some(json!({"query.session_id": "s"})))
+                    .await
+                    .collect()
+                    .await;
+                serde_json::from_str::<Value>(&responses[0]).unwrap()
+            })
+        };
+        let approval = loop {
+            if let Some(approval) = storage.list_pending(Some("s")).await.unwrap().pop() {
+                break approval;
+            }
+            tokio::task::yield_now().await;
+        };
+
+        let rejected: Vec<RespondResult> = loopback
+            .respond(approval.id, true, None, Some(json!("not json")))
+            .await
+            .collect()
+            .await;
+        assert!(matches!(&rejected[0], RespondResult::Err { message } if message.contains("not valid JSON")));
+
+        let edited = json!(r#"{"command": "rm -rf target/debug", "timeout": 60}"#);
+        let responded: Vec<RespondResult> =
+            loopback.respond(approval.id, true, None, Some(edited)).await.collect().await;
+        assert!(matches!(responded[0], RespondResult::Ok { .. }));
+
+        let response = permit.await.unwrap();
+        assert_eq!(response["behavior"], "allow");
+        assert_eq!(response["updatedInput"], json!({"command": "rm -rf target/debug", "timeout": 60}));
+
+        let resolved = storage.get_approval(&approval.id).await.unwrap();
+        assert_eq!(resolved.responder.as_deref(), Some(UNAUTHENTICATED_RESPONDER));
+        assert_eq!(resolved.input_diff.len(), 1);
+        assert_eq!(resolved.input_diff[0].field, "command");
+        assert_eq!(resolved.input_diff[0].before, Some(json!("rm -rf target")));
+    }
+
+    #[tokio::test]
+    async fn test_permit_applies_default_decision_on_timeout() {
+        let dir = tempdir().unwrap();
+        let loopback = loopback(&dir).await;
+        loopback.storage().set_session_settings(
+            "s",
+            ApprovalSettings { timeout_secs: 0, default_decision: ApprovalDecision::Approve },
+        );
+
+        let connection = json!({"query.session_id": "s"});
+        let responses: Vec<String> = loopback
+            .permit("Bash".into(), "t0".into(), json!({"command": "ls"}), Some(connection))
+            .await
+            .collect()
+            .await;
+        let response: Value = serde_json::from_str(&responses[0]).unwrap();
+        assert_eq!(response["behavior"], "allow");
+
+        // A late answer doesn't overwrite the decision the tool already ran with
+        let approval = loopback.storage().history(&ApprovalFilter::default(), None).await.unwrap().remove(0);
+        let late: Vec<RespondResult> = loopback.respond(approval.id, false, None, None).await.collect().await;
+        assert!(matches!(&late[..], [RespondResult::Err { message }] if message.contains("already resolved")), "{late:?}");
+        let approval = loopback.storage().get_approval(&approval.id).await.unwrap();
+        assert_eq!((approval.status, approval.responder.as_deref()), (ApprovalStatus::TimedOut, Some("timeout")));
+        assert_eq!(approval.updated_input, Some(json!({"command": "ls"})));
+        let timed_out = ApprovalFilter { status: Some(ApprovalStatus::TimedOut), ..ApprovalFilter::default() };
+        assert_eq!(loopback.storage().history(&timed_out, None).await.unwrap().len(), 1);
+    }
+}
@@ -1,4 +1,5 @@
 mod activation;
+mod policy;
 mod storage;
 mod types;
 
@@ -0,0 +1,238 @@
+//! Rule-based auto-approval for `permit`.
+//!
+//! Rules match on the tool name, glob patterns over input fields and an
+//! optional session scope. When several match, `deny` wins over `ask`, and
+//! `ask` over `allow`; when none do, the call goes to a person as before.
+//!
+//! `allow` rules are the risky ones, so they only match inputs whose meaning
+//! is plain: a `command` with no shell metacharacters, and paths without
+//! `..` segments. Path fields are matched in normalised form (`.` segments
+//! and repeated slashes removed, `..` resolved for `deny` and `ask`).
+
+use super::types::{LoopbackError, PolicyAction, PolicyRule};
+use crate::activations::bash::SHELL_METACHARACTERS;
+use regex::Regex;
+use serde_json::Value;
+
+/// Whether `field` holds a filesystem path (`path`, `file_path`, `cwd`, …)
+fn is_path_field(field: &str) -> bool {
+    matches!(field, "path" | "cwd") || field.ends_with("_path")
+}
+
+/// `path` with empty and `.` segments dropped and `..` resolved lexically,
+/// and whether it had any `..` segments.
+fn normalise_path(path: &str) -> (String, bool) {
+    let mut segments: Vec<&str> = Vec::new();
+    let mut parent = false;
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                parent = true;
+                segments.pop();
+            }
+            s => segments.push(s),
+        }
+    }
+    let joined = segments.join("/");
+    let normalised = if path.starts_with('/') { format!("/{joined}") } else { joined };
+    (normalised, parent)
+}
+
+/// Compile a glob (`*` any run of characters, `?` any one) into an anchored regex.
+pub(super) fn glob_regex(pattern: &str) -> Result<Regex, LoopbackError> {
+    let mut source = String::with_capacity(pattern.len() + 8);
+    source.push_str("(?s)^");
+    for c in pattern.chars() {
+        match c {
+            '*' => source.push_str(".*"),
+            '?' => source.push('.'),
+            c => source.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    source.push('$');
+    Regex::new(&source).map_err(|e| LoopbackError::InvalidPattern {
+        pattern: pattern.to_string(),
+        detail: e.to_string(),
+    })
+}
+
+struct CompiledRule {
+    rule: PolicyRule,
+    tool_name: Option<Regex>,
+    input: Vec<(String, Regex)>,
+}
+
+impl CompiledRule {
+    fn new(rule: PolicyRule) -> Result<Self, LoopbackError> {
+        let tool_name = rule.tool_name.as_deref().map(glob_regex).transpose()?;
+        let input = rule
+            .input
+            .iter()
+            .map(|(field, pattern)| Ok((field.clone(), glob_regex(pattern)?)))
+            .collect::<Result<_, LoopbackError>>()?;
+        Ok(Self { rule, tool_name, input })
+    }
+
+    fn matches(&self, tool_name: &str, input: &Value, scopes: &[String]) -> bool {
+        if let Some(scope) = &self.rule.scope {
+            if !scopes.contains(scope) {
+                return false;
+            }
+        }
+        if self.tool_name.as_ref().is_some_and(|re| !re.is_match(tool_name)) {
+            return false;
+        }
+        self.input.iter().all(|(field, re)| {
+            let Some(mut value) = input.get(field).and_then(field_text) else {
+                return false;
+            };
+            let allow = self.rule.action == PolicyAction::Allow;
+            // `git status*` must not approve `git status; rm -rf ~` or `git status > ~/.bashrc`
+            if allow && field == "command" && value.contains(SHELL_METACHARACTERS) {
+                return false;
+            }
+            if is_path_field(field) {
+                let (normalised, parent) = normalise_path(&value);
+                // `/repo/*` must not approve `/repo/../etc/passwd`
+                if allow && parent {
+                    return false;
+                }
+                value = normalised;
+            }
+            re.is_match(&value)
+        })
+    }
+}
+
+fn field_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// A set of compiled policy rules
+pub(super) struct PolicyEngine {
+    rules: Vec<CompiledRule>,
+}
+
+impl PolicyEngine {
+    /// Compile `rules`, failing on the first bad pattern.
+    pub(super) fn new(rules: Vec<PolicyRule>) -> Result<Self, LoopbackError> {
+        let rules = rules.into_iter().map(CompiledRule::new).collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+
+    /// The rule that decides a tool call, if any. `scopes` are the calling
+    /// session and its ancestors.
+    pub(super) fn evaluate(&self, tool_name: &str, input: &Value, scopes: &[String]) -> Option<&PolicyRule> {
+        [PolicyAction::Deny, PolicyAction::Ask, PolicyAction::Allow].into_iter().find_map(|action| {
+            self.rules
+                .iter()
+                .find(|r| r.rule.action == action && r.matches(tool_name, input, scopes))
+                .map(|r| &r.rule)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn rule(action: PolicyAction, tool_name: Option<&str>, input: &[(&str, &str)], scope: Option<&str>) -> PolicyRule {
+        PolicyRule {
+            id: Uuid::new_v4(),
+            action,
+            tool_name: tool_name.map(str::to_string),
+            input: input.iter().map(|(k, v)| ((*k).to_string(), (*v).to_string())).collect(),
+            scope: scope.map(str::to_string),
+            description: None,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_glob_regex() {
+        let re = glob_regex("/repo/src/*.rs").unwrap();
+        assert!(re.is_match("/repo/src/main.rs"));
+        assert!(re.is_match("/repo/src/a/b.rs"));
+        assert!(!re.is_match("/repo/src/main.rsx"));
+        assert!(glob_regex("Bas?").unwrap().is_match("Bash"));
+        assert!(glob_regex("a+b (1)").unwrap().is_match("a+b (1)"));
+    }
+
+    #[test]
+    fn test_deny_beats_ask_beats_allow() {
+        let engine = PolicyEngine::new(vec![
+            rule(PolicyAction::Allow, Some("Bash"), &[("command", "cargo *")], None),
+            rule(PolicyAction::Ask, Some("Bash"), &[("command", "cargo publish*")], None),
+            rule(PolicyAction::Deny, Some("Bash"), &[("command", "*--force*")], None),
+        ])
+        .unwrap();
+        let decide = |command: &str| {
+            engine.evaluate("Bash", &json!({ "command": command }), &[]).map(|r| r.action)
+        };
+
+        assert_eq!(decide("cargo test"), Some(PolicyAction::Allow));
+        assert_eq!(decide("cargo publish"), Some(PolicyAction::Ask));
+        assert_eq!(decide("cargo publish --force"), Some(PolicyAction::Deny));
+        assert_eq!(decide("rm -rf /"), None);
+        assert_eq!(decide("cargo test && rm -rf /"), None, "allow never covers chained commands");
+    }
+
+    #[test]
+    fn test_allow_rejects_shell_metacharacters() {
+        let engine = PolicyEngine::new(vec![rule(PolicyAction::Allow, Some("Bash"), &[("command", "cargo *")], None)]).unwrap();
+        let decide = |command: &str| engine.evaluate("Bash", &json!({ "command": command }), &[]).map(|r| r.action);
+
+        assert_eq!(decide("cargo test --all"), Some(PolicyAction::Allow));
+        for bypass in [
+            "cargo test & rm -rf ~",
+            "cargo test > ~/.bashrc",
+            "cargo test >> ~/.bashrc",
+            "cargo test < /etc/shadow",
+            "cargo test $HOME",
+            "cargo test ${HOME}",
+            "cargo test $(rm -rf ~)",
+            "cargo test `rm -rf ~`",
+            "cargo test\rrm -rf ~",
+        ] {
+            assert_eq!(decide(bypass), None, "{bypass:?}");
+        }
+    }
+
+    #[test]
+    fn test_path_fields_are_normalised() {
+        let engine = PolicyEngine::new(vec![
+            rule(PolicyAction::Allow, Some("Read"), &[("file_path", "/repo/*")], None),
+            rule(PolicyAction::Deny, Some("Read"), &[("file_path", "/etc/*")], None),
+        ])
+        .unwrap();
+        let decide = |path: &str| engine.evaluate("Read", &json!({ "file_path": path }), &[]).map(|r| r.action);
+
+        assert_eq!(decide("/repo/./src//main.rs"), Some(PolicyAction::Allow));
+        assert_eq!(decide("/repo/../home/me/.ssh/id_rsa"), None, "allow never covers `..`");
+        assert_eq!(decide("/repo/src/../../etc/passwd"), Some(PolicyAction::Deny));
+        assert_eq!(normalise_path("a/./b//c/"), ("a/b/c".to_string(), false));
+        assert_eq!(normalise_path("/a/../../b"), ("/b".to_string(), true));
+    }
+
+    #[test]
+    fn test_scope_and_missing_fields() {
+        let engine = PolicyEngine::new(vec![
+            rule(PolicyAction::Allow, Some("Read"), &[("file_path", "/repo/*")], Some("graph-1")),
+        ])
+        .unwrap();
+        let input = json!({ "file_path": "/repo/README.md" });
+        let scopes = ["node-session".to_string(), "graph-1".to_string()];
+
+        assert!(engine.evaluate("Read", &input, &scopes).is_some());
+        assert!(engine.evaluate("Read", &input, &["other".to_string()]).is_none());
+        assert!(engine.evaluate("Read", &json!({}), &scopes).is_none());
+        assert!(engine.evaluate("Write", &input, &scopes).is_none());
+    }
+}
@@ -1,15 +1,23 @@
-use super::types::{ApprovalId, ApprovalRequest, ApprovalStatus, LoopbackError};
+use super::policy::PolicyEngine;
+use super::types::{
+    ApprovalFilter, ApprovalId, ApprovalRequest, ApprovalSettings, ApprovalStats, ApprovalStatus, InputChange, LoopbackError,
+    PolicyAction, PolicyDecision, PolicyRule, PolicyRuleId, ResponderStats, ToolApprovalStats,
+};
 use crate::activations::storage::init_sqlite_pool;
 use crate::activation_db_path_from_module;
 use serde_json::Value;
 use sqlx::{sqlite::SqlitePool, Row};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
-use std::time::{SystemTime, UNIX_EPOCH};
-use tokio::sync::Notify;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, Notify};
 use uuid::Uuid;
 
+/// Columns `row_to_approval` reads
+const APPROVAL_COLUMNS: &str = "id, session_id, tool_name, tool_use_id, input, status, response_message, \
+     created_at, resolved_at, responder, latency_ms, updated_input, input_diff";
+
 #[derive(Debug, Clone)]
 pub struct LoopbackStorageConfig {
     pub db_path: PathBuf,
@@ -37,6 +45,13 @@ pub struct LoopbackStorage {
     /// Maps `parent_session_id` -> [`child_session_id`]
     /// Allows `list_pending` to include child session approvals when querying by parent
     session_children: RwLock<HashMap<String, Vec<String>>>,
+    /// Maps `approval_id` -> Notify woken when that approval is resolved
+    /// Lets `permit` and review gates wait for `respond` instead of polling
+    approval_waiters: RwLock<HashMap<ApprovalId, Arc<Notify>>>,
+    /// Every newly created approval, for `subscribe` to push to its client
+    created_tx: broadcast::Sender<ApprovalRequest>,
+    /// Maps `session_id` -> approval timeout and default decision
+    session_settings: RwLock<HashMap<String, ApprovalSettings>>,
 }
 
 impl LoopbackStorage {
@@ -49,6 +64,9 @@ impl LoopbackStorage {
             session_notifiers: Arc::new(RwLock::new(HashMap::new())),
             session_parents: RwLock::new(HashMap::new()),
             session_children: RwLock::new(HashMap::new()),
+            approval_waiters: RwLock::new(HashMap::new()),
+            created_tx: broadcast::channel(256).0,
+            session_settings: RwLock::new(HashMap::new()),
         };
         storage.run_migrations().await?;
         Ok(storage)
@@ -90,10 +108,43 @@ impl LoopbackStorage {
             );
             CREATE INDEX IF NOT EXISTS idx_loopback_session ON loopback_approvals(session_id);
             CREATE INDEX IF NOT EXISTS idx_loopback_status ON loopback_approvals(status);
+
+            CREATE TABLE IF NOT EXISTS loopback_policies (
+                id TEXT PRIMARY KEY,
+                action TEXT NOT NULL,
+                tool_name TEXT,
+                input TEXT NOT NULL DEFAULT '{}',
+                scope TEXT,
+                description TEXT,
+                created_at INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS loopback_decisions (
+                id TEXT PRIMARY KEY,
+                session_id TEXT NOT NULL,
+                tool_name TEXT NOT NULL,
+                tool_use_id TEXT NOT NULL,
+                input TEXT NOT NULL,
+                action TEXT NOT NULL,
+                rule_id TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_loopback_decisions_session ON loopback_decisions(session_id);
         ")
         .execute(&self.pool)
         .await
         .map_err(|e| LoopbackError::Storage { operation: "migration", detail: e.to_string() })?;
+
+        // Migration: resolution audit columns (fail harmlessly when present)
+        for column in ["created_ms INTEGER", "responder TEXT", "latency_ms INTEGER", "updated_input TEXT", "input_diff TEXT"] {
+            let _ = sqlx::query(&format!("ALTER TABLE loopback_approvals ADD COLUMN {column}"))
+                .execute(&self.pool)
+                .await;
+        }
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_loopback_created ON loopback_approvals(created_at)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| LoopbackError::Storage { operation: "migration", detail: e.to_string() })?;
         Ok(())
     }
 
@@ -105,13 +156,14 @@ impl LoopbackStorage {
         input: &Value,
     ) -> Result<ApprovalRequest, LoopbackError> {
         let id = Uuid::new_v4();
-        let now = current_timestamp();
+        let now_ms = current_timestamp_ms();
+        let now = now_ms / 1000;
         let input_json = serde_json::to_string(input)
             .map_err(|e| LoopbackError::Serialization { detail: e.to_string() })?;
 
         sqlx::query(
-            "INSERT INTO loopback_approvals (id, session_id, tool_name, tool_use_id, input, status, created_at)
-             VALUES (?, ?, ?, ?, ?, 'pending', ?)"
+            "INSERT INTO loopback_approvals (id, session_id, tool_name, tool_use_id, input, status, created_at, created_ms)
+             VALUES (?, ?, ?, ?, ?, 'pending', ?, ?)"
         )
         .bind(id.to_string())
         .bind(session_id)
@@ -119,6 +171,7 @@ impl LoopbackStorage {
         .bind(tool_use_id)
         .bind(&input_json)
         .bind(now)
+        .bind(now_ms)
         .execute(&self.pool)
         .await
         .map_err(|e| LoopbackError::Storage { operation: "create_approval", detail: e.to_string() })?;
@@ -126,7 +179,7 @@ impl LoopbackStorage {
         // Notify any waiters that a new approval has arrived
         self.notify_session(session_id);
 
-        Ok(ApprovalRequest {
+        let approval = ApprovalRequest {
             id,
             session_id: session_id.to_string(),
             tool_name: tool_name.to_string(),
@@ -136,14 +189,18 @@ impl LoopbackStorage {
             response_message: None,
             created_at: now,
             resolved_at: None,
-        })
+            responder: None,
+            latency_ms: None,
+            updated_input: None,
+            input_diff: Vec::new(),
+        };
+        // No subscribers is fine — nobody is pushing approvals to a client
+        let _ = self.created_tx.send(approval.clone());
+        Ok(approval)
     }
 
     pub async fn get_approval(&self, id: &ApprovalId) -> Result<ApprovalRequest, LoopbackError> {
-        let row = sqlx::query(
-            "SELECT id, session_id, tool_name, tool_use_id, input, status, response_message, created_at, resolved_at
-             FROM loopback_approvals WHERE id = ?"
-        )
+        let row = sqlx::query(&format!("SELECT {APPROVAL_COLUMNS} FROM loopback_approvals WHERE id = ?"))
         .bind(id.to_string())
         .fetch_optional(&self.pool)
         .await
@@ -159,26 +216,191 @@ impl LoopbackStorage {
         approved: bool,
         message: Option<String>,
     ) -> Result<(), LoopbackError> {
-        let now = current_timestamp();
-        let status = if approved { "approved" } else { "denied" };
+        self.resolve_approval_as(id, approved, message, None).await
+    }
+
+    /// Resolve an approval, recording who resolved it. Latency and, for
+    /// approvals, the input the tool runs with are recorded alongside.
+    ///
+    /// Only a pending approval can be resolved: the first resolution wins and
+    /// later ones get `AlreadyResolved` with the status it ended up in.
+    pub async fn resolve_approval_as(
+        &self,
+        id: &ApprovalId,
+        approved: bool,
+        message: Option<String>,
+        responder: Option<&str>,
+    ) -> Result<(), LoopbackError> {
+        let status = if approved { ApprovalStatus::Approved } else { ApprovalStatus::Denied };
+        self.resolve(id, status, approved, message, responder, None).await
+    }
+
+    /// Settle an approval nobody answered in time with the session's default
+    /// decision. It is recorded as `TimedOut` by responder `timeout`; when the
+    /// default approves, `updated_input` holds the input the tool runs with.
+    pub async fn time_out_approval(
+        &self,
+        id: &ApprovalId,
+        approve: bool,
+        message: Option<String>,
+    ) -> Result<(), LoopbackError> {
+        self.resolve(id, ApprovalStatus::TimedOut, approve, message, Some("timeout"), None).await
+    }
+
+    /// Approve with a replacement tool input. `updated_input` must be a JSON
+    /// object (or a string holding one); the fields changed from the
+    /// requested input are recorded as the approval's `input_diff`.
+    pub async fn approve_with_input(
+        &self,
+        id: &ApprovalId,
+        updated_input: Value,
+        message: Option<String>,
+        responder: Option<&str>,
+    ) -> Result<Vec<InputChange>, LoopbackError> {
+        let updated_input = parse_updated_input(updated_input)?;
+        let current = self.get_approval(id).await?;
+        let diff = InputChange::diff(&current.input, &updated_input);
+        self.resolve(id, ApprovalStatus::Approved, true, message, responder, Some((&updated_input, &diff))).await?;
+        Ok(diff)
+    }
+
+    /// Move a pending approval to `status`; `approved` says whether the tool
+    /// runs, and so whether `updated_input` is kept.
+    async fn resolve(
+        &self,
+        id: &ApprovalId,
+        status: ApprovalStatus,
+        approved: bool,
+        message: Option<String>,
+        responder: Option<&str>,
+        edit: Option<(&Value, &[InputChange])>,
+    ) -> Result<(), LoopbackError> {
+        let now_ms = current_timestamp_ms();
+        let (edited_input, diff) = match edit {
+            Some((input, diff)) => (
+                Some(serde_json::to_string(input).map_err(|e| LoopbackError::Serialization { detail: e.to_string() })?),
+                Some(serde_json::to_string(diff).map_err(|e| LoopbackError::Serialization { detail: e.to_string() })?),
+            ),
+            None => (None, None),
+        };
 
         let result = sqlx::query(
-            "UPDATE loopback_approvals SET status = ?, response_message = ?, resolved_at = ? WHERE id = ?"
+            "UPDATE loopback_approvals
+             SET status = ?, response_message = ?, resolved_at = ?, responder = ?,
+                 latency_ms = ? - COALESCE(created_ms, created_at * 1000),
+                 updated_input = CASE WHEN ? THEN COALESCE(?, input) ELSE NULL END,
+                 input_diff = ?
+             WHERE id = ? AND status = 'pending'"
         )
-        .bind(status)
+        .bind(status_str(status))
         .bind(&message)
-        .bind(now)
+        .bind(now_ms / 1000)
+        .bind(responder)
+        .bind(now_ms)
+        .bind(approved)
+        .bind(edited_input)
+        .bind(diff)
         .bind(id.to_string())
         .execute(&self.pool)
         .await
         .map_err(|e| LoopbackError::Storage { operation: "resolve_approval", detail: e.to_string() })?;
 
         if result.rows_affected() == 0 {
-            return Err(LoopbackError::ApprovalNotFound { id: id.to_string() });
+            let current = self.get_approval(id).await?;
+            return Err(LoopbackError::AlreadyResolved { id: id.to_string(), status: current.status });
+        }
+
+        // Wake everyone waiting on this approval
+        let waiter = self.approval_waiters.write().ok().and_then(|mut waiters| waiters.remove(id));
+        if let Some(waiter) = waiter {
+            waiter.notify_waiters();
         }
         Ok(())
     }
 
+    /// Wait until an approval is resolved, or `timeout` passes (`None` waits
+    /// forever). Returns the approval as it stands — still `Pending` if the
+    /// timeout ran out first.
+    pub async fn wait_for_resolution(
+        &self,
+        id: &ApprovalId,
+        timeout: Option<Duration>,
+    ) -> Result<ApprovalRequest, LoopbackError> {
+        let deadline = timeout.map(|t| tokio::time::Instant::now() + t);
+        let waiter = self.approval_waiter(id);
+
+        loop {
+            // Register interest before reading the status so a resolution
+            // landing in between still wakes us
+            let resolved = waiter.notified();
+            tokio::pin!(resolved);
+            resolved.as_mut().enable();
+
+            let current = self.get_approval(id).await?;
+            if current.status != ApprovalStatus::Pending {
+                if let Ok(mut waiters) = self.approval_waiters.write() {
+                    waiters.remove(id);
+                }
+                return Ok(current);
+            }
+
+            match deadline {
+                Some(deadline) => {
+                    if tokio::time::timeout_at(deadline, resolved).await.is_err() {
+                        return Ok(current);
+                    }
+                }
+                None => resolved.await,
+            }
+        }
+    }
+
+    fn approval_waiter(&self, id: &ApprovalId) -> Arc<Notify> {
+        self.approval_waiters
+            .write()
+            .ok()
+            .map(|mut waiters| waiters.entry(*id).or_insert_with(|| Arc::new(Notify::new())).clone())
+            // Unregistered, so only the wait's timeout ends it
+            .unwrap_or_default()
+    }
+
+    /// Receive every approval created from now on (all sessions)
+    pub fn subscribe_created(&self) -> broadcast::Receiver<ApprovalRequest> {
+        self.created_tx.subscribe()
+    }
+
+    /// Set the approval timeout and default decision for a session.
+    /// Child sessions without their own settings use their parent's.
+    pub fn set_session_settings(&self, session_id: &str, settings: ApprovalSettings) {
+        if let Ok(mut map) = self.session_settings.write() {
+            map.insert(session_id.to_string(), settings);
+        }
+    }
+
+    /// Approval settings for a session: its own, else its parent's, else the defaults
+    pub fn session_settings(&self, session_id: &str) -> ApprovalSettings {
+        let Ok(map) = self.session_settings.read() else {
+            return ApprovalSettings::default();
+        };
+        if let Some(settings) = map.get(session_id) {
+            return *settings;
+        }
+        self.session_parents
+            .read()
+            .ok()
+            .and_then(|parents| parents.get(session_id).and_then(|parent| map.get(parent)).copied())
+            .unwrap_or_default()
+    }
+
+    /// Whether `approval_session` is `session_id` or one of its registered children
+    pub fn in_session(&self, session_id: &str, approval_session: &str) -> bool {
+        session_id == approval_session
+            || self
+                .session_children
+                .read()
+                .is_ok_and(|children| children.get(session_id).is_some_and(|ids| ids.iter().any(|c| c == approval_session)))
+    }
+
     /// Get all pending approvals for a session
     pub async fn get_pending_approvals(&self, session_id: &str) -> Vec<ApprovalRequest> {
         let rows = sqlx::query(
@@ -205,10 +427,10 @@ impl LoopbackStorage {
             }
 
             if session_ids.len() == 1 {
-                sqlx::query(
-                    "SELECT id, session_id, tool_name, tool_use_id, input, status, response_message, created_at, resolved_at
+                sqlx::query(&format!(
+                    "SELECT {APPROVAL_COLUMNS}
                      FROM loopback_approvals WHERE session_id = ? AND status = 'pending' ORDER BY created_at"
-                )
+                ))
                 .bind(&session_ids[0])
                 .fetch_all(&self.pool)
                 .await
@@ -216,7 +438,7 @@ impl LoopbackStorage {
                 // Build IN clause for multiple session IDs
                 let placeholders = session_ids.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
                 let query_str = format!(
-                    "SELECT id, session_id, tool_name, tool_use_id, input, status, response_message, created_at, resolved_at
+                    "SELECT {APPROVAL_COLUMNS}
                      FROM loopback_approvals WHERE session_id IN ({placeholders}) AND status = 'pending' ORDER BY created_at"
                 );
                 let mut q = sqlx::query(&query_str);
@@ -226,10 +448,10 @@ impl LoopbackStorage {
                 q.fetch_all(&self.pool).await
             }
         } else {
-            sqlx::query(
-                "SELECT id, session_id, tool_name, tool_use_id, input, status, response_message, created_at, resolved_at
+            sqlx::query(&format!(
+                "SELECT {APPROVAL_COLUMNS}
                  FROM loopback_approvals WHERE status = 'pending' ORDER BY created_at"
-            )
+            ))
             .fetch_all(&self.pool)
             .await
         }
@@ -261,9 +483,340 @@ impl LoopbackStorage {
             response_message: row.get("response_message"),
             created_at: row.get("created_at"),
             resolved_at: row.get("resolved_at"),
+            responder: row.get("responder"),
+            latency_ms: row.get("latency_ms"),
+            updated_input: row
+                .get::<Option<String>, _>("updated_input")
+                .and_then(|json| serde_json::from_str(&json).ok()),
+            input_diff: row
+                .get::<Option<String>, _>("input_diff")
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default(),
         })
     }
 
+    // ─── History ────────────────────────────────────────────────────────────
+
+    /// Approvals matching `filter`, newest first.
+    pub async fn history(&self, filter: &ApprovalFilter, limit: Option<usize>) -> Result<Vec<ApprovalRequest>, LoopbackError> {
+        let mut clauses = Vec::new();
+        let mut binds: Vec<String> = Vec::new();
+        if let Some(session_id) = &filter.session_id {
+            clauses.push("session_id = ?".to_string());
+            binds.push(session_id.clone());
+        }
+        if let Some(graph_id) = &filter.graph_id {
+            let sessions = self.session_family(graph_id);
+            clauses.push(format!("session_id IN ({})", vec!["?"; sessions.len()].join(", ")));
+            binds.extend(sessions);
+        }
+        if let Some(tool_name) = &filter.tool_name {
+            clauses.push("tool_name = ?".to_string());
+            binds.push(tool_name.clone());
+        }
+        if let Some(status) = filter.status {
+            clauses.push("status = ?".to_string());
+            binds.push(status_str(status).to_string());
+        }
+        let mut query = format!("SELECT {APPROVAL_COLUMNS} FROM loopback_approvals WHERE 1 = 1");
+        for clause in &clauses {
+            query.push_str(" AND ");
+            query.push_str(clause);
+        }
+        query.push_str(" AND created_at >= ? AND created_at < ? ORDER BY created_at DESC, rowid DESC LIMIT ?");
+
+        let mut q = sqlx::query(&query);
+        for bind in &binds {
+            q = q.bind(bind);
+        }
+        let limit = limit.map_or(-1, |n| i64::try_from(n).unwrap_or(i64::MAX));
+        let rows = q
+            .bind(filter.since.unwrap_or(i64::MIN))
+            .bind(filter.until.unwrap_or(i64::MAX))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| LoopbackError::Storage { operation: "history", detail: e.to_string() })?;
+
+        rows.into_iter().map(|r| self.row_to_approval(r)).collect()
+    }
+
+    /// Counts, latencies and per-tool / per-responder breakdowns for the
+    /// approvals matching `filter`, plus the policy decisions in the same range.
+    pub async fn stats(&self, filter: &ApprovalFilter) -> Result<ApprovalStats, LoopbackError> {
+        let approvals = self.history(filter, None).await?;
+        let decisions = if filter.status.is_some() {
+            Vec::new()
+        } else {
+            self.list_decisions(None, usize::MAX)
+                .await?
+                .into_iter()
+                .filter(|d| {
+                    filter.session_id.as_ref().is_none_or(|s| *s == d.session_id)
+                        && filter.graph_id.as_ref().is_none_or(|g| self.in_session(g, &d.session_id))
+                        && filter.tool_name.as_ref().is_none_or(|t| *t == d.tool_name)
+                        && filter.since.is_none_or(|since| d.created_at >= since)
+                        && filter.until.is_none_or(|until| d.created_at < until)
+                })
+                .collect()
+        };
+
+        let mut stats = ApprovalStats::default();
+        let mut tools: HashMap<String, (ToolApprovalStats, Vec<i64>)> = HashMap::new();
+        let mut responders: HashMap<String, (ResponderStats, Vec<i64>)> = HashMap::new();
+        let mut latencies = Vec::new();
+
+        for approval in &approvals {
+            let (tool, tool_latencies) = tools.entry(approval.tool_name.clone()).or_insert_with(|| {
+                (ToolApprovalStats { tool_name: approval.tool_name.clone(), ..Default::default() }, Vec::new())
+            });
+            stats.total += 1;
+            tool.total += 1;
+            match approval.status {
+                ApprovalStatus::Approved => {
+                    stats.approved += 1;
+                    tool.approved += 1;
+                }
+                ApprovalStatus::Denied => {
+                    stats.denied += 1;
+                    tool.denied += 1;
+                }
+                ApprovalStatus::Pending => {
+                    stats.pending += 1;
+                    tool.pending += 1;
+                }
+                ApprovalStatus::TimedOut => stats.timed_out += 1,
+            }
+            if let Some(latency) = approval.latency_ms {
+                latencies.push(latency);
+                tool_latencies.push(latency);
+            }
+            if let Some(responder) = &approval.responder {
+                let (entry, responder_latencies) = responders.entry(responder.clone()).or_insert_with(|| {
+                    (ResponderStats { responder: responder.clone(), ..Default::default() }, Vec::new())
+                });
+                match approval.status {
+                    ApprovalStatus::Approved => entry.approved += 1,
+                    ApprovalStatus::Denied | ApprovalStatus::TimedOut => entry.denied += 1,
+                    ApprovalStatus::Pending => {}
+                }
+                responder_latencies.extend(approval.latency_ms);
+            }
+        }
+
+        for decision in &decisions {
+            let (tool, _) = tools.entry(decision.tool_name.clone()).or_insert_with(|| {
+                (ToolApprovalStats { tool_name: decision.tool_name.clone(), ..Default::default() }, Vec::new())
+            });
+            match decision.action {
+                PolicyAction::Allow => {
+                    stats.policy_allowed += 1;
+                    tool.policy_allowed += 1;
+                }
+                PolicyAction::Deny => {
+                    stats.policy_denied += 1;
+                    tool.policy_denied += 1;
+                }
+                PolicyAction::Ask => {}
+            }
+        }
+
+        stats.avg_latency_ms = mean(&latencies);
+        stats.max_latency_ms = latencies.iter().copied().max();
+        stats.by_tool = tools
+            .into_values()
+            .map(|(mut tool, latencies)| {
+                tool.avg_latency_ms = mean(&latencies);
+                tool
+            })
+            .collect();
+        stats.by_tool.sort_by(|a, b| {
+            (b.denied + b.policy_denied, b.total)
+                .cmp(&(a.denied + a.policy_denied, a.total))
+                .then_with(|| a.tool_name.cmp(&b.tool_name))
+        });
+        stats.by_responder = responders
+            .into_values()
+            .map(|(mut responder, latencies)| {
+                responder.avg_latency_ms = mean(&latencies);
+                responder
+            })
+            .collect();
+        stats.by_responder.sort_by(|a, b| {
+            (b.approved + b.denied).cmp(&(a.approved + a.denied)).then_with(|| a.responder.cmp(&b.responder))
+        });
+        Ok(stats)
+    }
+
+    /// A session and every child session registered under it
+    fn session_family(&self, session_id: &str) -> Vec<String> {
+        let mut sessions = vec![session_id.to_string()];
+        if let Ok(children) = self.session_children.read() {
+            if let Some(child_ids) = children.get(session_id) {
+                sessions.extend(child_ids.iter().cloned());
+            }
+        }
+        sessions
+    }
+
+    // ─── Policies ───────────────────────────────────────────────────────────
+
+    /// Store a policy rule, rejecting it if a pattern doesn't compile.
+    pub async fn add_policy(
+        &self,
+        action: PolicyAction,
+        tool_name: Option<String>,
+        input: BTreeMap<String, String>,
+        scope: Option<String>,
+        description: Option<String>,
+    ) -> Result<PolicyRule, LoopbackError> {
+        let rule = PolicyRule {
+            id: Uuid::new_v4(),
+            action,
+            tool_name,
+            input,
+            scope,
+            description,
+            created_at: current_timestamp(),
+        };
+        PolicyEngine::new(vec![rule.clone()])?;
+
+        let input_json = serde_json::to_string(&rule.input)
+            .map_err(|e| LoopbackError::Serialization { detail: e.to_string() })?;
+        sqlx::query(
+            "INSERT INTO loopback_policies (id, action, tool_name, input, scope, description, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(rule.id.to_string())
+        .bind(rule.action.as_str())
+        .bind(&rule.tool_name)
+        .bind(&input_json)
+        .bind(&rule.scope)
+        .bind(&rule.description)
+        .bind(rule.created_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| LoopbackError::Storage { operation: "add_policy", detail: e.to_string() })?;
+        Ok(rule)
+    }
+
+    /// Policy rules in creation order; with `scope`, only the rules scoped to it.
+    pub async fn list_policies(&self, scope: Option<&str>) -> Result<Vec<PolicyRule>, LoopbackError> {
+        let rows = sqlx::query(
+            "SELECT id, action, tool_name, input, scope, description, created_at
+             FROM loopback_policies WHERE ? IS NULL OR scope = ? ORDER BY created_at, rowid"
+        )
+        .bind(scope)
+        .bind(scope)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| LoopbackError::Storage { operation: "list_policies", detail: e.to_string() })?;
+
+        rows.into_iter().map(row_to_policy).collect()
+    }
+
+    pub async fn remove_policy(&self, id: &PolicyRuleId) -> Result<(), LoopbackError> {
+        let result = sqlx::query("DELETE FROM loopback_policies WHERE id = ?")
+            .bind(id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| LoopbackError::Storage { operation: "remove_policy", detail: e.to_string() })?;
+        if result.rows_affected() == 0 {
+            return Err(LoopbackError::PolicyNotFound { id: id.to_string() });
+        }
+        Ok(())
+    }
+
+    /// The policy rule that decides a tool call from `session_id`, if any.
+    pub async fn check_policies(
+        &self,
+        session_id: &str,
+        tool_name: &str,
+        input: &Value,
+    ) -> Result<Option<PolicyRule>, LoopbackError> {
+        let rules = self.list_policies(None).await?;
+        if rules.is_empty() {
+            return Ok(None);
+        }
+        let engine = PolicyEngine::new(rules)?;
+        Ok(engine.evaluate(tool_name, input, &self.session_scopes(session_id)).cloned())
+    }
+
+    /// A session and the chain of parents registered above it
+    fn session_scopes(&self, session_id: &str) -> Vec<String> {
+        let mut scopes = vec![session_id.to_string()];
+        if let Ok(parents) = self.session_parents.read() {
+            while let Some(parent) = parents.get(scopes.last().map_or("", String::as_str)) {
+                if scopes.contains(parent) {
+                    break;
+                }
+                scopes.push(parent.clone());
+            }
+        }
+        scopes
+    }
+
+    /// Record a tool call settled by a policy rule in the audit trail.
+    pub async fn record_decision(
+        &self,
+        session_id: &str,
+        tool_name: &str,
+        tool_use_id: &str,
+        input: &Value,
+        rule: &PolicyRule,
+    ) -> Result<PolicyDecision, LoopbackError> {
+        let decision = PolicyDecision {
+            id: Uuid::new_v4(),
+            session_id: session_id.to_string(),
+            tool_name: tool_name.to_string(),
+            tool_use_id: tool_use_id.to_string(),
+            input: input.clone(),
+            action: rule.action,
+            rule_id: rule.id,
+            created_at: current_timestamp(),
+        };
+        let input_json = serde_json::to_string(input)
+            .map_err(|e| LoopbackError::Serialization { detail: e.to_string() })?;
+        sqlx::query(
+            "INSERT INTO loopback_decisions (id, session_id, tool_name, tool_use_id, input, action, rule_id, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(decision.id.to_string())
+        .bind(session_id)
+        .bind(tool_name)
+        .bind(tool_use_id)
+        .bind(&input_json)
+        .bind(decision.action.as_str())
+        .bind(decision.rule_id.to_string())
+        .bind(decision.created_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| LoopbackError::Storage { operation: "record_decision", detail: e.to_string() })?;
+        Ok(decision)
+    }
+
+    /// Policy decisions, newest first; with `session_id`, only that session's
+    /// and its registered children's.
+    pub async fn list_decisions(
+        &self,
+        session_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<PolicyDecision>, LoopbackError> {
+        let rows = sqlx::query(
+            "SELECT id, session_id, tool_name, tool_use_id, input, action, rule_id, created_at
+             FROM loopback_decisions ORDER BY created_at DESC, rowid DESC"
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| LoopbackError::Storage { operation: "list_decisions", detail: e.to_string() })?;
+
+        rows.into_iter()
+            .map(row_to_decision)
+            .filter(|d| d.as_ref().map_or(true, |d| session_id.is_none_or(|sid| self.in_session(sid, &d.session_id))))
+            .take(limit)
+            .collect()
+    }
+
     /// Get or create a notifier for a session
     /// This allows multiple `wait_for_approval` calls to wait on the same session
     pub fn get_or_create_notifier(&self, session_id: &str) -> Arc<Notify> {
@@ -319,9 +872,192 @@ impl LoopbackStorage {
     }
 }
 
+fn parse_action(action: &str) -> Result<PolicyAction, LoopbackError> {
+    match action {
+        "allow" => Ok(PolicyAction::Allow),
+        "deny" => Ok(PolicyAction::Deny),
+        "ask" => Ok(PolicyAction::Ask),
+        other => Err(LoopbackError::InvalidData { detail: format!("Unknown policy action '{other}'") }),
+    }
+}
+
+fn parse_uuid(id: &str) -> Result<Uuid, LoopbackError> {
+    Uuid::parse_str(id).map_err(|e| LoopbackError::InvalidData { detail: format!("Invalid UUID '{id}': {e}") })
+}
+
+fn row_to_policy(row: sqlx::sqlite::SqliteRow) -> Result<PolicyRule, LoopbackError> {
+    let input_json: String = row.get("input");
+    Ok(PolicyRule {
+        id: parse_uuid(row.get("id"))?,
+        action: parse_action(row.get("action"))?,
+        tool_name: row.get("tool_name"),
+        input: serde_json::from_str(&input_json)
+            .map_err(|e| LoopbackError::InvalidData { detail: format!("Invalid policy input patterns: {e}") })?,
+        scope: row.get("scope"),
+        description: row.get("description"),
+        created_at: row.get("created_at"),
+    })
+}
+
+fn row_to_decision(row: sqlx::sqlite::SqliteRow) -> Result<PolicyDecision, LoopbackError> {
+    let input_json: String = row.get("input");
+    Ok(PolicyDecision {
+        id: parse_uuid(row.get("id"))?,
+        session_id: row.get("session_id"),
+        tool_name: row.get("tool_name"),
+        tool_use_id: row.get("tool_use_id"),
+        input: serde_json::from_str(&input_json).unwrap_or(Value::Null),
+        action: parse_action(row.get("action"))?,
+        rule_id: parse_uuid(row.get("rule_id"))?,
+        created_at: row.get("created_at"),
+    })
+}
+
+/// Accept a replacement tool input given as a JSON object or as a string of one.
+fn parse_updated_input(value: Value) -> Result<Value, LoopbackError> {
+    let value = match value {
+        Value::String(text) => serde_json::from_str(&text)
+            .map_err(|e| LoopbackError::InvalidInput { detail: format!("not valid JSON: {e}") })?,
+        other => other,
+    };
+    if value.is_object() {
+        Ok(value)
+    } else {
+        Err(LoopbackError::InvalidInput { detail: "tool input must be a JSON object".to_string() })
+    }
+}
+
+const fn status_str(status: ApprovalStatus) -> &'static str {
+    match status {
+        ApprovalStatus::Pending => "pending",
+        ApprovalStatus::Approved => "approved",
+        ApprovalStatus::Denied => "denied",
+        ApprovalStatus::TimedOut => "timed_out",
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn mean(values: &[i64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<i64>() as f64 / values.len() as f64)
+}
+
+fn current_timestamp_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+}
+
 fn current_timestamp() -> i64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs() as i64
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activations::claudecode_loopback::{ApprovalDecision, ApprovalFilter};
+    use tempfile::tempdir;
+
+    async fn storage(dir: &tempfile::TempDir) -> Arc<LoopbackStorage> {
+        let config = LoopbackStorageConfig { db_path: dir.path().join("loopback.db") };
+        Arc::new(LoopbackStorage::new(config).await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_resolution_wakes_on_resolve() {
+        let dir = tempdir().unwrap();
+        let storage = storage(&dir).await;
+        let approval = storage.create_approval("s", "Bash", "t1", &serde_json::json!({})).await.unwrap();
+
+        let waiter = {
+            let storage = storage.clone();
+            tokio::spawn(async move { storage.wait_for_resolution(&approval.id, None).await })
+        };
+        tokio::task::yield_now().await;
+        storage.resolve_approval(&approval.id, true, None).await.unwrap();
+
+        let resolved = tokio::time::timeout(Duration::from_secs(5), waiter).await.unwrap().unwrap().unwrap();
+        assert_eq!(resolved.status, ApprovalStatus::Approved);
+    }
+
+    #[tokio::test]
+    async fn test_first_resolution_wins() {
+        let dir = tempdir().unwrap();
+        let storage = storage(&dir).await;
+        let approval = storage.create_approval("s", "Bash", "t1", &serde_json::json!({})).await.unwrap();
+
+        storage.time_out_approval(&approval.id, false, Some("no".into())).await.unwrap();
+        let late = storage.resolve_approval_as(&approval.id, true, None, Some("alice")).await;
+        assert!(matches!(late, Err(LoopbackError::AlreadyResolved { status: ApprovalStatus::TimedOut, .. })), "{late:?}");
+
+        let current = storage.get_approval(&approval.id).await.unwrap();
+        assert_eq!((current.status, current.responder.as_deref()), (ApprovalStatus::TimedOut, Some("timeout")));
+        assert_eq!(current.updated_input, None);
+        let stats = storage.stats(&ApprovalFilter::default()).await.unwrap();
+        assert_eq!((stats.timed_out, stats.denied), (1, 0));
+        assert!(matches!(
+            storage.resolve_approval(&Uuid::new_v4(), true, None).await,
+            Err(LoopbackError::ApprovalNotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_resolution_times_out_pending() {
+        let dir = tempdir().unwrap();
+        let storage = storage(&dir).await;
+        let approval = storage.create_approval("s", "Bash", "t1", &serde_json::json!({})).await.unwrap();
+
+        let current = storage.wait_for_resolution(&approval.id, Some(Duration::from_millis(50))).await.unwrap();
+        assert_eq!(current.status, ApprovalStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn test_child_sessions_inherit_settings() {
+        let dir = tempdir().unwrap();
+        let storage = storage(&dir).await;
+        let settings = ApprovalSettings { timeout_secs: 10, default_decision: ApprovalDecision::Approve };
+        storage.set_session_settings("parent", settings);
+        storage.register_session_parent("child", "parent");
+
+        assert_eq!(storage.session_settings("child"), settings);
+        assert_eq!(storage.session_settings("other"), ApprovalSettings::default());
+        assert!(storage.in_session("parent", "child"));
+        assert!(!storage.in_session("child", "parent"));
+    }
+
+    #[tokio::test]
+    async fn test_history_and_stats_record_resolutions() {
+        let dir = tempdir().unwrap();
+        let storage = storage(&dir).await;
+        storage.register_session_parent("node", "graph");
+        let input = serde_json::json!({"command": "ls"});
+
+        let approved = storage.create_approval("node", "Bash", "t1", &input).await.unwrap();
+        let denied = storage.create_approval("graph", "Write", "t2", &serde_json::json!({})).await.unwrap();
+        storage.create_approval("other", "Bash", "t3", &input).await.unwrap();
+        storage.resolve_approval_as(&approved.id, true, None, Some("alice")).await.unwrap();
+        storage.resolve_approval_as(&denied.id, false, Some("no".into()), Some("alice")).await.unwrap();
+
+        let resolved = storage.get_approval(&approved.id).await.unwrap();
+        assert_eq!(resolved.responder.as_deref(), Some("alice"));
+        assert_eq!(resolved.updated_input, Some(input));
+        assert!(resolved.latency_ms.is_some_and(|ms| ms >= 0));
+        assert_eq!(storage.get_approval(&denied.id).await.unwrap().updated_input, None);
+
+        let graph = ApprovalFilter { graph_id: Some("graph".into()), ..Default::default() };
+        assert_eq!(storage.history(&graph, None).await.unwrap().len(), 2);
+        let bash = ApprovalFilter { tool_name: Some("Bash".into()), status: Some(ApprovalStatus::Pending), ..Default::default() };
+        assert_eq!(storage.history(&bash, None).await.unwrap()[0].session_id, "other");
+        let future = ApprovalFilter { since: Some(current_timestamp() + 60), ..Default::default() };
+        assert!(storage.history(&future, None).await.unwrap().is_empty());
+
+        let stats = storage.stats(&ApprovalFilter::default()).await.unwrap();
+        assert_eq!((stats.total, stats.approved, stats.denied, stats.pending), (3, 1, 1, 1));
+        assert_eq!(stats.by_tool[0].tool_name, "Write", "most-denied tool first");
+        assert_eq!(stats.by_responder.len(), 1);
+        assert_eq!((stats.by_responder[0].approved, stats.by_responder[0].denied), (1, 1));
+    }
+}
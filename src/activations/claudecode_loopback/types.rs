@@ -1,6 +1,7 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::BTreeMap;
 use thiserror::Error;
 use uuid::Uuid;
 
@@ -16,11 +17,23 @@ pub enum LoopbackError {
     #[error("Approval not found: {id}")]
     ApprovalNotFound { id: String },
 
+    #[error("Approval {id} was already resolved ({status:?})")]
+    AlreadyResolved { id: String, status: ApprovalStatus },
+
     #[error("Serialization failed: {detail}")]
     Serialization { detail: String },
 
     #[error("Invalid data: {detail}")]
     InvalidData { detail: String },
+
+    #[error("Policy rule not found: {id}")]
+    PolicyNotFound { id: String },
+
+    #[error("Invalid policy pattern '{pattern}': {detail}")]
+    InvalidPattern { pattern: String, detail: String },
+
+    #[error("Invalid updated input: {detail}")]
+    InvalidInput { detail: String },
 }
 
 impl From<LoopbackError> for String {
@@ -39,6 +52,9 @@ pub enum ApprovalStatus {
     TimedOut,
 }
 
+/// Responder recorded for resolutions made without an auth context
+pub const UNAUTHENTICATED_RESPONDER: &str = "unauthenticated";
+
 /// A pending approval request
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ApprovalRequest {
@@ -51,6 +67,203 @@ pub struct ApprovalRequest {
     pub response_message: Option<String>,
     pub created_at: i64,
     pub resolved_at: Option<i64>,
+    /// Who resolved it: the authenticated user, [`UNAUTHENTICATED_RESPONDER`]
+    /// for callers without an auth context, or the deciding component
+    /// (`timeout`, `bidirectional-client`, `orcha:…`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub responder: Option<String>,
+    /// Milliseconds between the request and its resolution
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<i64>,
+    /// The input the tool was allowed to run with (approved requests only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_input: Option<Value>,
+    /// Fields the approver changed in `updated_input`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub input_diff: Vec<InputChange>,
+}
+
+/// One top-level input field an approver changed before approving
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct InputChange {
+    pub field: String,
+    /// Requested value (`None`: the approver added the field)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<Value>,
+    /// Approved value (`None`: the approver removed the field)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<Value>,
+}
+
+impl InputChange {
+    /// Field-by-field changes from `before` to `after`. Non-object inputs
+    /// are compared whole, as the field `""`.
+    pub fn diff(before: &Value, after: &Value) -> Vec<Self> {
+        let (Value::Object(old), Value::Object(new)) = (before, after) else {
+            return if before == after {
+                Vec::new()
+            } else {
+                vec![Self { field: String::new(), before: Some(before.clone()), after: Some(after.clone()) }]
+            };
+        };
+        let fields: std::collections::BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+        fields
+            .into_iter()
+            .filter(|field| old.get(*field) != new.get(*field))
+            .map(|field| Self { field: field.clone(), before: old.get(field).cloned(), after: new.get(field).cloned() })
+            .collect()
+    }
+}
+
+/// Which approvals `history` and `stats` look at. Unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct ApprovalFilter {
+    /// Exactly this session
+    pub session_id: Option<String>,
+    /// This session (e.g. an orcha graph id) and the child sessions registered under it
+    pub graph_id: Option<String>,
+    pub tool_name: Option<String>,
+    pub status: Option<ApprovalStatus>,
+    /// Created at or after this unix timestamp (seconds)
+    pub since: Option<i64>,
+    /// Created before this unix timestamp (seconds)
+    pub until: Option<i64>,
+}
+
+/// Approval counts for one tool
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ToolApprovalStats {
+    pub tool_name: String,
+    pub total: u64,
+    pub approved: u64,
+    pub denied: u64,
+    pub pending: u64,
+    /// Calls settled by policy rules without an approval request
+    pub policy_allowed: u64,
+    pub policy_denied: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_latency_ms: Option<f64>,
+}
+
+/// Resolutions made by one responder
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ResponderStats {
+    pub responder: String,
+    pub approved: u64,
+    pub denied: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_latency_ms: Option<f64>,
+}
+
+/// Aggregates over the approvals matching a filter
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ApprovalStats {
+    pub total: u64,
+    pub approved: u64,
+    pub denied: u64,
+    pub pending: u64,
+    pub timed_out: u64,
+    pub policy_allowed: u64,
+    pub policy_denied: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_latency_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_latency_ms: Option<i64>,
+    /// Most-denied tools first
+    pub by_tool: Vec<ToolApprovalStats>,
+    /// Busiest responders first (unattributed resolutions are left out)
+    pub by_responder: Vec<ResponderStats>,
+}
+
+/// What happens to an approval nobody answers in time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalDecision {
+    Approve,
+    #[default]
+    Deny,
+}
+
+impl ApprovalDecision {
+    pub const fn approves(self) -> bool {
+        matches!(self, Self::Approve)
+    }
+}
+
+/// Per-session approval settings: how long `permit` (and a pushed confirm
+/// prompt) waits for an answer, and the decision applied when it runs out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ApprovalSettings {
+    #[serde(default = "default_timeout")]
+    pub timeout_secs: u64,
+    #[serde(default)]
+    pub default_decision: ApprovalDecision,
+}
+
+impl Default for ApprovalSettings {
+    fn default() -> Self {
+        Self { timeout_secs: default_timeout(), default_decision: ApprovalDecision::Deny }
+    }
+}
+
+/// What a policy rule does with a matching tool call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyAction {
+    /// Approve without asking anyone
+    Allow,
+    /// Deny without asking anyone
+    Deny,
+    /// Always ask, even if an `allow` rule also matches
+    Ask,
+}
+
+impl PolicyAction {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Deny => "deny",
+            Self::Ask => "ask",
+        }
+    }
+}
+
+/// Unique identifier for a policy rule
+pub type PolicyRuleId = Uuid;
+
+/// A declarative auto-approval rule, checked by `permit` before an approval
+/// request is created.
+///
+/// Patterns are globs: `*` matches any run of characters (`/` included) and
+/// `?` any single character; everything else matches literally.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PolicyRule {
+    pub id: PolicyRuleId,
+    pub action: PolicyAction,
+    /// Glob over the tool name (`None` matches every tool)
+    pub tool_name: Option<String>,
+    /// Globs over top-level input fields, e.g. `{"command": "cargo test*"}` or
+    /// `{"file_path": "/repo/src/*"}`. All must match; a missing field doesn't.
+    #[serde(default)]
+    pub input: BTreeMap<String, String>,
+    /// Session (or orcha graph) id the rule is limited to; it also covers
+    /// child sessions registered under it. `None` applies everywhere.
+    pub scope: Option<String>,
+    pub description: Option<String>,
+    pub created_at: i64,
+}
+
+/// An approval settled by a policy rule rather than a person
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PolicyDecision {
+    pub id: Uuid,
+    pub session_id: String,
+    pub tool_name: String,
+    pub tool_use_id: String,
+    pub input: Value,
+    pub action: PolicyAction,
+    pub rule_id: PolicyRuleId,
+    pub created_at: i64,
 }
 
 /// Request to the permit MCP tool
@@ -132,3 +345,103 @@ pub enum WaitForApprovalResult {
     #[serde(rename = "error")]
     Err { message: String },
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SessionSettingsResult {
+    #[serde(rename = "ok")]
+    Ok { session_id: String, settings: ApprovalSettings },
+    #[serde(rename = "error")]
+    Err { message: String },
+}
+
+/// Who settled a pushed approval
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DecidedBy {
+    /// The connected client answered the confirm prompt
+    Client,
+    /// The prompt timed out and the session's default decision applied
+    Default,
+}
+
+/// Events from `subscribe`, which pushes a session's approvals to the
+/// connected client as confirm prompts
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SubscribeEvent {
+    /// Listening; approvals for the session (and its child sessions) follow
+    Subscribed { session_id: String, settings: ApprovalSettings },
+    /// A confirm prompt went out for an approval
+    Prompted { approval_id: ApprovalId, tool_name: String },
+    /// The approval was settled from this subscription
+    Resolved { approval_id: ApprovalId, approved: bool, decided_by: DecidedBy },
+    /// Someone else (e.g. `respond`) settled the approval before the client did
+    Superseded { approval_id: ApprovalId },
+    #[serde(rename = "error")]
+    Err { message: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PolicyResult {
+    #[serde(rename = "ok")]
+    Ok { rule: PolicyRule },
+    #[serde(rename = "error")]
+    Err { message: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PoliciesResult {
+    #[serde(rename = "ok")]
+    Ok { rules: Vec<PolicyRule> },
+    #[serde(rename = "error")]
+    Err { message: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RemovePolicyResult {
+    #[serde(rename = "ok")]
+    Ok { rule_id: PolicyRuleId },
+    #[serde(rename = "error")]
+    Err { message: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DecisionsResult {
+    #[serde(rename = "ok")]
+    Ok { decisions: Vec<PolicyDecision> },
+    #[serde(rename = "error")]
+    Err { message: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HistoryResult {
+    #[serde(rename = "ok")]
+    Ok { approvals: Vec<ApprovalRequest> },
+    #[serde(rename = "error")]
+    Err { message: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
+pub enum GetApprovalResult {
+    #[serde(rename = "ok")]
+    Ok { approval: ApprovalRequest },
+    #[serde(rename = "error")]
+    Err { message: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StatsResult {
+    #[serde(rename = "ok")]
+    Ok { stats: ApprovalStats },
+    #[serde(rename = "error")]
+    Err { message: String },
+}
@@ -77,6 +77,13 @@ impl<P: HubContext> Cone<P> {
     pub const fn storage(&self) -> &Arc<ConeStorage> {
         &self.storage
     }
+
+    /// Get the LLM model registry cones resolve their `model_id` against
+    ///
+    /// Shared with Orcha so `cone:<model id>` agent nodes see the same models.
+    pub const fn llm_registry(&self) -> &Arc<ModelRegistry> {
+        &self.llm_registry
+    }
 }
 
 /// Convenience constructor and utilities for Cone with `NoParent` (standalone/testing)
@@ -1,9 +1,14 @@
+use super::render::RenderGraph;
 use super::storage::{LatticeStorage, LatticeStorageConfig};
-use super::types::{CreateResult, GraphId, NodeSpec, NodeId, AddNodeResult, EdgeCondition, AddEdgeResult, LatticeEventEnvelope, NodeOutput, NodeUpdateResult, GetNodeInputsResult, GetGraphResult, ListGraphsResult, CancelResult, GraphStatus, CreateChildGraphResult, GetChildGraphsResult};
+use super::types::{CreateResult, GraphId, NodeSpec, NodeId, AddNodeResult, EdgeCondition, EdgePredicate, AddEdgeResult, LatticeEventEnvelope, NodeOutput, NodeUpdateResult, GetNodeInputsResult, GetGraphResult, ListGraphsResult, CancelResult, ResumeResult, CreateChildGraphResult, GetChildGraphsResult, RenderFormat, RenderResult};
 use async_stream::stream;
 use futures::Stream;
 use serde_json::Value;
 use std::sync::Arc;
+use std::time::Duration;
+
+/// How often the watchdog checks node timeouts and graph deadlines.
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(5);
 
 /// Lattice — DAG execution engine
 ///
@@ -16,11 +21,11 @@ pub struct Lattice {
 }
 
 impl Lattice {
+    /// Open the storage and start the timeout/deadline watchdog.
     pub async fn new(config: LatticeStorageConfig) -> Result<Self, String> {
-        let storage = LatticeStorage::new(config).await?;
-        Ok(Self {
-            storage: Arc::new(storage),
-        })
+        let storage = Arc::new(LatticeStorage::new(config).await?);
+        LatticeStorage::spawn_watchdog(storage.clone(), WATCHDOG_INTERVAL);
+        Ok(Self { storage })
     }
 
     /// Expose the underlying storage for library consumers (e.g. Orcha).
@@ -56,7 +61,7 @@ impl Lattice {
     /// `node_id` is optional — a UUID is generated if not provided.
     #[plexus_macros::method(params(
         graph_id = "ID of the graph to add the node to",
-        spec = "Node specification: typed enum (task/scatter/gather/subgraph)",
+        spec = "Node specification: typed enum (task/scatter/gather/subgraph/loop)",
         node_id = "Optional node ID hint; a UUID is generated if not provided"
     ))]
     async fn add_node(
@@ -78,11 +83,16 @@ impl Lattice {
     ///
     /// condition optionally filters which token colors are routed on this edge.
     /// None (default) passes any token; Some(color) routes only matching-color tokens.
+    ///
+    /// predicate further filters on the token's data payload, e.g.
+    /// `{"op":"and","predicates":[{"op":"exists","path":"/exit_code"},{"op":"ne","path":"/exit_code","value":0}]}`.
+    /// A token is routed only if both condition and predicate accept it.
     #[plexus_macros::method(params(
         graph_id = "ID of the graph",
         from_node_id = "Predecessor node — must complete before to_node becomes ready",
         to_node_id = "Dependent node — becomes ready when all predecessors are complete",
-        condition = "Optional edge condition: filter tokens by color (null = pass any)"
+        condition = "Optional edge condition: filter tokens by color (null = pass any)",
+        predicate = "Optional payload predicate: eq/ne/gt/gte/lt/lte on a JSON pointer, exists, and/or/not (null = pass any)"
     ))]
     async fn add_edge(
         &self,
@@ -90,10 +100,11 @@ impl Lattice {
         from_node_id: NodeId,
         to_node_id: NodeId,
         condition: Option<EdgeCondition>,
+        predicate: Option<EdgePredicate>,
     ) -> impl Stream<Item = AddEdgeResult> + Send + 'static {
         let storage = self.storage.clone();
         stream! {
-            match storage.add_edge(&graph_id, &from_node_id, &to_node_id, condition.as_ref()).await {
+            match storage.add_edge(&graph_id, &from_node_id, &to_node_id, condition.as_ref(), predicate.as_ref()).await {
                 Ok(()) => yield AddEdgeResult::Ok,
                 Err(e) => yield AddEdgeResult::Err { message: e },
             }
@@ -110,7 +121,8 @@ impl Lattice {
     /// Pass the last `seq` from a `LatticeEventEnvelope` you successfully processed.
     ///
     /// **Replay from beginning** (`after_seq = 0`, or omitted on an already-Running graph):
-    /// Replays the complete event history then streams live.
+    /// Replays the complete event history then streams live. On a resumed graph,
+    /// omitting `after_seq` replays from the latest `GraphResumed` instead.
     ///
     /// The stream closes when `GraphDone` or `GraphFailed` is emitted.
     #[plexus_macros::method(params(
@@ -150,6 +162,9 @@ impl Lattice {
     }
 
     /// Signal that a node failed — triggers `GraphFailed`
+    ///
+    /// The lattice watchdog reports timeouts and missed deadlines through the
+    /// same path, tagging the resulting `NodeFailed` event with a `reason`.
     #[plexus_macros::method(params(
         graph_id = "ID of the graph",
         node_id = "ID of the failed node",
@@ -238,6 +253,8 @@ impl Lattice {
     }
 
     /// Cancel a running graph
+    ///
+    /// If the graph was launched by a `SubGraph` node, that node is failed.
     #[plexus_macros::method(params(
         graph_id = "ID of the graph to cancel"
     ))]
@@ -247,16 +264,41 @@ impl Lattice {
     ) -> impl Stream<Item = CancelResult> + Send + 'static {
         let storage = self.storage.clone();
         stream! {
-            match storage.update_graph_status(&graph_id, GraphStatus::Cancelled).await {
+            match storage.cancel_graph(&graph_id).await {
                 Ok(()) => yield CancelResult::Ok,
                 Err(e) => yield CancelResult::Err { message: e },
             }
         }
     }
 
-    /// Add a `SubGraph` node — when dispatched, runs the child graph to completion.
+    /// Resume a failed or cancelled graph from the point of failure.
     ///
-    /// On child success, the parent node receives `{"child_graph_id": "..."}` as output.
+    /// Failed nodes and their downstream dependents are reset to pending;
+    /// Complete nodes keep their output tokens and are not re-run. The graph goes
+    /// back to running under the same id and emits `GraphResumed` followed by
+    /// `NodeReady` for the nodes that can run now. Call `execute` without
+    /// `after_seq` to stream from the resume.
+    #[plexus_macros::method(params(
+        graph_id = "ID of the failed or cancelled graph to resume"
+    ))]
+    async fn resume(
+        &self,
+        graph_id: GraphId,
+    ) -> impl Stream<Item = ResumeResult> + Send + 'static {
+        let storage = self.storage.clone();
+        stream! {
+            match storage.resume_graph(&graph_id).await {
+                Ok(reset_node_ids) => yield ResumeResult::Ok { reset_node_ids },
+                Err(e) => yield ResumeResult::Err { message: e },
+            }
+        }
+    }
+
+    /// Create a graph linked to a parent, for use as the target of a `SubGraph` node.
+    ///
+    /// When the `SubGraph` node becomes ready, lattice starts the child itself.
+    /// On child success, the parent node receives the child's sink tokens as output
+    /// (`{"child_graph_id": "..."}` if the child has no sinks).
     /// On child failure, the parent node is failed (error edge fires if present).
     #[plexus_macros::method(params(
         parent_id = "ID of the parent graph",
@@ -292,4 +334,36 @@ impl Lattice {
             }
         }
     }
+
+    /// Render a graph as Graphviz DOT or a Mermaid flowchart
+    ///
+    /// Nodes are filled by status, edges carry their condition and predicate,
+    /// edges into OR-join nodes end in an open circle, and loop back-edges
+    /// are dashed. Child graphs are drawn as nested clusters.
+    #[plexus_macros::method(params(
+        graph_id = "ID of the graph to render",
+        format = "Output format: dot (default) or mermaid",
+        include_children = "Draw child graphs as nested clusters (default: true)"
+    ))]
+    async fn render(
+        &self,
+        graph_id: GraphId,
+        format: Option<String>,
+        include_children: Option<bool>,
+    ) -> impl Stream<Item = RenderResult> + Send + 'static {
+        let storage = self.storage.clone();
+        stream! {
+            let format = match RenderFormat::parse_or_default(format.as_deref()) {
+                Ok(format) => format,
+                Err(message) => {
+                    yield RenderResult::Err { message };
+                    return;
+                }
+            };
+            match RenderGraph::load(&storage, &graph_id, include_children.unwrap_or(true)).await {
+                Ok(picture) => yield RenderResult::Ok { format, source: picture.render(format) },
+                Err(e) => yield RenderResult::Err { message: e },
+            }
+        }
+    }
 }
@@ -1,7 +1,9 @@
 mod activation;
+mod render;
 mod storage;
 mod types;
 
 pub use activation::Lattice;
+pub use render::{edge_label, short_id, spec_kind, NodeShape, RenderEdge, RenderGraph, RenderNode};
 pub use storage::{LatticeStorage, LatticeStorageConfig};
 pub use types::*;
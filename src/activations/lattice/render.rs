@@ -0,0 +1,441 @@
+//! Graphviz DOT and Mermaid rendering of lattice graphs.
+//!
+//! A [`RenderGraph`] is a format-neutral picture of a graph: labelled nodes
+//! filled by status, edges annotated with their routing filters, and child
+//! graphs nested as clusters. [`RenderGraph::load`] builds one from storage;
+//! callers with better names for nodes (Orcha's ticket ids) relabel it before
+//! calling [`RenderGraph::render`].
+
+use super::storage::LatticeStorage;
+use super::types::{
+    EdgeCondition, EdgePredicate, GatherStrategy, JoinType, LatticeEdge, LatticeNode, NodeSpec,
+    NodeStatus, RenderFormat, TokenColor,
+};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+/// Node outline, chosen from what the node does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeShape {
+    /// Caller-executed work
+    Box,
+    /// Fan-out
+    Parallelogram,
+    /// Fan-in
+    Diamond,
+    /// Loop controller
+    Hexagon,
+    /// Launches a child graph
+    Subroutine,
+}
+
+impl NodeShape {
+    pub const fn for_spec(spec: &NodeSpec) -> Self {
+        match spec {
+            NodeSpec::Task { .. } => Self::Box,
+            NodeSpec::Scatter { .. } => Self::Parallelogram,
+            NodeSpec::Gather { .. } => Self::Diamond,
+            NodeSpec::Loop { .. } => Self::Hexagon,
+            NodeSpec::SubGraph { .. } => Self::Subroutine,
+        }
+    }
+
+    const fn dot(self) -> &'static str {
+        match self {
+            Self::Box => "box",
+            Self::Parallelogram => "parallelogram",
+            Self::Diamond => "diamond",
+            Self::Hexagon => "hexagon",
+            Self::Subroutine => "component",
+        }
+    }
+
+    fn mermaid(self, id: &str, label: &str) -> String {
+        let label = mermaid_escape(label);
+        match self {
+            Self::Box => format!("{id}[\"{label}\"]"),
+            Self::Parallelogram => format!("{id}[/\"{label}\"/]"),
+            Self::Diamond => format!("{id}{{\"{label}\"}}"),
+            Self::Hexagon => format!("{id}{{{{\"{label}\"}}}}"),
+            Self::Subroutine => format!("{id}[[\"{label}\"]]"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderNode {
+    pub id: String,
+    /// May span several lines
+    pub label: String,
+    pub shape: NodeShape,
+    /// `None` for graphs that haven't been built yet
+    pub status: Option<NodeStatus>,
+    pub join_type: JoinType,
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderEdge {
+    pub from: String,
+    pub to: String,
+    pub label: Option<String>,
+    /// Drawn dashed and left out of the layout ranking
+    pub back_edge: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderGraph {
+    pub id: String,
+    pub title: String,
+    pub nodes: Vec<RenderNode>,
+    pub edges: Vec<RenderEdge>,
+    /// Child graphs, drawn as clusters inside this one
+    pub children: Vec<RenderGraph>,
+    /// The node of the parent graph that launched this one, if known
+    pub parent_node: Option<String>,
+}
+
+impl RenderGraph {
+    /// Build the picture of a stored graph, and of its child graphs (recursively)
+    /// when `include_children` is set. A child is attached to the `SubGraph`
+    /// node that launched it.
+    pub async fn load(storage: &LatticeStorage, graph_id: &str, include_children: bool) -> Result<Self, String> {
+        let graph_id = graph_id.to_string();
+        let graph = storage.get_graph(&graph_id).await?;
+        let nodes = storage.get_nodes(&graph_id).await?;
+        let edges = storage.get_edges(&graph_id).await?;
+
+        let mut children = Vec::new();
+        if include_children {
+            for child in storage.get_child_graphs(&graph_id).await? {
+                let mut picture = Box::pin(Self::load(storage, &child.id, true)).await?;
+                picture.parent_node = nodes
+                    .iter()
+                    .find(|n| matches!(&n.spec, NodeSpec::SubGraph { graph_id } if *graph_id == child.id))
+                    .map(|n| n.id.clone());
+                children.push(picture);
+            }
+        }
+
+        Ok(Self {
+            title: format!("{} ({})", short_id(&graph.id), graph.status),
+            id: graph.id,
+            nodes: nodes.iter().map(RenderNode::from_lattice).collect(),
+            edges: edges.iter().map(RenderEdge::from_lattice).collect(),
+            children,
+            parent_node: None,
+        })
+    }
+
+    pub fn render(&self, format: RenderFormat) -> String {
+        match format {
+            RenderFormat::Dot => self.to_dot(),
+            RenderFormat::Mermaid => self.to_mermaid(),
+        }
+    }
+
+    fn to_dot(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "digraph \"{}\" {{", dot_escape(&self.id));
+        out.push_str("  compound=true;\n");
+        let _ = writeln!(out, "  label=\"{}\";", dot_escape(&self.title));
+        out.push_str("  labelloc=t;\n");
+        out.push_str("  node [fontname=\"Helvetica\", style=filled, fillcolor=\"white\"];\n");
+        out.push_str("  edge [fontname=\"Helvetica\", fontsize=10];\n");
+        let mut clusters = 0;
+        self.dot_body(&mut out, 1, &mut clusters);
+        out.push_str("}\n");
+        out
+    }
+
+    fn dot_body(&self, out: &mut String, depth: usize, clusters: &mut usize) {
+        let indent = "  ".repeat(depth);
+        let joins: HashMap<&str, &JoinType> = self.nodes.iter().map(|n| (n.id.as_str(), &n.join_type)).collect();
+
+        for node in &self.nodes {
+            let _ = write!(
+                out,
+                "{indent}\"{}\" [label=\"{}\", shape={}",
+                dot_escape(&node.id),
+                dot_escape(&node.label),
+                node.shape.dot(),
+            );
+            if let Some(status) = &node.status {
+                let _ = write!(out, ", fillcolor=\"{}\"", status_color(status));
+            }
+            out.push_str("];\n");
+        }
+
+        for child in &self.children {
+            let cluster = format!("cluster_{clusters}");
+            *clusters += 1;
+            let _ = writeln!(out, "{indent}subgraph \"{cluster}\" {{");
+            let _ = writeln!(out, "{indent}  label=\"{}\";", dot_escape(&child.title));
+            let _ = writeln!(out, "{indent}  style=dashed;");
+            child.dot_body(out, depth + 1, clusters);
+            let _ = writeln!(out, "{indent}}}");
+            if let (Some(parent), Some(first)) = (&child.parent_node, child.nodes.first()) {
+                let _ = writeln!(
+                    out,
+                    "{indent}\"{}\" -> \"{}\" [lhead=\"{cluster}\", style=dotted, label=\"spawns\"];",
+                    dot_escape(parent),
+                    dot_escape(&first.id),
+                );
+            }
+        }
+
+        for edge in &self.edges {
+            let mut attrs = Vec::new();
+            if let Some(label) = &edge.label {
+                attrs.push(format!("label=\"{}\"", dot_escape(label)));
+            }
+            if edge.back_edge {
+                attrs.push("style=dashed, constraint=false".to_string());
+            } else if matches!(joins.get(edge.to.as_str()), Some(JoinType::Any)) {
+                attrs.push("arrowhead=odot".to_string());
+            }
+            let _ = write!(out, "{indent}\"{}\" -> \"{}\"", dot_escape(&edge.from), dot_escape(&edge.to));
+            if !attrs.is_empty() {
+                let _ = write!(out, " [{}]", attrs.join(", "));
+            }
+            out.push_str(";\n");
+        }
+    }
+
+    fn to_mermaid(&self) -> String {
+        let mut out = String::new();
+        // A JSON string is a valid YAML double-quoted scalar.
+        let _ = writeln!(out, "---\ntitle: {}\n---", serde_json::Value::from(self.title.as_str()));
+        out.push_str("flowchart TD\n");
+        let mut ids = MermaidIds::default();
+        self.mermaid_body(&mut out, 1, &mut ids);
+
+        for status in [NodeStatus::Pending, NodeStatus::Ready, NodeStatus::Running, NodeStatus::Complete, NodeStatus::Failed] {
+            let class = status.to_string();
+            let Some(members) = ids.classes.get(&class) else { continue };
+            let _ = writeln!(out, "  classDef {class} fill:{}", status_color(&status));
+            let _ = writeln!(out, "  class {} {class}", members.join(","));
+        }
+        out
+    }
+
+    fn mermaid_body(&self, out: &mut String, depth: usize, ids: &mut MermaidIds) {
+        let indent = "  ".repeat(depth);
+        let joins: HashMap<&str, &JoinType> = self.nodes.iter().map(|n| (n.id.as_str(), &n.join_type)).collect();
+
+        for node in &self.nodes {
+            let id = ids.node(&node.id);
+            let _ = writeln!(out, "{indent}{}", node.shape.mermaid(&id, &node.label));
+            if let Some(status) = &node.status {
+                ids.classes.entry(status.to_string()).or_default().push(id);
+            }
+        }
+
+        for child in &self.children {
+            let cluster = format!("g{}", ids.clusters);
+            ids.clusters += 1;
+            let _ = writeln!(out, "{indent}subgraph {cluster} [\"{}\"]", mermaid_escape(&child.title));
+            child.mermaid_body(out, depth + 1, ids);
+            let _ = writeln!(out, "{indent}end");
+            if let Some(parent) = &child.parent_node {
+                let _ = writeln!(out, "{indent}{} -.->|\"spawns\"| {cluster}", ids.node(parent));
+            }
+        }
+
+        for edge in &self.edges {
+            let arrow = if edge.back_edge {
+                "-.->"
+            } else if matches!(joins.get(edge.to.as_str()), Some(JoinType::Any)) {
+                "--o"
+            } else {
+                "-->"
+            };
+            let label = edge.label.as_deref().map(|l| format!("|\"{}\"|", mermaid_escape(l))).unwrap_or_default();
+            let _ = writeln!(out, "{indent}{} {arrow}{label} {}", ids.node(&edge.from), ids.node(&edge.to));
+        }
+    }
+}
+
+impl RenderNode {
+    /// Labelled with the node's kind and short id.
+    pub fn from_lattice(node: &LatticeNode) -> Self {
+        Self {
+            id: node.id.clone(),
+            label: format!("{}\n{}", spec_kind(&node.spec), short_id(&node.id)),
+            shape: NodeShape::for_spec(&node.spec),
+            status: Some(node.status.clone()),
+            join_type: node.join_type.clone(),
+        }
+    }
+}
+
+impl RenderEdge {
+    pub fn from_lattice(edge: &LatticeEdge) -> Self {
+        let label = edge_label(edge.condition.as_ref(), edge.predicate.as_ref())
+            .or_else(|| edge.back_edge.then(|| "repeat".to_string()));
+        Self { from: edge.from_node_id.clone(), to: edge.to_node_id.clone(), label, back_edge: edge.back_edge }
+    }
+}
+
+/// Short description of what a node does: `task`, `gather first 2`, `loop ×3`, …
+pub fn spec_kind(spec: &NodeSpec) -> String {
+    match spec {
+        NodeSpec::Task { .. } => "task".to_string(),
+        NodeSpec::Scatter { .. } => "scatter".to_string(),
+        NodeSpec::Gather { strategy: GatherStrategy::All } => "gather all".to_string(),
+        NodeSpec::Gather { strategy: GatherStrategy::First { n } } => format!("gather first {n}"),
+        NodeSpec::SubGraph { .. } => "subgraph".to_string(),
+        NodeSpec::Loop { max_iterations, .. } => format!("loop ×{max_iterations}"),
+    }
+}
+
+/// `error`, `/exit_code != 0`, `ok, /score >= 0.8`; `None` when the edge routes everything.
+pub fn edge_label(condition: Option<&EdgeCondition>, predicate: Option<&EdgePredicate>) -> Option<String> {
+    let color = condition.and_then(|c| c.0.as_ref()).map(|color| match color {
+        TokenColor::Ok => "ok".to_string(),
+        TokenColor::Error => "error".to_string(),
+        TokenColor::Named { name } => name.clone(),
+    });
+    let parts: Vec<String> = color.into_iter().chain(predicate.map(describe_predicate)).collect();
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
+
+fn describe_predicate(predicate: &EdgePredicate) -> String {
+    let path = |p: &str| if p.is_empty() { ".".to_string() } else { p.to_string() };
+    let nested = |p: &EdgePredicate| match p {
+        EdgePredicate::And { .. } | EdgePredicate::Or { .. } => format!("({})", describe_predicate(p)),
+        _ => describe_predicate(p),
+    };
+    match predicate {
+        EdgePredicate::Eq { path: p, value } => format!("{} == {value}", path(p)),
+        EdgePredicate::Ne { path: p, value } => format!("{} != {value}", path(p)),
+        EdgePredicate::Gt { path: p, value } => format!("{} > {value}", path(p)),
+        EdgePredicate::Gte { path: p, value } => format!("{} >= {value}", path(p)),
+        EdgePredicate::Lt { path: p, value } => format!("{} < {value}", path(p)),
+        EdgePredicate::Lte { path: p, value } => format!("{} <= {value}", path(p)),
+        EdgePredicate::Exists { path: p } => format!("exists {}", path(p)),
+        EdgePredicate::And { predicates } => predicates.iter().map(nested).collect::<Vec<_>>().join(" && "),
+        EdgePredicate::Or { predicates } => predicates.iter().map(nested).collect::<Vec<_>>().join(" || "),
+        EdgePredicate::Not { predicate } => format!("!({})", describe_predicate(predicate)),
+    }
+}
+
+/// First eight characters of an id — enough to tell UUIDs apart in a picture.
+pub fn short_id(id: &str) -> &str {
+    id.char_indices().nth(8).map_or(id, |(i, _)| &id[..i])
+}
+
+const fn status_color(status: &NodeStatus) -> &'static str {
+    match status {
+        NodeStatus::Pending => "#eeeeee",
+        NodeStatus::Ready => "#fff3b0",
+        NodeStatus::Running => "#b3d9ff",
+        NodeStatus::Complete => "#c8e6c9",
+        NodeStatus::Failed => "#ffcdd2",
+    }
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn mermaid_escape(s: &str) -> String {
+    s.replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+        .replace('|', "#124;")
+        .replace('\n', "<br/>")
+}
+
+/// Mermaid ids must be plain words, so nodes are numbered in render order.
+#[derive(Default)]
+struct MermaidIds {
+    nodes: HashMap<String, String>,
+    clusters: usize,
+    classes: HashMap<String, Vec<String>>,
+}
+
+impl MermaidIds {
+    fn node(&mut self, id: &str) -> String {
+        let next = self.nodes.len();
+        self.nodes.entry(id.to_string()).or_insert_with(|| format!("n{next}")).clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activations::lattice::LatticeStorageConfig;
+    use tempfile::tempdir;
+
+    fn task() -> NodeSpec {
+        NodeSpec::Task { data: serde_json::json!({}), handle: None, timeout_secs: None }
+    }
+
+    #[tokio::test]
+    async fn test_render_annotates_edges_and_nests_child_graphs() {
+        let dir = tempdir().unwrap();
+        let config = LatticeStorageConfig { db_path: dir.path().join("test_lattice.db") };
+        let storage = LatticeStorage::new(config).await.unwrap();
+
+        let graph = storage.create_graph(serde_json::json!({})).await.unwrap();
+        let child = storage.create_child_graph(&graph, serde_json::json!({})).await.unwrap();
+        storage.add_node(&child, Some("child-task".into()), &task()).await.unwrap();
+
+        let work = storage.add_node(&graph, Some("work".into()), &task()).await.unwrap();
+        let retry = NodeSpec::Loop { target: work.clone(), max_iterations: 3, repeat_if: None };
+        let lp = storage.add_node(&graph, Some("retry".into()), &retry).await.unwrap();
+        let sub = storage
+            .add_node(&graph, Some("sub".into()), &NodeSpec::SubGraph { graph_id: child.clone() })
+            .await
+            .unwrap();
+        let predicate = EdgePredicate::Ne { path: "/exit_code".into(), value: serde_json::json!(0) };
+        storage.add_edge(&graph, &work, &lp, None, None).await.unwrap();
+        storage
+            .add_edge(&graph, &lp, &sub, Some(&EdgeCondition(Some(TokenColor::Error))), Some(&predicate))
+            .await
+            .unwrap();
+
+        let picture = RenderGraph::load(&storage, &graph, true).await.unwrap();
+        assert_eq!(picture.children.len(), 1);
+        assert_eq!(picture.children[0].parent_node.as_deref(), Some("sub"));
+
+        let dot = picture.render(RenderFormat::Dot);
+        assert!(dot.contains("\"work\" [label=\"task\\nwork\", shape=box, fillcolor=\"#eeeeee\"];"));
+        assert!(dot.contains("\"retry\" -> \"sub\" [label=\"error, /exit_code != 0\"];"));
+        assert!(dot.contains("\"retry\" -> \"work\" [label=\"repeat\", style=dashed, constraint=false];"));
+        assert!(dot.contains("subgraph \"cluster_0\" {"));
+        assert!(dot.contains("\"sub\" -> \"child-task\" [lhead=\"cluster_0\", style=dotted, label=\"spawns\"];"));
+
+        let mermaid = picture.render(RenderFormat::Mermaid);
+        assert!(mermaid.contains("n2[[\"subgraph<br/>sub\"]]"));
+        assert!(mermaid.contains("subgraph g0"));
+        assert!(mermaid.contains("n2 -.->|\"spawns\"| g0"));
+        assert!(mermaid.contains("n1 -.->|\"repeat\"| n0"));
+        assert!(mermaid.contains("n1 -->|\"error, /exit_code != 0\"| n2"));
+        assert!(mermaid.contains("classDef pending fill:#eeeeee"));
+
+        let flat = RenderGraph::load(&storage, &graph, false).await.unwrap();
+        assert!(flat.children.is_empty());
+    }
+
+    #[test]
+    fn test_any_join_edges_end_in_a_circle() {
+        let node = |id: &str, join_type| RenderNode {
+            id: id.into(),
+            label: id.into(),
+            shape: NodeShape::Box,
+            status: None,
+            join_type,
+        };
+        let picture = RenderGraph {
+            id: "g".into(),
+            title: "g".into(),
+            nodes: vec![node("a", JoinType::All), node("b", JoinType::Any)],
+            edges: vec![RenderEdge { from: "a".into(), to: "b".into(), label: None, back_edge: false }],
+            children: Vec::new(),
+            parent_node: None,
+        };
+        assert!(picture.render(RenderFormat::Dot).contains("\"a\" -> \"b\" [arrowhead=odot];"));
+        assert!(picture.render(RenderFormat::Mermaid).contains("n0 --o n1"));
+    }
+}
@@ -1,7 +1,7 @@
 use super::types::{
-    EdgeCondition, GatherStrategy, GraphId, GraphStatus, JoinType, LatticeEvent,
-    LatticeEventEnvelope, LatticeGraph, LatticeNode, NodeId, NodeOutput, NodeSpec, NodeStatus,
-    Token, TokenPayload,
+    EdgeCondition, EdgePredicate, GatherStrategy, GraphId, GraphStatus, JoinType, LatticeEdge,
+    LatticeEvent, LatticeEventEnvelope, LatticeGraph, LatticeNode, NodeFailureReason, NodeId, NodeOutput,
+    NodeSpec, NodeStatus, Token, TokenColor, TokenPayload,
 };
 use crate::activation_db_path_from_module;
 use crate::activations::storage::init_sqlite_pool;
@@ -9,7 +9,7 @@ use async_stream::stream;
 use futures::Stream;
 use serde_json::Value;
 use sqlx::{sqlite::SqlitePool, Row};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::{Arc, RwLock};
@@ -17,6 +17,10 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::Notify;
 use uuid::Uuid;
 
+/// Terminal result for a node, in the shape `advance_graph` takes:
+/// `(output, None)` completes the node, `(_, Some(error))` fails it.
+type NodeOutcome = (Option<NodeOutput>, Option<String>);
+
 #[derive(Debug, Clone)]
 pub struct LatticeStorageConfig {
     pub db_path: PathBuf,
@@ -107,6 +111,14 @@ impl LatticeStorage {
         // Add columns to existing tables (ignore if already exists)
         let _ = sqlx::query("ALTER TABLE lattice_edges ADD COLUMN condition TEXT")
             .execute(&self.pool).await;
+        let _ = sqlx::query("ALTER TABLE lattice_edges ADD COLUMN predicate TEXT")
+            .execute(&self.pool).await;
+        // Back-edges close `Loop` nodes; they carry feedback tokens but never
+        // count towards readiness, roots, sinks, or forward routing.
+        let _ = sqlx::query("ALTER TABLE lattice_edges ADD COLUMN back_edge INTEGER NOT NULL DEFAULT 0")
+            .execute(&self.pool).await;
+        let _ = sqlx::query("ALTER TABLE lattice_nodes ADD COLUMN iteration INTEGER NOT NULL DEFAULT 0")
+            .execute(&self.pool).await;
         let _ = sqlx::query("ALTER TABLE lattice_nodes ADD COLUMN join_type TEXT NOT NULL DEFAULT 'all'")
             .execute(&self.pool).await;
         let _ = sqlx::query(
@@ -115,6 +127,10 @@ impl LatticeStorage {
         let _ = sqlx::query(
             "CREATE INDEX IF NOT EXISTS idx_lattice_graphs_parent ON lattice_graphs(parent_graph_id)"
         ).execute(&self.pool).await;
+        let _ = sqlx::query("ALTER TABLE lattice_nodes ADD COLUMN started_at INTEGER")
+            .execute(&self.pool).await;
+        let _ = sqlx::query("ALTER TABLE lattice_graphs ADD COLUMN started_at INTEGER")
+            .execute(&self.pool).await;
 
         Ok(())
     }
@@ -166,7 +182,7 @@ impl LatticeStorage {
 
     pub async fn get_child_graphs(&self, parent_id: &str) -> Result<Vec<LatticeGraph>, String> {
         let rows = sqlx::query(
-            "SELECT id, metadata, status, created_at, parent_graph_id FROM lattice_graphs WHERE parent_graph_id = ? ORDER BY created_at"
+            "SELECT id, metadata, status, created_at, started_at, parent_graph_id FROM lattice_graphs WHERE parent_graph_id = ? ORDER BY created_at"
         )
         .bind(parent_id)
         .fetch_all(&self.pool)
@@ -208,6 +224,26 @@ impl LatticeStorage {
         let spec_json = serde_json::to_string(spec)
             .map_err(|e| format!("Failed to serialize spec: {e}"))?;
 
+        if let NodeSpec::Loop { target, max_iterations, repeat_if } = spec {
+            if *max_iterations == 0 {
+                return Err("Loop max_iterations must be at least 1".to_string());
+            }
+            if let Some(p) = repeat_if {
+                p.validate()?;
+            }
+            let target_exists: bool = sqlx::query_scalar(
+                "SELECT COUNT(*) > 0 FROM lattice_nodes WHERE id = ? AND graph_id = ?"
+            )
+            .bind(target)
+            .bind(graph_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to validate loop target: {e}"))?;
+            if !target_exists {
+                return Err(format!("Loop target {target} not found in graph {graph_id}"));
+            }
+        }
+
         sqlx::query(
             "INSERT INTO lattice_nodes (id, graph_id, spec, status, created_at) VALUES (?, ?, ?, 'pending', ?)"
         )
@@ -219,9 +255,25 @@ impl LatticeStorage {
         .await
         .map_err(|e| format!("Failed to add node: {e}"))?;
 
+        if let NodeSpec::Loop { target, .. } = spec {
+            sqlx::query(
+                "INSERT INTO lattice_edges (id, graph_id, from_node_id, to_node_id, back_edge)
+                 VALUES (?, ?, ?, ?, 1)"
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(graph_id)
+            .bind(&id)
+            .bind(target)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to add loop back-edge: {e}"))?;
+        }
+
         if let Ok(graph_status) = self.get_graph_status(graph_id).await {
             if graph_status == GraphStatus::Running {
-                let _ = self.check_and_ready(graph_id, &id).await;
+                if let Ok(Some((out, err))) = self.check_and_ready(graph_id, &id).await {
+                    self.advance_graph(graph_id, &id, out, err).await?;
+                }
             }
         }
 
@@ -234,7 +286,12 @@ impl LatticeStorage {
         from_node_id: &NodeId,
         to_node_id: &NodeId,
         condition: Option<&EdgeCondition>,
+        predicate: Option<&EdgePredicate>,
     ) -> Result<(), String> {
+        if let Some(p) = predicate {
+            p.validate()?;
+        }
+
         let from_exists: bool = sqlx::query_scalar(
             "SELECT COUNT(*) > 0 FROM lattice_nodes WHERE id = ? AND graph_id = ?"
         )
@@ -265,15 +322,20 @@ impl LatticeStorage {
         let condition_json = condition
             .map(|c| serde_json::to_string(c).map_err(|e| format!("Failed to serialize condition: {e}")))
             .transpose()?;
+        let predicate_json = predicate
+            .map(|p| serde_json::to_string(p).map_err(|e| format!("Failed to serialize predicate: {e}")))
+            .transpose()?;
 
         sqlx::query(
-            "INSERT INTO lattice_edges (id, graph_id, from_node_id, to_node_id, condition) VALUES (?, ?, ?, ?, ?)"
+            "INSERT INTO lattice_edges (id, graph_id, from_node_id, to_node_id, condition, predicate)
+             VALUES (?, ?, ?, ?, ?, ?)"
         )
         .bind(&edge_id)
         .bind(graph_id)
         .bind(from_node_id)
         .bind(to_node_id)
         .bind(condition_json.as_deref())
+        .bind(predicate_json.as_deref())
         .execute(&self.pool)
         .await
         .map_err(|e| format!("Failed to add edge: {e}"))?;
@@ -284,14 +346,14 @@ impl LatticeStorage {
                     if src_node.status == NodeStatus::Complete {
                         let tokens: Vec<Token> = src_node.output.as_ref().map_or_else(|| vec![Token::ok()], |o| o.tokens().into_iter().cloned().collect());
                         for token in &tokens {
-                            let matches = condition
-                                .is_none_or(|c| c.matches(&token.color));
-                            if matches {
+                            if edge_accepts(condition, predicate, token) {
                                 let seq = self.count_tokens_on_edge(&edge_id).await? + 1;
                                 self.deliver_token(&edge_id, graph_id, token, seq).await?;
                             }
                         }
-                        let _ = self.check_and_ready(graph_id, to_node_id).await;
+                        if let Ok(Some((out, err))) = self.check_and_ready(graph_id, to_node_id).await {
+                            self.advance_graph(graph_id, to_node_id, out, err).await?;
+                        }
                         self.notify_graph(graph_id);
                     }
                 }
@@ -303,7 +365,7 @@ impl LatticeStorage {
 
     pub async fn get_graph(&self, graph_id: &GraphId) -> Result<LatticeGraph, String> {
         let row = sqlx::query(
-            "SELECT id, metadata, status, created_at, parent_graph_id FROM lattice_graphs WHERE id = ?"
+            "SELECT id, metadata, status, created_at, started_at, parent_graph_id FROM lattice_graphs WHERE id = ?"
         )
         .bind(graph_id)
         .fetch_optional(&self.pool)
@@ -345,7 +407,7 @@ impl LatticeStorage {
 
     pub async fn get_nodes(&self, graph_id: &GraphId) -> Result<Vec<LatticeNode>, String> {
         let rows = sqlx::query(
-            "SELECT id, graph_id, spec, status, output, error, created_at, completed_at
+            "SELECT id, graph_id, spec, status, output, error, created_at, started_at, completed_at, iteration
              FROM lattice_nodes WHERE graph_id = ? ORDER BY created_at"
         )
         .bind(graph_id)
@@ -358,7 +420,7 @@ impl LatticeStorage {
 
     pub async fn get_node(&self, node_id: &NodeId) -> Result<LatticeNode, String> {
         let row = sqlx::query(
-            "SELECT id, graph_id, spec, status, output, error, created_at, completed_at
+            "SELECT id, graph_id, spec, status, output, error, created_at, started_at, completed_at, iteration
              FROM lattice_nodes WHERE id = ?"
         )
         .bind(node_id)
@@ -372,7 +434,7 @@ impl LatticeStorage {
 
     pub async fn get_inbound_edges(&self, node_id: &NodeId) -> Result<Vec<NodeId>, String> {
         let rows = sqlx::query(
-            "SELECT from_node_id FROM lattice_edges WHERE to_node_id = ?"
+            "SELECT from_node_id FROM lattice_edges WHERE to_node_id = ? AND back_edge = 0"
         )
         .bind(node_id)
         .fetch_all(&self.pool)
@@ -384,7 +446,7 @@ impl LatticeStorage {
 
     pub async fn get_outbound_edges(&self, node_id: &NodeId) -> Result<Vec<NodeId>, String> {
         let rows = sqlx::query(
-            "SELECT to_node_id FROM lattice_edges WHERE from_node_id = ?"
+            "SELECT to_node_id FROM lattice_edges WHERE from_node_id = ? AND back_edge = 0"
         )
         .bind(node_id)
         .fetch_all(&self.pool)
@@ -394,13 +456,14 @@ impl LatticeStorage {
         Ok(rows.into_iter().map(|r| r.get::<String, _>("to_node_id")).collect())
     }
 
-    /// Returns (`edge_id`, `to_node_id`, condition) for each outbound edge.
+    /// Returns every outbound edge of `node_id` with its routing filters.
     async fn get_outbound_edges_with_conditions(
         &self,
         node_id: &NodeId,
-    ) -> Result<Vec<(String, NodeId, Option<EdgeCondition>)>, String> {
+    ) -> Result<Vec<OutboundEdge>, String> {
         let rows = sqlx::query(
-            "SELECT id, to_node_id, condition FROM lattice_edges WHERE from_node_id = ?"
+            "SELECT id, to_node_id, condition, predicate FROM lattice_edges
+             WHERE from_node_id = ? AND back_edge = 0"
         )
         .bind(node_id)
         .fetch_all(&self.pool)
@@ -409,17 +472,34 @@ impl LatticeStorage {
 
         rows.into_iter()
             .map(|row| {
-                let edge_id: String = row.get("id");
-                let to_node_id: String = row.get("to_node_id");
-                let condition_json: Option<String> = row.get("condition");
-                let condition = condition_json
-                    .as_deref()
-                    .map(|s| {
-                        serde_json::from_str::<EdgeCondition>(s)
-                            .map_err(|e| format!("Failed to deserialize edge condition: {e}"))
-                    })
-                    .transpose()?;
-                Ok((edge_id, to_node_id, condition))
+                let (condition, predicate) = edge_filters(&row)?;
+                Ok(OutboundEdge { id: row.get("id"), to_node_id: row.get("to_node_id"), condition, predicate })
+            })
+            .collect()
+    }
+
+    /// Every edge of a graph, back-edges included, in insertion order.
+    pub async fn get_edges(&self, graph_id: &GraphId) -> Result<Vec<LatticeEdge>, String> {
+        let rows = sqlx::query(
+            "SELECT id, from_node_id, to_node_id, condition, predicate, back_edge FROM lattice_edges
+             WHERE graph_id = ? ORDER BY rowid"
+        )
+        .bind(graph_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch edges: {e}"))?;
+
+        rows.into_iter()
+            .map(|row| {
+                let (condition, predicate) = edge_filters(&row)?;
+                Ok(LatticeEdge {
+                    id: row.get("id"),
+                    from_node_id: row.get("from_node_id"),
+                    to_node_id: row.get("to_node_id"),
+                    condition,
+                    predicate,
+                    back_edge: row.get::<i64, _>("back_edge") != 0,
+                })
             })
             .collect()
     }
@@ -430,12 +510,12 @@ impl LatticeStorage {
     ) -> Result<Vec<LatticeNode>, String> {
         // Exclude self-loop edges when determining root nodes
         let rows = sqlx::query(
-            "SELECT id, graph_id, spec, status, output, error, created_at, completed_at
+            "SELECT id, graph_id, spec, status, output, error, created_at, started_at, completed_at, iteration
              FROM lattice_nodes
              WHERE graph_id = ?
                AND id NOT IN (
                    SELECT to_node_id FROM lattice_edges
-                   WHERE graph_id = ? AND from_node_id != to_node_id
+                   WHERE graph_id = ? AND from_node_id != to_node_id AND back_edge = 0
                )
              ORDER BY created_at"
         )
@@ -460,17 +540,22 @@ impl LatticeStorage {
             NodeStatus::Complete | NodeStatus::Failed => Some(now),
             _ => None,
         };
+        // Entering Running restarts the watchdog clock; other transitions keep it.
+        let started_at = (status == NodeStatus::Running).then_some(now);
         let output_json = output
             .map(|o| serde_json::to_string(o).map_err(|e| format!("Failed to serialize output: {e}")))
             .transpose()?;
 
         sqlx::query(
-            "UPDATE lattice_nodes SET status = ?, output = ?, error = ?, completed_at = ? WHERE id = ?"
+            "UPDATE lattice_nodes
+             SET status = ?, output = ?, error = ?, completed_at = ?, started_at = COALESCE(?, started_at)
+             WHERE id = ?"
         )
         .bind(status.to_string())
         .bind(output_json.as_deref())
         .bind(error)
         .bind(completed_at)
+        .bind(started_at)
         .bind(node_id)
         .execute(&self.pool)
         .await
@@ -495,7 +580,7 @@ impl LatticeStorage {
 
     pub async fn list_graphs(&self) -> Result<Vec<LatticeGraph>, String> {
         let rows = sqlx::query(
-            "SELECT id, metadata, status, created_at, parent_graph_id FROM lattice_graphs ORDER BY created_at DESC"
+            "SELECT id, metadata, status, created_at, started_at, parent_graph_id FROM lattice_graphs ORDER BY created_at DESC"
         )
         .fetch_all(&self.pool)
         .await
@@ -546,7 +631,7 @@ impl LatticeStorage {
     /// 'ready' state when the substrate last shut down.
     pub async fn reset_node_to_pending(&self, node_id: &NodeId) -> Result<(), String> {
         sqlx::query(
-            "UPDATE lattice_nodes SET status = 'pending', output = NULL, error = NULL, completed_at = NULL WHERE id = ?"
+            "UPDATE lattice_nodes SET status = 'pending', output = NULL, error = NULL, started_at = NULL, completed_at = NULL WHERE id = ?"
         )
         .bind(node_id)
         .execute(&self.pool)
@@ -600,7 +685,8 @@ impl LatticeStorage {
         node_id: &NodeId,
     ) -> Result<(usize, usize), String> {
         let total: i64 = sqlx::query_scalar(
-            "SELECT COUNT(*) FROM lattice_edges WHERE to_node_id = ? AND from_node_id != to_node_id"
+            "SELECT COUNT(*) FROM lattice_edges
+             WHERE to_node_id = ? AND from_node_id != to_node_id AND back_edge = 0"
         )
         .bind(node_id)
         .fetch_one(&self.pool)
@@ -610,7 +696,8 @@ impl LatticeStorage {
         let delivered: i64 = sqlx::query_scalar(
             "SELECT COUNT(DISTINCT edge_id) FROM lattice_edge_tokens
              WHERE edge_id IN (
-                 SELECT id FROM lattice_edges WHERE to_node_id = ? AND from_node_id != to_node_id
+                 SELECT id FROM lattice_edges
+                 WHERE to_node_id = ? AND from_node_id != to_node_id AND back_edge = 0
              )"
         )
         .bind(node_id)
@@ -668,6 +755,19 @@ impl LatticeStorage {
         Ok(result.last_insert_rowid() as u64)
     }
 
+    /// Sequence number of the graph's most recent `GraphResumed` event, if any.
+    pub async fn last_resume_seq(&self, graph_id: &GraphId) -> Result<Option<u64>, String> {
+        let seq: Option<i64> = sqlx::query_scalar(
+            "SELECT MAX(seq) FROM lattice_events
+             WHERE graph_id = ? AND json_extract(event, '$.type') = 'graph_resumed'"
+        )
+        .bind(graph_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch events: {e}"))?;
+        Ok(seq.map(|s| s as u64))
+    }
+
     /// Read all events for a graph with seq > `after_seq`, in order.
     pub async fn get_events_after(
         &self,
@@ -720,8 +820,9 @@ impl LatticeStorage {
     pub async fn start_graph(&self, graph_id: &GraphId) -> Result<(), String> {
         // CAS: only transition if still Pending
         let result = sqlx::query(
-            "UPDATE lattice_graphs SET status = 'running' WHERE id = ? AND status = 'pending'"
+            "UPDATE lattice_graphs SET status = 'running', started_at = ? WHERE id = ? AND status = 'pending'"
         )
+        .bind(current_timestamp())
         .bind(graph_id)
         .execute(&self.pool)
         .await
@@ -735,16 +836,27 @@ impl LatticeStorage {
         let root_nodes = self.get_nodes_with_no_predecessors(graph_id).await?;
 
         if root_nodes.is_empty() {
-            self.update_graph_status(graph_id, GraphStatus::Complete).await?;
-            self.persist_event(graph_id, &LatticeEvent::GraphDone {
+            self.finish_graph(graph_id, LatticeEvent::GraphDone {
                 graph_id: graph_id.clone(),
             }).await?;
-        } else {
-            for node in root_nodes {
-                if let NodeSpec::Gather { .. } = &node.spec {
+            return Ok(());
+        }
+
+        // Sub-graphs that are already terminal resolve immediately; advance them
+        // only after every root is seeded so a fast failure can't race the rest.
+        let mut resolved: Vec<(NodeId, NodeOutcome)> = Vec::new();
+        for node in root_nodes {
+            match &node.spec {
+                NodeSpec::Gather { .. } => {
                     // Root Gather node with no predecessors — skip NodeReady.
                     // It will stay Pending (degenerate: no tokens will ever arrive).
-                } else {
+                }
+                NodeSpec::SubGraph { graph_id: child_graph_id } => {
+                    if let Some(outcome) = self.launch_subgraph(graph_id, &node.id, child_graph_id).await? {
+                        resolved.push((node.id, outcome));
+                    }
+                }
+                _ => {
                     self.set_node_status(&node.id, NodeStatus::Ready, None, None).await?;
                     self.persist_event(graph_id, &LatticeEvent::NodeReady {
                         node_id: node.id,
@@ -754,10 +866,59 @@ impl LatticeStorage {
             }
         }
 
+        for (node_id, (out, err)) in resolved {
+            Box::pin(self.advance_graph(graph_id, &node_id, out, err)).await?;
+        }
+
         self.notify_graph(graph_id);
         Ok(())
     }
 
+    /// Mark a dispatched node Running and persist `NodeStarted`.
+    ///
+    /// Returns `false` without touching the node if it is already terminal —
+    /// e.g. the watchdog failed it while it sat Ready past the graph deadline —
+    /// so the caller can skip the work instead of resurrecting the node.
+    pub async fn start_node(&self, graph_id: &GraphId, node_id: &NodeId) -> Result<bool, String> {
+        let result = sqlx::query(
+            "UPDATE lattice_nodes SET status = 'running', started_at = ?
+             WHERE id = ? AND status NOT IN ('complete', 'failed')"
+        )
+        .bind(current_timestamp())
+        .bind(node_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to start node: {e}"))?;
+
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        self.persist_event(graph_id, &LatticeEvent::NodeStarted {
+            node_id: node_id.clone(),
+        }).await?;
+        self.notify_graph(graph_id);
+        Ok(true)
+    }
+
+    /// Cancel a graph and fail any `SubGraph` node that is waiting on it.
+    pub async fn cancel_graph(&self, graph_id: &GraphId) -> Result<(), String> {
+        self.update_graph_status(graph_id, GraphStatus::Cancelled).await?;
+        self.notify_graph(graph_id);
+        self.resolve_parent_subgraphs(graph_id).await
+    }
+
+    /// Fail a pending or running graph for a reason outside any node (e.g. a
+    /// caller-enforced budget), blaming `node_id`. Pending nodes never run;
+    /// a graph that is already terminal is left alone.
+    pub async fn fail_graph(&self, graph_id: &GraphId, node_id: &NodeId, error: String) -> Result<(), String> {
+        self.finish_graph(graph_id, LatticeEvent::GraphFailed {
+            graph_id: graph_id.clone(),
+            node_id: node_id.clone(),
+            error,
+        }).await
+    }
+
     /// Reset a zombie Running node back to Ready for crash recovery.
     ///
     /// Inbound edge tokens are never deleted, so the node's join condition
@@ -773,6 +934,14 @@ impl LatticeStorage {
         if node.status != NodeStatus::Running {
             return Ok(());
         }
+        if let NodeSpec::SubGraph { graph_id: child_graph_id } = &node.spec {
+            // Engine-owned: there is no caller to re-dispatch. Only resolve the
+            // node if its child finished while nobody was around to propagate it.
+            if let Some((out, err)) = self.subgraph_outcome(child_graph_id).await? {
+                self.advance_graph(graph_id, node_id, out, err).await?;
+            }
+            return Ok(());
+        }
         self.set_node_status(node_id, NodeStatus::Ready, None, None).await?;
         self.persist_event(graph_id, &LatticeEvent::NodeReady {
             node_id: node_id.clone(),
@@ -802,6 +971,156 @@ impl LatticeStorage {
         Ok(())
     }
 
+    /// Resume a failed or cancelled graph under the same id.
+    ///
+    /// Failed nodes and everything downstream of them go back to Pending, and
+    /// the tokens they emitted are discarded. Complete nodes outside that set
+    /// keep their status and output tokens, so work that already succeeded is
+    /// not repeated. Loop iteration counters start over, and the graph deadline
+    /// (if any) is measured from the resume. A reset `SubGraph` node whose
+    /// child graph failed resumes the child as well.
+    ///
+    /// Nodes that were still Running when the graph stopped lost their
+    /// dispatcher, so they go back to Ready in the same transaction; their
+    /// inbound tokens are kept. A Running `SubGraph` node is left to its child
+    /// and only resolved if the child has finished meanwhile.
+    ///
+    /// Persists `GraphResumed`, then re-enables every reset node whose inputs
+    /// are satisfied and re-emits `NodeReady` for every Ready node.
+    /// Returns the ids of the nodes reset to Pending.
+    pub async fn resume_graph(&self, graph_id: &GraphId) -> Result<Vec<NodeId>, String> {
+        let graph = self.get_graph(graph_id).await?;
+        if !matches!(graph.status, GraphStatus::Failed | GraphStatus::Cancelled) {
+            return Err(format!(
+                "Graph {graph_id} is {}; only failed or cancelled graphs can be resumed",
+                graph.status
+            ));
+        }
+
+        let nodes = self.get_nodes(graph_id).await?;
+        let (forward, _) = self.dependency_maps(graph_id).await?;
+        let mut downstream: HashSet<String> = HashSet::new();
+        for node in nodes.iter().filter(|n| n.status == NodeStatus::Failed) {
+            downstream.extend(reachable(&forward, &node.id));
+        }
+        let reset: Vec<&LatticeNode> = nodes.iter().filter(|n| downstream.contains(&n.id)).collect();
+        let (running_subgraphs, interrupted): (Vec<&LatticeNode>, Vec<&LatticeNode>) = nodes
+            .iter()
+            .filter(|n| n.status == NodeStatus::Running && !downstream.contains(&n.id))
+            .partition(|n| matches!(n.spec, NodeSpec::SubGraph { .. }));
+
+        let mut tx = self.pool.begin().await.map_err(|e| format!("Failed to begin resume: {e}"))?;
+        sqlx::query("UPDATE lattice_nodes SET iteration = 0 WHERE graph_id = ?")
+            .bind(graph_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to reset loop iterations: {e}"))?;
+        for node in &reset {
+            sqlx::query(
+                "UPDATE lattice_nodes
+                 SET status = 'pending', output = NULL, error = NULL, started_at = NULL, completed_at = NULL
+                 WHERE id = ?"
+            )
+            .bind(&node.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to reset node: {e}"))?;
+
+            sqlx::query(
+                "DELETE FROM lattice_edge_tokens WHERE edge_id IN (
+                     SELECT id FROM lattice_edges WHERE from_node_id = ?
+                 )"
+            )
+            .bind(&node.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to clear node tokens: {e}"))?;
+        }
+        for node in &interrupted {
+            sqlx::query(
+                "UPDATE lattice_nodes SET status = 'ready', started_at = NULL
+                 WHERE id = ? AND status = 'running'"
+            )
+            .bind(&node.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to reset running node: {e}"))?;
+        }
+        let result = sqlx::query(
+            "UPDATE lattice_graphs SET status = 'running', started_at = ?
+             WHERE id = ? AND status IN ('failed', 'cancelled')"
+        )
+        .bind(current_timestamp())
+        .bind(graph_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to resume graph: {e}"))?;
+        if result.rows_affected() == 0 {
+            return Err(format!("Graph {graph_id} changed status while resuming"));
+        }
+        tx.commit().await.map_err(|e| format!("Failed to commit resume: {e}"))?;
+
+        let reset_node_ids: Vec<NodeId> = reset.iter().map(|n| n.id.clone()).collect();
+        self.persist_event(graph_id, &LatticeEvent::GraphResumed {
+            graph_id: graph_id.clone(),
+            reset_node_ids: reset_node_ids.clone(),
+        }).await?;
+
+        // Children of reset sub-graph nodes must be running again before the
+        // nodes are re-launched, or they would just pick up the old failure.
+        for node in &reset {
+            if let NodeSpec::SubGraph { graph_id: child_graph_id } = &node.spec {
+                let child_status = self.get_graph_status(child_graph_id).await?;
+                if matches!(child_status, GraphStatus::Failed | GraphStatus::Cancelled) {
+                    Box::pin(self.resume_graph(child_graph_id)).await?;
+                }
+            }
+        }
+
+        self.reemit_ready_nodes(graph_id).await?;
+
+        let mut resolved: Vec<(NodeId, NodeOutcome)> = Vec::new();
+        for node in &running_subgraphs {
+            if let NodeSpec::SubGraph { graph_id: child_graph_id } = &node.spec {
+                if let Some(outcome) = self.subgraph_outcome(child_graph_id).await? {
+                    resolved.push((node.id.clone(), outcome));
+                }
+            }
+        }
+        for node in &reset {
+            let (_, inbound) = self.count_edges_with_tokens(&node.id).await?;
+            let outcome = if inbound == 0 {
+                // Root node: nothing to wait for (a root Gather never fires, as in start_graph).
+                if matches!(node.spec, NodeSpec::Gather { .. }) {
+                    continue;
+                }
+                let node = self.get_node(&node.id).await?;
+                self.enable_node(graph_id, &node).await?
+            } else {
+                self.check_and_ready(graph_id, &node.id).await?
+            };
+            if let Some(outcome) = outcome {
+                resolved.push((node.id.clone(), outcome));
+            }
+        }
+        for (node_id, (out, err)) in resolved {
+            Box::pin(self.advance_graph(graph_id, &node_id, out, err)).await?;
+        }
+
+        // Nothing left to run, e.g. a cancelled graph with no failures.
+        let idle = !self.get_nodes(graph_id).await?
+            .iter()
+            .any(|n| matches!(n.status, NodeStatus::Ready | NodeStatus::Running));
+        if idle {
+            self.finish_graph(graph_id, LatticeEvent::GraphDone {
+                graph_id: graph_id.clone(),
+            }).await?;
+        }
+
+        self.notify_graph(graph_id);
+        Ok(reset_node_ids)
+    }
+
     // ─── Transition Logic ────────────────────────────────────────────────────
 
     /// Called by `node_complete` / `node_failed`.
@@ -809,22 +1128,43 @@ impl LatticeStorage {
     /// Uses an iterative queue to handle Gather auto-execution without async recursion.
     /// Implements the colored token model:
     ///   1. Produce tokens from output/error
-    ///   2. Route tokens on outbound edges (filtered by edge condition)
-    ///   3. For each downstream node: `check_and_ready` (enables Gather auto-execution)
+    ///   2. Route tokens on outbound edges (filtered by edge condition and predicate)
+    ///   3. For each downstream node: `check_and_ready` (enables Gather auto-execution
+    ///      and launches `SubGraph` children)
     ///   4. Error fallback if no tokens delivered
-    ///   5. Check graph completion
+    ///   5. Check graph completion — a finished graph resolves the parent `SubGraph`
+    ///      node waiting on it, if any
     pub async fn advance_graph(
         &self,
         graph_id: &GraphId,
         completed_node_id: &NodeId,
         output: Option<NodeOutput>,
         error: Option<String>,
+    ) -> Result<(), String> {
+        self.advance_graph_with_reason(graph_id, completed_node_id, output, error, None).await
+    }
+
+    /// `advance_graph` with an engine-side failure reason for `completed_node_id`.
+    ///
+    /// The reason is recorded on the `NodeFailed` event and folded into the
+    /// Error token's payload so error-edge handlers can tell a timeout apart
+    /// from an ordinary failure.
+    async fn advance_graph_with_reason(
+        &self,
+        graph_id: &GraphId,
+        completed_node_id: &NodeId,
+        output: Option<NodeOutput>,
+        error: Option<String>,
+        mut reason: Option<NodeFailureReason>,
     ) -> Result<(), String> {
         // Queue: (node_id, output, error) — Gather nodes are auto-executed via queue
         let mut queue: Vec<(NodeId, Option<NodeOutput>, Option<String>)> =
             vec![(completed_node_id.clone(), output, error)];
 
         while let Some((nid, out, err)) = queue.pop() {
+            // The reason only ever applies to the first node popped.
+            let reason = reason.take();
+
             // IDEMPOTENCY: skip if already terminal
             let node = match self.get_node(&nid).await {
                 Ok(n) => n,
@@ -843,8 +1183,14 @@ impl LatticeStorage {
                 self.persist_event(graph_id, &LatticeEvent::NodeFailed {
                     node_id: nid.clone(),
                     error: err_msg.clone(),
+                    reason: reason.clone(),
                 }).await?;
-                vec![Token::error(err_msg)]
+                let mut token = Token::error(err_msg);
+                if let (Some(reason), Some(TokenPayload::Data { value })) = (&reason, &mut token.payload) {
+                    value["reason"] = serde_json::to_value(reason)
+                        .map_err(|e| format!("Failed to serialize failure reason: {e}"))?;
+                }
+                vec![token]
             } else {
                 self.set_node_status(&nid, NodeStatus::Complete, out.as_ref(), None).await?;
                 self.persist_event(graph_id, &LatticeEvent::NodeDone {
@@ -859,18 +1205,15 @@ impl LatticeStorage {
             let mut any_delivered = false;
 
             for token in &tokens {
-                for (edge_id, to_node_id, condition) in &outbound {
-                    let matches = condition.as_ref()
-                        .is_none_or(|c| c.matches(&token.color));
-
-                    if matches {
-                        let seq = self.count_tokens_on_edge(edge_id).await? + 1;
-                        self.deliver_token(edge_id, graph_id, token, seq).await?;
+                for edge in &outbound {
+                    if edge_accepts(edge.condition.as_ref(), edge.predicate.as_ref(), token) {
+                        let seq = self.count_tokens_on_edge(&edge.id).await? + 1;
+                        self.deliver_token(&edge.id, graph_id, token, seq).await?;
                         any_delivered = true;
 
                         // Check if downstream node is now enabled
-                        if let Some(gather_output) = self.check_and_ready(graph_id, to_node_id).await? {
-                            queue.push((to_node_id.clone(), Some(gather_output), None));
+                        if let Some((out, err)) = self.check_and_ready(graph_id, &edge.to_node_id).await? {
+                            queue.push((edge.to_node_id.clone(), out, err));
                         }
                     }
                 }
@@ -879,31 +1222,26 @@ impl LatticeStorage {
             // ERROR FALLBACK: no handler found for error token
             if failed && !any_delivered {
                 let err_str = tokens.first()
-                    .and_then(|t| match &t.payload {
-                        Some(TokenPayload::Data { value }) => {
-                            value.get("message").and_then(|v| v.as_str()).map(std::string::ToString::to_string)
-                        }
-                        _ => None,
-                    })
+                    .and_then(Token::message)
+                    .map(std::string::ToString::to_string)
                     .unwrap_or_default();
-                self.update_graph_status(graph_id, GraphStatus::Failed).await?;
-                self.persist_event(graph_id, &LatticeEvent::GraphFailed {
+                self.finish_graph(graph_id, LatticeEvent::GraphFailed {
                     graph_id: graph_id.clone(),
                     node_id: nid.clone(),
                     error: err_str,
                 }).await?;
-                self.notify_graph(graph_id);
                 return Ok(());
             }
         }
 
-        // GRAPH COMPLETION: check if all nodes are complete
-        let all_done = self.get_nodes(graph_id).await?
+        // GRAPH COMPLETION: done once nothing is ready or running. Nodes still
+        // pending at that point sit on branches no token was routed to (edge
+        // conditions/predicates filtered it out), so they can never fire.
+        let quiescent = !self.get_nodes(graph_id).await?
             .iter()
-            .all(|n| n.status == NodeStatus::Complete);
-        if all_done {
-            self.update_graph_status(graph_id, GraphStatus::Complete).await?;
-            self.persist_event(graph_id, &LatticeEvent::GraphDone {
+            .any(|n| matches!(n.status, NodeStatus::Ready | NodeStatus::Running));
+        if quiescent {
+            self.finish_graph(graph_id, LatticeEvent::GraphDone {
                 graph_id: graph_id.clone(),
             }).await?;
         }
@@ -912,14 +1250,433 @@ impl LatticeStorage {
         Ok(())
     }
 
+    /// Move a graph to its terminal status and persist the closing event, then
+    /// resolve any parent `SubGraph` node waiting on it.
+    ///
+    /// Compare-and-set: a graph that is already terminal (or cancelled) is left
+    /// untouched and no event is written, so a graph closes exactly once even
+    /// when nested sub-graph resolution re-enters `advance_graph`.
+    async fn finish_graph(&self, graph_id: &GraphId, event: LatticeEvent) -> Result<(), String> {
+        let status = match &event {
+            LatticeEvent::GraphDone { .. } => GraphStatus::Complete,
+            LatticeEvent::GraphFailed { .. } => GraphStatus::Failed,
+            other => return Err(format!("Not a terminal graph event: {other:?}")),
+        };
+
+        let result = sqlx::query(
+            "UPDATE lattice_graphs SET status = ? WHERE id = ? AND status IN ('pending', 'running')"
+        )
+        .bind(status.to_string())
+        .bind(graph_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to update graph status: {e}"))?;
+
+        if result.rows_affected() == 0 {
+            return Ok(());
+        }
+
+        self.persist_event(graph_id, &event).await?;
+        self.notify_graph(graph_id);
+        self.resolve_parent_subgraphs(graph_id).await
+    }
+
+    // ─── Loops ───────────────────────────────────────────────────────────────
+
+    /// Start another pass of a `Loop` node's body.
+    ///
+    /// The body is every node on a forward path from `target` to the loop. Its
+    /// nodes go back to Pending with their iteration bumped, tokens on edges
+    /// inside the body are cleared (inputs from outside it are kept), and
+    /// `trigger` replaces whatever the back-edge carried before. `target` is
+    /// then enabled directly — its outside inputs are still satisfied — which
+    /// persists the next `NodeReady` after the `LoopIteration` event.
+    async fn restart_loop(
+        &self,
+        graph_id: &GraphId,
+        loop_node: &LatticeNode,
+        target: &NodeId,
+        max_iterations: u32,
+        trigger: Token,
+    ) -> Result<Option<NodeOutcome>, String> {
+        let body = self.loop_body(graph_id, target, &loop_node.id).await?;
+        if body.is_empty() {
+            self.set_node_status(&loop_node.id, NodeStatus::Running, None, None).await?;
+            return Ok(Some((None, Some(format!(
+                "Loop target {target} is not upstream of loop node {}", loop_node.id
+            )))));
+        }
+        let iteration = loop_node.iteration + 1;
+
+        let mut tx = self.pool.begin().await.map_err(|e| format!("Failed to begin loop reset: {e}"))?;
+        for node_id in &body {
+            sqlx::query(
+                "UPDATE lattice_nodes
+                 SET status = 'pending', output = NULL, error = NULL, started_at = NULL,
+                     completed_at = NULL, iteration = ?
+                 WHERE id = ?"
+            )
+            .bind(i64::from(iteration))
+            .bind(node_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to reset loop body node: {e}"))?;
+
+        }
+        // Tokens on edges between body nodes (including the back-edge into the
+        // target) belong to the pass being discarded. Tokens a body node sent
+        // out of the loop stay with their receivers.
+        let placeholders = vec!["?"; body.len()].join(", ");
+        let sql = format!(
+            "DELETE FROM lattice_edge_tokens WHERE edge_id IN (
+                 SELECT id FROM lattice_edges
+                 WHERE from_node_id IN ({placeholders}) AND to_node_id IN ({placeholders})
+             )"
+        );
+        let mut query = sqlx::query(&sql);
+        for node_id in body.iter().chain(&body) {
+            query = query.bind(node_id);
+        }
+        query
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to clear loop body tokens: {e}"))?;
+        tx.commit().await.map_err(|e| format!("Failed to commit loop reset: {e}"))?;
+
+        let back_edge_id: String = sqlx::query_scalar(
+            "SELECT id FROM lattice_edges WHERE from_node_id = ? AND back_edge = 1"
+        )
+        .bind(&loop_node.id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to find loop back-edge: {e}"))?;
+        self.deliver_token(&back_edge_id, graph_id, &trigger, 1).await?;
+
+        self.persist_event(graph_id, &LatticeEvent::LoopIteration {
+            node_id: loop_node.id.clone(),
+            target_node_id: target.clone(),
+            iteration,
+            max_iterations,
+            reset_node_ids: body.into_iter().collect(),
+            token: trigger,
+        }).await?;
+
+        let target_node = self.get_node(target).await?;
+        if let Some((out, err)) = Box::pin(self.enable_node(graph_id, &target_node)).await? {
+            Box::pin(self.advance_graph(graph_id, target, out, err)).await?;
+        }
+        Ok(None)
+    }
+
+    /// Nodes on a forward path from `target` to `loop_node_id`, both included,
+    /// in creation order. Empty if the loop isn't downstream of its target.
+    async fn loop_body(
+        &self,
+        graph_id: &GraphId,
+        target: &NodeId,
+        loop_node_id: &NodeId,
+    ) -> Result<Vec<NodeId>, String> {
+        let (forward, backward) = self.dependency_maps(graph_id).await?;
+
+        let downstream = reachable(&forward, target);
+        if !downstream.contains(loop_node_id) {
+            return Ok(Vec::new());
+        }
+        let upstream = reachable(&backward, loop_node_id);
+
+        Ok(self.get_nodes(graph_id).await?
+            .into_iter()
+            .map(|n| n.id)
+            .filter(|id| downstream.contains(id) && upstream.contains(id))
+            .collect())
+    }
+
+    /// Forward and backward adjacency over a graph's dependency edges
+    /// (back-edges and self-loops excluded).
+    async fn dependency_maps(
+        &self,
+        graph_id: &GraphId,
+    ) -> Result<(HashMap<String, Vec<String>>, HashMap<String, Vec<String>>), String> {
+        let rows = sqlx::query(
+            "SELECT from_node_id, to_node_id FROM lattice_edges
+             WHERE graph_id = ? AND back_edge = 0 AND from_node_id != to_node_id"
+        )
+        .bind(graph_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch edges: {e}"))?;
+
+        let mut forward: HashMap<String, Vec<String>> = HashMap::new();
+        let mut backward: HashMap<String, Vec<String>> = HashMap::new();
+        for row in rows {
+            let from: String = row.get("from_node_id");
+            let to: String = row.get("to_node_id");
+            forward.entry(from.clone()).or_default().push(to.clone());
+            backward.entry(to).or_default().push(from);
+        }
+        Ok((forward, backward))
+    }
+
+    // ─── Watchdog ────────────────────────────────────────────────────────────
+
+    /// Spawn the background sweep that enforces node timeouts and graph deadlines.
+    pub fn spawn_watchdog(storage: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = storage.expire_overdue_nodes().await {
+                    tracing::warn!("Lattice watchdog sweep failed: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Run one watchdog pass over every running graph.
+    ///
+    /// A Running node whose spec sets `timeout_secs` is failed once that many
+    /// seconds have passed since it started. A graph whose metadata sets
+    /// `deadline_secs` has every Ready/Running node failed once that many seconds
+    /// have passed since the graph started; if it is still running after that,
+    /// the graph itself is failed. Expired nodes go through the normal failure
+    /// path, so their Error token is routed on error edges.
+    ///
+    /// Returns the number of nodes failed.
+    pub async fn expire_overdue_nodes(&self) -> Result<usize, String> {
+        let now = current_timestamp();
+        let mut expired = 0;
+        for graph_id in self.get_running_graph_ids().await? {
+            expired += self.expire_graph(&graph_id, now).await?;
+        }
+        Ok(expired)
+    }
+
+    async fn expire_graph(&self, graph_id: &GraphId, now: i64) -> Result<usize, String> {
+        let graph = self.get_graph(graph_id).await?;
+        let deadline_secs = graph.metadata.get("deadline_secs")
+            .and_then(Value::as_u64)
+            .filter(|secs| graph.started_at.is_some_and(|t| now >= t.saturating_add(*secs as i64)));
+
+        let mut expired = 0;
+        let mut last_expired: Option<NodeId> = None;
+        loop {
+            let overdue: Vec<(LatticeNode, NodeFailureReason)> = self.get_nodes(graph_id).await?
+                .into_iter()
+                .filter_map(|node| {
+                    let reason = overdue_reason(&node, deadline_secs, now)?;
+                    Some((node, reason))
+                })
+                .collect();
+            if overdue.is_empty() {
+                break;
+            }
+
+            for (node, reason) in overdue {
+                tracing::warn!("Lattice watchdog failing node {} in {}: {}", node.id, graph_id, reason);
+                Box::pin(self.advance_graph_with_reason(
+                    graph_id,
+                    &node.id,
+                    None,
+                    Some(reason.to_string()),
+                    Some(reason),
+                )).await?;
+                if let NodeSpec::SubGraph { graph_id: child_graph_id } = &node.spec {
+                    Box::pin(self.cancel_graph(child_graph_id)).await?;
+                }
+                expired += 1;
+                last_expired = Some(node.id);
+            }
+
+            // Error handlers made ready by the failures above are also past the
+            // deadline; keep sweeping until nothing is in flight. Plain timeouts
+            // only ever need one pass — new Running nodes have fresh clocks.
+            if deadline_secs.is_none() || self.get_graph_status(graph_id).await? != GraphStatus::Running {
+                break;
+            }
+        }
+
+        if let Some(deadline_secs) = deadline_secs {
+            if self.get_graph_status(graph_id).await? == GraphStatus::Running {
+                self.finish_graph(graph_id, LatticeEvent::GraphFailed {
+                    graph_id: graph_id.clone(),
+                    node_id: last_expired.unwrap_or_default(),
+                    error: NodeFailureReason::DeadlineExceeded { deadline_secs }.to_string(),
+                }).await?;
+            }
+        }
+
+        Ok(expired)
+    }
+
+    // ─── Sub-graphs ──────────────────────────────────────────────────────────
+
+    /// Launch the child graph referenced by a `SubGraph` node.
+    ///
+    /// Marks the node Running, links the child under `graph_id` (unless it
+    /// already has a parent), persists `SubGraphStarted`, and starts the child.
+    /// Returns the node's outcome if the child is already terminal — an empty
+    /// child completes immediately — otherwise `None`; the outcome is delivered
+    /// later by `resolve_parent_subgraphs` when the child finishes.
+    async fn launch_subgraph(
+        &self,
+        graph_id: &GraphId,
+        node_id: &NodeId,
+        child_graph_id: &GraphId,
+    ) -> Result<Option<NodeOutcome>, String> {
+        self.set_node_status(node_id, NodeStatus::Running, None, None).await?;
+
+        if child_graph_id == graph_id {
+            return Ok(Some((None, Some("SubGraph node cannot run its own graph".to_string()))));
+        }
+        let child = match self.get_graph(child_graph_id).await {
+            Ok(g) => g,
+            Err(e) => return Ok(Some((None, Some(format!("Failed to launch sub-graph: {e}"))))),
+        };
+
+        if child.parent_graph_id.is_none() {
+            sqlx::query("UPDATE lattice_graphs SET parent_graph_id = ? WHERE id = ? AND parent_graph_id IS NULL")
+                .bind(graph_id)
+                .bind(child_graph_id)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to link sub-graph: {e}"))?;
+        }
+
+        self.persist_event(graph_id, &LatticeEvent::SubGraphStarted {
+            node_id: node_id.clone(),
+            child_graph_id: child_graph_id.clone(),
+        }).await?;
+        self.notify_graph(graph_id);
+
+        if child.status == GraphStatus::Pending {
+            Box::pin(self.start_graph(child_graph_id)).await?;
+        }
+
+        self.subgraph_outcome(child_graph_id).await
+    }
+
+    /// Resolve every Running `SubGraph` node that references `child_graph_id`,
+    /// if the child has reached a terminal status.
+    async fn resolve_parent_subgraphs(&self, child_graph_id: &GraphId) -> Result<(), String> {
+        let Some((out, err)) = self.subgraph_outcome(child_graph_id).await? else {
+            return Ok(());
+        };
+
+        let rows = sqlx::query(
+            "SELECT id, graph_id FROM lattice_nodes
+             WHERE status = 'running'
+               AND json_extract(spec, '$.type') = 'sub_graph'
+               AND json_extract(spec, '$.graph_id') = ?"
+        )
+        .bind(child_graph_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch parent sub-graph nodes: {e}"))?;
+
+        for row in rows {
+            let node_id: String = row.get("id");
+            let parent_graph_id: String = row.get("graph_id");
+            Box::pin(self.advance_graph(&parent_graph_id, &node_id, out.clone(), err.clone())).await?;
+        }
+        Ok(())
+    }
+
+    /// The outcome a `SubGraph` node takes from its child, or `None` while the
+    /// child is still pending or running.
+    ///
+    /// A complete child yields the tokens of its sink nodes (those with no
+    /// outbound edges); a child with no sinks yields `{"child_graph_id": ...}`.
+    /// A failed or cancelled child yields an error naming the child.
+    async fn subgraph_outcome(&self, child_graph_id: &GraphId) -> Result<Option<NodeOutcome>, String> {
+        let status = match self.get_graph_status(child_graph_id).await {
+            Ok(s) => s,
+            Err(e) => return Ok(Some((None, Some(format!("Sub-graph {child_graph_id} unavailable: {e}"))))),
+        };
+
+        match status {
+            GraphStatus::Pending | GraphStatus::Running => Ok(None),
+            GraphStatus::Complete => {
+                let mut tokens = self.get_sink_tokens(child_graph_id).await?;
+                let output = match tokens.len() {
+                    0 => NodeOutput::Single(Token::ok_data(
+                        serde_json::json!({ "child_graph_id": child_graph_id }),
+                    )),
+                    1 => NodeOutput::Single(tokens.remove(0)),
+                    _ => NodeOutput::Many { tokens },
+                };
+                Ok(Some((Some(output), None)))
+            }
+            GraphStatus::Failed => {
+                let error = self.get_graph_failure(child_graph_id).await?
+                    .unwrap_or_else(|| "unknown error".to_string());
+                Ok(Some((None, Some(format!("Child graph {child_graph_id} failed: {error}")))))
+            }
+            GraphStatus::Cancelled => {
+                Ok(Some((None, Some(format!("Child graph {child_graph_id} was cancelled")))))
+            }
+        }
+    }
+
+    /// Output tokens of every Complete node with no outbound edges, in creation order.
+    async fn get_sink_tokens(&self, graph_id: &GraphId) -> Result<Vec<Token>, String> {
+        let rows = sqlx::query(
+            "SELECT id, graph_id, spec, status, output, error, created_at, started_at, completed_at, iteration
+             FROM lattice_nodes
+             WHERE graph_id = ?
+               AND status = 'complete'
+               AND id NOT IN (
+                   SELECT from_node_id FROM lattice_edges
+                   WHERE graph_id = ? AND from_node_id != to_node_id AND back_edge = 0
+               )
+             ORDER BY created_at"
+        )
+        .bind(graph_id)
+        .bind(graph_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch sink nodes: {e}"))?;
+
+        let mut tokens = Vec::new();
+        for row in rows {
+            let node = self.row_to_node(row)?;
+            match node.output {
+                Some(output) => tokens.extend(output.tokens().into_iter().cloned()),
+                None => tokens.push(Token::ok()),
+            }
+        }
+        Ok(tokens)
+    }
+
+    /// Error message from the most recent `GraphFailed` event of a graph.
+    async fn get_graph_failure(&self, graph_id: &GraphId) -> Result<Option<String>, String> {
+        let rows = sqlx::query(
+            "SELECT event FROM lattice_events WHERE graph_id = ? ORDER BY seq DESC"
+        )
+        .bind(graph_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch events: {e}"))?;
+
+        for row in rows {
+            let event_json: String = row.get("event");
+            if let Ok(LatticeEvent::GraphFailed { error, .. }) = serde_json::from_str(&event_json) {
+                return Ok(Some(error));
+            }
+        }
+        Ok(None)
+    }
+
     /// Check if a node is enabled (all/any inbound edges delivered tokens).
-    /// Returns Some(output) for Gather nodes (caller should queue for processing).
+    /// Returns Some(outcome) for engine-executed nodes that finished on the spot —
+    /// Gather always, `SubGraph` when its child is already terminal — which the
+    /// caller should feed back into `advance_graph`.
     /// Returns None for Task/Scatter (sets Ready and persists `NodeReady`).
     async fn check_and_ready(
         &self,
         graph_id: &GraphId,
         node_id: &NodeId,
-    ) -> Result<Option<NodeOutput>, String> {
+    ) -> Result<Option<NodeOutcome>, String> {
         let node = self.get_node(node_id).await?;
 
         // Only transition from Pending or Complete (Complete → Ready allows self-loops)
@@ -937,25 +1694,66 @@ impl LatticeStorage {
             return Ok(None);
         }
 
-        if let NodeSpec::Gather { strategy } = &node.spec {
-            let tokens = self.get_node_inputs(node_id).await?;
-            let output = match strategy {
-                GatherStrategy::All => NodeOutput::Many { tokens },
-                GatherStrategy::First { n } => {
-                    let take = (*n).min(tokens.len());
-                    NodeOutput::Many { tokens: tokens[..take].to_vec() }
+        self.enable_node(graph_id, &node).await
+    }
+
+    /// Run the spec-specific transition for a node whose inputs are satisfied:
+    /// caller-executed nodes go Ready, engine-executed ones produce their outcome.
+    async fn enable_node(
+        &self,
+        graph_id: &GraphId,
+        node: &LatticeNode,
+    ) -> Result<Option<NodeOutcome>, String> {
+        let node_id = &node.id;
+        match &node.spec {
+            NodeSpec::Gather { strategy } => {
+                let tokens = self.get_node_inputs(node_id).await?;
+                let output = match strategy {
+                    GatherStrategy::All => NodeOutput::Many { tokens },
+                    GatherStrategy::First { n } => {
+                        let take = (*n).min(tokens.len());
+                        NodeOutput::Many { tokens: tokens[..take].to_vec() }
+                    }
+                };
+                self.set_node_status(node_id, NodeStatus::Running, None, None).await?;
+                Ok(Some((Some(output), None)))
+            }
+            NodeSpec::SubGraph { graph_id: child_graph_id } => {
+                self.launch_subgraph(graph_id, node_id, child_graph_id).await
+            }
+            NodeSpec::Loop { target, max_iterations, repeat_if } => {
+                let tokens = self.get_node_inputs(node_id).await?;
+                let trigger = tokens.iter().find(|t| {
+                    repeat_if.as_ref().map_or(t.color == TokenColor::Error, |p| p.matches(t))
+                });
+                if let Some(trigger) = trigger {
+                    if node.iteration + 1 < *max_iterations {
+                        return self.restart_loop(graph_id, node, target, *max_iterations, trigger.clone()).await;
+                    }
                 }
-            };
-            self.set_node_status(node_id, NodeStatus::Running, None, None).await?;
-            Ok(Some(output))
-        } else {
-            // Task / Scatter / SubGraph — emit NodeReady for caller to handle
-            self.set_node_status(node_id, NodeStatus::Ready, None, None).await?;
-            self.persist_event(graph_id, &LatticeEvent::NodeReady {
-                node_id: node_id.clone(),
-                spec: node.spec.clone(),
-            }).await?;
-            Ok(None)
+
+                // Exit: pass the inputs on, or fail if the body never recovered.
+                self.set_node_status(node_id, NodeStatus::Running, None, None).await?;
+                let runs = node.iteration + 1;
+                if let Some(err) = tokens.iter().find(|t| t.color == TokenColor::Error) {
+                    let message = err.message().unwrap_or_default();
+                    return Ok(Some((None, Some(format!("Loop gave up after {runs} iteration(s): {message}")))));
+                }
+                let output = match <[Token; 1]>::try_from(tokens) {
+                    Ok([token]) => NodeOutput::Single(token),
+                    Err(tokens) => NodeOutput::Many { tokens },
+                };
+                Ok(Some((Some(output), None)))
+            }
+            NodeSpec::Task { .. } | NodeSpec::Scatter { .. } => {
+                // Caller-executed — emit NodeReady for caller to handle
+                self.set_node_status(node_id, NodeStatus::Ready, None, None).await?;
+                self.persist_event(graph_id, &LatticeEvent::NodeReady {
+                    node_id: node_id.clone(),
+                    spec: node.spec.clone(),
+                }).await?;
+                Ok(None)
+            }
         }
     }
 
@@ -1010,8 +1808,28 @@ impl LatticeStorage {
                 }
             }
 
+            // Without a cursor, a resumed graph is replayed from its latest
+            // resume — the earlier attempt's events describe state it discarded.
+            let start = match after_seq {
+                Some(seq) => seq,
+                None => match storage.last_resume_seq(&graph_id).await {
+                    Ok(seq) => seq.map_or(0, |s| s - 1),
+                    Err(e) => {
+                        yield LatticeEventEnvelope {
+                            seq: 0,
+                            event: LatticeEvent::GraphFailed {
+                                graph_id: graph_id.clone(),
+                                node_id: String::new(),
+                                error: format!("Event read error: {e}"),
+                            },
+                        };
+                        return;
+                    }
+                },
+            };
+
             let notifier = storage.get_or_create_notifier(&graph_id);
-            let mut cursor = after_seq.unwrap_or(0);
+            let mut cursor = start;
 
             loop {
                 let events = match storage.get_events_after(&graph_id, cursor).await {
@@ -1029,19 +1847,25 @@ impl LatticeStorage {
                     }
                 };
 
+                let last = events.last().map(|(seq, _)| *seq);
                 for (seq, event) in events {
                     let done = matches!(
                         event,
                         LatticeEvent::GraphDone { .. } | LatticeEvent::GraphFailed { .. }
                     );
                     cursor = seq;
+                    // A terminal event followed by more events was superseded by
+                    // a resume; replaying it would close the stream too early.
+                    if done && Some(seq) != last {
+                        continue;
+                    }
                     yield LatticeEventEnvelope { seq, event };
                     if done { return; }
                 }
 
                 tokio::select! {
                     _ = notifier.notified() => {}
-                    _ = tokio::time::sleep(Duration::from_secs(3600)) => {
+                    _ = tokio::time::sleep(Duration::from_hours(1)) => {
                         let event = LatticeEvent::GraphFailed {
                             graph_id: graph_id.clone(),
                             node_id: "timeout".to_string(),
@@ -1076,6 +1900,7 @@ impl LatticeStorage {
             metadata: serde_json::from_str(&metadata_json).unwrap_or(serde_json::json!({})),
             status: GraphStatus::from_str(&status_str).unwrap_or(GraphStatus::Pending),
             created_at: row.get("created_at"),
+            started_at: row.try_get::<Option<i64>, _>("started_at").unwrap_or(None),
             node_count,
             edge_count,
             parent_graph_id: row.try_get::<Option<String>, _>("parent_graph_id").unwrap_or(None),
@@ -1112,14 +1937,590 @@ impl LatticeStorage {
             output,
             error: row.get("error"),
             created_at: row.get("created_at"),
+            started_at: row.try_get::<Option<i64>, _>("started_at").unwrap_or(None),
             completed_at: row.get("completed_at"),
+            iteration: row.try_get::<i64, _>("iteration").map_or(0, |i| i as u32),
         })
     }
 }
 
+/// An outbound edge with the filters that decide which tokens it carries.
+struct OutboundEdge {
+    id: String,
+    to_node_id: NodeId,
+    condition: Option<EdgeCondition>,
+    predicate: Option<EdgePredicate>,
+}
+
+/// Decode an edge row's color condition and payload predicate.
+fn edge_filters(row: &sqlx::sqlite::SqliteRow) -> Result<(Option<EdgeCondition>, Option<EdgePredicate>), String> {
+    let condition_json: Option<String> = row.get("condition");
+    let condition = condition_json
+        .as_deref()
+        .map(|s| {
+            serde_json::from_str::<EdgeCondition>(s)
+                .map_err(|e| format!("Failed to deserialize edge condition: {e}"))
+        })
+        .transpose()?;
+    // predicate column may not exist on older databases
+    let predicate_json: Option<String> = row.try_get("predicate").ok().flatten();
+    let predicate = predicate_json
+        .as_deref()
+        .map(|s| {
+            serde_json::from_str::<EdgePredicate>(s)
+                .map_err(|e| format!("Failed to deserialize edge predicate: {e}"))
+        })
+        .transpose()?;
+    Ok((condition, predicate))
+}
+
+/// An edge routes a token only if both its color condition and its payload
+/// predicate (when present) accept it.
+fn edge_accepts(condition: Option<&EdgeCondition>, predicate: Option<&EdgePredicate>, token: &Token) -> bool {
+    condition.is_none_or(|c| c.matches(&token.color)) && predicate.is_none_or(|p| p.matches(token))
+}
+
+/// Every node reachable from `start` (inclusive) over `adjacency`.
+fn reachable(adjacency: &HashMap<String, Vec<String>>, start: &str) -> HashSet<String> {
+    let mut seen: HashSet<String> = HashSet::from([start.to_string()]);
+    let mut stack = vec![start.to_string()];
+    while let Some(node) = stack.pop() {
+        for next in adjacency.get(&node).into_iter().flatten() {
+            if seen.insert(next.clone()) {
+                stack.push(next.clone());
+            }
+        }
+    }
+    seen
+}
+
+/// Why `node` should be expired right now, if at all.
+fn overdue_reason(node: &LatticeNode, deadline_secs: Option<u64>, now: i64) -> Option<NodeFailureReason> {
+    if let Some(deadline_secs) = deadline_secs {
+        if matches!(node.status, NodeStatus::Ready | NodeStatus::Running) {
+            return Some(NodeFailureReason::DeadlineExceeded { deadline_secs });
+        }
+    }
+    if node.status != NodeStatus::Running {
+        return None;
+    }
+    let timeout_secs = node.spec.timeout_secs()?;
+    let started_at = node.started_at?;
+    (now >= started_at.saturating_add(timeout_secs as i64))
+        .then_some(NodeFailureReason::Timeout { timeout_secs })
+}
+
 fn current_timestamp() -> i64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs() as i64
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::{tempdir, TempDir};
+
+    async fn create_test_storage() -> (LatticeStorage, TempDir) {
+        let dir = tempdir().unwrap();
+        let config = LatticeStorageConfig {
+            db_path: dir.path().join("test_lattice.db"),
+        };
+        let storage = LatticeStorage::new(config).await.unwrap();
+        (storage, dir)
+    }
+
+    fn task() -> NodeSpec {
+        NodeSpec::Task { data: serde_json::json!({}), handle: None, timeout_secs: None }
+    }
+
+    #[tokio::test]
+    async fn test_subgraph_completes_with_child_sink_tokens() {
+        let (storage, _dir) = create_test_storage().await;
+
+        let parent = storage.create_graph(serde_json::json!({})).await.unwrap();
+        let child = storage.create_graph(serde_json::json!({})).await.unwrap();
+        let child_task = storage.add_node(&child, None, &task()).await.unwrap();
+        let sub = storage
+            .add_node(&parent, None, &NodeSpec::SubGraph { graph_id: child.clone() })
+            .await
+            .unwrap();
+
+        storage.start_graph(&parent).await.unwrap();
+
+        // The engine launched and linked the child; the parent node waits on it.
+        assert_eq!(storage.get_node(&sub).await.unwrap().status, NodeStatus::Running);
+        let child_graph = storage.get_graph(&child).await.unwrap();
+        assert_eq!(child_graph.status, GraphStatus::Running);
+        assert_eq!(child_graph.parent_graph_id.as_deref(), Some(parent.as_str()));
+        assert_eq!(storage.get_node(&child_task).await.unwrap().status, NodeStatus::Ready);
+
+        let output = NodeOutput::Single(Token::ok_data(serde_json::json!({ "text": "done" })));
+        storage.advance_graph(&child, &child_task, Some(output), None).await.unwrap();
+
+        let sub_node = storage.get_node(&sub).await.unwrap();
+        assert_eq!(sub_node.status, NodeStatus::Complete);
+        match sub_node.output {
+            Some(NodeOutput::Single(Token { payload: Some(TokenPayload::Data { value }), .. })) => {
+                assert_eq!(value["text"], "done");
+            }
+            other => panic!("unexpected sub-graph output: {other:?}"),
+        }
+        assert_eq!(storage.get_graph(&parent).await.unwrap().status, GraphStatus::Complete);
+
+        let events = storage.get_events_after(&parent, 0).await.unwrap();
+        let done_count = events.iter()
+            .filter(|(_, e)| matches!(e, LatticeEvent::GraphDone { .. }))
+            .count();
+        assert_eq!(done_count, 1);
+        assert!(events.iter().any(|(_, e)| matches!(
+            e,
+            LatticeEvent::SubGraphStarted { child_graph_id, .. } if *child_graph_id == child
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_subgraph_failure_routes_error_token() {
+        let (storage, _dir) = create_test_storage().await;
+
+        let parent = storage.create_graph(serde_json::json!({})).await.unwrap();
+        let child = storage.create_child_graph(&parent, serde_json::json!({})).await.unwrap();
+        let child_task = storage.add_node(&child, None, &task()).await.unwrap();
+        let sub = storage
+            .add_node(&parent, None, &NodeSpec::SubGraph { graph_id: child.clone() })
+            .await
+            .unwrap();
+        let handler = storage.add_node(&parent, None, &task()).await.unwrap();
+        storage
+            .add_edge(&parent, &sub, &handler, Some(&EdgeCondition(Some(TokenColor::Error))), None)
+            .await
+            .unwrap();
+
+        storage.start_graph(&parent).await.unwrap();
+        storage
+            .advance_graph(&child, &child_task, None, Some("boom".to_string()))
+            .await
+            .unwrap();
+
+        let sub_node = storage.get_node(&sub).await.unwrap();
+        assert_eq!(sub_node.status, NodeStatus::Failed);
+        assert!(sub_node.error.unwrap().contains("boom"));
+        assert_eq!(storage.get_node(&handler).await.unwrap().status, NodeStatus::Ready);
+        assert_eq!(storage.get_graph(&parent).await.unwrap().status, GraphStatus::Running);
+    }
+
+    #[tokio::test]
+    async fn test_empty_subgraph_completes_immediately() {
+        let (storage, _dir) = create_test_storage().await;
+
+        let parent = storage.create_graph(serde_json::json!({})).await.unwrap();
+        let child = storage.create_graph(serde_json::json!({})).await.unwrap();
+        let sub = storage
+            .add_node(&parent, None, &NodeSpec::SubGraph { graph_id: child.clone() })
+            .await
+            .unwrap();
+
+        storage.start_graph(&parent).await.unwrap();
+
+        assert_eq!(storage.get_node(&sub).await.unwrap().status, NodeStatus::Complete);
+        assert_eq!(storage.get_graph(&parent).await.unwrap().status, GraphStatus::Complete);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_subgraph_fails_parent_node() {
+        let (storage, _dir) = create_test_storage().await;
+
+        let parent = storage.create_graph(serde_json::json!({})).await.unwrap();
+        let child = storage.create_graph(serde_json::json!({})).await.unwrap();
+        storage.add_node(&child, None, &task()).await.unwrap();
+        let sub = storage
+            .add_node(&parent, None, &NodeSpec::SubGraph { graph_id: child.clone() })
+            .await
+            .unwrap();
+
+        storage.start_graph(&parent).await.unwrap();
+        storage.cancel_graph(&child).await.unwrap();
+
+        assert_eq!(storage.get_node(&sub).await.unwrap().status, NodeStatus::Failed);
+        assert_eq!(storage.get_graph(&parent).await.unwrap().status, GraphStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_watchdog_times_out_running_node() {
+        let (storage, _dir) = create_test_storage().await;
+
+        let graph = storage.create_graph(serde_json::json!({})).await.unwrap();
+        let slow = storage
+            .add_node(&graph, None, &NodeSpec::Task {
+                data: serde_json::json!({}),
+                handle: None,
+                timeout_secs: Some(0),
+            })
+            .await
+            .unwrap();
+        let unbounded = storage.add_node(&graph, None, &task()).await.unwrap();
+        let handler = storage.add_node(&graph, None, &task()).await.unwrap();
+        storage
+            .add_edge(&graph, &slow, &handler, Some(&EdgeCondition(Some(TokenColor::Error))), None)
+            .await
+            .unwrap();
+
+        storage.start_graph(&graph).await.unwrap();
+        assert!(storage.start_node(&graph, &slow).await.unwrap());
+        assert!(storage.start_node(&graph, &unbounded).await.unwrap());
+
+        assert_eq!(storage.expire_overdue_nodes().await.unwrap(), 1);
+
+        let slow_node = storage.get_node(&slow).await.unwrap();
+        assert_eq!(slow_node.status, NodeStatus::Failed);
+        assert_eq!(slow_node.error.as_deref(), Some("node timed out after 0s"));
+        assert_eq!(storage.get_node(&unbounded).await.unwrap().status, NodeStatus::Running);
+        assert_eq!(storage.get_node(&handler).await.unwrap().status, NodeStatus::Ready);
+
+        let inputs = storage.get_node_inputs(&handler).await.unwrap();
+        assert_eq!(inputs[0].color, TokenColor::Error);
+        match &inputs[0].payload {
+            Some(TokenPayload::Data { value }) => assert_eq!(value["reason"]["type"], "timeout"),
+            other => panic!("unexpected error token payload: {other:?}"),
+        }
+
+        let events = storage.get_events_after(&graph, 0).await.unwrap();
+        assert!(events.iter().any(|(_, e)| matches!(
+            e,
+            LatticeEvent::NodeFailed { node_id, reason: Some(NodeFailureReason::Timeout { timeout_secs: 0 }), .. }
+                if *node_id == slow
+        )));
+
+        // A late result from the abandoned work is ignored.
+        storage.advance_graph(&graph, &slow, None, None).await.unwrap();
+        assert_eq!(storage.get_node(&slow).await.unwrap().status, NodeStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_watchdog_enforces_graph_deadline() {
+        let (storage, _dir) = create_test_storage().await;
+
+        let graph = storage.create_graph(serde_json::json!({ "deadline_secs": 0 })).await.unwrap();
+        let first = storage.add_node(&graph, None, &task()).await.unwrap();
+        let second = storage.add_node(&graph, None, &task()).await.unwrap();
+        let handler = storage.add_node(&graph, None, &task()).await.unwrap();
+        for from in [&first, &second] {
+            storage
+                .add_edge(&graph, from, &handler, Some(&EdgeCondition(Some(TokenColor::Error))), None)
+                .await
+                .unwrap();
+        }
+
+        storage.start_graph(&graph).await.unwrap();
+        assert!(storage.start_node(&graph, &first).await.unwrap());
+
+        // first (Running), second (Ready), then the handler their error tokens made ready.
+        assert_eq!(storage.expire_overdue_nodes().await.unwrap(), 3);
+        for node_id in [&first, &second, &handler] {
+            let node = storage.get_node(node_id).await.unwrap();
+            assert_eq!(node.status, NodeStatus::Failed);
+            assert_eq!(node.error.as_deref(), Some("graph deadline of 0s exceeded"));
+        }
+        assert_eq!(storage.get_graph(&graph).await.unwrap().status, GraphStatus::Failed);
+
+        // A dispatcher that picks up an expired Ready node must not revive it.
+        assert!(!storage.start_node(&graph, &second).await.unwrap());
+        assert_eq!(storage.get_node(&second).await.unwrap().status, NodeStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_predicate_routes_on_payload_and_unreached_branch_completes() {
+        let (storage, _dir) = create_test_storage().await;
+
+        let graph = storage.create_graph(serde_json::json!({})).await.unwrap();
+        let check = storage.add_node(&graph, None, &task()).await.unwrap();
+        let fix = storage.add_node(&graph, None, &task()).await.unwrap();
+        let ship = storage.add_node(&graph, None, &task()).await.unwrap();
+
+        let failing = EdgePredicate::Ne { path: "/exit_code".into(), value: serde_json::json!(0) };
+        let passing = EdgePredicate::And {
+            predicates: vec![
+                EdgePredicate::Exists { path: "/exit_code".into() },
+                EdgePredicate::Not { predicate: Box::new(failing.clone()) },
+            ],
+        };
+        storage.add_edge(&graph, &check, &fix, None, Some(&failing)).await.unwrap();
+        storage.add_edge(&graph, &check, &ship, None, Some(&passing)).await.unwrap();
+
+        storage.start_graph(&graph).await.unwrap();
+        let output = NodeOutput::Single(Token::ok_data(serde_json::json!({ "exit_code": 2 })));
+        storage.advance_graph(&graph, &check, Some(output), None).await.unwrap();
+
+        assert_eq!(storage.get_node(&fix).await.unwrap().status, NodeStatus::Ready);
+        assert_eq!(storage.get_node(&ship).await.unwrap().status, NodeStatus::Pending);
+
+        // Once the taken branch finishes, the graph completes without `ship`.
+        storage.advance_graph(&graph, &fix, None, None).await.unwrap();
+        assert_eq!(storage.get_node(&ship).await.unwrap().status, NodeStatus::Pending);
+        assert_eq!(storage.get_graph(&graph).await.unwrap().status, GraphStatus::Complete);
+    }
+
+    #[tokio::test]
+    async fn test_predicate_comparisons() {
+        let token = Token::ok_data(serde_json::json!({
+            "severity": "high",
+            "score": 7.5,
+            "tags": ["a", "b"],
+        }));
+        let pred = |v: serde_json::Value| serde_json::from_value::<EdgePredicate>(v).unwrap();
+
+        assert!(pred(serde_json::json!({ "op": "eq", "path": "/severity", "value": "high" })).matches(&token));
+        assert!(pred(serde_json::json!({ "op": "gte", "path": "/score", "value": 7 })).matches(&token));
+        assert!(!pred(serde_json::json!({ "op": "lt", "path": "/score", "value": 7 })).matches(&token));
+        assert!(pred(serde_json::json!({ "op": "eq", "path": "/tags/1", "value": "b" })).matches(&token));
+        // Mixed types never order; missing paths fail every comparison, `ne` included.
+        assert!(!pred(serde_json::json!({ "op": "gt", "path": "/severity", "value": 1 })).matches(&token));
+        assert!(!pred(serde_json::json!({ "op": "ne", "path": "/missing", "value": 0 })).matches(&token));
+        assert!(pred(serde_json::json!({
+            "op": "or",
+            "predicates": [
+                { "op": "exists", "path": "/missing" },
+                { "op": "not", "predicate": { "op": "exists", "path": "/missing" } }
+            ]
+        })).matches(&token));
+        // Color-only tokens carry no data to point into.
+        assert!(!pred(serde_json::json!({ "op": "exists", "path": "" })).matches(&Token::ok()));
+
+        assert!(pred(serde_json::json!({ "op": "exists", "path": "severity" })).validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_loop_reruns_body_until_it_succeeds() {
+        let (storage, _dir) = create_test_storage().await;
+
+        let graph = storage.create_graph(serde_json::json!({})).await.unwrap();
+        let work = storage.add_node(&graph, None, &task()).await.unwrap();
+        let check = storage.add_node(&graph, None, &task()).await.unwrap();
+        let retry = NodeSpec::Loop { target: work.clone(), max_iterations: 3, repeat_if: None };
+        let lp = storage.add_node(&graph, None, &retry).await.unwrap();
+        let after = storage.add_node(&graph, None, &task()).await.unwrap();
+        storage.add_edge(&graph, &work, &check, None, None).await.unwrap();
+        storage.add_edge(&graph, &check, &lp, None, None).await.unwrap();
+        storage.add_edge(&graph, &lp, &after, None, None).await.unwrap();
+
+        // The back-edge doesn't make `work` wait on the loop.
+        storage.start_graph(&graph).await.unwrap();
+        assert_eq!(storage.get_node(&work).await.unwrap().status, NodeStatus::Ready);
+
+        storage.advance_graph(&graph, &work, None, None).await.unwrap();
+        storage.advance_graph(&graph, &check, None, Some("tests failed".into())).await.unwrap();
+
+        let work_node = storage.get_node(&work).await.unwrap();
+        assert_eq!(work_node.status, NodeStatus::Ready);
+        assert_eq!(work_node.iteration, 1);
+        assert_eq!(storage.get_node(&check).await.unwrap().status, NodeStatus::Pending);
+        assert_eq!(storage.get_node(&lp).await.unwrap().status, NodeStatus::Pending);
+        // The failure is fed back to the target so the next pass can see it.
+        let inputs = storage.get_node_inputs(&work).await.unwrap();
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(inputs[0].message(), Some("tests failed"));
+
+        let events = storage.get_events_after(&graph, 0).await.unwrap();
+        let iteration = events.iter().find_map(|(_, e)| match e {
+            LatticeEvent::LoopIteration { iteration, reset_node_ids, .. } => Some((*iteration, reset_node_ids.len())),
+            _ => None,
+        });
+        assert_eq!(iteration, Some((1, 3)));
+
+        storage.advance_graph(&graph, &work, None, None).await.unwrap();
+        let fixed = NodeOutput::Single(Token::ok_data(serde_json::json!({ "passed": true })));
+        storage.advance_graph(&graph, &check, Some(fixed), None).await.unwrap();
+
+        assert_eq!(storage.get_node(&lp).await.unwrap().status, NodeStatus::Complete);
+        assert_eq!(storage.get_node(&after).await.unwrap().status, NodeStatus::Ready);
+        let forwarded = storage.get_node_inputs(&after).await.unwrap();
+        assert_eq!(forwarded.len(), 1);
+        assert_eq!(forwarded[0].color, TokenColor::Ok);
+
+        storage.advance_graph(&graph, &after, None, None).await.unwrap();
+        assert_eq!(storage.get_graph(&graph).await.unwrap().status, GraphStatus::Complete);
+    }
+
+    #[tokio::test]
+    async fn test_loop_fails_after_max_iterations() {
+        let (storage, _dir) = create_test_storage().await;
+
+        let graph = storage.create_graph(serde_json::json!({})).await.unwrap();
+        let work = storage.add_node(&graph, None, &task()).await.unwrap();
+        let retry = NodeSpec::Loop { target: work.clone(), max_iterations: 2, repeat_if: None };
+        let lp = storage.add_node(&graph, None, &retry).await.unwrap();
+        storage.add_edge(&graph, &work, &lp, None, None).await.unwrap();
+
+        storage.start_graph(&graph).await.unwrap();
+        storage.advance_graph(&graph, &work, None, Some("flaky".into())).await.unwrap();
+        assert_eq!(storage.get_node(&work).await.unwrap().status, NodeStatus::Ready);
+        storage.advance_graph(&graph, &work, None, Some("flaky".into())).await.unwrap();
+
+        let loop_node = storage.get_node(&lp).await.unwrap();
+        assert_eq!(loop_node.status, NodeStatus::Failed);
+        assert_eq!(loop_node.error.as_deref(), Some("Loop gave up after 2 iteration(s): flaky"));
+        assert_eq!(storage.get_graph(&graph).await.unwrap().status, GraphStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_loop_keeps_tokens_sent_out_of_the_body() {
+        let (storage, _dir) = create_test_storage().await;
+
+        let graph = storage.create_graph(serde_json::json!({})).await.unwrap();
+        let work = storage.add_node(&graph, None, &task()).await.unwrap();
+        let retry = NodeSpec::Loop { target: work.clone(), max_iterations: 3, repeat_if: None };
+        let lp = storage.add_node(&graph, None, &retry).await.unwrap();
+        let other = storage.add_node(&graph, None, &task()).await.unwrap();
+        let report = storage.add_node(&graph, None, &task()).await.unwrap();
+        storage.add_edge(&graph, &work, &lp, None, None).await.unwrap();
+        // `report` joins a body node with a node outside the loop.
+        storage.add_edge(&graph, &work, &report, None, None).await.unwrap();
+        storage.add_edge(&graph, &other, &report, None, None).await.unwrap();
+
+        storage.start_graph(&graph).await.unwrap();
+        storage.advance_graph(&graph, &work, None, Some("flaky".into())).await.unwrap();
+        assert_eq!(storage.get_node(&work).await.unwrap().iteration, 1);
+
+        // The token `work` already sent to `report` survives the restart.
+        storage.advance_graph(&graph, &other, None, None).await.unwrap();
+        assert_eq!(storage.get_node(&report).await.unwrap().status, NodeStatus::Ready);
+        let inputs = storage.get_node_inputs(&report).await.unwrap();
+        assert_eq!(inputs.iter().map(|t| t.color.clone()).collect::<Vec<_>>(), [TokenColor::Error, TokenColor::Ok]);
+    }
+
+    #[tokio::test]
+    async fn test_loop_repeat_if_predicate() {
+        let (storage, _dir) = create_test_storage().await;
+
+        let graph = storage.create_graph(serde_json::json!({})).await.unwrap();
+        let work = storage.add_node(&graph, None, &task()).await.unwrap();
+        let retry = NodeSpec::Loop {
+            target: work.clone(),
+            max_iterations: 5,
+            repeat_if: Some(EdgePredicate::Lt { path: "/score".into(), value: serde_json::json!(8) }),
+        };
+        let lp = storage.add_node(&graph, None, &retry).await.unwrap();
+        storage.add_edge(&graph, &work, &lp, None, None).await.unwrap();
+        storage.start_graph(&graph).await.unwrap();
+
+        let score = |n: u32| Some(NodeOutput::Single(Token::ok_data(serde_json::json!({ "score": n }))));
+        storage.advance_graph(&graph, &work, score(5), None).await.unwrap();
+        assert_eq!(storage.get_node(&work).await.unwrap().iteration, 1);
+        storage.advance_graph(&graph, &work, score(9), None).await.unwrap();
+
+        let loop_node = storage.get_node(&lp).await.unwrap();
+        assert_eq!(loop_node.status, NodeStatus::Complete);
+        assert_eq!(loop_node.iteration, 1);
+        assert_eq!(storage.get_graph(&graph).await.unwrap().status, GraphStatus::Complete);
+    }
+
+    #[tokio::test]
+    async fn test_resume_reruns_failed_branch_and_keeps_completed_work() {
+        use futures::StreamExt;
+
+        let (storage, _dir) = create_test_storage().await;
+        let storage = Arc::new(storage);
+
+        let graph = storage.create_graph(serde_json::json!({})).await.unwrap();
+        let plan = storage.add_node(&graph, None, &task()).await.unwrap();
+        let build = storage.add_node(&graph, None, &task()).await.unwrap();
+        let ship = storage.add_node(&graph, None, &task()).await.unwrap();
+        let docs = storage.add_node(&graph, None, &task()).await.unwrap();
+        storage.add_edge(&graph, &plan, &build, None, None).await.unwrap();
+        let ok_only = EdgeCondition(Some(TokenColor::Ok));
+        storage.add_edge(&graph, &build, &ship, Some(&ok_only), None).await.unwrap();
+        storage.add_edge(&graph, &plan, &docs, None, None).await.unwrap();
+
+        storage.start_graph(&graph).await.unwrap();
+        let planned = NodeOutput::Single(Token::ok_data(serde_json::json!({ "text": "the plan" })));
+        storage.advance_graph(&graph, &plan, Some(planned), None).await.unwrap();
+        storage.advance_graph(&graph, &docs, None, None).await.unwrap();
+        storage.advance_graph(&graph, &build, None, Some("compile error".into())).await.unwrap();
+        assert_eq!(storage.get_graph(&graph).await.unwrap().status, GraphStatus::Failed);
+        assert!(storage.resume_graph(&graph).await.is_ok());
+
+        // Only the failed node and its dependents were reset.
+        assert_eq!(storage.get_graph(&graph).await.unwrap().status, GraphStatus::Running);
+        assert_eq!(storage.get_node(&plan).await.unwrap().status, NodeStatus::Complete);
+        assert_eq!(storage.get_node(&docs).await.unwrap().status, NodeStatus::Complete);
+        assert_eq!(storage.get_node(&build).await.unwrap().status, NodeStatus::Ready);
+        assert_eq!(storage.get_node(&ship).await.unwrap().status, NodeStatus::Pending);
+        let inputs = storage.get_node_inputs(&build).await.unwrap();
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(inputs[0].color, TokenColor::Ok);
+
+        // A fresh execute stream starts at the resume and re-dispatches `build`.
+        let mut live = Box::pin(LatticeStorage::execute_stream(storage.clone(), graph.clone(), None));
+        match live.next().await.unwrap().event {
+            LatticeEvent::GraphResumed { reset_node_ids, .. } => {
+                assert_eq!(reset_node_ids, vec![build.clone(), ship.clone()]);
+            }
+            other => panic!("expected GraphResumed, got {other:?}"),
+        }
+        match live.next().await.unwrap().event {
+            LatticeEvent::NodeReady { node_id, .. } => assert_eq!(node_id, build),
+            other => panic!("expected NodeReady, got {other:?}"),
+        }
+
+        storage.advance_graph(&graph, &build, None, None).await.unwrap();
+        storage.advance_graph(&graph, &ship, None, None).await.unwrap();
+        assert_eq!(storage.get_graph(&graph).await.unwrap().status, GraphStatus::Complete);
+
+        // Full replay skips the superseded failure and closes on the final GraphDone.
+        let replay: Vec<LatticeEvent> = LatticeStorage::execute_stream(storage.clone(), graph.clone(), Some(0))
+            .map(|e| e.event)
+            .collect()
+            .await;
+        assert!(!replay.iter().any(|e| matches!(e, LatticeEvent::GraphFailed { .. })));
+        assert!(matches!(replay.last(), Some(LatticeEvent::GraphDone { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_resume_redispatches_nodes_running_at_cancel() {
+        use futures::StreamExt;
+
+        let (storage, _dir) = create_test_storage().await;
+        let storage = Arc::new(storage);
+
+        let graph = storage.create_graph(serde_json::json!({})).await.unwrap();
+        let build = storage.add_node(&graph, None, &task()).await.unwrap();
+        let ship = storage.add_node(&graph, None, &task()).await.unwrap();
+        storage.add_edge(&graph, &build, &ship, None, None).await.unwrap();
+
+        storage.start_graph(&graph).await.unwrap();
+        assert!(storage.start_node(&graph, &build).await.unwrap());
+        storage.cancel_graph(&graph).await.unwrap();
+
+        assert_eq!(storage.resume_graph(&graph).await.unwrap(), Vec::<NodeId>::new());
+        assert_eq!(storage.get_node(&build).await.unwrap().status, NodeStatus::Ready);
+
+        let mut live = Box::pin(LatticeStorage::execute_stream(storage.clone(), graph.clone(), None));
+        assert!(matches!(live.next().await.unwrap().event, LatticeEvent::GraphResumed { .. }));
+        match live.next().await.unwrap().event {
+            LatticeEvent::NodeReady { node_id, .. } => assert_eq!(node_id, build),
+            other => panic!("expected NodeReady, got {other:?}"),
+        }
+
+        assert!(storage.start_node(&graph, &build).await.unwrap());
+        storage.advance_graph(&graph, &build, None, None).await.unwrap();
+        storage.advance_graph(&graph, &ship, None, None).await.unwrap();
+        assert_eq!(storage.get_graph(&graph).await.unwrap().status, GraphStatus::Complete);
+    }
+
+    #[tokio::test]
+    async fn test_resume_requires_failed_or_cancelled_graph() {
+        let (storage, _dir) = create_test_storage().await;
+
+        let graph = storage.create_graph(serde_json::json!({})).await.unwrap();
+        let only = storage.add_node(&graph, None, &task()).await.unwrap();
+        storage.start_graph(&graph).await.unwrap();
+        assert!(storage.resume_graph(&graph).await.is_err());
+
+        // A cancelled graph with nothing failed just picks up its ready work.
+        storage.cancel_graph(&graph).await.unwrap();
+        assert_eq!(storage.resume_graph(&graph).await.unwrap(), Vec::<NodeId>::new());
+        assert_eq!(storage.get_graph(&graph).await.unwrap().status, GraphStatus::Running);
+        assert_eq!(storage.get_node(&only).await.unwrap().status, NodeStatus::Ready);
+    }
+}
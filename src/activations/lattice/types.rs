@@ -66,6 +66,14 @@ impl Token {
             }),
         }
     }
+
+    /// The `message` field of a data payload, as set by `Token::error`.
+    pub fn message(&self) -> Option<&str> {
+        match &self.payload {
+            Some(TokenPayload::Data { value }) => value.get("message").and_then(Value::as_str),
+            _ => None,
+        }
+    }
 }
 
 /// Output produced when completing a node.
@@ -118,16 +126,56 @@ pub enum GatherStrategy {
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum NodeSpec {
     /// Caller-executed: engine emits `NodeReady`, caller drives and reports output.
-    Task { data: Value, handle: Option<Handle> },
+    ///
+    /// `timeout_secs` bounds how long the node may stay Running; the lattice
+    /// watchdog fails it with `NodeFailureReason::Timeout` once exceeded.
+    Task {
+        data: Value,
+        handle: Option<Handle>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout_secs: Option<u64>,
+    },
 
     /// Like Task but expected to produce `NodeOutput::Many` for fan-out.
-    Scatter { data: Value, handle: Option<Handle> },
+    Scatter {
+        data: Value,
+        handle: Option<Handle>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout_secs: Option<u64>,
+    },
 
     /// Engine-executed: collects inbound tokens per strategy, produces Many output.
     Gather { strategy: GatherStrategy },
 
-    /// Engine-executed: launch nested graph. Not yet implemented (reserved).
+    /// Engine-executed: launch the referenced graph as a child of this one.
+    /// The node stays Running until the child reaches a terminal status, then
+    /// completes with the child's sink tokens or fails with the child's error.
     SubGraph { graph_id: String },
+
+    /// Engine-executed: bounded loop closing a back-edge to `target`.
+    ///
+    /// When its inputs arrive, the loop repeats if any input token satisfies
+    /// `repeat_if` (default: the token is Error-colored) and the body has run
+    /// fewer than `max_iterations` times. Repeating resets the body — every node
+    /// on a path from `target` to this node — to Pending, hands the triggering
+    /// token to `target` on the back-edge, and re-readies `target`. Otherwise the
+    /// loop completes with its inputs, or fails if one of them is an Error token.
+    Loop {
+        target: NodeId,
+        max_iterations: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        repeat_if: Option<EdgePredicate>,
+    },
+}
+
+impl NodeSpec {
+    /// Per-node Running timeout, for caller-executed nodes that declare one.
+    pub const fn timeout_secs(&self) -> Option<u64> {
+        match self {
+            NodeSpec::Task { timeout_secs, .. } | NodeSpec::Scatter { timeout_secs, .. } => *timeout_secs,
+            NodeSpec::Gather { .. } | NodeSpec::SubGraph { .. } | NodeSpec::Loop { .. } => None,
+        }
+    }
 }
 
 /// Edge condition — filter tokens by color.
@@ -144,6 +192,93 @@ impl EdgeCondition {
     }
 }
 
+/// Payload predicate on an edge — a token is routed only if it holds.
+///
+/// Evaluated against the token's `TokenPayload::Data` value. `path` is a JSON
+/// pointer (RFC 6901) into that value; `""` addresses the whole value.
+/// Comparisons on a path that doesn't resolve are false (including `ne`),
+/// so tokens without a data payload never satisfy a comparison or `exists`.
+/// Ordering operators compare numbers numerically and strings lexically;
+/// mixed types are false.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum EdgePredicate {
+    Eq { path: String, value: Value },
+    Ne { path: String, value: Value },
+    Gt { path: String, value: Value },
+    Gte { path: String, value: Value },
+    Lt { path: String, value: Value },
+    Lte { path: String, value: Value },
+    Exists { path: String },
+    And { predicates: Vec<EdgePredicate> },
+    Or { predicates: Vec<EdgePredicate> },
+    Not { predicate: Box<EdgePredicate> },
+}
+
+impl EdgePredicate {
+    /// Check that every path is a well-formed JSON pointer.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            EdgePredicate::Eq { path, .. }
+            | EdgePredicate::Ne { path, .. }
+            | EdgePredicate::Gt { path, .. }
+            | EdgePredicate::Gte { path, .. }
+            | EdgePredicate::Lt { path, .. }
+            | EdgePredicate::Lte { path, .. }
+            | EdgePredicate::Exists { path } => {
+                if path.is_empty() || path.starts_with('/') {
+                    Ok(())
+                } else {
+                    Err(format!("Invalid JSON pointer '{path}': must be empty or start with '/'"))
+                }
+            }
+            EdgePredicate::And { predicates } | EdgePredicate::Or { predicates } => {
+                predicates.iter().try_for_each(Self::validate)
+            }
+            EdgePredicate::Not { predicate } => predicate.validate(),
+        }
+    }
+
+    /// Evaluate against a token's payload.
+    pub fn matches(&self, token: &Token) -> bool {
+        let data = match &token.payload {
+            Some(TokenPayload::Data { value }) => Some(value),
+            _ => None,
+        };
+        self.eval(data)
+    }
+
+    fn eval(&self, data: Option<&Value>) -> bool {
+        use std::cmp::Ordering;
+
+        let lookup = |path: &str| data.and_then(|d| d.pointer(path));
+        let compare = |path: &str, value: &Value, accept: fn(Ordering) -> bool| {
+            lookup(path).and_then(|v| compare_values(v, value)).is_some_and(accept)
+        };
+
+        match self {
+            EdgePredicate::Eq { path, value } => lookup(path) == Some(value),
+            EdgePredicate::Ne { path, value } => lookup(path).is_some_and(|v| v != value),
+            EdgePredicate::Gt { path, value } => compare(path, value, Ordering::is_gt),
+            EdgePredicate::Gte { path, value } => compare(path, value, Ordering::is_ge),
+            EdgePredicate::Lt { path, value } => compare(path, value, Ordering::is_lt),
+            EdgePredicate::Lte { path, value } => compare(path, value, Ordering::is_le),
+            EdgePredicate::Exists { path } => lookup(path).is_some(),
+            EdgePredicate::And { predicates } => predicates.iter().all(|p| p.eval(data)),
+            EdgePredicate::Or { predicates } => predicates.iter().any(|p| p.eval(data)),
+            EdgePredicate::Not { predicate } => !predicate.eval(data),
+        }
+    }
+}
+
+fn compare_values(a: &Value, b: &Value) -> Option<std::cmp::Ordering> {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64()?.partial_cmp(&y.as_f64()?),
+        (Value::String(x), Value::String(y)) => Some(x.cmp(y)),
+        _ => None,
+    }
+}
+
 // ─── Node / Graph Status ──────────────────────────────────────────────────────
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
@@ -220,6 +355,29 @@ impl std::str::FromStr for GraphStatus {
     }
 }
 
+/// Why the lattice watchdog failed a node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NodeFailureReason {
+    /// The node stayed Running longer than its spec's `timeout_secs`.
+    Timeout { timeout_secs: u64 },
+    /// The graph's `deadline_secs` elapsed while the node was ready or running.
+    DeadlineExceeded { deadline_secs: u64 },
+}
+
+impl std::fmt::Display for NodeFailureReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeFailureReason::Timeout { timeout_secs } => {
+                write!(f, "node timed out after {timeout_secs}s")
+            }
+            NodeFailureReason::DeadlineExceeded { deadline_secs } => {
+                write!(f, "graph deadline of {deadline_secs}s exceeded")
+            }
+        }
+    }
+}
+
 // ─── Graph / Node Data Models ─────────────────────────────────────────────────
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -232,7 +390,12 @@ pub struct LatticeNode {
     pub output: Option<NodeOutput>,
     pub error: Option<String>,
     pub created_at: i64,
+    /// When the node last entered Running — the watchdog measures timeouts from here.
+    pub started_at: Option<i64>,
     pub completed_at: Option<i64>,
+    /// Which pass of an enclosing `Loop` body this node is on (0 = first run).
+    #[serde(default)]
+    pub iteration: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -241,11 +404,25 @@ pub struct LatticeGraph {
     pub metadata: Value,
     pub status: GraphStatus,
     pub created_at: i64,
+    /// When the graph moved to Running; `metadata.deadline_secs` counts from here.
+    pub started_at: Option<i64>,
     pub node_count: usize,
     pub edge_count: usize,
     pub parent_graph_id: Option<String>,
 }
 
+/// One edge of a graph with its routing filters. `back_edge` marks the edge
+/// a `Loop` node closes to its target.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LatticeEdge {
+    pub id: String,
+    pub from_node_id: NodeId,
+    pub to_node_id: NodeId,
+    pub condition: Option<EdgeCondition>,
+    pub predicate: Option<EdgePredicate>,
+    pub back_edge: bool,
+}
+
 // ─── Events ───────────────────────────────────────────────────────────────────
 
 /// Events emitted by the `execute()` stream
@@ -254,10 +431,38 @@ pub struct LatticeGraph {
 pub enum LatticeEvent {
     NodeReady { node_id: NodeId, spec: NodeSpec },
     NodeStarted { node_id: NodeId },
+    /// A `SubGraph` node launched its child graph. The engine completes or fails
+    /// `node_id` when the child terminates; callers that dispatch Task nodes
+    /// should attach to `child_graph_id` to drive the child's work.
+    SubGraphStarted { node_id: NodeId, child_graph_id: GraphId },
     NodeDone { node_id: NodeId, output: Option<NodeOutput> },
-    NodeFailed { node_id: NodeId, error: String },
+    /// `reason` is set when the engine failed the node itself (watchdog expiry);
+    /// it is absent for failures reported by the caller.
+    NodeFailed {
+        node_id: NodeId,
+        error: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<NodeFailureReason>,
+    },
+    /// A `Loop` node started another pass: `reset_node_ids` (the body, including
+    /// `target_node_id` and the loop itself) went back to Pending, and `token`
+    /// was handed to the target on the back-edge. `iteration` is the new pass
+    /// number, matching the body nodes' `LatticeNode::iteration` (first repeat = 1).
+    LoopIteration {
+        node_id: NodeId,
+        target_node_id: NodeId,
+        iteration: u32,
+        max_iterations: u32,
+        reset_node_ids: Vec<NodeId>,
+        token: Token,
+    },
     GraphDone { graph_id: GraphId },
     GraphFailed { graph_id: GraphId, node_id: NodeId, error: String },
+    /// A failed or cancelled graph is running again. `reset_node_ids` — the
+    /// failed nodes and everything downstream of them — went back to Pending;
+    /// the `GraphFailed` this follows no longer closes `execute` streams.
+    /// Nodes interrupted while Running are re-announced with `NodeReady`.
+    GraphResumed { graph_id: GraphId, reset_node_ids: Vec<NodeId> },
 }
 
 /// An event paired with its durable sequence number.
@@ -272,6 +477,30 @@ pub struct LatticeEventEnvelope {
     pub event: LatticeEvent,
 }
 
+// ─── Rendering ────────────────────────────────────────────────────────────────
+
+/// Text format for `lattice.render` / `orcha.render_graph`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderFormat {
+    /// Graphviz DOT
+    #[default]
+    Dot,
+    /// Mermaid flowchart
+    Mermaid,
+}
+
+impl RenderFormat {
+    /// Parse `dot` (or `graphviz`) and `mermaid` (or `mmd`); `None` means DOT.
+    pub fn parse_or_default(format: Option<&str>) -> Result<Self, String> {
+        match format.map(str::to_ascii_lowercase).as_deref() {
+            None | Some("dot" | "graphviz") => Ok(Self::Dot),
+            Some("mermaid" | "mmd") => Ok(Self::Mermaid),
+            Some(other) => Err(format!("Unknown render format '{other}' (expected dot or mermaid)")),
+        }
+    }
+}
+
 // ─── Result Types ─────────────────────────────────────────────────────────────
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -309,6 +538,13 @@ pub enum CancelResult {
     Err { message: String },
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResumeResult {
+    Ok { reset_node_ids: Vec<NodeId> },
+    Err { message: String },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum GetGraphResult {
@@ -343,3 +579,10 @@ pub enum GetChildGraphsResult {
     Ok { graphs: Vec<LatticeGraph> },
     Err { message: String },
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RenderResult {
+    Ok { format: RenderFormat, source: String },
+    Err { message: String },
+}
@@ -1,12 +1,16 @@
+use super::agents::AgentTarget;
 use super::graph_runner;
 use super::graph_runtime::GraphRuntime;
-use super::orchestrator::run_orchestration_task;
+use super::orchestrator::{parse_model, run_orchestration_task};
 use super::pm;
+use super::render;
 use super::storage::OrchaStorage;
+use super::templates;
 use super::ticket_compiler;
-use super::types::{OrchaEvent, RunTaskRequest, CreateSessionRequest, CreateSessionResult, AgentMode, SessionId, SessionState, UpdateSessionStateResult, GetSessionRequest, GetSessionResult, ExtractValidationResult, ValidationArtifact, RunValidationResult, IncrementRetryResult, ListSessionsResult, DeleteSessionResult, RunTaskAsyncResult, ListMonitorTreesResult, MonitorTreeInfo, CheckStatusRequest, CheckStatusResult, AgentSummary, SpawnAgentRequest, SpawnAgentResult, ListAgentsRequest, ListAgentsResult, GetAgentRequest, GetAgentResult, ListApprovalsRequest, ListApprovalsResult, ApprovalInfo, ApproveRequest, ApprovalActionResult, DenyRequest, OrchaCreateGraphResult, OrchaAddNodeResult, GatherStrategy, OrchaAddDependencyResult, OrchaNodeDef, OrchaEdgeDef, OrchaNodeSpec, ValidationResult, AgentInfo};
-use crate::activations::claudecode::{ClaudeCode, Model};
-use crate::activations::claudecode_loopback::ClaudeCodeLoopback;
+use super::worktree::Integration;
+use super::types::{OrchaEvent, RunTaskRequest, CreateSessionRequest, CreateSessionResult, AgentMode, SessionId, SessionState, UpdateSessionStateResult, GetSessionRequest, GetSessionResult, ExtractValidationResult, ValidationArtifact, RunValidationResult, IncrementRetryResult, ListSessionsResult, DeleteSessionResult, RunTaskAsyncResult, ListMonitorTreesResult, MonitorTreeInfo, CheckStatusRequest, CheckStatusResult, AgentSummary, SpawnAgentRequest, SpawnAgentResult, ListAgentsRequest, ListAgentsResult, GetAgentRequest, GetAgentResult, ListApprovalsRequest, ListApprovalsResult, ApprovalInfo, ApproveRequest, ApprovalActionResult, DenyRequest, OrchaCreateGraphResult, OrchaAddNodeResult, GatherStrategy, OrchaAddDependencyResult, EdgePredicate, OrchaNodeDef, OrchaEdgeDef, ValidationResult, AgentInfo, SaveTemplateResult, GetTemplateResult, ListTemplatesResult, LintTicketsResult, RenderFormat, RenderResult};
+use crate::activations::claudecode::ClaudeCode;
+use crate::activations::claudecode_loopback::{ClaudeCodeLoopback, UNAUTHENTICATED_RESPONDER};
 use crate::plexus::{HubContext, NoParent};
 use async_stream::stream;
 use futures::Stream;
@@ -190,13 +194,14 @@ impl<P: HubContext> Orcha<P> {
             let graph_meta = lattice_storage.get_graph(&graph_id_clone).await.ok()
                 .and_then(|g| g.metadata.get("_plexus_run_config").cloned());
 
-            let model_enum = graph_meta.as_ref()
+            let agent_target = graph_meta.as_ref()
                 .and_then(|c| c.get("model"))
                 .and_then(|m| m.as_str())
-                .map_or(crate::activations::claudecode::Model::Sonnet, |s| match s {
-                    "opus" => crate::activations::claudecode::Model::Opus,
-                    "haiku" => crate::activations::claudecode::Model::Haiku,
-                    _ => crate::activations::claudecode::Model::Sonnet,
+                .map_or_else(AgentTarget::default, |s| {
+                    AgentTarget::parse(s).unwrap_or_else(|e| {
+                        tracing::warn!("Graph {}: {}; recovering with the default model", graph_id_clone, e);
+                        AgentTarget::default()
+                    })
                 });
 
             let working_directory = graph_meta.as_ref()
@@ -228,7 +233,7 @@ impl<P: HubContext> Orcha<P> {
                     pm_for_recovery,
                     graph_runtime_recovery,
                     cancel_registry.clone(),
-                    model_enum,
+                    agent_target,
                     working_directory,
                     cancel_rx,
                     node_to_ticket,
@@ -289,6 +294,33 @@ async fn watch_single_graph(
                 })
             }
             crate::activations::lattice::LatticeEvent::NodeStarted { .. } => None,
+            crate::activations::lattice::LatticeEvent::SubGraphStarted { node_id, .. } => {
+                let ticket_id = node_to_ticket.get(&node_id).cloned();
+                Some(OrchaEvent::NodeStarted {
+                    node_id,
+                    label: None,
+                    ticket_id,
+                    percentage: calc_pct(complete_nodes, total_nodes),
+                })
+            }
+            crate::activations::lattice::LatticeEvent::LoopIteration {
+                target_node_id, iteration, max_iterations, reset_node_ids, token, ..
+            } => {
+                // The loop itself never finished; every other body node is undone.
+                complete_nodes = complete_nodes.saturating_sub(reset_node_ids.len().saturating_sub(1));
+                let ticket_id = node_to_ticket.get(&target_node_id).cloned();
+                Some(OrchaEvent::Retrying {
+                    node_id: target_node_id,
+                    ticket_id,
+                    attempt: iteration as usize,
+                    max_attempts: max_iterations as usize,
+                    error: token.message().unwrap_or_default().to_string(),
+                })
+            }
+            crate::activations::lattice::LatticeEvent::GraphResumed { graph_id, reset_node_ids } => {
+                complete_nodes = complete_nodes.saturating_sub(reset_node_ids.len());
+                Some(OrchaEvent::GraphResumed { graph_id, reset_node_ids })
+            }
             crate::activations::lattice::LatticeEvent::NodeDone { node_id, .. } => {
                 complete_nodes += 1;
                 let ticket_id = node_to_ticket.get(&node_id).cloned();
@@ -300,7 +332,7 @@ async fn watch_single_graph(
                     percentage: calc_pct(complete_nodes, total_nodes),
                 })
             }
-            crate::activations::lattice::LatticeEvent::NodeFailed { node_id, error } => {
+            crate::activations::lattice::LatticeEvent::NodeFailed { node_id, error, .. } => {
                 complete_nodes += 1;
                 let ticket_id = node_to_ticket.get(&node_id).cloned();
                 Some(OrchaEvent::NodeFailed {
@@ -945,12 +977,12 @@ impl<P: HubContext> Orcha<P> {
                 return;
             }
 
-            // Parse model
-            let model = match session.model.as_str() {
-                "opus" => crate::activations::claudecode::Model::Opus,
-                "sonnet" => crate::activations::claudecode::Model::Sonnet,
-                "haiku" => crate::activations::claudecode::Model::Haiku,
-                _ => crate::activations::claudecode::Model::Sonnet,
+            let model = match parse_model(&session.model) {
+                Ok(model) => model,
+                Err(message) => {
+                    yield SpawnAgentResult::Err { message };
+                    return;
+                }
             };
 
             // Create ClaudeCode session for this agent
@@ -1110,7 +1142,9 @@ impl<P: HubContext> Orcha<P> {
     /// Approve a pending request
     ///
     /// Approves a tool use request and unblocks the waiting agent.
-    /// The `approval_id` comes from `list_pending_approvals`.
+    /// The `approval_id` comes from `list_pending_approvals`. With
+    /// `updated_input` the tool runs with that input instead. The history
+    /// records the responder as `unauthenticated`.
     #[plexus_macros::method]
     async fn approve_request(
         &self,
@@ -1119,14 +1153,24 @@ impl<P: HubContext> Orcha<P> {
         let loopback = self.loopback.clone();
         let approval_id = request.approval_id.clone();
         let message = request.message;
+        let updated_input = request.updated_input;
 
         stream! {
             match uuid::Uuid::parse_str(&approval_id) {
                 Ok(uuid_id) => {
-                    match loopback.storage()
-                        .resolve_approval(&uuid_id, true, message.clone())
-                        .await
-                    {
+                    let storage = loopback.storage();
+                    let resolved = match updated_input {
+                        Some(input) => storage
+                            .approve_with_input(&uuid_id, input, message.clone(), Some(UNAUTHENTICATED_RESPONDER))
+                            .await
+                            .map(|_| ()),
+                        None => {
+                            storage
+                                .resolve_approval_as(&uuid_id, true, message.clone(), Some(UNAUTHENTICATED_RESPONDER))
+                                .await
+                        }
+                    };
+                    match resolved {
                         Ok(_) => {
                             yield ApprovalActionResult::Ok {
                                 approval_id: approval_id.clone(),
@@ -1166,7 +1210,7 @@ impl<P: HubContext> Orcha<P> {
             match uuid::Uuid::parse_str(&approval_id) {
                 Ok(uuid_id) => {
                     match loopback.storage()
-                        .resolve_approval(&uuid_id, false, reason.clone())
+                        .resolve_approval_as(&uuid_id, false, reason.clone(), Some(UNAUTHENTICATED_RESPONDER))
                         .await
                     {
                         Ok(_) => {
@@ -1271,7 +1315,7 @@ impl<P: HubContext> Orcha<P> {
     /// Streams `OrchaEvent` progress events until the graph completes or fails.
     #[plexus_macros::method(params(
         graph_id = "ID of the lattice graph to execute",
-        model = "Model for task nodes: opus, sonnet, haiku (default: sonnet)",
+        model = "Model for task nodes: opus, sonnet, haiku, cone:<model id> or cmd:<program> (default: sonnet)",
         working_directory = "Working directory for task nodes (default: /workspace)"
     ))]
     async fn run_graph(
@@ -1280,11 +1324,6 @@ impl<P: HubContext> Orcha<P> {
         model: Option<String>,
         working_directory: Option<String>,
     ) -> impl Stream<Item = OrchaEvent> + Send + 'static {
-        let model_enum = match model.as_deref().unwrap_or("sonnet") {
-            "opus" => Model::Opus,
-            "haiku" => Model::Haiku,
-            _ => Model::Sonnet,
-        };
         let wd = working_directory.unwrap_or_else(|| "/workspace".to_string());
 
         let cancel_rx = self.register_cancel(&graph_id).await;
@@ -1296,6 +1335,14 @@ impl<P: HubContext> Orcha<P> {
         let pm = self.pm.clone();
         let graph_runtime = self.graph_runtime.clone();
         stream! {
+            let agent_target = match AgentTarget::parse_or_default(model.as_deref()) {
+                Ok(target) => target,
+                Err(error) => {
+                    cancel_registry.lock().await.remove(&graph_id);
+                    yield OrchaEvent::Failed { session_id: graph_id, error };
+                    return;
+                }
+            };
             let execution = graph_runner::run_graph_execution(
                 graph,
                 claudecode,
@@ -1304,7 +1351,7 @@ impl<P: HubContext> Orcha<P> {
                 pm,
                 graph_runtime,
                 cancel_registry.clone(),
-                model_enum,
+                agent_target,
                 wd,
                 cancel_rx,
                 std::collections::HashMap::new(),
@@ -1317,6 +1364,93 @@ impl<P: HubContext> Orcha<P> {
         }
     }
 
+    /// Resume a failed (or cancelled) graph from the point of failure and execute it.
+    ///
+    /// Failed nodes and everything downstream of them are reset and run again;
+    /// completed tickets keep their output and are not re-run. Execution continues
+    /// under the same `graph_id`, so `pm.graph_status` and the ticket map stay valid.
+    ///
+    /// `model` and `working_directory` default to the values the graph was first
+    /// run with. Streams `GraphResumed` followed by the usual `OrchaEvents`.
+    #[plexus_macros::method(params(
+        graph_id = "ID of the failed or cancelled graph",
+        model = "Model for task nodes: opus, sonnet, haiku, cone:<model id> or cmd:<program> (default: as originally run)",
+        working_directory = "Working directory for task nodes (default: as originally run)"
+    ))]
+    async fn retry_graph(
+        &self,
+        graph_id: String,
+        model: Option<String>,
+        working_directory: Option<String>,
+    ) -> impl Stream<Item = OrchaEvent> + Send + 'static {
+        let graph_runtime = self.graph_runtime.clone();
+        let claudecode = self.claudecode.clone();
+        let arbor_storage = self.arbor_storage.clone();
+        let loopback_storage = self.loopback.storage();
+        let pm = self.pm.clone();
+        let cancel_registry = self.cancel_registry.clone();
+        stream! {
+            let lattice_storage = graph_runtime.storage();
+            let run_config = match lattice_storage.get_graph(&graph_id).await {
+                Ok(g) => g.metadata.get("_plexus_run_config").cloned(),
+                Err(e) => {
+                    yield OrchaEvent::Failed { session_id: graph_id, error: e };
+                    return;
+                }
+            };
+            let configured = |key: &str| {
+                run_config.as_ref()
+                    .and_then(|c| c.get(key))
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            };
+            let agent_target = match AgentTarget::parse_or_default(model.or_else(|| configured("model")).as_deref()) {
+                Ok(target) => target,
+                Err(error) => {
+                    yield OrchaEvent::Failed { session_id: graph_id, error };
+                    return;
+                }
+            };
+            let wd = working_directory
+                .or_else(|| configured("working_directory"))
+                .unwrap_or_else(|| "/workspace".to_string());
+            if !std::path::Path::new(&wd).is_dir() {
+                yield OrchaEvent::Failed {
+                    session_id: graph_id,
+                    error: format!("Working directory does not exist: '{wd}'"),
+                };
+                return;
+            }
+
+            // Register before resuming so the new run can be cancelled right away.
+            let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+            cancel_registry.lock().await.insert(graph_id.clone(), cancel_tx);
+
+            if let Err(e) = lattice_storage.resume_graph(&graph_id).await {
+                cancel_registry.lock().await.remove(&graph_id);
+                yield OrchaEvent::Failed { session_id: graph_id, error: e };
+                return;
+            }
+
+            let node_to_ticket: HashMap<String, String> = pm.get_ticket_map(&graph_id).await
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(ticket_id, node_id)| (node_id, ticket_id))
+                .collect();
+            let graph = Arc::new(graph_runtime.open_graph(graph_id.clone()));
+            let execution = graph_runner::run_graph_execution(
+                graph, claudecode, arbor_storage, loopback_storage, pm,
+                graph_runtime, cancel_registry.clone(),
+                agent_target, wd, cancel_rx, node_to_ticket,
+            );
+            tokio::pin!(execution);
+            while let Some(event) = execution.next().await {
+                yield event;
+            }
+            cancel_registry.lock().await.remove(&graph_id);
+        }
+    }
+
     /// Run a complete orchestration task driven by a single planning prompt.
     ///
     /// This is the single-call counterpart to the three-step sequence:
@@ -1327,7 +1461,7 @@ impl<P: HubContext> Orcha<P> {
     /// events until the graph completes or fails.
     #[plexus_macros::method(params(
         task = "Natural-language task — passed directly to Claude as the planning prompt",
-        model = "Model for all nodes: opus, sonnet, haiku (default: sonnet)",
+        model = "Model for all nodes: opus, sonnet, haiku, cone:<model id> or cmd:<program> (default: sonnet)",
         working_directory = "Working directory for task nodes (default: /workspace)"
     ))]
     async fn run_plan(
@@ -1336,12 +1470,6 @@ impl<P: HubContext> Orcha<P> {
         model: Option<String>,
         working_directory: Option<String>,
     ) -> impl Stream<Item = OrchaEvent> + Send + 'static {
-        let model_str = model.as_deref().unwrap_or("sonnet").to_string();
-        let model_enum = match model_str.as_str() {
-            "opus" => Model::Opus,
-            "haiku" => Model::Haiku,
-            _ => Model::Sonnet,
-        };
         let wd = working_directory.unwrap_or_else(|| "/workspace".to_string());
         let graph_runtime = self.graph_runtime.clone();
         let cancel_registry = self.cancel_registry.clone();
@@ -1351,9 +1479,16 @@ impl<P: HubContext> Orcha<P> {
         let pm = self.pm.clone();
 
         stream! {
+            let agent_target = match AgentTarget::parse_or_default(model.as_deref()) {
+                Ok(target) => target,
+                Err(error) => {
+                    yield OrchaEvent::Failed { session_id: String::new(), error };
+                    return;
+                }
+            };
             let metadata = serde_json::json!({
                 "_plexus_run_config": {
-                    "model": model_str,
+                    "model": agent_target.to_string(),
                     "working_directory": wd,
                 }
             });
@@ -1387,7 +1522,7 @@ impl<P: HubContext> Orcha<P> {
             let execution = graph_runner::run_graph_execution(
                 graph, claudecode, arbor, lb, pm,
                 graph_runtime, cancel_registry.clone(),
-                model_enum, wd, cancel_rx, node_to_ticket,
+                agent_target, wd, cancel_rx, node_to_ticket,
             );
             tokio::pin!(execution);
             while let Some(event) = execution.next().await {
@@ -1415,27 +1550,7 @@ impl<P: HubContext> Orcha<P> {
         let cancel_registry = self.cancel_registry.clone();
         let lattice_storage = self.graph_runtime.storage();
         stream! {
-            // BFS to collect the root graph and all descendant graph IDs.
-            let mut all_graph_ids: Vec<String> = Vec::new();
-            let mut to_visit: std::collections::VecDeque<String> = std::collections::VecDeque::new();
-            to_visit.push_back(graph_id.clone());
-            while let Some(gid) = to_visit.pop_front() {
-                all_graph_ids.push(gid.clone());
-                if let Ok(children) = lattice_storage.get_child_graphs(&gid).await {
-                    for child in children {
-                        to_visit.push_back(child.id);
-                    }
-                }
-            }
-
-            // Lock the registry once and cancel all collected graphs.
-            let mut registry = cancel_registry.lock().await;
-            let root_cancelled = registry.contains_key(&graph_id);
-            for gid in all_graph_ids {
-                if let Some(cancel_tx) = registry.remove(&gid) {
-                    let _ = cancel_tx.send(true);
-                }
-            }
+            let root_cancelled = graph_runner::cancel_graph_tree(&lattice_storage, &cancel_registry, &graph_id).await;
 
             if root_cancelled {
                 yield OrchaEvent::Cancelled { graph_id };
@@ -1511,6 +1626,33 @@ impl<P: HubContext> Orcha<P> {
                     crate::activations::lattice::LatticeEvent::NodeStarted { .. } => {
                         // Already emitted NodeStarted on NodeReady; suppress duplicate.
                     }
+                    crate::activations::lattice::LatticeEvent::SubGraphStarted { node_id, .. } => {
+                        // Engine-executed: SubGraph nodes never emit NodeReady.
+                        let ticket_id = node_to_ticket.get(&node_id).cloned();
+                        yield OrchaEvent::NodeStarted {
+                            node_id,
+                            label: None,
+                            ticket_id,
+                            percentage: calc_percentage(complete_nodes, total_nodes),
+                        };
+                    }
+                    crate::activations::lattice::LatticeEvent::LoopIteration {
+                        target_node_id, iteration, max_iterations, reset_node_ids, token, ..
+                    } => {
+                        complete_nodes = complete_nodes.saturating_sub(reset_node_ids.len().saturating_sub(1));
+                        let ticket_id = node_to_ticket.get(&target_node_id).cloned();
+                        yield OrchaEvent::Retrying {
+                            node_id: target_node_id,
+                            ticket_id,
+                            attempt: iteration as usize,
+                            max_attempts: max_iterations as usize,
+                            error: token.message().unwrap_or_default().to_string(),
+                        };
+                    }
+                    crate::activations::lattice::LatticeEvent::GraphResumed { graph_id, reset_node_ids } => {
+                        complete_nodes = complete_nodes.saturating_sub(reset_node_ids.len());
+                        yield OrchaEvent::GraphResumed { graph_id, reset_node_ids };
+                    }
                     crate::activations::lattice::LatticeEvent::NodeDone { node_id, .. } => {
                         complete_nodes += 1;
                         let ticket_id = node_to_ticket.get(&node_id).cloned();
@@ -1522,7 +1664,7 @@ impl<P: HubContext> Orcha<P> {
                             percentage: calc_percentage(complete_nodes, total_nodes),
                         };
                     }
-                    crate::activations::lattice::LatticeEvent::NodeFailed { node_id, error } => {
+                    crate::activations::lattice::LatticeEvent::NodeFailed { node_id, error, .. } => {
                         complete_nodes += 1;
                         let ticket_id = node_to_ticket.get(&node_id).cloned();
                         yield OrchaEvent::NodeFailed {
@@ -1638,6 +1780,38 @@ impl<P: HubContext> Orcha<P> {
         }
     }
 
+    /// Render a graph and the child graphs its plan nodes created as
+    /// Graphviz DOT or a Mermaid flowchart.
+    ///
+    /// Nodes are labelled with their ticket id and type and filled by status;
+    /// edges carry their predicates. Each planned child graph is a cluster
+    /// linked from the plan node that built it.
+    #[plexus_macros::method(params(
+        graph_id = "Root graph ID to render",
+        format = "Output format: dot (default) or mermaid"
+    ))]
+    async fn render_graph(
+        &self,
+        graph_id: String,
+        format: Option<String>,
+    ) -> impl Stream<Item = RenderResult> + Send + 'static {
+        let storage = self.graph_runtime.storage();
+        let pm = self.pm.clone();
+        stream! {
+            let format = match RenderFormat::parse_or_default(format.as_deref()) {
+                Ok(format) => format,
+                Err(message) => {
+                    yield RenderResult::Err { message };
+                    return;
+                }
+            };
+            match render::graph_picture(&storage, &pm, &graph_id).await {
+                Ok(picture) => yield RenderResult::Ok { format, source: picture.render(format) },
+                Err(e) => yield RenderResult::Err { message: e },
+            }
+        }
+    }
+
     // ─── Graph Builder API ───────────────────────────────────────────────────────
 
     /// Create an empty Orcha execution graph.
@@ -1669,7 +1843,7 @@ impl<P: HubContext> Orcha<P> {
     ) -> impl Stream<Item = OrchaAddNodeResult> + Send + 'static {
         let graph = self.graph_runtime.open_graph(graph_id);
         stream! {
-            match graph.add_task(task, None).await {
+            match graph.add_task(task).await {
                 Ok(node_id) => yield OrchaAddNodeResult::Ok { node_id },
                 Err(e) => yield OrchaAddNodeResult::Err { message: e },
             }
@@ -1688,7 +1862,7 @@ impl<P: HubContext> Orcha<P> {
     ) -> impl Stream<Item = OrchaAddNodeResult> + Send + 'static {
         let graph = self.graph_runtime.open_graph(graph_id);
         stream! {
-            match graph.add_synthesize(task, None).await {
+            match graph.add_synthesize(task).await {
                 Ok(node_id) => yield OrchaAddNodeResult::Ok { node_id },
                 Err(e) => yield OrchaAddNodeResult::Err { message: e },
             }
@@ -1709,7 +1883,7 @@ impl<P: HubContext> Orcha<P> {
     ) -> impl Stream<Item = OrchaAddNodeResult> + Send + 'static {
         let graph = self.graph_runtime.open_graph(graph_id);
         stream! {
-            match graph.add_validate(command, cwd, None).await {
+            match graph.add_validate(command, cwd).await {
                 Ok(node_id) => yield OrchaAddNodeResult::Ok { node_id },
                 Err(e) => yield OrchaAddNodeResult::Err { message: e },
             }
@@ -1735,9 +1909,9 @@ impl<P: HubContext> Orcha<P> {
         }
     }
 
-    /// Add a `SubGraph` node — when dispatched, runs the child graph to completion.
+    /// Add a `SubGraph` node — when ready, lattice launches the child graph itself.
     ///
-    /// On child success, the parent node receives `{"child_graph_id": "..."}` as output.
+    /// On child success, the parent node receives the child's sink tokens as output.
     /// On child failure, the parent node is failed (error edge fires if present).
     #[plexus_macros::method(params(
         graph_id = "Graph to add the node to",
@@ -1757,21 +1931,53 @@ impl<P: HubContext> Orcha<P> {
         }
     }
 
+    /// Add a bounded loop node closing a back-edge to `target_node_id`.
+    ///
+    /// Wire the last node of the body into it with `add_dependency`. While its
+    /// input is an error token (or matches `repeat_if`), the body from the target
+    /// to this node is reset and re-run, up to `max_iterations` passes in total.
+    #[plexus_macros::method(params(
+        graph_id = "Graph to add the node to",
+        target_node_id = "First node of the loop body — re-run on every pass",
+        max_iterations = "Maximum passes of the body, including the first",
+        repeat_if = "Optional payload predicate on the loop's input that triggers another pass (default: error token)"
+    ))]
+    async fn add_loop_node(
+        &self,
+        graph_id: String,
+        target_node_id: String,
+        max_iterations: u32,
+        repeat_if: Option<EdgePredicate>,
+    ) -> impl Stream<Item = OrchaAddNodeResult> + Send + 'static {
+        let graph = self.graph_runtime.open_graph(graph_id);
+        stream! {
+            match graph.add_loop(&target_node_id, max_iterations, repeat_if).await {
+                Ok(node_id) => yield OrchaAddNodeResult::Ok { node_id },
+                Err(e) => yield OrchaAddNodeResult::Err { message: e },
+            }
+        }
+    }
+
     /// Declare that `dependent_node_id` waits for `dependency_node_id` to complete.
+    ///
+    /// With a `predicate`, the dependency's output only flows to the dependent
+    /// when the predicate holds on it.
     #[plexus_macros::method(params(
         graph_id = "Graph containing both nodes",
         dependent_node_id = "Node that must wait",
-        dependency_node_id = "Node that must complete first"
+        dependency_node_id = "Node that must complete first",
+        predicate = "Optional payload predicate gating the edge (see lattice.add_edge)"
     ))]
     async fn add_dependency(
         &self,
         graph_id: String,
         dependent_node_id: String,
         dependency_node_id: String,
+        predicate: Option<EdgePredicate>,
     ) -> impl Stream<Item = OrchaAddDependencyResult> + Send + 'static {
         let graph = self.graph_runtime.open_graph(graph_id);
         stream! {
-            match graph.depends_on(&dependent_node_id, &dependency_node_id).await {
+            match graph.depends_on_when(&dependent_node_id, &dependency_node_id, predicate).await {
                 Ok(()) => yield OrchaAddDependencyResult::Ok,
                 Err(e) => yield OrchaAddDependencyResult::Err { message: e },
             }
@@ -1815,6 +2021,59 @@ impl<P: HubContext> Orcha<P> {
         }
     }
 
+    /// Check a ticket file without creating anything.
+    ///
+    /// Reports every error and warning with its line and column — unknown
+    /// types, bad metadata values, duplicate ids, unknown `blocked_by` ids and
+    /// dependency cycles — and, when there are no errors, the nodes, edges and
+    /// execution order `run_tickets` would build.
+    #[plexus_macros::method(params(
+        tickets = "Raw ticket file content"
+    ))]
+    async fn lint_tickets(
+        &self,
+        tickets: String,
+    ) -> impl Stream<Item = LintTicketsResult> + Send + 'static {
+        stream! {
+            let lint = ticket_compiler::lint_tickets(&tickets);
+            yield match lint.graph {
+                Some(graph) => LintTicketsResult::Ok {
+                    order: graph.topological_order(),
+                    diagnostics: lint.diagnostics,
+                    nodes: graph.nodes,
+                    edges: graph.edges,
+                },
+                None => LintTicketsResult::Invalid { diagnostics: lint.diagnostics },
+            };
+        }
+    }
+
+    /// Render a ticket file as Graphviz DOT or a Mermaid flowchart without
+    /// building or running anything — e.g. to attach the planned graph to a PR.
+    #[plexus_macros::method(params(
+        tickets = "Raw ticket file content",
+        format = "Output format: dot (default) or mermaid"
+    ))]
+    async fn render_tickets(
+        &self,
+        tickets: String,
+        format: Option<String>,
+    ) -> impl Stream<Item = RenderResult> + Send + 'static {
+        stream! {
+            let format = match RenderFormat::parse_or_default(format.as_deref()) {
+                Ok(format) => format,
+                Err(message) => {
+                    yield RenderResult::Err { message };
+                    return;
+                }
+            };
+            yield match ticket_compiler::compile_tickets(&tickets) {
+                Ok(compiled) => RenderResult::Ok { format, source: render::compiled_picture(&compiled).render(format) },
+                Err(e) => RenderResult::Err { message: format!("Ticket compile error: {e}") },
+            };
+        }
+    }
+
     /// Compile a ticket file and execute the resulting graph.
     ///
     /// Parses the ticket DSL, builds a graph, and streams execution events.
@@ -1831,8 +2090,13 @@ impl<P: HubContext> Orcha<P> {
     #[plexus_macros::method(params(
         tickets = "Raw ticket file content",
         metadata = "Arbitrary JSON metadata attached to the graph",
-        model = "Model for task nodes: opus, sonnet, haiku (default: sonnet)",
-        working_directory = "Working directory for task nodes (default: /workspace)"
+        model = "Model for task nodes: opus, sonnet, haiku, cone:<model id> or cmd:<program> (default: sonnet)",
+        working_directory = "Working directory for task nodes (default: /workspace)",
+        isolation = "Per-ticket git worktree isolation: none, worktree (merge back) or worktree-rebase (default: none)",
+        max_agents = "Most agent nodes (task, synthesize, plan) this graph runs at once (default: server limit only)",
+        max_progs = "Most validate nodes this graph runs at once (default: server limit only)",
+        max_cost_usd = "Fail the graph once its agents (and its child graphs' agents) have spent more than this many dollars (default: no limit)",
+        max_tokens = "Fail the graph once its agents (and its child graphs' agents) have used more than this many input + output tokens (default: no limit)"
     ))]
     async fn run_tickets(
         &self,
@@ -1840,6 +2104,11 @@ impl<P: HubContext> Orcha<P> {
         metadata: Value,
         model: Option<String>,
         working_directory: Option<String>,
+        isolation: Option<String>,
+        max_agents: Option<u32>,
+        max_progs: Option<u32>,
+        max_cost_usd: Option<f64>,
+        max_tokens: Option<u64>,
     ) -> impl Stream<Item = OrchaEvent> + Send + 'static {
         let graph_runtime = self.graph_runtime.clone();
         let claudecode = self.claudecode.clone();
@@ -1848,6 +2117,13 @@ impl<P: HubContext> Orcha<P> {
         let pm = self.pm.clone();
         let cancel_registry = self.cancel_registry.clone();
         stream! {
+            let integration = match Integration::parse(isolation.as_deref()) {
+                Ok(integration) => integration,
+                Err(error) => {
+                    yield OrchaEvent::Failed { session_id: "tickets".to_string(), error };
+                    return;
+                }
+            };
             let compiled = match ticket_compiler::compile_tickets(&tickets) {
                 Ok(c) => c,
                 Err(e) => {
@@ -1858,7 +2134,14 @@ impl<P: HubContext> Orcha<P> {
                     return;
                 }
             };
-            let model_str = model.as_deref().unwrap_or("sonnet").to_string();
+            let agent_target = match AgentTarget::parse_or_default(model.as_deref()) {
+                Ok(target) => target,
+                Err(error) => {
+                    yield OrchaEvent::Failed { session_id: "tickets".to_string(), error };
+                    return;
+                }
+            };
+            let model_str = agent_target.to_string();
             let wd = working_directory.unwrap_or_else(|| "/workspace".to_string());
             let mut enriched_metadata = if metadata.is_object() {
                 metadata.clone()
@@ -1868,6 +2151,11 @@ impl<P: HubContext> Orcha<P> {
             enriched_metadata["_plexus_run_config"] = serde_json::json!({
                 "model": model_str,
                 "working_directory": wd,
+                "isolation": integration.map(Integration::as_str),
+                "max_agents": max_agents,
+                "max_progs": max_progs,
+                "max_cost_usd": max_cost_usd,
+                "max_tokens": max_tokens,
             });
             let (graph_id, id_map) = match build_graph_from_definition(
                 graph_runtime.clone(), enriched_metadata, compiled.nodes, compiled.edges,
@@ -1886,11 +2174,6 @@ impl<P: HubContext> Orcha<P> {
 
             yield OrchaEvent::GraphStarted { graph_id: graph_id.clone() };
 
-            let model_enum = match model_str.as_str() {
-                "opus" => Model::Opus,
-                "haiku" => Model::Haiku,
-                _ => Model::Sonnet,
-            };
 
             // Validate working directory early — before the graph starts executing —
             // so the caller gets a clear error instead of every node failing with an
@@ -1929,7 +2212,7 @@ impl<P: HubContext> Orcha<P> {
                     pm,
                     graph_runtime,
                     cancel_registry.clone(),
-                    model_enum,
+                    agent_target,
                     wd,
                     cancel_rx,
                     node_to_ticket,
@@ -1963,8 +2246,13 @@ impl<P: HubContext> Orcha<P> {
     #[plexus_macros::method(params(
         tickets = "Raw ticket file content",
         metadata = "Arbitrary JSON metadata",
-        model = "Model: opus, sonnet, haiku (default: sonnet)",
-        working_directory = "Working directory (default: /workspace)"
+        model = "Model: opus, sonnet, haiku, cone:<model id> or cmd:<program> (default: sonnet)",
+        working_directory = "Working directory (default: /workspace)",
+        isolation = "Per-ticket git worktree isolation: none, worktree (merge back) or worktree-rebase (default: none)",
+        max_agents = "Most agent nodes (task, synthesize, plan) this graph runs at once (default: server limit only)",
+        max_progs = "Most validate nodes this graph runs at once (default: server limit only)",
+        max_cost_usd = "Fail the graph once its agents (and its child graphs' agents) have spent more than this many dollars (default: no limit)",
+        max_tokens = "Fail the graph once its agents (and its child graphs' agents) have used more than this many input + output tokens (default: no limit)"
     ))]
     async fn run_tickets_async(
         &self,
@@ -1972,6 +2260,11 @@ impl<P: HubContext> Orcha<P> {
         metadata: Value,
         model: Option<String>,
         working_directory: Option<String>,
+        isolation: Option<String>,
+        max_agents: Option<u32>,
+        max_progs: Option<u32>,
+        max_cost_usd: Option<f64>,
+        max_tokens: Option<u64>,
     ) -> impl Stream<Item = OrchaEvent> + Send + 'static {
         let graph_runtime = self.graph_runtime.clone();
         let claudecode = self.claudecode.clone();
@@ -1980,6 +2273,13 @@ impl<P: HubContext> Orcha<P> {
         let pm = self.pm.clone();
         let cancel_registry = self.cancel_registry.clone();
         stream! {
+            let integration = match Integration::parse(isolation.as_deref()) {
+                Ok(integration) => integration,
+                Err(error) => {
+                    yield OrchaEvent::Failed { session_id: "tickets".to_string(), error };
+                    return;
+                }
+            };
             let compiled = match ticket_compiler::compile_tickets(&tickets) {
                 Ok(c) => c,
                 Err(e) => {
@@ -1991,7 +2291,14 @@ impl<P: HubContext> Orcha<P> {
                 }
             };
 
-            let model_str = model.as_deref().unwrap_or("sonnet").to_string();
+            let agent_target = match AgentTarget::parse_or_default(model.as_deref()) {
+                Ok(target) => target,
+                Err(error) => {
+                    yield OrchaEvent::Failed { session_id: "tickets".to_string(), error };
+                    return;
+                }
+            };
+            let model_str = agent_target.to_string();
             let wd = working_directory.unwrap_or_else(|| "/workspace".to_string());
 
             // Validate working directory before building the graph so the caller
@@ -2015,6 +2322,11 @@ impl<P: HubContext> Orcha<P> {
             enriched_metadata["_plexus_run_config"] = serde_json::json!({
                 "model": model_str,
                 "working_directory": wd,
+                "isolation": integration.map(Integration::as_str),
+                "max_agents": max_agents,
+                "max_progs": max_progs,
+                "max_cost_usd": max_cost_usd,
+                "max_tokens": max_tokens,
             });
 
             let (graph_id, id_map) = match build_graph_from_definition(
@@ -2035,11 +2347,6 @@ impl<P: HubContext> Orcha<P> {
 
             yield OrchaEvent::GraphStarted { graph_id: graph_id.clone() };
 
-            let model_enum = match model_str.as_str() {
-                "opus" => Model::Opus,
-                "haiku" => Model::Haiku,
-                _ => Model::Sonnet,
-            };
 
             let graph = Arc::new(graph_runtime.open_graph(graph_id.clone()));
 
@@ -2062,7 +2369,7 @@ impl<P: HubContext> Orcha<P> {
                     pm,
                     graph_runtime,
                     cancel_registry.clone(),
-                    model_enum,
+                    agent_target,
                     wd,
                     cancel_rx,
                     node_to_ticket,
@@ -2097,8 +2404,13 @@ impl<P: HubContext> Orcha<P> {
     #[plexus_macros::method(params(
         paths = "Absolute paths to ticket markdown files, e.g. [\"/workspace/plans/batch.tickets.md\"]",
         metadata = "Arbitrary JSON metadata attached to the graph",
-        model = "Model for task nodes: opus, sonnet, haiku (default: sonnet)",
-        working_directory = "Working directory for task nodes (default: /workspace)"
+        model = "Model for task nodes: opus, sonnet, haiku, cone:<model id> or cmd:<program> (default: sonnet)",
+        working_directory = "Working directory for task nodes (default: /workspace)",
+        isolation = "Per-ticket git worktree isolation: none, worktree (merge back) or worktree-rebase (default: none)",
+        max_agents = "Most agent nodes (task, synthesize, plan) this graph runs at once (default: server limit only)",
+        max_progs = "Most validate nodes this graph runs at once (default: server limit only)",
+        max_cost_usd = "Fail the graph once its agents (and its child graphs' agents) have spent more than this many dollars (default: no limit)",
+        max_tokens = "Fail the graph once its agents (and its child graphs' agents) have used more than this many input + output tokens (default: no limit)"
     ))]
     async fn run_tickets_files(
         &self,
@@ -2106,6 +2418,11 @@ impl<P: HubContext> Orcha<P> {
         metadata: Value,
         model: Option<String>,
         working_directory: Option<String>,
+        isolation: Option<String>,
+        max_agents: Option<u32>,
+        max_progs: Option<u32>,
+        max_cost_usd: Option<f64>,
+        max_tokens: Option<u64>,
     ) -> impl Stream<Item = OrchaEvent> + Send + 'static {
         let graph_runtime = self.graph_runtime.clone();
         let claudecode = self.claudecode.clone();
@@ -2114,6 +2431,13 @@ impl<P: HubContext> Orcha<P> {
         let pm = self.pm.clone();
         let cancel_registry = self.cancel_registry.clone();
         stream! {
+            let integration = match Integration::parse(isolation.as_deref()) {
+                Ok(integration) => integration,
+                Err(error) => {
+                    yield OrchaEvent::Failed { session_id: "tickets".to_string(), error };
+                    return;
+                }
+            };
             // Read and concatenate all files.
             let mut parts: Vec<String> = Vec::new();
             for path in &paths {
@@ -2140,12 +2464,24 @@ impl<P: HubContext> Orcha<P> {
                     return;
                 }
             };
-            let model_str = model.as_deref().unwrap_or("sonnet").to_string();
+            let agent_target = match AgentTarget::parse_or_default(model.as_deref()) {
+                Ok(target) => target,
+                Err(error) => {
+                    yield OrchaEvent::Failed { session_id: "tickets".to_string(), error };
+                    return;
+                }
+            };
+            let model_str = agent_target.to_string();
             let wd = working_directory.unwrap_or_else(|| "/workspace".to_string());
             let mut enriched_metadata = if metadata.is_object() { metadata.clone() } else { serde_json::json!({}) };
             enriched_metadata["_plexus_run_config"] = serde_json::json!({
                 "model": model_str,
                 "working_directory": wd,
+                "isolation": integration.map(Integration::as_str),
+                "max_agents": max_agents,
+                "max_progs": max_progs,
+                "max_cost_usd": max_cost_usd,
+                "max_tokens": max_tokens,
             });
             let (graph_id, id_map) = match build_graph_from_definition(
                 graph_runtime.clone(), enriched_metadata, compiled.nodes, compiled.edges,
@@ -2161,11 +2497,6 @@ impl<P: HubContext> Orcha<P> {
 
             yield OrchaEvent::GraphStarted { graph_id: graph_id.clone() };
 
-            let model_enum = match model_str.as_str() {
-                "opus" => Model::Opus,
-                "haiku" => Model::Haiku,
-                _ => Model::Sonnet,
-            };
             if !std::path::Path::new(&wd).is_dir() {
                 yield OrchaEvent::Failed {
                     session_id: "tickets".to_string(),
@@ -2181,7 +2512,7 @@ impl<P: HubContext> Orcha<P> {
             let execution = graph_runner::run_graph_execution(
                 graph, claudecode, arbor_storage, loopback_storage, pm,
                 graph_runtime, cancel_registry.clone(),
-                model_enum, wd, cancel_rx, node_to_ticket,
+                agent_target, wd, cancel_rx, node_to_ticket,
             );
             tokio::pin!(execution);
             while let Some(event) = execution.next().await {
@@ -2197,8 +2528,13 @@ impl<P: HubContext> Orcha<P> {
     #[plexus_macros::method(params(
         paths = "Absolute paths to ticket markdown files",
         metadata = "Arbitrary JSON metadata attached to the graph",
-        model = "Model for task nodes: opus, sonnet, haiku (default: sonnet)",
-        working_directory = "Working directory for task nodes (default: /workspace)"
+        model = "Model for task nodes: opus, sonnet, haiku, cone:<model id> or cmd:<program> (default: sonnet)",
+        working_directory = "Working directory for task nodes (default: /workspace)",
+        isolation = "Per-ticket git worktree isolation: none, worktree (merge back) or worktree-rebase (default: none)",
+        max_agents = "Most agent nodes (task, synthesize, plan) this graph runs at once (default: server limit only)",
+        max_progs = "Most validate nodes this graph runs at once (default: server limit only)",
+        max_cost_usd = "Fail the graph once its agents (and its child graphs' agents) have spent more than this many dollars (default: no limit)",
+        max_tokens = "Fail the graph once its agents (and its child graphs' agents) have used more than this many input + output tokens (default: no limit)"
     ))]
     async fn run_tickets_async_files(
         &self,
@@ -2206,6 +2542,11 @@ impl<P: HubContext> Orcha<P> {
         metadata: Value,
         model: Option<String>,
         working_directory: Option<String>,
+        isolation: Option<String>,
+        max_agents: Option<u32>,
+        max_progs: Option<u32>,
+        max_cost_usd: Option<f64>,
+        max_tokens: Option<u64>,
     ) -> impl Stream<Item = OrchaEvent> + Send + 'static {
         let graph_runtime = self.graph_runtime.clone();
         let claudecode = self.claudecode.clone();
@@ -2214,6 +2555,13 @@ impl<P: HubContext> Orcha<P> {
         let pm = self.pm.clone();
         let cancel_registry = self.cancel_registry.clone();
         stream! {
+            let integration = match Integration::parse(isolation.as_deref()) {
+                Ok(integration) => integration,
+                Err(error) => {
+                    yield OrchaEvent::Failed { session_id: "tickets".to_string(), error };
+                    return;
+                }
+            };
             let mut parts: Vec<String> = Vec::new();
             for path in &paths {
                 match tokio::fs::read_to_string(path).await {
@@ -2239,12 +2587,24 @@ impl<P: HubContext> Orcha<P> {
                     return;
                 }
             };
-            let model_str = model.as_deref().unwrap_or("sonnet").to_string();
+            let agent_target = match AgentTarget::parse_or_default(model.as_deref()) {
+                Ok(target) => target,
+                Err(error) => {
+                    yield OrchaEvent::Failed { session_id: "tickets".to_string(), error };
+                    return;
+                }
+            };
+            let model_str = agent_target.to_string();
             let wd = working_directory.unwrap_or_else(|| "/workspace".to_string());
             let mut enriched_metadata = if metadata.is_object() { metadata.clone() } else { serde_json::json!({}) };
             enriched_metadata["_plexus_run_config"] = serde_json::json!({
                 "model": model_str,
                 "working_directory": wd,
+                "isolation": integration.map(Integration::as_str),
+                "max_agents": max_agents,
+                "max_progs": max_progs,
+                "max_cost_usd": max_cost_usd,
+                "max_tokens": max_tokens,
             });
             let (graph_id, id_map) = match build_graph_from_definition(
                 graph_runtime.clone(), enriched_metadata, compiled.nodes, compiled.edges,
@@ -2260,11 +2620,6 @@ impl<P: HubContext> Orcha<P> {
 
             yield OrchaEvent::GraphStarted { graph_id: graph_id.clone() };
 
-            let model_enum = match model_str.as_str() {
-                "opus" => Model::Opus,
-                "haiku" => Model::Haiku,
-                _ => Model::Sonnet,
-            };
             if !std::path::Path::new(&wd).is_dir() {
                 yield OrchaEvent::Failed {
                     session_id: "tickets".to_string(),
@@ -2281,7 +2636,7 @@ impl<P: HubContext> Orcha<P> {
                 let execution = graph_runner::run_graph_execution(
                     graph, claudecode, arbor_storage, loopback_storage, pm,
                     graph_runtime, cancel_registry.clone(),
-                    model_enum, wd, cancel_rx, node_to_ticket,
+                    agent_target, wd, cancel_rx, node_to_ticket,
                 );
                 tokio::pin!(execution);
                 while let Some(event) = execution.next().await {
@@ -2300,16 +2655,238 @@ impl<P: HubContext> Orcha<P> {
         }
     }
 
+    /// Compile a ticket file and save it as a named graph template.
+    ///
+    /// Ticket text may contain mustache `{{placeholders}}` in task bodies and
+    /// `validate:` commands; they are filled in by `run_template`. Saving under an
+    /// existing name adds a new version — earlier versions stay runnable.
+    #[plexus_macros::method(params(
+        name = "Template name",
+        tickets = "Raw ticket file content, with {{placeholders}}",
+        description = "Optional human-readable description"
+    ))]
+    async fn save_template(
+        &self,
+        name: String,
+        tickets: String,
+        description: Option<String>,
+    ) -> impl Stream<Item = SaveTemplateResult> + Send + 'static {
+        let storage = self.storage.clone();
+        stream! {
+            if name.trim().is_empty() {
+                yield SaveTemplateResult::Err { message: "Template name must not be empty".to_string() };
+                return;
+            }
+            let compiled = match ticket_compiler::compile_tickets(&tickets) {
+                Ok(c) => c,
+                Err(e) => {
+                    yield SaveTemplateResult::Err { message: format!("Ticket compile error: {e}") };
+                    return;
+                }
+            };
+            let parameters = templates::parameters(&compiled.nodes);
+
+            // Surface mustache syntax errors now rather than on the first run.
+            let blank: serde_json::Map<String, Value> = parameters
+                .iter()
+                .map(|p| (p.clone(), Value::String(String::new())))
+                .collect();
+            if let Err(e) = templates::render(&compiled.nodes, &Value::Object(blank)) {
+                yield SaveTemplateResult::Err { message: e };
+                return;
+            }
+
+            match storage.save_template(
+                &name, description.as_deref(), &parameters, &tickets, &compiled.nodes, &compiled.edges,
+            ).await {
+                Ok(version) => yield SaveTemplateResult::Ok { name, version, parameters },
+                Err(e) => yield SaveTemplateResult::Err { message: e },
+            }
+        }
+    }
+
+    /// Get a saved graph template (latest version unless `version` is given).
+    #[plexus_macros::method(params(
+        name = "Template name",
+        version = "Template version (default: latest)"
+    ))]
+    async fn get_template(
+        &self,
+        name: String,
+        version: Option<u32>,
+    ) -> impl Stream<Item = GetTemplateResult> + Send + 'static {
+        let storage = self.storage.clone();
+        stream! {
+            match storage.get_template(&name, version).await {
+                Ok(template) => yield GetTemplateResult::Ok { template },
+                Err(e) => yield GetTemplateResult::Err { message: e },
+            }
+        }
+    }
+
+    /// List all saved graph templates and their versions.
+    #[plexus_macros::method]
+    async fn list_templates(&self) -> impl Stream<Item = ListTemplatesResult> + Send + 'static {
+        let storage = self.storage.clone();
+        stream! {
+            match storage.list_templates().await {
+                Ok(templates) => yield ListTemplatesResult::Ok { templates },
+                Err(e) => yield ListTemplatesResult::Err { message: e },
+            }
+        }
+    }
+
+    /// Instantiate a saved template with parameters and execute it.
+    ///
+    /// `params` fills the template's `{{placeholders}}`. `model` and
+    /// `working_directory` fall back to the same keys in `params`, and their
+    /// resolved values are available to placeholders either way. No ticket
+    /// markdown is recompiled — the stored graph is rendered and built directly.
+    ///
+    /// Streams `OrchaEvents` until the graph completes or fails.
+    #[plexus_macros::method(params(
+        name = "Template name",
+        version = "Template version (default: latest)",
+        params = "JSON object of placeholder values, e.g. {\"crate\": \"core\"}",
+        metadata = "Arbitrary JSON metadata attached to the graph",
+        model = "Model for task nodes: opus, sonnet, haiku, cone:<model id> or cmd:<program> (default: params.model, then sonnet)",
+        working_directory = "Working directory for task nodes (default: params.working_directory, then /workspace)",
+        isolation = "Per-ticket git worktree isolation: none, worktree (merge back) or worktree-rebase (default: none)",
+        max_agents = "Most agent nodes (task, synthesize, plan) this graph runs at once (default: server limit only)",
+        max_progs = "Most validate nodes this graph runs at once (default: server limit only)"
+    ))]
+    async fn run_template(
+        &self,
+        name: String,
+        version: Option<u32>,
+        params: Option<Value>,
+        metadata: Value,
+        model: Option<String>,
+        working_directory: Option<String>,
+        isolation: Option<String>,
+        max_agents: Option<u32>,
+        max_progs: Option<u32>,
+    ) -> impl Stream<Item = OrchaEvent> + Send + 'static {
+        let storage = self.storage.clone();
+        let graph_runtime = self.graph_runtime.clone();
+        let claudecode = self.claudecode.clone();
+        let arbor_storage = self.arbor_storage.clone();
+        let loopback_storage = self.loopback.storage();
+        let pm = self.pm.clone();
+        let cancel_registry = self.cancel_registry.clone();
+        stream! {
+            let integration = match Integration::parse(isolation.as_deref()) {
+                Ok(integration) => integration,
+                Err(error) => {
+                    yield OrchaEvent::Failed { session_id: "template".to_string(), error };
+                    return;
+                }
+            };
+            let template = match storage.get_template(&name, version).await {
+                Ok(t) => t,
+                Err(e) => {
+                    yield OrchaEvent::Failed { session_id: "template".to_string(), error: e };
+                    return;
+                }
+            };
+            let mut params = params.unwrap_or_else(|| serde_json::json!({}));
+            if !params.is_object() {
+                yield OrchaEvent::Failed {
+                    session_id: "template".to_string(),
+                    error: "Template params must be a JSON object".to_string(),
+                };
+                return;
+            }
+            let from_params = |key: &str| params.get(key).and_then(Value::as_str).map(str::to_string);
+            let agent_target = match AgentTarget::parse_or_default(model.or_else(|| from_params("model")).as_deref()) {
+                Ok(target) => target,
+                Err(error) => {
+                    yield OrchaEvent::Failed { session_id: "template".to_string(), error };
+                    return;
+                }
+            };
+            let model_str = agent_target.to_string();
+            let wd = working_directory
+                .or_else(|| from_params("working_directory"))
+                .unwrap_or_else(|| "/workspace".to_string());
+            params["model"] = Value::String(model_str.clone());
+            params["working_directory"] = Value::String(wd.clone());
+
+            let nodes = match templates::render(&template.nodes, &params) {
+                Ok(n) => n,
+                Err(e) => {
+                    yield OrchaEvent::Failed {
+                        session_id: "template".to_string(),
+                        error: format!("Template {} v{}: {e}", template.name, template.version),
+                    };
+                    return;
+                }
+            };
+            let source = templates::render_text(&template.source, &params)
+                .unwrap_or_else(|_| template.source.clone());
+
+            let mut enriched_metadata = if metadata.is_object() { metadata.clone() } else { serde_json::json!({}) };
+            enriched_metadata["_plexus_run_config"] = serde_json::json!({
+                "model": model_str,
+                "working_directory": wd,
+                "isolation": integration.map(Integration::as_str),
+                "max_agents": max_agents,
+                "max_progs": max_progs,
+            });
+            enriched_metadata["_plexus_template"] = serde_json::json!({
+                "name": template.name,
+                "version": template.version,
+                "params": params,
+            });
+            let (graph_id, id_map) = match build_graph_from_definition(
+                graph_runtime.clone(), enriched_metadata, nodes, template.edges,
+            ).await {
+                Ok(pair) => pair,
+                Err(e) => {
+                    yield OrchaEvent::Failed { session_id: "template".to_string(), error: e };
+                    return;
+                }
+            };
+            let _ = pm.save_ticket_map(&graph_id, &id_map).await;
+            let _ = pm.save_ticket_source(&graph_id, &source).await;
+
+            yield OrchaEvent::GraphStarted { graph_id: graph_id.clone() };
+
+            if !std::path::Path::new(&wd).is_dir() {
+                yield OrchaEvent::Failed {
+                    session_id: "template".to_string(),
+                    error: format!("Working directory does not exist: '{wd}'"),
+                };
+                return;
+            }
+            let node_to_ticket: std::collections::HashMap<String, String> = id_map
+                .iter().map(|(t, n)| (n.clone(), t.clone())).collect();
+            let graph = Arc::new(graph_runtime.open_graph(graph_id.clone()));
+            let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+            cancel_registry.lock().await.insert(graph_id.clone(), cancel_tx);
+            let execution = graph_runner::run_graph_execution(
+                graph, claudecode, arbor_storage, loopback_storage, pm,
+                graph_runtime, cancel_registry.clone(),
+                agent_target, wd, cancel_rx, node_to_ticket,
+            );
+            tokio::pin!(execution);
+            while let Some(event) = execution.next().await {
+                yield event;
+            }
+            cancel_registry.lock().await.remove(&graph_id);
+        }
+    }
+
     /// Build and execute a graph from an inline definition.
     ///
     /// Nodes use caller-supplied string ids; edges reference those ids.
     /// Streams `OrchaEvents`. The `graph_id` appears in progress and complete/failed events.
     #[plexus_macros::method(params(
         metadata = "Arbitrary JSON metadata attached to the graph",
-        model = "Model for task nodes: opus, sonnet, haiku (default: sonnet)",
+        model = "Model for task nodes: opus, sonnet, haiku, cone:<model id> or cmd:<program> (default: sonnet)",
         working_directory = "Working directory for task nodes (default: /workspace)",
         nodes = "Array of OrchaNodeDef: [{\"id\":\"...\",\"spec\":{\"type\":\"task\",\"task\":\"...\"}}]",
-        edges = "Array of OrchaEdgeDef: [{\"from\":\"id1\",\"to\":\"id2\"}]"
+        edges = "Array of OrchaEdgeDef: [{\"from\":\"id1\",\"to\":\"id2\",\"predicate\":null}]"
     ))]
     async fn run_graph_definition(
         &self,
@@ -2359,15 +2936,9 @@ async fn build_graph_from_definition(
     let graph_id = graph.graph_id.clone();
 
     let mut id_map: HashMap<String, String> = HashMap::new();
-    for OrchaNodeDef { id, spec } in nodes {
-        let result = match spec {
-            OrchaNodeSpec::Task { task, max_retries } => graph.add_task(task, max_retries).await,
-            OrchaNodeSpec::Synthesize { task, max_retries } => graph.add_synthesize(task, max_retries).await,
-            OrchaNodeSpec::Validate { command, cwd, max_retries } => graph.add_validate(command, cwd, max_retries).await,
-            OrchaNodeSpec::Gather { strategy } => graph.add_gather(strategy).await,
-            OrchaNodeSpec::Review { prompt } => graph.add_review(prompt).await,
-            OrchaNodeSpec::Plan { task } => graph.add_plan(task).await,
-        };
+    for def in nodes {
+        let id = def.id.clone();
+        let result = graph.add_node_def(def, &id_map).await;
         let lattice_id = match result {
             Ok(lid) => lid,
             Err(e) => return Err(format!("Failed to add node '{id}': {e}")),
@@ -2375,7 +2946,7 @@ async fn build_graph_from_definition(
         id_map.insert(id, lattice_id);
     }
 
-    for OrchaEdgeDef { from, to } in edges {
+    for OrchaEdgeDef { from, to, predicate } in edges {
         let dep_id = id_map
             .get(&from)
             .ok_or_else(|| format!("Unknown node id in edge.from: '{from}'"))?
@@ -2385,7 +2956,7 @@ async fn build_graph_from_definition(
             .ok_or_else(|| format!("Unknown node id in edge.to: '{to}'"))?
             .clone();
         graph
-            .depends_on(&node_id, &dep_id)
+            .depends_on_when(&node_id, &dep_id, predicate)
             .await
             .map_err(|e| format!("Failed to add edge {from} → {to}: {e}"))?;
     }
@@ -2409,8 +2980,17 @@ fn build_and_run_graph_definition<P: HubContext + 'static>(
     edges: Vec<OrchaEdgeDef>,
 ) -> impl Stream<Item = OrchaEvent> + Send + 'static {
     stream! {
+        // Inline nodes retry through loop nodes, like compiled tickets
+        let mut definition = ticket_compiler::CompiledGraph { nodes, edges };
+        if let Err(e) = definition.lower_retries() {
+            yield OrchaEvent::Failed {
+                session_id: "graph_definition".to_string(),
+                error: e,
+            };
+            return;
+        }
         let (graph_id, _) = match build_graph_from_definition(
-            graph_runtime.clone(), metadata, nodes, edges,
+            graph_runtime.clone(), metadata, definition.nodes, definition.edges,
         ).await {
             Ok(pair) => pair,
             Err(e) => {
@@ -2427,10 +3007,12 @@ fn build_and_run_graph_definition<P: HubContext + 'static>(
             percentage: None,
         };
 
-        let model_enum = match model.as_deref().unwrap_or("sonnet") {
-            "opus" => Model::Opus,
-            "haiku" => Model::Haiku,
-            _ => Model::Sonnet,
+        let agent_target = match AgentTarget::parse_or_default(model.as_deref()) {
+            Ok(target) => target,
+            Err(error) => {
+                yield OrchaEvent::Failed { session_id: graph_id, error };
+                return;
+            }
         };
         let wd = working_directory.unwrap_or_else(|| "/workspace".to_string());
 
@@ -2446,7 +3028,7 @@ fn build_and_run_graph_definition<P: HubContext + 'static>(
             pm,
             graph_runtime,
             cancel_registry.clone(),
-            model_enum,
+            agent_target,
             wd,
             cancel_rx,
             std::collections::HashMap::new(),
@@ -0,0 +1,497 @@
+//! Agent backends for task, synthesize and plan nodes.
+//!
+//! A node's `model` names an `AgentTarget`: a Claude CLI session (the
+//! default), a Cone model from the cllient `ModelRegistry`, or a local
+//! command that speaks Claude's `stream-json` protocol (handy as a cheap
+//! stand-in for tests and dry runs). `AgentFactory` turns a target into an
+//! `AgentExecutor`, and `graph_runner` consumes the resulting `AgentEvent`
+//! stream the same way whichever backend produced it.
+
+use crate::activations::bash::BashExecutor;
+use crate::activations::claudecode::{
+    ChatEvent, ClaudeCode, ClaudeCodeExecutor, CreateResult, LaunchConfig, Model, RawClaudeEvent,
+    RawContentBlock,
+};
+use crate::plexus::HubContext;
+use async_stream::stream;
+use cllient::{Message, ModelRegistry};
+use futures::{Stream, StreamExt};
+use serde_json::Value;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Which backend runs an agent node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AgentTarget {
+    /// A Claude CLI session via `ClaudeCode` — `opus`, `sonnet`, `haiku`
+    /// (optionally prefixed `claude:`)
+    Claude(Model),
+    /// A one-shot completion from a cllient `ModelRegistry` model — `cone:<model id>`
+    Cone(String),
+    /// A local program invoked like the Claude CLI, emitting `stream-json` — `cmd:<program>`
+    Command(String),
+}
+
+impl Default for AgentTarget {
+    fn default() -> Self {
+        Self::Claude(Model::Sonnet)
+    }
+}
+
+impl AgentTarget {
+    /// Parse a `model` value. Unknown values are an error rather than a
+    /// silent fallback to Sonnet.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        let unknown = || {
+            format!(
+                "Unknown model '{value}' (expected opus, sonnet, haiku, claude:<model>, \
+                 cone:<model id> or cmd:<program>)"
+            )
+        };
+        if let Some(model_id) = value.strip_prefix("cone:") {
+            let model_id = model_id.trim();
+            return if model_id.is_empty() { Err(unknown()) } else { Ok(Self::Cone(model_id.to_string())) };
+        }
+        if let Some(program) = value.strip_prefix("cmd:") {
+            let program = program.trim();
+            return if program.is_empty() { Err(unknown()) } else { Ok(Self::Command(program.to_string())) };
+        }
+        let model = value.strip_prefix("claude:").unwrap_or(value);
+        Model::from_str(model.trim()).map(Self::Claude).ok_or_else(unknown)
+    }
+
+    /// Parse an optional `model`, defaulting to Sonnet when absent.
+    pub fn parse_or_default(value: Option<&str>) -> Result<Self, String> {
+        value.map_or_else(|| Ok(Self::default()), Self::parse)
+    }
+
+    /// The node's own `model` (from its data) if it has one, else `default`.
+    pub fn for_node(data: &Value, default: &Self) -> Result<Self, String> {
+        match data.get("model").and_then(Value::as_str) {
+            Some(model) => Self::parse(model),
+            None => Ok(default.clone()),
+        }
+    }
+
+    /// The Claude model sessions for this target are created with. Only
+    /// meaningful for `Claude`; the stand-in command is passed it too.
+    const fn claude_model(&self) -> Model {
+        match self {
+            Self::Claude(model) => *model,
+            Self::Cone(_) | Self::Command(_) => Model::Sonnet,
+        }
+    }
+}
+
+impl fmt::Display for AgentTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Claude(model) => f.write_str(model.as_str()),
+            Self::Cone(model_id) => write!(f, "cone:{model_id}"),
+            Self::Command(program) => write!(f, "cmd:{program}"),
+        }
+    }
+}
+
+/// One agent invocation.
+#[derive(Debug, Clone)]
+pub struct AgentRequest {
+    /// Session name (Claude backend)
+    pub session_name: String,
+    pub prompt: String,
+    pub working_directory: String,
+    /// Graph the node belongs to; Claude sessions use it for loopback approvals
+    pub graph_id: String,
+}
+
+/// What an agent reports while it runs, whichever backend it is.
+#[derive(Debug, Clone)]
+pub enum AgentEvent {
+    Start { session_id: String, detail: Value },
+    Content { text: String },
+    ToolUse { tool_name: String, tool_use_id: String, input: Value },
+    ToolResult { tool_use_id: String, output: String, is_error: bool },
+    Passthrough { event_type: String, data: Value },
+    Complete { session_id: Option<String>, usage: Option<Value> },
+    Error { message: String },
+}
+
+pub type AgentStream = Pin<Box<dyn Stream<Item = AgentEvent> + Send + 'static>>;
+
+/// A backend that runs one prompt to completion.
+pub trait AgentExecutor: Send + Sync {
+    /// The target this executor was built for (logged with each prompt).
+    fn target(&self) -> &AgentTarget;
+
+    /// Run `request`. The stream ends after `Complete` or `Error`.
+    fn run(&self, request: AgentRequest) -> AgentStream;
+}
+
+/// Builds executors for targets. Cheap to clone.
+pub struct AgentFactory<P: HubContext> {
+    claudecode: Arc<ClaudeCode<P>>,
+    model_registry: Option<Arc<ModelRegistry>>,
+    executor: BashExecutor,
+}
+
+impl<P: HubContext> Clone for AgentFactory<P> {
+    fn clone(&self) -> Self {
+        Self {
+            claudecode: self.claudecode.clone(),
+            model_registry: self.model_registry.clone(),
+            executor: self.executor.clone(),
+        }
+    }
+}
+
+impl<P: HubContext + 'static> AgentFactory<P> {
+    /// `model_registry` backs `cone:` targets; stand-in commands must pass
+    /// `executor`'s exec policy and run under its limits, like validate commands.
+    pub const fn new(
+        claudecode: Arc<ClaudeCode<P>>,
+        model_registry: Option<Arc<ModelRegistry>>,
+        executor: BashExecutor,
+    ) -> Self {
+        Self { claudecode, model_registry, executor }
+    }
+
+    pub const fn claudecode(&self) -> &Arc<ClaudeCode<P>> {
+        &self.claudecode
+    }
+
+    /// An executor for `target`.
+    pub fn agent(&self, target: &AgentTarget) -> Result<Arc<dyn AgentExecutor>, String> {
+        match target {
+            AgentTarget::Claude(_) => Ok(Arc::new(ClaudeSessionAgent {
+                claudecode: self.claudecode.clone(),
+                target: target.clone(),
+            })),
+            AgentTarget::Cone(_) => {
+                let registry = self
+                    .model_registry
+                    .clone()
+                    .ok_or_else(|| format!("Model '{target}' needs a model registry, but none is configured"))?;
+                Ok(Arc::new(ConeAgent { registry, target: target.clone() }))
+            }
+            AgentTarget::Command(program) => {
+                let policy = self.executor.policy();
+                policy
+                    .check_command(program)
+                    .map_err(|violation| format!("Agent command rejected by exec policy: {violation}"))?;
+                Ok(Arc::new(CommandAgent {
+                    executor: ClaudeCodeExecutor::with_path(program.clone()).with_policy(policy.clone()),
+                    target: target.clone(),
+                }))
+            }
+        }
+    }
+}
+
+// ─── Claude CLI sessions ─────────────────────────────────────────────────────
+
+/// Runs the prompt in a `ClaudeCode` session with loopback approvals, so the
+/// conversation is kept in arbor like any other session.
+struct ClaudeSessionAgent<P: HubContext> {
+    claudecode: Arc<ClaudeCode<P>>,
+    target: AgentTarget,
+}
+
+impl<P: HubContext + 'static> AgentExecutor for ClaudeSessionAgent<P> {
+    fn target(&self) -> &AgentTarget {
+        &self.target
+    }
+
+    fn run(&self, request: AgentRequest) -> AgentStream {
+        let claudecode = self.claudecode.clone();
+        let model = self.target.claude_model();
+        Box::pin(stream! {
+            let AgentRequest { session_name, prompt, working_directory, graph_id } = request;
+            let create_stream = claudecode
+                .create(session_name.clone(), working_directory, model, None, Some(true), Some(graph_id))
+                .await;
+            tokio::pin!(create_stream);
+            if let Some(CreateResult::Err { message }) = create_stream.next().await {
+                yield AgentEvent::Error { message: format!("Failed to create claudecode session: {message}") };
+                return;
+            }
+
+            let chat_stream = claudecode.chat(session_name, prompt, None, None).await;
+            tokio::pin!(chat_stream);
+            while let Some(event) = chat_stream.next().await {
+                yield match event {
+                    ChatEvent::Start { id, user_position } => AgentEvent::Start {
+                        session_id: id.to_string(),
+                        detail: serde_json::json!({ "user_position": user_position }),
+                    },
+                    ChatEvent::Content { text } => AgentEvent::Content { text },
+                    ChatEvent::ToolUse { tool_name, tool_use_id, input } => {
+                        AgentEvent::ToolUse { tool_name, tool_use_id, input }
+                    }
+                    ChatEvent::ToolResult { tool_use_id, output, is_error } => {
+                        AgentEvent::ToolResult { tool_use_id, output, is_error }
+                    }
+                    ChatEvent::Passthrough { event_type, data, .. } => AgentEvent::Passthrough { event_type, data },
+                    ChatEvent::Complete { claude_session_id, usage, .. } => AgentEvent::Complete {
+                        session_id: Some(claude_session_id),
+                        usage: usage.and_then(|u| serde_json::to_value(u).ok()),
+                    },
+                    ChatEvent::Err { message } => AgentEvent::Error { message },
+                    ChatEvent::Thinking { .. } => continue,
+                };
+            }
+        })
+    }
+}
+
+// ─── Cone models ─────────────────────────────────────────────────────────────
+
+/// Sends the prompt as a single user message to a cllient model. No tools,
+/// no working directory — suited to planning and synthesis, not file edits.
+struct ConeAgent {
+    registry: Arc<ModelRegistry>,
+    target: AgentTarget,
+}
+
+impl AgentExecutor for ConeAgent {
+    fn target(&self) -> &AgentTarget {
+        &self.target
+    }
+
+    fn run(&self, request: AgentRequest) -> AgentStream {
+        let registry = self.registry.clone();
+        let AgentTarget::Cone(model_id) = self.target.clone() else {
+            unreachable!("ConeAgent is only built for cone targets")
+        };
+        Box::pin(stream! {
+            let builder = match registry.from_id(&model_id) {
+                Ok(builder) => builder,
+                Err(e) => {
+                    yield AgentEvent::Error { message: format!("Unknown cone model '{model_id}': {e}") };
+                    return;
+                }
+            };
+            let mut llm_stream = match builder.messages(vec![Message::user(&request.prompt)]).stream().await {
+                Ok(s) => s,
+                Err(e) => {
+                    yield AgentEvent::Error { message: format!("Failed to start LLM stream: {e}") };
+                    return;
+                }
+            };
+            yield AgentEvent::Start {
+                session_id: request.session_name,
+                detail: serde_json::json!({ "model_id": model_id }),
+            };
+
+            let mut usage = None;
+            while let Some(event) = llm_stream.next().await {
+                match event {
+                    Ok(cllient::streaming::StreamEvent::Content(text)) => yield AgentEvent::Content { text },
+                    Ok(cllient::streaming::StreamEvent::Usage { input_tokens, output_tokens, .. }) => {
+                        usage = Some(serde_json::json!({
+                            "input_tokens": input_tokens,
+                            "output_tokens": output_tokens,
+                        }));
+                    }
+                    Ok(cllient::streaming::StreamEvent::Error(e)) => {
+                        yield AgentEvent::Error { message: format!("LLM error: {e}") };
+                        return;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        yield AgentEvent::Error { message: format!("Stream error: {e}") };
+                        return;
+                    }
+                }
+            }
+            yield AgentEvent::Complete { session_id: None, usage };
+        })
+    }
+}
+
+// ─── Stand-in commands ───────────────────────────────────────────────────────
+
+/// Launches a local program with the Claude CLI's arguments and reads its
+/// `stream-json` output. Only complete `assistant` / `user` messages are
+/// used; partial `stream_event` chunks are ignored.
+struct CommandAgent {
+    executor: ClaudeCodeExecutor,
+    target: AgentTarget,
+}
+
+impl AgentExecutor for CommandAgent {
+    fn target(&self) -> &AgentTarget {
+        &self.target
+    }
+
+    fn run(&self, request: AgentRequest) -> AgentStream {
+        let executor = self.executor.clone();
+        let model = self.target.claude_model();
+        Box::pin(stream! {
+            let raw = executor.launch(LaunchConfig {
+                query: request.prompt,
+                model,
+                working_dir: request.working_directory,
+                ..LaunchConfig::default()
+            }).await;
+            tokio::pin!(raw);
+
+            let mut stderr: Vec<String> = Vec::new();
+            while let Some(event) = raw.next().await {
+                match event {
+                    RawClaudeEvent::System { session_id, .. } => {
+                        yield AgentEvent::Start {
+                            session_id: session_id.unwrap_or_else(|| request.session_name.clone()),
+                            detail: Value::Null,
+                        };
+                    }
+                    RawClaudeEvent::Assistant { message } | RawClaudeEvent::User { message } => {
+                        for block in message.and_then(|m| m.content).unwrap_or_default() {
+                            match block {
+                                RawContentBlock::Text { text } => yield AgentEvent::Content { text },
+                                RawContentBlock::ToolUse { id, name, input } => {
+                                    yield AgentEvent::ToolUse { tool_name: name, tool_use_id: id, input };
+                                }
+                                RawContentBlock::ToolResult { tool_use_id, content, is_error } => {
+                                    yield AgentEvent::ToolResult {
+                                        tool_use_id,
+                                        output: content.unwrap_or_default(),
+                                        is_error: is_error.unwrap_or(false),
+                                    };
+                                }
+                                RawContentBlock::Thinking { .. } => {}
+                            }
+                        }
+                    }
+                    RawClaudeEvent::Result { is_error: Some(true), error, result, .. } => {
+                        let message = error.or(result).unwrap_or_else(|| "Agent command reported an error".to_string());
+                        yield AgentEvent::Error { message };
+                        return;
+                    }
+                    RawClaudeEvent::Result { session_id, cost_usd, num_turns, .. } => {
+                        yield AgentEvent::Complete {
+                            session_id,
+                            usage: Some(serde_json::json!({ "cost_usd": cost_usd, "num_turns": num_turns })),
+                        };
+                        return;
+                    }
+                    RawClaudeEvent::Unknown { event_type, data } => {
+                        yield AgentEvent::Passthrough { event_type, data };
+                    }
+                    RawClaudeEvent::Stderr { text } => stderr.push(text),
+                    RawClaudeEvent::StreamEvent { .. } | RawClaudeEvent::LaunchCommand { .. } => {}
+                }
+            }
+            let detail = if stderr.is_empty() { String::new() } else { format!(": {}", stderr.join("\n")) };
+            yield AgentEvent::Error { message: format!("Agent command exited without a result event{detail}") };
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activations::bash::{ExecPolicy, ExecPolicyConfig, ResourceLimits};
+
+    #[test]
+    fn test_parse_targets() {
+        assert_eq!(AgentTarget::parse("opus"), Ok(AgentTarget::Claude(Model::Opus)));
+        assert_eq!(AgentTarget::parse("claude:Haiku"), Ok(AgentTarget::Claude(Model::Haiku)));
+        assert_eq!(
+            AgentTarget::parse("cone:gpt-4o-mini"),
+            Ok(AgentTarget::Cone("gpt-4o-mini".to_string()))
+        );
+        assert_eq!(
+            AgentTarget::parse("cmd:/usr/local/bin/fake-claude"),
+            Ok(AgentTarget::Command("/usr/local/bin/fake-claude".to_string()))
+        );
+        // No silent fallback to Sonnet
+        assert!(AgentTarget::parse("sonet").is_err());
+        assert!(AgentTarget::parse("cone:").is_err());
+        assert_eq!(AgentTarget::parse_or_default(None), Ok(AgentTarget::Claude(Model::Sonnet)));
+
+        for target in ["haiku", "cone:gpt-4o-mini", "cmd:fake-claude"] {
+            assert_eq!(AgentTarget::parse(target).unwrap().to_string(), target);
+        }
+    }
+
+    #[test]
+    fn test_node_model_overrides_graph_default() {
+        let default = AgentTarget::Claude(Model::Sonnet);
+        let data = serde_json::json!({ "orcha_type": "task", "task": "x", "model": "cone:gpt-4o" });
+        assert_eq!(AgentTarget::for_node(&data, &default), Ok(AgentTarget::Cone("gpt-4o".to_string())));
+        let data = serde_json::json!({ "orcha_type": "task", "task": "x" });
+        assert_eq!(AgentTarget::for_node(&data, &default), Ok(default));
+    }
+
+    #[tokio::test]
+    async fn test_command_agent_speaks_stream_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("fake-claude");
+        std::fs::write(
+            &script,
+            "#!/bin/sh\n\
+             echo '{\"type\":\"system\",\"subtype\":\"init\",\"session_id\":\"s-1\"}'\n\
+             echo '{\"type\":\"stream_event\",\"event\":{\"type\":\"message_stop\"}}'\n\
+             echo '{\"type\":\"assistant\",\"message\":{\"content\":[{\"type\":\"text\",\"text\":\"done\"}]}}'\n\
+             echo '{\"type\":\"result\",\"subtype\":\"success\",\"session_id\":\"s-1\",\"is_error\":false}'\n",
+        )
+        .unwrap();
+        std::process::Command::new("chmod").arg("+x").arg(&script).status().unwrap();
+
+        let agent = CommandAgent {
+            executor: ClaudeCodeExecutor::with_path(script.to_string_lossy().into_owned()),
+            target: AgentTarget::Command(script.to_string_lossy().into_owned()),
+        };
+        let events: Vec<AgentEvent> = agent
+            .run(AgentRequest {
+                session_name: "node-1".to_string(),
+                prompt: "do it".to_string(),
+                working_directory: dir.path().to_string_lossy().into_owned(),
+                graph_id: "g".to_string(),
+            })
+            .collect()
+            .await;
+
+        assert!(matches!(&events[0], AgentEvent::Start { session_id, .. } if session_id == "s-1"));
+        assert!(events.iter().any(|e| matches!(e, AgentEvent::Content { text } if text == "done")));
+        assert!(matches!(events.last(), Some(AgentEvent::Complete { session_id: Some(id), .. }) if id == "s-1"));
+    }
+
+    #[tokio::test]
+    async fn test_command_agent_runs_under_exec_policy_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("fake-claude");
+        std::fs::write(
+            &script,
+            "#!/bin/sh\n\
+             printf '{\"type\":\"assistant\",\"message\":{\"content\":[{\"type\":\"text\",\"text\":\"%s\"}]}}\\n' \"$(ulimit -n)\"\n\
+             echo '{\"type\":\"result\",\"subtype\":\"success\",\"is_error\":false}'\n",
+        )
+        .unwrap();
+        std::process::Command::new("chmod").arg("+x").arg(&script).status().unwrap();
+
+        let policy = ExecPolicy::new(ExecPolicyConfig {
+            limits: ResourceLimits { open_files: Some(64), ..ResourceLimits::default() },
+            ..ExecPolicyConfig::default()
+        })
+        .unwrap();
+        let program = script.to_string_lossy().into_owned();
+        let agent = CommandAgent {
+            executor: ClaudeCodeExecutor::with_path(program.clone()).with_policy(policy),
+            target: AgentTarget::Command(program),
+        };
+        let events: Vec<AgentEvent> = agent
+            .run(AgentRequest {
+                session_name: "node-1".to_string(),
+                prompt: "do it".to_string(),
+                working_directory: dir.path().to_string_lossy().into_owned(),
+                graph_id: "g".to_string(),
+            })
+            .collect()
+            .await;
+
+        assert!(events.iter().any(|e| matches!(e, AgentEvent::Content { text } if text == "64")), "{events:?}");
+    }
+}
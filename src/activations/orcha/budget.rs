@@ -0,0 +1,126 @@
+//! Spend limits for graph runs.
+//!
+//! A run may cap what its agents spend with `max_cost_usd` and/or
+//! `max_tokens` in the graph's `_plexus_run_config`. Every agent run logs its
+//! usage to the pm node log; after each one the graph runner totals the spend
+//! of the budgeted graph and every graph below it — planned child graphs and
+//! sub-graphs run under their nearest budgeted ancestor — and once a limit is
+//! crossed, fails that graph and cancels its remaining nodes.
+
+use crate::activations::lattice::LatticeStorage;
+use serde_json::Value;
+
+use super::types::Usage;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Budget {
+    /// The graph whose run config set the limits; its whole tree is charged
+    pub(super) graph_id: String,
+    pub(super) max_cost_usd: Option<f64>,
+    pub(super) max_tokens: Option<u64>,
+}
+
+impl Budget {
+    /// Limits from a graph's `_plexus_run_config`; `None` when it sets none.
+    pub(super) fn from_run_config(graph_id: &str, run_config: &Value) -> Option<Self> {
+        let max_cost_usd = run_config.get("max_cost_usd").and_then(Value::as_f64).filter(|c| *c > 0.0);
+        let max_tokens = run_config.get("max_tokens").and_then(Value::as_u64).filter(|n| *n > 0);
+        (max_cost_usd.is_some() || max_tokens.is_some()).then(|| Self {
+            graph_id: graph_id.to_string(),
+            max_cost_usd,
+            max_tokens,
+        })
+    }
+
+    /// The budget `graph_id` runs under: its own, else its nearest ancestor's.
+    pub(super) async fn resolve(storage: &LatticeStorage, graph_id: &str) -> Result<Option<Self>, String> {
+        let mut next = Some(graph_id.to_string());
+        while let Some(id) = next {
+            let graph = storage.get_graph(&id).await?;
+            let run_config = graph.metadata.get("_plexus_run_config").cloned().unwrap_or_default();
+            if let Some(budget) = Self::from_run_config(&id, &run_config) {
+                return Ok(Some(budget));
+            }
+            next = graph.parent_graph_id;
+        }
+        Ok(None)
+    }
+
+    /// Why `spent` is over budget, if it is.
+    pub(super) fn exceeded(&self, spent: &Usage) -> Option<String> {
+        if let Some(max) = self.max_cost_usd.filter(|max| spent.cost_usd > *max) {
+            return Some(format!("Budget exceeded: spent ${:.4} of max_cost_usd ${max}", spent.cost_usd));
+        }
+        if let Some(max) = self.max_tokens.filter(|max| spent.total_tokens() > *max) {
+            return Some(format!("Budget exceeded: used {} tokens of max_tokens {max}", spent.total_tokens()));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activations::lattice::LatticeStorageConfig;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_budget_from_run_config() {
+        assert_eq!(Budget::from_run_config("g", &serde_json::json!({"max_agents": 2})), None);
+        assert_eq!(Budget::from_run_config("g", &serde_json::json!({"max_cost_usd": 0, "max_tokens": 0})), None);
+        assert_eq!(
+            Budget::from_run_config("g", &serde_json::json!({"max_cost_usd": 1.5})),
+            Some(Budget { graph_id: "g".into(), max_cost_usd: Some(1.5), max_tokens: None })
+        );
+    }
+
+    #[test]
+    fn test_budget_exceeded_only_past_the_limit() {
+        let budget = Budget { graph_id: "g".into(), max_cost_usd: Some(1.0), max_tokens: Some(1000) };
+        let spent = |cost_usd, tokens| Usage { input_tokens: tokens, output_tokens: 0, cost_usd };
+        assert_eq!(budget.exceeded(&spent(1.0, 1000)), None);
+        assert_eq!(
+            budget.exceeded(&spent(1.25, 10)).as_deref(),
+            Some("Budget exceeded: spent $1.2500 of max_cost_usd $1")
+        );
+        assert_eq!(
+            budget.exceeded(&spent(0.5, 1001)).as_deref(),
+            Some("Budget exceeded: used 1001 tokens of max_tokens 1000")
+        );
+    }
+
+    #[test]
+    fn test_usage_from_agent_reports() {
+        let claude = serde_json::json!({"input_tokens": 10, "output_tokens": 5, "cost_usd": 0.02, "num_turns": 3});
+        assert_eq!(
+            Usage::from_agent(&claude),
+            Some(Usage { input_tokens: 10, output_tokens: 5, cost_usd: 0.02 })
+        );
+        let command = serde_json::json!({"cost_usd": 0.5, "num_turns": 1});
+        assert_eq!(Usage::from_agent(&command), Some(Usage { cost_usd: 0.5, ..Usage::default() }));
+        assert_eq!(Usage::from_agent(&serde_json::json!({"cost_usd": null, "num_turns": 1})), None);
+    }
+
+    #[tokio::test]
+    async fn test_child_graphs_run_under_the_nearest_budget() {
+        let dir = tempdir().unwrap();
+        let config = LatticeStorageConfig { db_path: dir.path().join("lattice.db") };
+        let storage = LatticeStorage::new(config).await.unwrap();
+
+        let root = storage
+            .create_graph(serde_json::json!({"_plexus_run_config": {"max_tokens": 500}}))
+            .await
+            .unwrap();
+        let plan = storage
+            .create_child_graph(&root, serde_json::json!({"_plexus_run_config": {"max_agents": 1}}))
+            .await
+            .unwrap();
+        let nested = storage.create_child_graph(&plan, serde_json::json!({})).await.unwrap();
+        let unbudgeted = storage.create_graph(serde_json::json!({})).await.unwrap();
+
+        let budget = Budget::resolve(&storage, &nested).await.unwrap().unwrap();
+        assert_eq!(budget.graph_id, root);
+        assert_eq!(budget.max_tokens, Some(500));
+        assert_eq!(Budget::resolve(&storage, &unbudgeted).await.unwrap(), None);
+    }
+}
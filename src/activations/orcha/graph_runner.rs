@@ -1,16 +1,21 @@
 use crate::activations::arbor::ArborStorage;
-use crate::activations::claudecode::{ChatEvent, ClaudeCode, CreateResult, Model};
-use crate::activations::claudecode_loopback::LoopbackStorage;
-use crate::activations::lattice::{LatticeEvent, LatticeEventEnvelope, NodeOutput, NodeSpec, Token, TokenPayload};
+use crate::activations::bash::{BashEvent, BashExecutor, BashOptions};
+use crate::activations::claudecode::ClaudeCode;
+use crate::activations::claudecode_loopback::{ApprovalStatus, LoopbackStorage};
+use crate::activations::lattice::{LatticeEvent, LatticeEventEnvelope, LatticeStorage, NodeOutput, NodeSpec, Token, TokenColor, TokenPayload};
 use crate::plexus::HubContext;
 use async_stream::stream;
 use futures::{Stream, StreamExt};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 
+use super::agents::{AgentEvent, AgentExecutor, AgentFactory, AgentRequest, AgentTarget};
+use super::budget::Budget;
 use super::graph_runtime::{GraphRuntime, OrchaGraph};
 use super::pm::Pm;
-use super::types::{OrchaEvent, OrchaNodeKind};
+use super::scheduler::{ConcurrencyLimits, Pool, SchedulerPermit};
+use super::types::{OrchaEvent, OrchaNodeKind, Usage};
+use super::worktree::{IntegrateOutcome, Integration, TicketIsolation, TicketWorktree, INTEGRATE_LOG_SEQ, WORKTREE_LOG_SEQ};
 
 type CancelRegistry = Arc<tokio::sync::Mutex<HashMap<String, tokio::sync::watch::Sender<bool>>>>;
 
@@ -21,9 +26,26 @@ type CancelRegistry = Arc<tokio::sync::Mutex<HashMap<String, tokio::sync::watch:
 /// - Spawns a tokio task per node; the task calls `graph.complete_node` / `fail_node`
 /// - Tracks dispatched nodes to prevent double-dispatch on reconnect
 ///
+/// For each `SubGraphStarted` event it attaches a nested runner to the child graph
+/// so the child's task nodes get dispatched.  Lattice itself resolves the parent
+/// `SubGraph` node when the child terminates.
+///
 /// `cancel_rx`: a watch receiver; when its value flips to `true`, all spawned node tasks
 /// will abandon their current chat stream and return an error, causing the graph to fail.
 ///
+/// When the graph's run config sets `isolation`, each ticket's nodes run in their
+/// own git worktree (see `worktree`), integrated back when the ticket completes.
+///
+/// Agent and validate nodes wait for a slot from the runtime's `Scheduler`
+/// before starting, under its server-wide limits and the run config's
+/// `max_agents` / `max_progs`. Until then they stay `Ready`.
+///
+/// `model` is the graph's default agent target; a node's own `model` wins.
+///
+/// Under a `Budget` (see `budget`), each agent run's usage is checked against
+/// the budgeted graph tree's total; crossing it emits `BudgetExceeded`, fails
+/// the budgeted graph and cancels every graph under it.
+///
 /// Returns a stream of `OrchaEvent` for monitoring.
 /// The stream closes when the graph reaches `GraphDone` or `GraphFailed`.
 pub(super) fn run_graph_execution<P: HubContext + 'static>(
@@ -34,7 +56,7 @@ pub(super) fn run_graph_execution<P: HubContext + 'static>(
     pm: Arc<Pm>,
     graph_runtime: Arc<GraphRuntime>,
     cancel_registry: CancelRegistry,
-    model: Model,
+    model: AgentTarget,
     working_directory: String,
     cancel_rx: tokio::sync::watch::Receiver<bool>,
     node_to_ticket: HashMap<String, String>,
@@ -43,22 +65,47 @@ pub(super) fn run_graph_execution<P: HubContext + 'static>(
         let event_stream = graph.watch(None);
         tokio::pin!(event_stream);
 
-        // Channel for events emitted from within spawned dispatch tasks (e.g. Retrying, NodeOutput).
+        // Channel for events emitted from within spawned dispatch tasks (e.g. NodeOutput).
         // The receiver is drained in the select! loop below alongside the lattice event stream.
         let (node_event_tx, mut output_rx) = tokio::sync::mpsc::unbounded_channel::<OrchaEvent>();
 
+        // Loop pass each node is on in the database, and the pass the replayed
+        // event log has reached for it (advanced by LoopIteration events).
+        let current_pass: HashMap<String, u32> = graph.get_node_iterations().await.unwrap_or_default();
+        let mut replay_pass: HashMap<String, u32> = HashMap::new();
+
         // Pre-populate dispatched from nodes already complete/failed (recovery safety).
         // On reconnect, the event log replays NodeReady for every node that was ever ready;
-        // without this, already-finished nodes would be re-dispatched.
-        let mut dispatched: HashSet<String> = graph.get_terminal_node_ids().await
+        // without this, already-finished nodes would be re-dispatched.  Keyed by
+        // (node, loop pass) so a Loop body's next pass is dispatched afresh.
+        let mut dispatched: HashSet<(String, u32)> = graph.get_terminal_node_ids().await
             .unwrap_or_default()
             .into_iter()
-            .collect::<HashSet<String>>();
+            .map(|id| {
+                let pass = current_pass.get(&id).copied().unwrap_or(0);
+                (id, pass)
+            })
+            .collect();
+
+        // Child graphs that already have a runner attached (replay may repeat SubGraphStarted).
+        let mut attached_children: HashSet<String> = HashSet::new();
+
+        // In-flight dispatch tasks, so work on a node the watchdog expired can be dropped.
+        let mut in_flight: HashMap<String, tokio::task::AbortHandle> = HashMap::new();
 
         // Progress tracking: re-fetched on each completion to stay accurate for live graphs.
         let mut total_nodes: usize = graph.count_nodes().await.unwrap_or(0);
         let mut complete_nodes: usize = 0;
 
+        let run_config = graph.run_config().await.unwrap_or_default();
+        let integration = Integration::from_run_config(&run_config);
+        let limits = ConcurrencyLimits::from_run_config(&run_config);
+        let budget = Budget::resolve(&graph_runtime.storage(), &graph.graph_id).await.unwrap_or_else(|e| {
+            tracing::warn!("Graph {}: budget not enforced: {}", graph.graph_id, e);
+            None
+        });
+        let mut over_budget = false;
+
         /// Compute percentage as integer 0–100.
         fn calc_percentage(complete: usize, total: usize) -> Option<u32> {
             if total == 0 {
@@ -73,11 +120,15 @@ pub(super) fn run_graph_execution<P: HubContext + 'static>(
                     let Some(LatticeEventEnvelope { event, .. }) = envelope else { break };
                     match event {
                         LatticeEvent::NodeReady { node_id, spec } => {
-                            // Skip if already dispatched (reconnect replay)
-                            if dispatched.contains(&node_id) {
+                            // Skip if already dispatched, or from a loop pass that has
+                            // since been superseded (reconnect replay)
+                            let pass = replay_pass.get(&node_id).copied().unwrap_or(0);
+                            if pass < current_pass.get(&node_id).copied().unwrap_or(0) {
+                                continue;
+                            }
+                            if !dispatched.insert((node_id.clone(), pass)) {
                                 continue;
                             }
-                            dispatched.insert(node_id.clone());
 
                             let ticket_id = node_to_ticket.get(&node_id).cloned();
                             yield OrchaEvent::NodeStarted {
@@ -86,6 +137,9 @@ pub(super) fn run_graph_execution<P: HubContext + 'static>(
                                 ticket_id: ticket_id.clone(),
                                 percentage: calc_percentage(complete_nodes, total_nodes),
                             };
+                            let isolation = integration
+                                .and_then(|i| TicketIsolation::for_node(i, &node_to_ticket, &node_id));
+                            let slot = Pool::for_spec(&spec);
 
                             let g = graph.clone();
                             let cc = claudecode.clone();
@@ -95,15 +149,32 @@ pub(super) fn run_graph_execution<P: HubContext + 'static>(
                             let gr = graph_runtime.clone();
                             let cr = cancel_registry.clone();
                             let nid = node_id.clone();
+                            let target = model.clone();
                             let wd = working_directory.clone();
                             let tx = node_event_tx.clone();
                             let cancel = cancel_rx.clone();
 
-                            tokio::spawn(async move {
-                                // Emit NodeStarted before executing
-                                let _ = g.start_node(&nid).await;
+                            let task = tokio::spawn(async move {
+                                // Wait for a concurrency slot; the node stays Ready (queued)
+                                // meanwhile. A cancelled graph gives up its place in line.
+                                let permit = match slot {
+                                    Some((pool, priority)) => {
+                                        let acquire = gr.scheduler().acquire(&g.graph_id, &nid, pool, priority, limits);
+                                        tokio::select! {
+                                            permit = acquire => Some(permit),
+                                            () = cancelled(cancel.clone()) => return,
+                                        }
+                                    }
+                                    None => None,
+                                };
 
-                                let result = dispatch_node(cc, arbor, lb, pm_log, gr, cr, &g, &spec, &nid, model, wd, tx, cancel, ticket_id).await;
+                                // Emit NodeStarted before executing; a node the watchdog
+                                // already expired must not be run.
+                                if g.start_node(&nid).await == Ok(false) {
+                                    return;
+                                }
+
+                                let result = dispatch_node(cc, arbor, lb, pm_log, gr, cr, &g, &spec, &nid, target, wd, tx, cancel, ticket_id, isolation, permit).await;
                                 match result {
                                     Ok(output) => {
                                         if let Err(e) = g.complete_node(&nid, output).await {
@@ -115,6 +186,65 @@ pub(super) fn run_graph_execution<P: HubContext + 'static>(
                                     }
                                 }
                             });
+                            in_flight.retain(|_, h| !h.is_finished());
+                            in_flight.insert(node_id, task.abort_handle());
+                        }
+
+                        LatticeEvent::SubGraphStarted { node_id, child_graph_id } => {
+                            if !attached_children.insert(child_graph_id.clone()) {
+                                continue;
+                            }
+
+                            yield OrchaEvent::NodeStarted {
+                                node_id: node_id.clone(),
+                                label: None,
+                                ticket_id: node_to_ticket.get(&node_id).cloned(),
+                                percentage: calc_percentage(complete_nodes, total_nodes),
+                            };
+
+                            // Child graphs don't have a ticket map — pass an empty map.
+                            let child = Arc::new(graph.open_child_graph(child_graph_id));
+                            let events = run_graph_execution(
+                                child,
+                                claudecode.clone(),
+                                arbor_storage.clone(),
+                                loopback_storage.clone(),
+                                pm.clone(),
+                                graph_runtime.clone(),
+                                cancel_registry.clone(),
+                                model.clone(),
+                                working_directory.clone(),
+                                cancel_rx.clone(),
+                                HashMap::new(),
+                            );
+                            tokio::spawn(async move {
+                                tokio::pin!(events);
+                                while events.next().await.is_some() {}
+                            });
+                        }
+
+                        LatticeEvent::LoopIteration {
+                            target_node_id, iteration, max_iterations, reset_node_ids, token, ..
+                        } => {
+                            // The body runs again; its next NodeReady events belong to the new pass.
+                            for id in reset_node_ids {
+                                replay_pass.insert(id, iteration);
+                            }
+                            complete_nodes = graph.get_terminal_node_ids().await
+                                .map_or(complete_nodes, |ids| ids.len());
+                            yield OrchaEvent::Retrying {
+                                node_id: target_node_id.clone(),
+                                ticket_id: node_to_ticket.get(&target_node_id).cloned(),
+                                attempt: iteration as usize,
+                                max_attempts: max_iterations as usize,
+                                error: token.message().unwrap_or_default().to_string(),
+                            };
+                        }
+
+                        LatticeEvent::GraphResumed { graph_id, reset_node_ids } => {
+                            complete_nodes = graph.get_terminal_node_ids().await
+                                .map_or(complete_nodes, |ids| ids.len());
+                            yield OrchaEvent::GraphResumed { graph_id, reset_node_ids };
                         }
 
                         LatticeEvent::NodeDone { node_id, output } => {
@@ -133,7 +263,14 @@ pub(super) fn run_graph_execution<P: HubContext + 'static>(
                             };
                         }
 
-                        LatticeEvent::NodeFailed { node_id, error } => {
+                        LatticeEvent::NodeFailed { node_id, error, reason } => {
+                            // Engine-side expiry: the dispatch task is still holding the
+                            // hung session or shell. Its result would be ignored anyway.
+                            if reason.is_some() {
+                                if let Some(task) = in_flight.remove(&node_id) {
+                                    task.abort();
+                                }
+                            }
                             complete_nodes += 1;
                             total_nodes = graph.count_nodes().await.unwrap_or(total_nodes);
                             let ticket_id = node_to_ticket.get(&node_id).cloned();
@@ -163,8 +300,35 @@ pub(super) fn run_graph_execution<P: HubContext + 'static>(
                     }
                 }
                 chunk_event = output_rx.recv() => {
-                    if let Some(evt) = chunk_event {
-                        yield evt;
+                    let Some(evt) = chunk_event else { continue };
+                    let crossed = match (&evt, &budget) {
+                        (OrchaEvent::NodeUsage { node_id, ticket_id, .. }, Some(budget)) if !over_budget => {
+                            match pm.tree_usage(&budget.graph_id).await {
+                                Ok(spent) => budget.exceeded(&spent).map(|reason| (node_id.clone(), ticket_id.clone(), spent, reason)),
+                                Err(e) => {
+                                    tracing::warn!("Graph {}: failed to total usage: {}", budget.graph_id, e);
+                                    None
+                                }
+                            }
+                        }
+                        _ => None,
+                    };
+                    yield evt;
+                    if let (Some((node_id, ticket_id, spent, reason)), Some(budget)) = (crossed, &budget) {
+                        over_budget = true;
+                        yield OrchaEvent::BudgetExceeded {
+                            graph_id: budget.graph_id.clone(),
+                            node_id: node_id.clone(),
+                            ticket_id,
+                            spent,
+                            max_cost_usd: budget.max_cost_usd,
+                            max_tokens: budget.max_tokens,
+                        };
+                        let storage = graph_runtime.storage();
+                        if let Err(e) = storage.fail_graph(&budget.graph_id, &node_id, reason).await {
+                            tracing::error!("Graph {}: failed to stop over-budget graph: {}", budget.graph_id, e);
+                        }
+                        cancel_graph_tree(&storage, &cancel_registry, &budget.graph_id).await;
                     }
                 }
             }
@@ -173,6 +337,15 @@ pub(super) fn run_graph_execution<P: HubContext + 'static>(
 }
 
 /// Dispatch a single node to its type handler.
+///
+/// With `isolation`, ticket work (task, synthesize and validate nodes) runs in
+/// the ticket's worktree, and the ticket's last node integrates the branch on
+/// success. A conflict completes the node with an Error-colored token.
+///
+/// Agent nodes run on the backend their `model` names, else on `model`.
+///
+/// `permit` is the node's concurrency slot, held until dispatch returns
+/// (a plan node hands it back once its planning agent is done).
 async fn dispatch_node<P: HubContext + 'static>(
     claudecode: Arc<ClaudeCode<P>>,
     arbor: Arc<ArborStorage>,
@@ -183,17 +356,14 @@ async fn dispatch_node<P: HubContext + 'static>(
     graph: &OrchaGraph,
     spec: &NodeSpec,
     node_id: &str,
-    model: Model,
+    model: AgentTarget,
     working_directory: String,
     output_tx: tokio::sync::mpsc::UnboundedSender<OrchaEvent>,
     cancel_rx: tokio::sync::watch::Receiver<bool>,
     ticket_id: Option<String>,
+    isolation: Option<TicketIsolation>,
+    permit: Option<SchedulerPermit>,
 ) -> Result<Option<NodeOutput>, String> {
-    // Early-return for SubGraph — raw NodeSpec variant, not OrchaNodeKind
-    if let NodeSpec::SubGraph { graph_id } = spec {
-        return dispatch_subgraph(claudecode, arbor, loopback_storage, pm, graph_runtime, cancel_registry, graph, graph_id.clone(), node_id, model, working_directory, cancel_rx).await;
-    }
-
     let data = match spec {
         NodeSpec::Task { data, .. } | NodeSpec::Scatter { data, .. } => data,
         _ => return Err("Engine-internal node type reached Orcha dispatcher".to_string()),
@@ -201,40 +371,121 @@ async fn dispatch_node<P: HubContext + 'static>(
 
     let kind: OrchaNodeKind = serde_json::from_value(data.clone())
         .map_err(|e| format!("Node data is not a valid OrchaNodeKind: {e}"))?;
+    let agents = AgentFactory::new(claudecode, graph_runtime.model_registry().cloned(), graph_runtime.executor().clone());
+    let node_agent = || AgentTarget::for_node(data, &model).and_then(|target| agents.agent(&target));
 
     // Fetch and resolve input tokens (replaces old handle_context mechanism)
     let resolved_inputs = graph.get_resolved_inputs(node_id, &arbor).await?;
 
-    match kind {
-        OrchaNodeKind::Task { task, max_retries, .. } => {
-            dispatch_task_with_retry(claudecode, loopback_storage, pm, task, resolved_inputs, node_id, model, working_directory, &graph.graph_id, output_tx, cancel_rx, ticket_id, max_retries.unwrap_or(0) as usize).await
+    let isolation = isolation.filter(|_| matches!(
+        kind,
+        OrchaNodeKind::Task { .. } | OrchaNodeKind::Synthesize { .. } | OrchaNodeKind::Validate { .. }
+    ));
+    let worktree = match &isolation {
+        Some(iso) => {
+            let wt = TicketWorktree::prepare(&working_directory, &graph.graph_id, &iso.ticket_id).await?;
+            pm.log_node_event(
+                &graph.graph_id, node_id, ticket_id.as_deref(), WORKTREE_LOG_SEQ, "worktree",
+                serde_json::json!({
+                    "path": wt.path().to_string_lossy(),
+                    "branch": wt.branch,
+                    "working_directory": wt.workdir_string(),
+                }),
+            ).await;
+            Some(wt)
         }
-        OrchaNodeKind::Synthesize { task, max_retries, .. } => {
-            dispatch_synthesize_with_retry(claudecode, arbor, loopback_storage, pm, graph, task, resolved_inputs, node_id, model, working_directory, output_tx, cancel_rx, ticket_id, max_retries.unwrap_or(0) as usize).await
+        None => None,
+    };
+    let working_directory = worktree.as_ref().map_or(working_directory, TicketWorktree::workdir_string);
+
+    let result = match kind {
+        OrchaNodeKind::Task { task } => {
+            dispatch_task(node_agent()?, loopback_storage, pm.clone(), task, resolved_inputs, node_id, working_directory, &graph.graph_id, output_tx, cancel_rx, ticket_id.clone()).await
         }
-        OrchaNodeKind::Validate { command, cwd, max_retries } => {
-            dispatch_validate_with_retry(claudecode, arbor, loopback_storage, pm, graph, node_id, command, cwd, model, working_directory, output_tx, cancel_rx, max_retries.unwrap_or(3) as usize).await
+        OrchaNodeKind::Synthesize { task } => {
+            dispatch_synthesize(node_agent()?, arbor, loopback_storage, pm.clone(), graph, task, resolved_inputs, node_id, working_directory, output_tx, cancel_rx, ticket_id.clone()).await
+        }
+        OrchaNodeKind::Validate { command, cwd } => {
+            let executor = graph_runtime.executor().clone();
+            // An isolated check with no explicit cwd runs against the ticket's worktree
+            let cwd = cwd.or_else(|| worktree.as_ref().map(TicketWorktree::workdir_string));
+            dispatch_validate(&executor, command, cwd, resolved_inputs).await
         }
         OrchaNodeKind::Review { prompt } => {
             dispatch_review(loopback_storage, &graph.graph_id, prompt, output_tx, cancel_rx).await
         }
         OrchaNodeKind::Plan { task } => {
-            dispatch_plan(claudecode, arbor, loopback_storage, pm, graph_runtime, cancel_registry, graph, task, resolved_inputs, node_id, model, working_directory, output_tx, cancel_rx, ticket_id).await
+            let agent = node_agent()?;
+            dispatch_plan(agent, agents.claudecode().clone(), arbor, loopback_storage, pm.clone(), graph_runtime, cancel_registry, graph, task, resolved_inputs, node_id, model, working_directory, output_tx, cancel_rx, ticket_id.clone(), permit).await
         }
+    }?;
+
+    let (Some(iso), Some(wt)) = (isolation.filter(|iso| iso.integrate), worktree) else {
+        return Ok(result);
+    };
+    let outcome = wt.integrate(iso.integration, &format!("orcha: {}", iso.ticket_id)).await?;
+    pm.log_node_event(
+        &graph.graph_id, node_id, ticket_id.as_deref(), INTEGRATE_LOG_SEQ, "integrate",
+        serde_json::json!({
+            "branch": wt.branch,
+            "strategy": iso.integration.as_str(),
+            "outcome": &outcome,
+        }),
+    ).await;
+    match outcome {
+        IntegrateOutcome::Conflict { message } => Ok(Some(NodeOutput::Single(Token::error(format!(
+            "Ticket {} could not be integrated from {} (worktree kept at {}): {message}",
+            iso.ticket_id, wt.branch, wt.path().display(),
+        ))))),
+        IntegrateOutcome::Integrated { .. } | IntegrateOutcome::Unchanged => Ok(result),
+    }
+}
+
+/// Signal cancellation to `graph_id` and every graph below it that has a
+/// registered cancel sender. Returns whether `graph_id` itself was registered.
+pub(super) async fn cancel_graph_tree(storage: &LatticeStorage, cancel_registry: &CancelRegistry, graph_id: &str) -> bool {
+    // BFS to collect the root graph and all descendant graph IDs.
+    let mut all_graph_ids: Vec<String> = Vec::new();
+    let mut to_visit: VecDeque<String> = VecDeque::new();
+    to_visit.push_back(graph_id.to_string());
+    while let Some(gid) = to_visit.pop_front() {
+        all_graph_ids.push(gid.clone());
+        if let Ok(children) = storage.get_child_graphs(&gid).await {
+            for child in children {
+                to_visit.push_back(child.id);
+            }
+        }
+    }
+
+    // Lock the registry once and cancel all collected graphs.
+    let mut registry = cancel_registry.lock().await;
+    let root_registered = registry.contains_key(graph_id);
+    for gid in all_graph_ids {
+        if let Some(cancel_tx) = registry.remove(&gid) {
+            let _ = cancel_tx.send(true);
+        }
+    }
+    root_registered
+}
+
+/// Resolves once `cancel_rx` reads `true`; never, if the sender is dropped first.
+async fn cancelled(mut cancel_rx: tokio::sync::watch::Receiver<bool>) {
+    if cancel_rx.wait_for(|cancelled| *cancelled).await.is_err() {
+        std::future::pending::<()>().await;
     }
 }
 
 /// Dispatch a "review" node — human-in-the-loop gate.
 ///
 /// Creates a loopback approval record keyed on the `graph_id`, emits an
-/// `ApprovalPending` event, then polls every second until the approval is
-/// resolved.  On approval returns `Token::ok()`; on denial returns an error.
+/// `ApprovalPending` event, then waits until the approval is resolved.
+/// On approval returns `Token::ok()`; on denial returns an error.
 async fn dispatch_review(
     loopback_storage: Arc<LoopbackStorage>,
     graph_id: &str,
     prompt: String,
     output_tx: tokio::sync::mpsc::UnboundedSender<OrchaEvent>,
-    mut cancel_rx: tokio::sync::watch::Receiver<bool>,
+    cancel_rx: tokio::sync::watch::Receiver<bool>,
 ) -> Result<Option<NodeOutput>, String> {
     // Use a generated UUID as the tool_use_id for this review gate.
     let tool_use_id = uuid::Uuid::new_v4().to_string();
@@ -255,51 +506,35 @@ async fn dispatch_review(
         created_at: chrono::Utc::now().to_rfc3339(),
     });
 
-    loop {
-        tokio::select! {
-            result = cancel_rx.changed() => {
-                if result.is_ok() && *cancel_rx.borrow() {
-                    return Err("Graph cancelled".to_string());
-                }
-                // sender dropped — continue polling
-            }
-            _ = tokio::time::sleep(std::time::Duration::from_secs(1)) => {
-                match loopback_storage.get_approval(&approval_id).await {
-                    Ok(r) => {
-                        use crate::activations::claudecode_loopback::ApprovalStatus;
-                        match r.status {
-                            ApprovalStatus::Approved => {
-                                return Ok(Some(NodeOutput::Single(Token::ok())));
-                            }
-                            ApprovalStatus::Denied => {
-                                let reason = r.response_message.unwrap_or_default();
-                                return Err(format!("Review denied: {reason}"));
-                            }
-                            _ => {} // still pending or timed out — keep polling
-                        }
-                    }
-                    Err(e) => {
-                        tracing::warn!("Failed to poll review approval {}: {}", approval_id, e);
-                    }
-                }
-            }
+    let resolved = tokio::select! {
+        () = cancelled(cancel_rx) => return Err("Graph cancelled".to_string()),
+        resolved = loopback_storage.wait_for_resolution(&approval_id, None) => resolved,
+    };
+    let record = resolved.map_err(|e| format!("Failed to wait for review approval {approval_id}: {e}"))?;
+    match record.status {
+        ApprovalStatus::Approved => Ok(Some(NodeOutput::Single(Token::ok()))),
+        // Timed out under an approving default
+        ApprovalStatus::TimedOut if record.updated_input.is_some() => Ok(Some(NodeOutput::Single(Token::ok()))),
+        ApprovalStatus::Denied => {
+            let reason = record.response_message.unwrap_or_default();
+            Err(format!("Review denied: {reason}"))
         }
+        ApprovalStatus::TimedOut | ApprovalStatus::Pending => Err("Review timed out".to_string()),
     }
 }
 
-/// Dispatch a "task" or "synthesize" node — creates a `ClaudeCode` session and runs the prompt.
+/// Dispatch a "task" or "synthesize" node — runs the prompt on `agent`.
 ///
 /// Any resolved input tokens with `{"text": "..."}` data are concatenated as `<prior_work>`.
-/// Loopback is enabled so tool-use approval requests are routed through the orcha approval API,
-/// keyed by `graph_id` so callers can poll `list_pending_approvals(session_id=graph_id)`.
-async fn dispatch_task<P: HubContext + 'static>(
-    claudecode: Arc<ClaudeCode<P>>,
+/// Claude sessions have loopback enabled so tool-use approval requests are routed through the
+/// orcha approval API, keyed by `graph_id` so callers can poll `list_pending_approvals(session_id=graph_id)`.
+async fn dispatch_task(
+    agent: Arc<dyn AgentExecutor>,
     loopback_storage: Arc<LoopbackStorage>,
     pm: Arc<Pm>,
     task: String,
     resolved_inputs: Vec<crate::activations::lattice::ResolvedToken>,
     node_id: &str,
-    model: Model,
     working_directory: String,
     graph_id: &str,
     output_tx: tokio::sync::mpsc::UnboundedSender<OrchaEvent>,
@@ -308,7 +543,7 @@ async fn dispatch_task<P: HubContext + 'static>(
 ) -> Result<Option<NodeOutput>, String> {
     // Build prior_work context from resolved input tokens
     let prior_work: Vec<String> = resolved_inputs
-        .into_iter()
+        .iter()
         .filter_map(|t| {
             t.data.as_ref()
                 .and_then(|v| v.get("text"))
@@ -316,14 +551,29 @@ async fn dispatch_task<P: HubContext + 'static>(
                 .map(std::string::ToString::to_string)
         })
         .collect();
+    // Error tokens: a failed upstream node, or the failure a retry loop
+    // handed back to this node for another pass
+    let failures: Vec<&str> = resolved_inputs
+        .iter()
+        .filter(|t| t.color == TokenColor::Error)
+        .filter_map(|t| t.data.as_ref()?.get("message")?.as_str())
+        .collect();
 
     let prompt = if prior_work.is_empty() {
         task
     } else {
         format!("<prior_work>\n{}\n</prior_work>\n\n{}", prior_work.join("\n\n"), task)
     };
+    let prompt = if failures.is_empty() {
+        prompt
+    } else {
+        format!(
+            "{prompt}\n\n<previous_failure>\n{}\nPlease fix the issue and try again.\n</previous_failure>",
+            failures.join("\n\n")
+        )
+    };
 
-    // Validate working directory before attempting to create a Claude session.
+    // Validate working directory before starting the agent.
     // A missing directory causes the Claude CLI process to exit immediately with a
     // terse error that gives no hint of the root cause.
     if !std::path::Path::new(&working_directory).is_dir() {
@@ -339,12 +589,11 @@ async fn dispatch_task<P: HubContext + 'static>(
     }
 
     // Log the prompt and invocation context — seq 0.
-    let model_str = format!("{model:?}").to_lowercase();
     pm.log_node_event(
         graph_id, node_id, ticket_id.as_deref(), 0, "prompt",
         serde_json::json!({
             "task": prompt,
-            "model": model_str,
+            "model": agent.target().to_string(),
             "working_directory": working_directory,
             "prior_work_count": prior_work.len(),
         }),
@@ -369,7 +618,7 @@ async fn dispatch_task<P: HubContext + 'static>(
                 _ = notifier.notified() => {
                     if let Ok(pending) = lb.list_pending(Some(&gid)).await {
                         for approval in pending {
-                            let _ = lb.resolve_approval(&approval.id, true, None).await;
+                            let _ = lb.resolve_approval_as(&approval.id, true, None, Some("orcha:auto-approve")).await;
                         }
                     }
                 }
@@ -377,23 +626,12 @@ async fn dispatch_task<P: HubContext + 'static>(
         }
     });
 
-    let create_stream = claudecode
-        .create(session_name.clone(), working_directory, model, None, Some(true), Some(graph_id.to_string()))
-        .await;
-    tokio::pin!(create_stream);
-
-    if let Some(result) = create_stream.next().await {
-        match result {
-            CreateResult::Ok { .. } => {}
-            CreateResult::Err { message } => {
-                let _ = approver_stop_tx.send(());
-                return Err(format!("Failed to create claudecode session: {message}"));
-            }
-        }
-    }
-
-    let chat_stream = claudecode.chat(session_name, prompt, None, None).await;
-    tokio::pin!(chat_stream);
+    let mut chat_stream = agent.run(AgentRequest {
+        session_name,
+        prompt,
+        working_directory,
+        graph_id: graph_id.to_string(),
+    });
 
     let mut output_text = String::new();
     let mut chat_error: Option<String> = None;
@@ -412,7 +650,7 @@ async fn dispatch_task<P: HubContext + 'static>(
             maybe_event = chat_stream.next() => {
                 match maybe_event {
                     None => break,
-                    Some(ChatEvent::Content { text }) => {
+                    Some(AgentEvent::Content { text }) => {
                         let _ = output_tx.send(OrchaEvent::NodeOutput {
                             node_id: node_id.to_string(),
                             ticket_id: ticket_id.clone(),
@@ -420,14 +658,15 @@ async fn dispatch_task<P: HubContext + 'static>(
                         });
                         output_text.push_str(&text);
                     }
-                    Some(ChatEvent::Start { id, user_position }) => {
-                        pm.log_node_event(
-                            graph_id, node_id, ticket_id.as_deref(), log_seq, "start",
-                            serde_json::json!({ "session_id": id, "user_position": user_position }),
-                        ).await;
+                    Some(AgentEvent::Start { session_id, detail }) => {
+                        let mut entry = serde_json::json!({ "session_id": session_id });
+                        if let (Some(entry), serde_json::Value::Object(detail)) = (entry.as_object_mut(), detail) {
+                            entry.extend(detail);
+                        }
+                        pm.log_node_event(graph_id, node_id, ticket_id.as_deref(), log_seq, "start", entry).await;
                         log_seq += 1;
                     }
-                    Some(ChatEvent::ToolUse { tool_name, tool_use_id, input }) => {
+                    Some(AgentEvent::ToolUse { tool_name, tool_use_id, input }) => {
                         pm.log_node_event(
                             graph_id, node_id, ticket_id.as_deref(), log_seq, "tool_use",
                             serde_json::json!({
@@ -438,7 +677,7 @@ async fn dispatch_task<P: HubContext + 'static>(
                         ).await;
                         log_seq += 1;
                     }
-                    Some(ChatEvent::ToolResult { tool_use_id, output, is_error }) => {
+                    Some(AgentEvent::ToolResult { tool_use_id, output, is_error }) => {
                         pm.log_node_event(
                             graph_id, node_id, ticket_id.as_deref(), log_seq, "tool_result",
                             serde_json::json!({
@@ -451,19 +690,32 @@ async fn dispatch_task<P: HubContext + 'static>(
                         ).await;
                         log_seq += 1;
                     }
-                    Some(ChatEvent::Complete { claude_session_id, usage, .. }) => {
+                    Some(AgentEvent::Complete { session_id, usage }) => {
                         pm.log_node_event(
                             graph_id, node_id, ticket_id.as_deref(), log_seq, "complete",
                             serde_json::json!({
-                                "claude_session_id": claude_session_id,
+                                "claude_session_id": session_id,
                                 "output_length": output_text.len(),
                                 "usage": usage,
                             }),
                         ).await;
                         log_seq += 1;
+                        // Spend is charged even if the run's output is then rejected
+                        if let Some(usage) = usage.as_ref().and_then(Usage::from_agent) {
+                            pm.log_node_event(
+                                graph_id, node_id, ticket_id.as_deref(), log_seq, "usage",
+                                serde_json::to_value(usage).unwrap_or_default(),
+                            ).await;
+                            log_seq += 1;
+                            let _ = output_tx.send(OrchaEvent::NodeUsage {
+                                node_id: node_id.to_string(),
+                                ticket_id: ticket_id.clone(),
+                                usage,
+                            });
+                        }
                         break;
                     }
-                    Some(ChatEvent::Err { message }) => {
+                    Some(AgentEvent::Error { message }) => {
                         pm.log_node_event(
                             graph_id, node_id, ticket_id.as_deref(), log_seq, "error",
                             serde_json::json!({ "message": message }),
@@ -472,14 +724,13 @@ async fn dispatch_task<P: HubContext + 'static>(
                         chat_error = Some(message);
                         break;
                     }
-                    Some(ChatEvent::Passthrough { event_type, data, .. }) => {
+                    Some(AgentEvent::Passthrough { event_type, data }) => {
                         pm.log_node_event(
                             graph_id, node_id, ticket_id.as_deref(), log_seq, "passthrough",
                             serde_json::json!({ "event_type": event_type, "data": data }),
                         ).await;
                         log_seq += 1;
                     }
-                    Some(_) => {}
                 }
             }
         }
@@ -496,7 +747,7 @@ async fn dispatch_task<P: HubContext + 'static>(
     }
 
     if output_text.is_empty() {
-        let msg = "Task produced no output — Claude session returned empty text".to_string();
+        let msg = format!("Task produced no output — {} agent returned empty text", agent.target());
         pm.log_node_event(
             graph_id, node_id, ticket_id.as_deref(), log_seq, "outcome",
             serde_json::json!({ "status": "error", "error": msg }),
@@ -521,8 +772,8 @@ async fn dispatch_task<P: HubContext + 'static>(
 /// Dispatch a "synthesize" node — like task, but prepends a `<join_context>` block
 /// listing the intent of upstream task/synthesize nodes so the join agent knows
 /// what each contributing branch was trying to accomplish.
-async fn dispatch_synthesize<P: HubContext + 'static>(
-    claudecode: Arc<ClaudeCode<P>>,
+async fn dispatch_synthesize(
+    agent: Arc<dyn AgentExecutor>,
     _arbor: Arc<ArborStorage>,
     loopback_storage: Arc<LoopbackStorage>,
     pm: Arc<Pm>,
@@ -530,7 +781,6 @@ async fn dispatch_synthesize<P: HubContext + 'static>(
     task: String,
     resolved_inputs: Vec<crate::activations::lattice::ResolvedToken>,
     node_id: &str,
-    model: Model,
     working_directory: String,
     output_tx: tokio::sync::mpsc::UnboundedSender<OrchaEvent>,
     cancel_rx: tokio::sync::watch::Receiver<bool>,
@@ -569,58 +819,21 @@ async fn dispatch_synthesize<P: HubContext + 'static>(
     };
 
     let prompt = format!("{join_context}{task}");
-    dispatch_task(claudecode, loopback_storage, pm, prompt, resolved_inputs, node_id, model, working_directory, &graph.graph_id, output_tx, cancel_rx, ticket_id).await
-}
-
-/// Dispatch a "subgraph" node — runs the child graph to completion.
-///
-/// On child `GraphDone` → emits `Token::ok_data({"child_graph_id": "..."})`.
-/// On child `GraphFailed` → returns `Err(...)` so the parent node is failed.
-async fn dispatch_subgraph<P: HubContext + 'static>(
-    claudecode: Arc<ClaudeCode<P>>,
-    arbor: Arc<ArborStorage>,
-    loopback_storage: Arc<LoopbackStorage>,
-    pm: Arc<Pm>,
-    graph_runtime: Arc<GraphRuntime>,
-    cancel_registry: CancelRegistry,
-    graph: &OrchaGraph,
-    child_graph_id: String,
-    _node_id: &str,
-    model: Model,
-    working_directory: String,
-    cancel_rx: tokio::sync::watch::Receiver<bool>,
-) -> Result<Option<NodeOutput>, String> {
-    let child = Arc::new(graph.open_child_graph(child_graph_id.clone()));
-    // Child graphs don't have a ticket map — pass an empty map.
-    let events = run_graph_execution(child, claudecode, arbor, loopback_storage, pm, graph_runtime, cancel_registry, model, working_directory, cancel_rx, HashMap::new());
-    tokio::pin!(events);
-
-    while let Some(event) = events.next().await {
-        match event {
-            OrchaEvent::Complete { .. } => {
-                return Ok(Some(NodeOutput::Single(Token::ok_data(
-                    serde_json::json!({ "child_graph_id": child_graph_id }),
-                ))));
-            }
-            OrchaEvent::Failed { error, .. } => {
-                return Err(format!("Child graph failed: {error}"));
-            }
-            _ => {}
-        }
-    }
-
-    Err("Child graph stream ended without completion".to_string())
+    dispatch_task(agent, loopback_storage, pm, prompt, resolved_inputs, node_id, working_directory, &graph.graph_id, output_tx, cancel_rx, ticket_id).await
 }
 
 /// Dispatch a "plan" node — uses Claude to generate a ticket file, compiles it
 /// into a child graph, executes that child graph, and streams its events.
 ///
 /// Phases:
-/// 1. Run Claude with the plan prompt → ticket source (raw text)
+/// 1. Run the planning agent with the plan prompt → ticket source (raw text)
 /// 2. Compile the ticket source into nodes + edges
 /// 3. Build a child graph under the current graph
 /// 4. Execute the child graph, forwarding events to the parent stream
+///
+/// The child graph's nodes default to `model`, the parent graph's target.
 async fn dispatch_plan<P: HubContext + 'static>(
+    agent: Arc<dyn AgentExecutor>,
     claudecode: Arc<ClaudeCode<P>>,
     arbor: Arc<ArborStorage>,
     loopback_storage: Arc<LoopbackStorage>,
@@ -631,21 +844,21 @@ async fn dispatch_plan<P: HubContext + 'static>(
     task: String,
     resolved_inputs: Vec<crate::activations::lattice::ResolvedToken>,
     node_id: &str,
-    model: Model,
+    model: AgentTarget,
     working_directory: String,
     output_tx: tokio::sync::mpsc::UnboundedSender<OrchaEvent>,
     cancel_rx: tokio::sync::watch::Receiver<bool>,
     ticket_id: Option<String>,
+    permit: Option<SchedulerPermit>,
 ) -> Result<Option<NodeOutput>, String> {
-    // Phase 1 — run Claude to generate ticket source
+    // Phase 1 — run the planning agent to generate ticket source
     let ticket_result = dispatch_task(
-        claudecode.clone(),
+        agent,
         loopback_storage.clone(),
         pm.clone(),
         task,
         resolved_inputs,
         node_id,
-        model,
         working_directory.clone(),
         &graph.graph_id,
         output_tx.clone(),
@@ -670,15 +883,24 @@ async fn dispatch_plan<P: HubContext + 'static>(
         _ => return Err("Plan task produced no output".to_string()),
     };
 
+    // The planning agent is done. Free its slot before the child graph's
+    // agents queue for theirs, or a small limit would deadlock.
+    drop(permit);
+
     // Phase 2 — compile ticket source
     let compiled = crate::activations::orcha::ticket_compiler::compile_tickets(&ticket_source)
         .map_err(|e| format!("Plan ticket compile error: {e}"))?;
 
-    // Phase 3 — build child graph
+    // Phase 3 — build child graph; planned tickets inherit the parent's
+    // isolation and concurrency limits
+    let parent_config = graph.run_config().await.unwrap_or_default();
     let child_metadata = serde_json::json!({
         "_plexus_run_config": {
-            "model": format!("{model:?}").to_lowercase(),
+            "model": model.to_string(),
             "working_directory": working_directory,
+            "isolation": Integration::from_run_config(&parent_config).map(Integration::as_str),
+            "max_agents": parent_config.get("max_agents"),
+            "max_progs": parent_config.get("max_progs"),
         },
         "parent_graph_id": graph.graph_id,
         "plan_node_id": node_id,
@@ -745,32 +967,72 @@ async fn dispatch_plan<P: HubContext + 'static>(
 }
 
 /// Dispatch a "validate" node — runs a shell command and checks the exit code.
+///
+/// An Error token among the inputs fails the node with that error without
+/// running the command, so a failed agent reaches the retry loop closing over
+/// it as itself. On success the node passes the text of its inputs on, and a
+/// synthesize node downstream of the check still gets the work as `<prior_work>`.
+///
+/// Without a `cwd` the command runs in `/workspace`, or in the exec policy's
+/// `cwd_root` when it has one.
 async fn dispatch_validate(
+    executor: &BashExecutor,
     command: String,
     cwd: Option<String>,
+    resolved_inputs: Vec<crate::activations::lattice::ResolvedToken>,
 ) -> Result<Option<NodeOutput>, String> {
-    let cwd = cwd.unwrap_or_else(|| "/workspace".to_string());
+    if let Some(failed) = resolved_inputs.iter().find(|t| t.color == TokenColor::Error) {
+        let message = failed.data.as_ref().and_then(|v| v.get("message")).and_then(serde_json::Value::as_str);
+        return Err(message.unwrap_or("Upstream node failed").to_string());
+    }
+    let text: Vec<&str> = resolved_inputs
+        .iter()
+        .filter_map(|t| t.data.as_ref()?.get("text")?.as_str())
+        .filter(|t| !t.is_empty())
+        .collect();
 
-    let output = tokio::process::Command::new("sh")
-        .arg("-c")
-        .arg(&command)
-        .current_dir(&cwd)
-        .output()
-        .await
-        .map_err(|e| format!("Failed to run validate command: {e}"))?;
+    let cwd = cwd.or_else(|| {
+        executor
+            .policy()
+            .config()
+            .cwd_root
+            .is_none()
+            .then(|| "/workspace".to_string())
+    });
+    let options = BashOptions {
+        cwd,
+        ..BashOptions::default()
+    };
 
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-    let combined = format!("{stdout}{stderr}").trim().to_string();
+    // Stdout and stderr arrive interleaved, so failures read in context
+    let mut output = String::new();
+    let mut exit_code = None;
+    for event in executor.execute_collect_with(&command, options).await {
+        match event {
+            BashEvent::Stdout { line, .. } | BashEvent::Stderr { line, .. } => {
+                output.push_str(&line);
+                output.push('\n');
+            }
+            BashEvent::Exit { code } => exit_code = Some(code),
+            BashEvent::Error { message } => {
+                return Err(format!("Failed to run validate command: {message}"));
+            }
+            BashEvent::PolicyViolation { violation } => {
+                return Err(format!("Validate command rejected by exec policy: {violation}"));
+            }
+            BashEvent::Truncated { .. } | BashEvent::TimedOut { .. } => {}
+        }
+    }
+    let combined = output.trim().to_string();
 
-    if output.status.success() {
-        Ok(Some(NodeOutput::Single(Token::ok())))
-    } else {
-        Err(format!(
+    match exit_code {
+        Some(0) if text.is_empty() => Ok(Some(NodeOutput::Single(Token::ok()))),
+        Some(0) => Ok(Some(NodeOutput::Single(Token::ok_data(serde_json::json!({ "text": text.join("\n\n") }))))),
+        code => Err(format!(
             "Validation failed (exit {}): {}",
-            output.status.code().unwrap_or(-1),
+            code.unwrap_or(-1),
             combined
-        ))
+        )),
     }
 }
 
@@ -826,239 +1088,3 @@ fn output_text(output: &NodeOutput) -> Option<String> {
     }
     None
 }
-
-fn is_empty_output(output: &NodeOutput) -> bool {
-    output_text(output).is_none_or(|t| t.is_empty())
-}
-
-async fn dispatch_task_with_retry<P: HubContext + 'static>(
-    claudecode: Arc<ClaudeCode<P>>,
-    loopback_storage: Arc<LoopbackStorage>,
-    pm: Arc<Pm>,
-    task: String,
-    resolved_inputs: Vec<crate::activations::lattice::ResolvedToken>,
-    node_id: &str,
-    model: Model,
-    working_directory: String,
-    graph_id: &str,
-    output_tx: tokio::sync::mpsc::UnboundedSender<OrchaEvent>,
-    cancel_rx: tokio::sync::watch::Receiver<bool>,
-    ticket_id: Option<String>,
-    max_retries: usize,
-) -> Result<Option<NodeOutput>, String> {
-    let mut last_error: Option<String> = None;
-    for attempt in 0..=max_retries {
-        if attempt > 0 {
-            let secs = 1u64 << (attempt - 1).min(3);
-            tokio::time::sleep(tokio::time::Duration::from_secs(secs)).await;
-            let _ = output_tx.send(OrchaEvent::Retrying {
-                node_id: node_id.to_string(),
-                ticket_id: ticket_id.clone(),
-                attempt,
-                max_attempts: max_retries,
-                error: last_error.clone().unwrap_or_default(),
-            });
-        }
-        match dispatch_task(
-            claudecode.clone(), loopback_storage.clone(), pm.clone(),
-            task.clone(), resolved_inputs.clone(), node_id, model,
-            working_directory.clone(), graph_id, output_tx.clone(), cancel_rx.clone(),
-            ticket_id.clone(),
-        ).await {
-            Ok(output) => {
-                let empty = output.as_ref().is_none_or(is_empty_output);
-                if !empty {
-                    return Ok(output);
-                }
-                last_error = Some("Task produced no output".to_string());
-            }
-            Err(e) => {
-                if attempt >= max_retries {
-                    return Err(e);
-                }
-                last_error = Some(e);
-            }
-        }
-    }
-    Err(last_error.unwrap_or_else(|| "Task failed after all retries".to_string()))
-}
-
-async fn dispatch_synthesize_with_retry<P: HubContext + 'static>(
-    claudecode: Arc<ClaudeCode<P>>,
-    arbor: Arc<ArborStorage>,
-    loopback_storage: Arc<LoopbackStorage>,
-    pm: Arc<Pm>,
-    graph: &OrchaGraph,
-    task: String,
-    resolved_inputs: Vec<crate::activations::lattice::ResolvedToken>,
-    node_id: &str,
-    model: Model,
-    working_directory: String,
-    output_tx: tokio::sync::mpsc::UnboundedSender<OrchaEvent>,
-    cancel_rx: tokio::sync::watch::Receiver<bool>,
-    ticket_id: Option<String>,
-    max_retries: usize,
-) -> Result<Option<NodeOutput>, String> {
-    let mut last_error: Option<String> = None;
-    for attempt in 0..=max_retries {
-        if attempt > 0 {
-            let secs = 1u64 << (attempt - 1).min(3);
-            tokio::time::sleep(tokio::time::Duration::from_secs(secs)).await;
-            let _ = output_tx.send(OrchaEvent::Retrying {
-                node_id: node_id.to_string(),
-                ticket_id: ticket_id.clone(),
-                attempt,
-                max_attempts: max_retries,
-                error: last_error.clone().unwrap_or_default(),
-            });
-        }
-        match dispatch_synthesize(
-            claudecode.clone(), arbor.clone(), loopback_storage.clone(), pm.clone(),
-            graph, task.clone(), resolved_inputs.clone(), node_id, model,
-            working_directory.clone(), output_tx.clone(), cancel_rx.clone(),
-            ticket_id.clone(),
-        ).await {
-            Ok(output) => {
-                let empty = output.as_ref().is_none_or(is_empty_output);
-                if !empty {
-                    return Ok(output);
-                }
-                last_error = Some("Synthesize produced no output".to_string());
-            }
-            Err(e) => {
-                if attempt >= max_retries {
-                    return Err(e);
-                }
-                last_error = Some(e);
-            }
-        }
-    }
-    Err(last_error.unwrap_or_else(|| "Synthesize failed after all retries".to_string()))
-}
-
-/// Validate with automatic agent retry on failure.
-///
-/// Two improvements over a bare validate call:
-///
-/// 1. **Retry across joins**: Uses BFS to find ALL upstream task/synthesize
-///    nodes, traversing through gather nodes and chained validates.  All of
-///    them are re-run on each retry so the validation has the latest work from
-///    every contributing agent.
-///
-/// 2. **Token passthrough**: On success, emits `Token::ok_data({"text": ...})`
-///    carrying the concatenated latest output from every upstream task, instead
-///    of bare `Token::ok()`.  A downstream `[agent/synthesize]` blocked on this
-///    validate node therefore receives fresh `<prior_work>` context regardless
-///    of how many retries occurred.
-async fn dispatch_validate_with_retry<P: HubContext + 'static>(
-    claudecode: Arc<ClaudeCode<P>>,
-    arbor: Arc<ArborStorage>,
-    loopback_storage: Arc<LoopbackStorage>,
-    pm: Arc<Pm>,
-    graph: &OrchaGraph,
-    validate_node_id: &str,
-    command: String,
-    cwd: Option<String>,
-    model: Model,
-    working_directory: String,
-    output_tx: tokio::sync::mpsc::UnboundedSender<OrchaEvent>,
-    cancel_rx: tokio::sync::watch::Receiver<bool>,
-    max_retries: usize,
-) -> Result<Option<NodeOutput>, String> {
-
-    // BFS: find every task/synthesize node that (transitively) feeds this validate.
-    let task_ids = find_upstream_tasks(graph, validate_node_id).await;
-
-    // Seed task_outputs from whatever is already stored in the lattice.
-    // These will be overwritten on each successful retry dispatch.
-    let mut task_outputs: std::collections::HashMap<String, String> = std::collections::HashMap::new();
-    for tid in &task_ids {
-        if let Ok(Some(output)) = graph.get_node_output(tid).await {
-            if let Some(text) = output_text(&output) {
-                task_outputs.insert(tid.clone(), text);
-            }
-        }
-    }
-
-    let mut error_context: Option<String> = None;
-
-    for attempt in 0..=max_retries {
-        if let Some(ref err) = error_context {
-            let _ = output_tx.send(OrchaEvent::Retrying {
-                node_id: validate_node_id.to_string(),
-                ticket_id: None,
-                attempt,
-                max_attempts: max_retries,
-                error: err.clone(),
-            });
-            if task_ids.is_empty() {
-                return Err(format!(
-                    "Validation failed with no upstream task to retry: {err}"
-                ));
-            }
-
-            // Re-run every upstream task sequentially with the error as context.
-            for tid in &task_ids {
-                let Ok(spec) = graph.get_node_spec(tid).await else { continue };
-                let data = match spec {
-                    NodeSpec::Task { data, .. } => data,
-                    _ => continue,
-                };
-                let Ok(kind) = serde_json::from_value::<OrchaNodeKind>(data) else { continue };
-                let task_text = match kind {
-                    OrchaNodeKind::Task { task, .. } | OrchaNodeKind::Synthesize { task, .. } => task,
-                    _ => continue,
-                };
-
-                let resolved = graph.get_resolved_inputs(tid, &arbor).await.unwrap_or_default();
-
-                let retry_prompt = format!(
-                    "{task_text}\n\n\
-                     <validation_error attempt=\"{attempt}\">\n\
-                     The validation command `{command}` failed:\n\
-                     {err}\n\
-                     Please fix the issue and try again.\n\
-                     </validation_error>"
-                );
-
-                if let Ok(Some(ref output)) = dispatch_task(
-                    claudecode.clone(), loopback_storage.clone(), pm.clone(), retry_prompt, resolved,
-                    tid, model, working_directory.clone(), &graph.graph_id, output_tx.clone(), cancel_rx.clone(), None,
-                ).await {
-                    if let Some(text) = output_text(output) {
-                        task_outputs.insert(tid.clone(), text);
-                    }
-                }
-            }
-        }
-
-        match dispatch_validate(command.clone(), cwd.clone()).await {
-            Ok(_) => {
-                // Pass the latest task output text through as this node's token.
-                // Downstream synthesize nodes receive fresh <prior_work> context.
-                let combined: String = task_outputs.values()
-                    .filter(|t| !t.is_empty())
-                    .cloned()
-                    .collect::<Vec<_>>()
-                    .join("\n\n");
-
-                let token = if combined.is_empty() {
-                    Token::ok()
-                } else {
-                    Token::ok_data(serde_json::json!({ "text": combined }))
-                };
-                return Ok(Some(NodeOutput::Single(token)));
-            }
-            Err(e) => {
-                if attempt >= max_retries {
-                    return Err(format!(
-                        "Validation failed after {max_retries} retries. Last error: {e}"
-                    ));
-                }
-                error_context = Some(e);
-            }
-        }
-    }
-
-    unreachable!()
-}
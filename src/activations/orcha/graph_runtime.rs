@@ -1,14 +1,17 @@
 use crate::activations::arbor::ArborStorage;
+use crate::activations::bash::BashExecutor;
 use crate::activations::lattice::{
-    GatherStrategy, LatticeEvent, LatticeEventEnvelope, LatticeStorage, NodeOutput, NodeSpec,
+    GatherStrategy, LatticeEventEnvelope, LatticeStorage, NodeOutput, NodeSpec,
     NodeStatus, ResolvedToken, Token, TokenPayload,
 };
+use cllient::ModelRegistry;
 use futures::Stream;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use super::types::{OrchaEdgeDef, OrchaNodeDef, OrchaNodeKind, OrchaNodeSpec};
+use super::scheduler::Scheduler;
+use super::types::{EdgePredicate, OrchaEdgeDef, OrchaNodeDef, OrchaNodeKind, OrchaNodeSpec};
 
 // ─── GraphRuntime (factory) ───────────────────────────────────────────────────
 
@@ -16,14 +19,60 @@ use super::types::{OrchaEdgeDef, OrchaNodeDef, OrchaNodeKind, OrchaNodeSpec};
 ///
 /// Serves as the factory for `OrchaGraph` handles.  The lattice backend is
 /// an implementation detail — callers only ever see `OrchaGraph`.
+///
+/// Also carries the `BashExecutor` validate nodes run their commands with,
+/// so they are held to the same exec policy as `bash.execute`, and the
+/// `Scheduler` that caps how many nodes run at once across all graphs, and
+/// the `ModelRegistry` that `cone:<model id>` agent nodes run against.
 #[derive(Clone)]
 pub struct GraphRuntime {
     storage: Arc<LatticeStorage>,
+    executor: BashExecutor,
+    scheduler: Scheduler,
+    model_registry: Option<Arc<ModelRegistry>>,
 }
 
 impl GraphRuntime {
-    pub const fn new(storage: Arc<LatticeStorage>) -> Self {
-        Self { storage }
+    pub fn new(storage: Arc<LatticeStorage>) -> Self {
+        Self {
+            storage,
+            executor: BashExecutor::new(),
+            scheduler: Scheduler::default(),
+            model_registry: None,
+        }
+    }
+
+    /// Share `scheduler` (and its server-wide limits) between graph runs.
+    pub fn with_scheduler(mut self, scheduler: Scheduler) -> Self {
+        self.scheduler = scheduler;
+        self
+    }
+
+    /// The scheduler nodes take their concurrency slots from.
+    pub const fn scheduler(&self) -> &Scheduler {
+        &self.scheduler
+    }
+
+    /// Let agent nodes target models from `registry` (`cone:<model id>`).
+    pub fn with_model_registry(mut self, registry: Arc<ModelRegistry>) -> Self {
+        self.model_registry = Some(registry);
+        self
+    }
+
+    /// The registry `cone:` agent nodes resolve their model against, if any.
+    pub const fn model_registry(&self) -> Option<&Arc<ModelRegistry>> {
+        self.model_registry.as_ref()
+    }
+
+    /// Run validate commands with `executor` (and its exec policy).
+    pub fn with_executor(mut self, executor: BashExecutor) -> Self {
+        self.executor = executor;
+        self
+    }
+
+    /// The executor validate nodes run their commands with.
+    pub const fn executor(&self) -> &BashExecutor {
+        &self.executor
     }
 
     /// Expose the underlying lattice storage.
@@ -68,20 +117,14 @@ impl GraphRuntime {
         let graph_id = graph.graph_id.clone();
 
         let mut id_map: HashMap<String, String> = HashMap::new();
-        for OrchaNodeDef { id, spec } in nodes {
-            let result = match spec {
-                OrchaNodeSpec::Task { task, max_retries } => graph.add_task(task, max_retries).await,
-                OrchaNodeSpec::Synthesize { task, max_retries } => graph.add_synthesize(task, max_retries).await,
-                OrchaNodeSpec::Validate { command, cwd, max_retries } => graph.add_validate(command, cwd, max_retries).await,
-                OrchaNodeSpec::Gather { strategy } => graph.add_gather(strategy).await,
-                OrchaNodeSpec::Review { prompt } => graph.add_review(prompt).await,
-                OrchaNodeSpec::Plan { task } => graph.add_plan(task).await,
-            };
+        for def in nodes {
+            let id = def.id.clone();
+            let result = graph.add_node_def(def, &id_map).await;
             let lattice_id = result.map_err(|e| format!("Failed to add node '{id}': {e}"))?;
             id_map.insert(id, lattice_id);
         }
 
-        for OrchaEdgeDef { from, to } in edges {
+        for OrchaEdgeDef { from, to, predicate } in edges {
             let dep_id = id_map
                 .get(&from)
                 .ok_or_else(|| format!("Unknown node id in edge.from: '{from}'"))?
@@ -91,7 +134,7 @@ impl GraphRuntime {
                 .ok_or_else(|| format!("Unknown node id in edge.to: '{to}'"))?
                 .clone();
             graph
-                .depends_on(&node_id, &dep_id)
+                .depends_on_when(&node_id, &dep_id, predicate)
                 .await
                 .map_err(|e| format!("Failed to add edge {from} → {to}: {e}"))?;
         }
@@ -126,11 +169,12 @@ impl OrchaGraph {
     // ─── Node builders ───────────────────────────────────────────────────────
 
     /// Add a task node.
-    pub async fn add_task(&self, task: impl Into<String>, max_retries: Option<u8>) -> Result<String, String> {
-        let kind = OrchaNodeKind::Task { task: task.into(), max_retries };
+    pub async fn add_task(&self, task: impl Into<String>) -> Result<String, String> {
+        let kind = OrchaNodeKind::Task { task: task.into() };
         self.add_spec(NodeSpec::Task {
             data: serde_json::to_value(&kind).map_err(|e| e.to_string())?,
             handle: None,
+            timeout_secs: None,
         })
         .await
     }
@@ -138,11 +182,12 @@ impl OrchaGraph {
     /// Add a synthesize node.
     ///
     /// Like task, but `graph_runner` prepends resolved input tokens as `<prior_work>` context.
-    pub async fn add_synthesize(&self, task: impl Into<String>, max_retries: Option<u8>) -> Result<String, String> {
-        let kind = OrchaNodeKind::Synthesize { task: task.into(), max_retries };
+    pub async fn add_synthesize(&self, task: impl Into<String>) -> Result<String, String> {
+        let kind = OrchaNodeKind::Synthesize { task: task.into() };
         self.add_spec(NodeSpec::Task {
             data: serde_json::to_value(&kind).map_err(|e| e.to_string())?,
             handle: None,
+            timeout_secs: None,
         })
         .await
     }
@@ -154,30 +199,84 @@ impl OrchaGraph {
         &self,
         command: impl Into<String>,
         cwd: Option<impl Into<String>>,
-        max_retries: Option<u8>,
     ) -> Result<String, String> {
         let kind = OrchaNodeKind::Validate {
             command: command.into(),
             cwd: cwd.map(std::convert::Into::into),
-            max_retries,
         };
         self.add_spec(NodeSpec::Task {
             data: serde_json::to_value(&kind).map_err(|e| e.to_string())?,
             handle: None,
+            timeout_secs: None,
         })
         .await
     }
 
+    /// Add a node from an inline definition.
+    ///
+    /// `id_map` (definition id → node id) resolves loop targets. `priority`,
+    /// `model` and `tags` are recorded in the node data: the scheduler starts
+    /// higher-priority nodes first when slots are scarce, and an agent node
+    /// with a `model` runs on that backend instead of the graph's.
+    /// `timeout_secs` becomes the lattice node's Running timeout.
+    /// `max_retries` is ignored: lower it into loop nodes first with
+    /// `CompiledGraph::lower_retries`.
+    pub async fn add_node_def(
+        &self,
+        def: OrchaNodeDef,
+        id_map: &HashMap<String, String>,
+    ) -> Result<String, String> {
+        let OrchaNodeDef { spec, priority, model, timeout_secs, tags, .. } = def;
+        let kind = match spec {
+            OrchaNodeSpec::Task { task, .. } => OrchaNodeKind::Task { task },
+            OrchaNodeSpec::Synthesize { task, .. } => OrchaNodeKind::Synthesize { task },
+            OrchaNodeSpec::Validate { command, cwd, .. } => OrchaNodeKind::Validate { command, cwd },
+            OrchaNodeSpec::Review { prompt } => OrchaNodeKind::Review { prompt },
+            OrchaNodeSpec::Plan { task } => OrchaNodeKind::Plan { task },
+            OrchaNodeSpec::Gather { strategy } => return self.add_gather(strategy).await,
+            OrchaNodeSpec::Loop { target, max_iterations, repeat_if } => {
+                return match id_map.get(&target) {
+                    Some(target_id) => self.add_loop(target_id, max_iterations, repeat_if).await,
+                    None => Err(format!("Loop target '{target}' must be defined before the loop")),
+                };
+            }
+        };
+        let mut data = serde_json::to_value(&kind).map_err(|e| e.to_string())?;
+        if let Some(priority) = priority {
+            data["priority"] = Value::from(priority);
+        }
+        if let Some(model) = model {
+            data["model"] = Value::from(model);
+        }
+        if !tags.is_empty() {
+            data["tags"] = Value::from(tags);
+        }
+        self.add_spec(NodeSpec::Task { data, handle: None, timeout_secs }).await
+    }
+
     /// Add a gather node — engine-executed, auto-fires when all inbound tokens arrive.
     pub async fn add_gather(&self, strategy: GatherStrategy) -> Result<String, String> {
         self.add_spec(NodeSpec::Gather { strategy }).await
     }
 
-    /// Add a `SubGraph` node — when ready, runs the child graph to completion.
+    /// Add a `SubGraph` node — when ready, lattice launches the child graph and
+    /// completes or fails this node with the child's terminal tokens.
     pub async fn add_subgraph(&self, child_graph_id: impl Into<String>) -> Result<String, String> {
         self.add_spec(NodeSpec::SubGraph { graph_id: child_graph_id.into() }).await
     }
 
+    /// Add a loop node that re-runs everything between `target` and itself,
+    /// up to `max_iterations` passes in total, while its input is an error
+    /// token (or matches `repeat_if`). Wire the body's last node into it.
+    pub async fn add_loop(
+        &self,
+        target: &str,
+        max_iterations: u32,
+        repeat_if: Option<EdgePredicate>,
+    ) -> Result<String, String> {
+        self.add_spec(NodeSpec::Loop { target: target.to_string(), max_iterations, repeat_if }).await
+    }
+
     /// Open a sibling graph by ID sharing the same `LatticeStorage`.
     pub fn open_child_graph(&self, graph_id: impl Into<String>) -> OrchaGraph {
         OrchaGraph { graph_id: graph_id.into(), storage: self.storage.clone() }
@@ -192,6 +291,7 @@ impl OrchaGraph {
         self.add_spec(NodeSpec::Task {
             data: serde_json::to_value(&kind).map_err(|e| e.to_string())?,
             handle: None,
+            timeout_secs: None,
         })
         .await
     }
@@ -202,6 +302,7 @@ impl OrchaGraph {
         self.add_spec(NodeSpec::Task {
             data: serde_json::to_value(&kind).map_err(|e| e.to_string())?,
             handle: None,
+            timeout_secs: None,
         })
         .await
     }
@@ -216,6 +317,18 @@ impl OrchaGraph {
         &self,
         dependent: &str,
         dependency: &str,
+    ) -> Result<(), String> {
+        self.depends_on_when(dependent, dependency, None).await
+    }
+
+    /// Like `depends_on`, but `dependency`'s output only reaches `dependent`
+    /// when `predicate` holds for it. If it never does, `dependent` is left
+    /// unreached and the graph finishes without it.
+    pub async fn depends_on_when(
+        &self,
+        dependent: &str,
+        dependency: &str,
+        predicate: Option<EdgePredicate>,
     ) -> Result<(), String> {
         self.storage
             .add_edge(
@@ -223,6 +336,7 @@ impl OrchaGraph {
                 &dependency.to_string(),
                 &dependent.to_string(),
                 None,
+                predicate.as_ref(),
             )
             .await
     }
@@ -238,17 +352,11 @@ impl OrchaGraph {
     }
 
     /// Signal that a node started executing.
-    pub async fn start_node(&self, node_id: &str) -> Result<(), String> {
-        self.storage
-            .set_node_status(&node_id.to_string(), NodeStatus::Running, None, None)
-            .await?;
-        self.storage
-            .persist_event(&self.graph_id, &LatticeEvent::NodeStarted {
-                node_id: node_id.to_string(),
-            })
-            .await?;
-        self.storage.notify_graph(&self.graph_id);
-        Ok(())
+    ///
+    /// Returns `false` if the node already reached a terminal state (e.g. the
+    /// lattice watchdog expired it) and should not be executed.
+    pub async fn start_node(&self, node_id: &str) -> Result<bool, String> {
+        self.storage.start_node(&self.graph_id, &node_id.to_string()).await
     }
 
     /// Signal that a node completed successfully, optionally carrying output.
@@ -284,11 +392,24 @@ impl OrchaGraph {
         self.storage.get_node(&node_id.to_string()).await.map(|n| n.output)
     }
 
+    /// The run configuration persisted in this graph's metadata under
+    /// `_plexus_run_config`, or `Null` if there is none.
+    pub async fn run_config(&self) -> Result<Value, String> {
+        let graph = self.storage.get_graph(&self.graph_id).await?;
+        Ok(graph.metadata.get("_plexus_run_config").cloned().unwrap_or(Value::Null))
+    }
+
     /// Count the total number of nodes in this graph.
     pub async fn count_nodes(&self) -> Result<usize, String> {
         self.storage.count_nodes(&self.graph_id).await
     }
 
+    /// Current loop pass of every node (`0` outside loops or on the first pass).
+    pub async fn get_node_iterations(&self) -> Result<HashMap<String, u32>, String> {
+        let nodes = self.storage.get_nodes(&self.graph_id).await?;
+        Ok(nodes.into_iter().map(|n| (n.id, n.iteration)).collect())
+    }
+
     /// Get the IDs of nodes that have already reached a terminal state (Complete or Failed).
     /// Used by `run_graph_execution` to pre-populate the dispatched set on reconnect.
     pub async fn get_terminal_node_ids(&self) -> Result<Vec<String>, String> {
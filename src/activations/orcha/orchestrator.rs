@@ -11,6 +11,9 @@ use futures::StreamExt;
 use std::sync::Arc;
 use uuid::Uuid;
 
+/// Responder recorded for approvals settled by the orcha approval agent
+const APPROVAL_AGENT: &str = "orcha:approval-agent";
+
 /// Run a complete orchestration task with full approval loop and validation
 ///
 /// This is the main orchestration function that:
@@ -32,6 +35,16 @@ pub(super) async fn run_orchestration_task<P: HubContext>(
     stream! {
         // 1. Create Orcha context for tracking orchestration events
         let session_id = session_id_override.unwrap_or_else(|| format!("orcha-{}", Uuid::new_v4()));
+
+        // Parse model string to Model enum (once, stable across retries)
+        let model = match parse_model(&request.model) {
+            Ok(m) => m,
+            Err(e) => {
+                yield OrchaEvent::Failed { session_id, error: e };
+                return;
+            }
+        };
+
         let ctx = match OrchaContext::new(
             arbor.clone(),
             session_id.clone(),
@@ -75,14 +88,6 @@ pub(super) async fn run_orchestration_task<P: HubContext>(
             };
         }
 
-        // Parse model string to Model enum (once, stable across retries)
-        let model = match request.model.as_str() {
-            "opus" => Model::Opus,
-            "sonnet" => Model::Sonnet,
-            "haiku" => Model::Haiku,
-            _ => Model::Sonnet,
-        };
-
         // Stable session name for this Orcha session's claudecode record.
         let cc_session_name = format!("{session_id}-cc");
 
@@ -206,13 +211,11 @@ pub(super) async fn run_orchestration_task<P: HubContext>(
             // 4. Process chat events
             while let Some(event) = chat_stream.next().await {
                 match event {
-                    ChatEvent::Start { .. } => {
-                        if request.verbose {
-                            yield OrchaEvent::Progress {
-                                message: "Agent started processing task".to_string(),
-                                percentage: Some(40.0),
-                            };
-                        }
+                    ChatEvent::Start { .. } if request.verbose => {
+                        yield OrchaEvent::Progress {
+                            message: "Agent started processing task".to_string(),
+                            percentage: Some(40.0),
+                        };
                     }
                     ChatEvent::Content { text } => {
                         accumulated_text.push_str(&text);
@@ -273,15 +276,13 @@ pub(super) async fn run_orchestration_task<P: HubContext>(
                             };
                         }
                     }
-                    ChatEvent::ToolResult { tool_use_id, output, is_error } => {
-                        // Only emit tool results if verbose
-                        if request.verbose {
-                            yield OrchaEvent::ToolResult {
-                                tool_id: tool_use_id,
-                                content: output,
-                                is_error,
-                            };
-                        }
+                    // Only emit tool results if verbose
+                    ChatEvent::ToolResult { tool_use_id, output, is_error } if request.verbose => {
+                        yield OrchaEvent::ToolResult {
+                            tool_id: tool_use_id,
+                            content: output,
+                            is_error,
+                        };
                     }
                     ChatEvent::Complete { .. } => {
                         // Record Claude session completion in arbor via context
@@ -419,6 +420,12 @@ pub(super) async fn run_orchestration_task<P: HubContext>(
 // Helper Functions
 // ═══════════════════════════════════════════════════════════════════════════
 
+/// Parse a session's model name. Unknown names are an error rather than a
+/// silent fallback to Sonnet.
+pub(super) fn parse_model(model: &str) -> Result<Model, String> {
+    Model::from_str(model).ok_or_else(|| format!("Unknown model '{model}' (expected opus, sonnet or haiku)"))
+}
+
 /// Extract validation artifact from accumulated text
 fn extract_validation_artifact(text: &str) -> Option<ValidationArtifact> {
     // Look for {"orcha_validate": {...}} pattern
@@ -493,7 +500,7 @@ async fn handle_tool_approval<P: HubContext>(
     // Approvals are keyed by cc_session_id (the Claude Code session that owns the tool call).
     // The cc_session's notifier also propagates to orcha_session_id via register_session_parent.
     let notifier = loopback.storage().get_or_create_notifier(&cc_session_id);
-    let deadline = tokio::time::Instant::now() + tokio::time::Duration::from_secs(3600);
+    let deadline = tokio::time::Instant::now() + tokio::time::Duration::from_hours(1);
 
     let approval_id = loop {
         let approvals = loopback.storage().get_pending_approvals(&cc_session_id).await;
@@ -548,10 +555,11 @@ async fn handle_tool_approval<P: HubContext>(
     if !created {
         tracing::error!("Failed to create approval decision session");
         // Auto-deny if we can't create decision session
-        if let Err(e) = loopback.storage().resolve_approval(
+        if let Err(e) = loopback.storage().resolve_approval_as(
             &approval_id,
             false,
             Some("Failed to create approval decision session".to_string()),
+            Some(APPROVAL_AGENT),
         ).await {
             tracing::warn!("Failed to resolve approval {} after decision session creation failure: {}", approval_id, e);
         }
@@ -602,7 +610,7 @@ async fn handle_tool_approval<P: HubContext>(
         format!("Denied by orcha approval agent: {}", decision.trim())
     };
 
-    match loopback.storage().resolve_approval(&approval_id, approved, Some(message)).await {
+    match loopback.storage().resolve_approval_as(&approval_id, approved, Some(message), Some(APPROVAL_AGENT)).await {
         Ok(_) => {
             tracing::info!("Tool use {} {} for session {}", tool_use_id, if approved { "approved" } else { "denied" }, orcha_session_id);
         }
@@ -951,11 +959,12 @@ async fn handle_agent_spawn_request<P: HubContext>(
     };
 
     // Parse model
-    let model = match session.model.as_str() {
-        "opus" => Model::Opus,
-        "sonnet" => Model::Sonnet,
-        "haiku" => Model::Haiku,
-        _ => Model::Sonnet,
+    let model = match parse_model(&session.model) {
+        Ok(m) => m,
+        Err(e) => {
+            tracing::error!("Cannot spawn helper agent: {}", e);
+            return;
+        }
     };
 
     // Create ClaudeCode session for this helper agent
@@ -1,11 +1,11 @@
 use crate::activations::lattice::{LatticeStorage, NodeSpec, NodeStatus};
-use crate::activations::orcha::OrchaNodeKind;
+use crate::activations::orcha::{OrchaNodeKind, Scheduler, Usage};
 use async_stream::stream;
 use futures::Stream;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 
 use super::storage::PmStorage;
@@ -20,6 +20,12 @@ pub(super) struct PmTicketStatus {
     pub kind: String,
     pub label: Option<String>,
     pub child_graph_id: Option<String>,
+    /// Loop pass the ticket is on — `0` unless a `Loop` node has re-run it.
+    #[serde(default)]
+    pub iteration: u32,
+    /// What the ticket's agent runs have spent so far (`graph_status` only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -29,6 +35,8 @@ pub(super) enum PmGraphStatusResult {
         graph_id: String,
         graph_status: String,
         tickets: Vec<PmTicketStatus>,
+        /// Total spend of the graph's nodes (and child graphs, when recursive)
+        usage: Usage,
     },
     Err {
         message: String,
@@ -108,15 +116,25 @@ pub(super) enum PmListGraphsResult {
 
 // ─── Pm activation ────────────────────────────────────────────────────────────
 
+// `pm_storage` names the storage, not the struct
+#[allow(clippy::struct_field_names)]
 #[derive(Clone)]
 pub struct Pm {
     pm_storage: Arc<PmStorage>,
     lattice_storage: Arc<LatticeStorage>,
+    scheduler: Option<Scheduler>,
 }
 
 impl Pm {
     pub const fn new(pm_storage: Arc<PmStorage>, lattice_storage: Arc<LatticeStorage>) -> Self {
-        Self { pm_storage, lattice_storage }
+        Self { pm_storage, lattice_storage, scheduler: None }
+    }
+
+    /// Report Ready tickets waiting on `scheduler` for a slot as `queued`.
+    #[must_use]
+    pub fn with_scheduler(mut self, scheduler: Scheduler) -> Self {
+        self.scheduler = Some(scheduler);
+        self
     }
 
     /// Save ticket→node mappings for a graph (called by Orcha after build).
@@ -171,10 +189,48 @@ impl Pm {
             tracing::warn!("log_node_event failed for {}/{}: {}", graph_id, node_id, e);
         }
     }
+
+    /// Spend of `graph_id` and every graph below it — planned child graphs
+    /// and sub-graphs — from the `usage` entries in the node logs.
+    pub async fn tree_usage(&self, graph_id: &str) -> Result<Usage, String> {
+        tree_usage(&self.pm_storage, &self.lattice_storage, graph_id).await
+    }
 }
 
 // ─── Helpers ─────────────────────────────────────────────────────────────────
 
+/// Spend of `graph_id` and every graph below it.
+async fn tree_usage(
+    pm_storage: &PmStorage,
+    lattice_storage: &LatticeStorage,
+    graph_id: &str,
+) -> Result<Usage, String> {
+    let mut total = Usage::default();
+    let mut to_visit = VecDeque::from([graph_id.to_string()]);
+    while let Some(gid) = to_visit.pop_front() {
+        for usage in pm_storage.get_usage(&gid).await?.into_values() {
+            total += usage;
+        }
+        to_visit.extend(lattice_storage.get_child_graphs(&gid).await?.into_iter().map(|g| g.id));
+    }
+    Ok(total)
+}
+
+/// Node ids of `graph_id` that are Ready but waiting for a concurrency slot.
+fn queued_nodes(scheduler: Option<&Scheduler>, graph_id: &str) -> HashSet<String> {
+    scheduler.map(|s| s.queued(graph_id).into_iter().collect()).unwrap_or_default()
+}
+
+/// Ticket status: the node status, except Ready nodes still waiting for a
+/// concurrency slot are `queued`.
+fn ticket_status_str(status: &NodeStatus, node_id: &str, queued: &HashSet<String>) -> &'static str {
+    if *status == NodeStatus::Ready && queued.contains(node_id) {
+        "queued"
+    } else {
+        node_status_str(status)
+    }
+}
+
 const fn node_status_str(status: &NodeStatus) -> &'static str {
     match status {
         NodeStatus::Pending => "pending",
@@ -215,6 +271,9 @@ fn extract_kind_and_label(spec: &NodeSpec) -> (String, Option<String>) {
         NodeSpec::Gather { .. } => ("gather".to_string(), None),
         NodeSpec::Scatter { .. } => ("scatter".to_string(), None),
         NodeSpec::SubGraph { .. } => ("subgraph".to_string(), None),
+        NodeSpec::Loop { max_iterations, .. } => {
+            ("loop".to_string(), Some(format!("up to {max_iterations} iterations")))
+        }
     }
 }
 
@@ -225,9 +284,12 @@ version = "1.0.0",
 description = "Project management view of orcha graph execution in ticket vocabulary")]
 impl Pm {
     /// Get the status of all tickets in a graph.
+    ///
+    /// Each ticket carries the tokens and dollars its agent runs have spent,
+    /// and the result totals them for the graph.
     #[plexus_macros::method(params(
         graph_id   = "The lattice graph ID returned by build_tickets or run_tickets",
-        recursive  = "Optional: when true, include child_graph_id from completed node outputs (default false)"
+        recursive  = "Optional: when true, include child_graph_id from completed node outputs and count child graphs' spend in usage (default false)"
     ))]
     async fn graph_status(
         &self,
@@ -236,6 +298,7 @@ impl Pm {
     ) -> impl Stream<Item = PmGraphStatusResult> + Send + 'static {
         let pm_storage = self.pm_storage.clone();
         let lattice_storage = self.lattice_storage.clone();
+        let scheduler = self.scheduler.clone();
 
         stream! {
             let ticket_map = match pm_storage.get_ticket_map(&graph_id).await {
@@ -243,6 +306,12 @@ impl Pm {
                 Err(e) => { yield PmGraphStatusResult::Err { message: e }; return; }
             };
 
+            let node_usage = match pm_storage.get_usage(&graph_id).await {
+                Ok(u) => u,
+                Err(e) => { yield PmGraphStatusResult::Err { message: e }; return; }
+            };
+
+            let queued = queued_nodes(scheduler.as_ref(), &graph_id);
             let mut tickets = Vec::new();
             let mut has_pending = false;
             let mut has_ready = false;
@@ -261,7 +330,9 @@ impl Pm {
                             NodeStatus::Complete => {}
                         }
                         let (kind, label) = extract_kind_and_label(&node.spec);
-                        let child_graph_id = if recursive.unwrap_or(false) && node.status == NodeStatus::Complete {
+                        let child_graph_id = if let NodeSpec::SubGraph { graph_id: child } = &node.spec {
+                            recursive.unwrap_or(false).then(|| child.clone())
+                        } else if recursive.unwrap_or(false) && node.status == NodeStatus::Complete {
                             node.output.as_ref().and_then(|o| {
                                 if let crate::activations::lattice::NodeOutput::Single(token) = o {
                                     if let Some(crate::activations::lattice::TokenPayload::Data { value }) = &token.payload {
@@ -272,13 +343,24 @@ impl Pm {
                         } else {
                             None
                         };
+                        // A plan or sub-graph ticket also owns what its child graph spent
+                        let mut usage = node_usage.get(node_id).copied();
+                        if let Some(child) = &child_graph_id {
+                            match tree_usage(&pm_storage, &lattice_storage, child).await {
+                                Ok(spent) if spent != Usage::default() => *usage.get_or_insert_default() += spent,
+                                Ok(_) => {}
+                                Err(e) => { yield PmGraphStatusResult::Err { message: e }; return; }
+                            }
+                        }
                         tickets.push(PmTicketStatus {
                             ticket_id: ticket_id.clone(),
                             node_id: node_id.clone(),
-                            status: node_status_str(&node.status).to_string(),
+                            status: ticket_status_str(&node.status, node_id, &queued).to_string(),
                             kind,
                             label,
                             child_graph_id,
+                            iteration: node.iteration,
+                            usage,
                         });
                     }
                     Err(e) => {
@@ -302,15 +384,30 @@ impl Pm {
                 "pending"
             };
 
+            let usage = if recursive.unwrap_or(false) {
+                match tree_usage(&pm_storage, &lattice_storage, &graph_id).await {
+                    Ok(u) => u,
+                    Err(e) => { yield PmGraphStatusResult::Err { message: e }; return; }
+                }
+            } else {
+                let mut total = Usage::default();
+                for spent in node_usage.into_values() {
+                    total += spent;
+                }
+                total
+            };
+
             yield PmGraphStatusResult::Ok {
                 graph_id,
                 graph_status: graph_status.to_string(),
                 tickets,
+                usage,
             };
         }
     }
 
-    /// Get tickets that are ready or running (next actionable items).
+    /// Get tickets that are ready, queued for a concurrency slot, or running
+    /// (next actionable items).
     #[plexus_macros::method(params(
         graph_id = "The lattice graph ID returned by build_tickets or run_tickets"
     ))]
@@ -320,6 +417,7 @@ impl Pm {
     ) -> impl Stream<Item = PmWhatNextResult> + Send + 'static {
         let pm_storage = self.pm_storage.clone();
         let lattice_storage = self.lattice_storage.clone();
+        let scheduler = self.scheduler.clone();
 
         stream! {
             let ticket_map = match pm_storage.get_ticket_map(&graph_id).await {
@@ -327,6 +425,7 @@ impl Pm {
                 Err(e) => { yield PmWhatNextResult::Err { message: e }; return; }
             };
 
+            let queued = queued_nodes(scheduler.as_ref(), &graph_id);
             let mut tickets = Vec::new();
             for (ticket_id, node_id) in &ticket_map {
                 match lattice_storage.get_node(node_id).await {
@@ -336,10 +435,12 @@ impl Pm {
                             tickets.push(PmTicketStatus {
                                 ticket_id: ticket_id.clone(),
                                 node_id: node_id.clone(),
-                                status: node_status_str(&node.status).to_string(),
+                                status: ticket_status_str(&node.status, node_id, &queued).to_string(),
                                 kind,
                                 label,
                                 child_graph_id: None,
+                                iteration: node.iteration,
+                                usage: None,
                             });
                         }
                     }
@@ -392,12 +493,16 @@ impl Pm {
                 .map(|o| serde_json::to_value(o).unwrap_or(Value::Null));
             let error = node.error.clone();
 
-            let child_graph_id = output.as_ref()
-                .and_then(|o| o.get("payload"))
-                .and_then(|p| p.get("value"))
-                .and_then(|v| v.get("child_graph_id"))
-                .and_then(|id| id.as_str())
-                .map(std::string::ToString::to_string);
+            let child_graph_id = if let NodeSpec::SubGraph { graph_id: child } = &node.spec {
+                Some(child.clone())
+            } else {
+                output.as_ref()
+                    .and_then(|o| o.get("payload"))
+                    .and_then(|p| p.get("value"))
+                    .and_then(|v| v.get("child_graph_id"))
+                    .and_then(|id| id.as_str())
+                    .map(std::string::ToString::to_string)
+            };
 
             match &node.spec {
                 NodeSpec::Task { data, .. } => {
@@ -533,6 +638,8 @@ impl Pm {
                     kind,
                     label,
                     child_graph_id: None,
+                    iteration: pred_node.iteration,
+                    usage: None,
                 });
             }
 
@@ -1,3 +1,4 @@
+use crate::activations::orcha::Usage;
 use crate::activations::storage::{activation_db_path, init_sqlite_pool};
 use sqlx::{sqlite::SqlitePool, Row};
 use std::collections::HashMap;
@@ -218,7 +219,7 @@ impl PmStorage {
     /// Append a single log entry for a node execution event.
     ///
     /// `event_type` is one of: "prompt", "start", "`tool_use`", "`tool_result`",
-    /// "complete", "error", "passthrough", "outcome".
+    /// "complete", "usage", "error", "passthrough", "outcome".
     /// `event_data` is a JSON string.
     pub async fn append_node_log(
         &self,
@@ -274,6 +275,28 @@ impl PmStorage {
             .collect();
         Ok(entries)
     }
+
+    /// Total `usage` entries logged per node of a graph, across every run of
+    /// the node (retries and loop passes included).
+    pub async fn get_usage(&self, graph_id: &str) -> Result<HashMap<String, Usage>, String> {
+        let rows = sqlx::query(
+            "SELECT node_id, event_data FROM orcha_node_logs \
+             WHERE graph_id = ? AND event_type = 'usage'",
+        )
+        .bind(graph_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch node usage: {e}"))?;
+
+        let mut usage: HashMap<String, Usage> = HashMap::new();
+        for row in rows {
+            let data: String = row.get("event_data");
+            let entry: Usage = serde_json::from_str(&data)
+                .map_err(|e| format!("Failed to parse usage entry: {e}"))?;
+            *usage.entry(row.get("node_id")).or_default() += entry;
+        }
+        Ok(usage)
+    }
 }
 
 /// A single entry in the node execution log.
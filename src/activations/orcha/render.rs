@@ -0,0 +1,162 @@
+//! Orcha's pictures of graphs: nodes named by ticket id and orcha kind, plan
+//! nodes attached to the child graphs they built, and pre-run pictures drawn
+//! straight from compiled tickets.
+
+use super::pm::Pm;
+use super::ticket_compiler::CompiledGraph;
+use super::types::{GatherStrategy, OrchaNodeSpec};
+use crate::activations::lattice::{
+    edge_label, short_id, spec_kind, JoinType, LatticeStorage, NodeShape, NodeSpec, RenderEdge,
+    RenderGraph, RenderNode,
+};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Picture of a built graph and every child graph its plan nodes created.
+pub(super) async fn graph_picture(storage: &LatticeStorage, pm: &Pm, graph_id: &str) -> Result<RenderGraph, String> {
+    let mut picture = RenderGraph::load(storage, graph_id, true).await?;
+    name_nodes(storage, pm, &mut picture).await?;
+    Ok(picture)
+}
+
+/// Relabel nodes as `<ticket id>\n<kind>` (short node id when the graph has
+/// no ticket map) and link planned child graphs to their plan node.
+async fn name_nodes(storage: &LatticeStorage, pm: &Pm, picture: &mut RenderGraph) -> Result<(), String> {
+    let node_to_ticket: HashMap<String, String> = pm
+        .get_ticket_map(&picture.id)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(ticket, node)| (node, ticket))
+        .collect();
+    let specs: HashMap<String, NodeSpec> =
+        storage.get_nodes(&picture.id).await?.into_iter().map(|n| (n.id, n.spec)).collect();
+
+    for node in &mut picture.nodes {
+        let Some(spec) = specs.get(&node.id) else { continue };
+        let kind = node_kind(spec);
+        if kind == "plan" {
+            node.shape = NodeShape::Subroutine;
+        }
+        let name = node_to_ticket.get(&node.id).map_or_else(|| short_id(&node.id), String::as_str);
+        node.label = format!("{name}\n{kind}");
+    }
+
+    for child in &mut picture.children {
+        if child.parent_node.is_none() {
+            let metadata = storage.get_graph(&child.id).await?.metadata;
+            child.parent_node = metadata.get("plan_node_id").and_then(Value::as_str).map(str::to_string);
+        }
+        Box::pin(name_nodes(storage, pm, child)).await?;
+    }
+    Ok(())
+}
+
+/// The orcha node type stored in a task's data, else the lattice kind.
+fn node_kind(spec: &NodeSpec) -> String {
+    match spec {
+        NodeSpec::Task { data, .. } => data
+            .get("orcha_type")
+            .and_then(Value::as_str)
+            .map_or_else(|| spec_kind(spec), str::to_string),
+        _ => spec_kind(spec),
+    }
+}
+
+/// Picture of compiled tickets before a graph is built, for reviewing a
+/// ticket file. Nodes are ticket ids in execution order and carry no status.
+pub(super) fn compiled_picture(compiled: &CompiledGraph) -> RenderGraph {
+    let mut nodes = Vec::with_capacity(compiled.nodes.len());
+    let mut edges: Vec<RenderEdge> = compiled
+        .edges
+        .iter()
+        .map(|e| RenderEdge {
+            from: e.from.clone(),
+            to: e.to.clone(),
+            label: edge_label(None, e.predicate.as_ref()),
+            back_edge: false,
+        })
+        .collect();
+
+    for id in compiled.topological_order() {
+        let Some(def) = compiled.nodes.iter().find(|n| n.id == id) else { continue };
+        let (kind, shape) = match &def.spec {
+            OrchaNodeSpec::Task { .. } => ("task".to_string(), NodeShape::Box),
+            OrchaNodeSpec::Synthesize { .. } => ("synthesize".to_string(), NodeShape::Box),
+            OrchaNodeSpec::Validate { .. } => ("validate".to_string(), NodeShape::Box),
+            OrchaNodeSpec::Review { .. } => ("review".to_string(), NodeShape::Box),
+            OrchaNodeSpec::Plan { .. } => ("plan".to_string(), NodeShape::Subroutine),
+            OrchaNodeSpec::Gather { strategy: GatherStrategy::All } => ("gather all".to_string(), NodeShape::Diamond),
+            OrchaNodeSpec::Gather { strategy: GatherStrategy::First { n } } => {
+                (format!("gather first {n}"), NodeShape::Diamond)
+            }
+            OrchaNodeSpec::Loop { target, max_iterations, .. } => {
+                edges.push(RenderEdge {
+                    from: def.id.clone(),
+                    to: target.clone(),
+                    label: Some("repeat".to_string()),
+                    back_edge: true,
+                });
+                (format!("loop ×{max_iterations}"), NodeShape::Hexagon)
+            }
+        };
+        nodes.push(RenderNode {
+            label: format!("{}\n{kind}", def.id),
+            id: def.id.clone(),
+            shape,
+            status: None,
+            join_type: JoinType::All,
+        });
+    }
+
+    RenderGraph {
+        id: "tickets".to_string(),
+        title: "tickets (not started)".to_string(),
+        nodes,
+        edges,
+        children: Vec::new(),
+        parent_node: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activations::lattice::RenderFormat;
+    use crate::activations::orcha::ticket_compiler::compile_tickets;
+
+    const TICKETS: &str = "\
+# T01: Schema [agent]
+
+Design the schema.
+
+validate: cargo test -- schema
+
+# T02: Api [agent]
+
+Build the API.
+
+blocked_by: [T01]
+";
+
+    #[test]
+    fn test_compiled_picture_renders_tickets_in_order() {
+        let compiled = compile_tickets(TICKETS).unwrap();
+        let picture = compiled_picture(&compiled);
+        let ids: Vec<&str> = picture.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, ["T01", "T01-validate", "T01-validate-retry", "T02"]);
+
+        let dot = picture.render(RenderFormat::Dot);
+        assert!(dot.starts_with("digraph \"tickets\" {"));
+        assert!(dot.contains("\"T01\" [label=\"T01\\ntask\", shape=box];"));
+        assert!(dot.contains("\"T01-validate\" [label=\"T01-validate\\nvalidate\", shape=box];"));
+        assert!(dot.contains("\"T01-validate-retry\" [label=\"T01-validate-retry\\nloop ×4\", shape=hexagon];"));
+        assert!(dot.contains("\"T01-validate-retry\" -> \"T02\";"));
+
+        let mermaid = picture.render(RenderFormat::Mermaid);
+        assert!(mermaid.contains("flowchart TD"));
+        assert!(mermaid.contains("n0[\"T01<br/>task\"]"));
+        assert!(mermaid.contains("n2 --> n3"));
+        assert!(!mermaid.contains("classDef"), "pre-run pictures have no status");
+    }
+}
@@ -0,0 +1,371 @@
+//! Concurrency limits for graph execution.
+//!
+//! Every Orcha node that runs an agent or a shell command takes a slot from
+//! the `Scheduler` before it starts. Slots come from two pools — agents
+//! (`task`, `synthesize`, `plan`) and programs (`validate`) — each capped
+//! server-wide and, optionally, per graph. A node that can't get a slot waits
+//! in `Ready` (shown as `queued` by `pm`); when a slot frees up, the waiting
+//! node with the highest priority (then the longest wait) goes next.
+
+use crate::activations::lattice::NodeSpec;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::oneshot;
+
+/// Which pool a node draws its slot from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pool {
+    /// Claude-backed nodes: `task`, `synthesize`, `plan`
+    Agent,
+    /// Shell-backed nodes: `validate`
+    Prog,
+}
+
+impl Pool {
+    /// The pool and priority of an Orcha node, or `None` for nodes that don't
+    /// consume a slot (review gates, engine-internal nodes).
+    pub fn for_spec(spec: &NodeSpec) -> Option<(Self, i32)> {
+        let NodeSpec::Task { data, .. } = spec else { return None };
+        let pool = match data.get("orcha_type").and_then(Value::as_str)? {
+            "task" | "synthesize" | "plan" => Self::Agent,
+            "validate" => Self::Prog,
+            _ => return None,
+        };
+        let priority = data
+            .get("priority")
+            .and_then(Value::as_i64)
+            .and_then(|p| i32::try_from(p).ok())
+            .unwrap_or(0);
+        Some((pool, priority))
+    }
+}
+
+/// Maximum concurrent nodes per pool. `None` is unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConcurrencyLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_agents: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_progs: Option<usize>,
+}
+
+impl ConcurrencyLimits {
+    /// Server-wide limits from `PLEXUS_ORCHA_MAX_AGENTS` and
+    /// `PLEXUS_ORCHA_MAX_PROGS`. Unset, unparsable or zero means unlimited.
+    pub fn from_env() -> Self {
+        let var = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                .filter(|n| *n > 0)
+        };
+        Self {
+            max_agents: var("PLEXUS_ORCHA_MAX_AGENTS"),
+            max_progs: var("PLEXUS_ORCHA_MAX_PROGS"),
+        }
+    }
+
+    /// Per-graph limits from a graph's `_plexus_run_config`.
+    pub fn from_run_config(run_config: &Value) -> Self {
+        let limit = |key: &str| {
+            run_config
+                .get(key)
+                .and_then(Value::as_u64)
+                .and_then(|n| usize::try_from(n).ok())
+                .filter(|n| *n > 0)
+        };
+        Self {
+            max_agents: limit("max_agents"),
+            max_progs: limit("max_progs"),
+        }
+    }
+
+    const fn get(&self, pool: Pool) -> Option<usize> {
+        match pool {
+            Pool::Agent => self.max_agents,
+            Pool::Prog => self.max_progs,
+        }
+    }
+}
+
+/// Running counts for one scope (the server, or one graph).
+#[derive(Debug, Default, Clone, Copy)]
+struct Running {
+    agents: usize,
+    progs: usize,
+}
+
+impl Running {
+    const fn slot(&mut self, pool: Pool) -> &mut usize {
+        match pool {
+            Pool::Agent => &mut self.agents,
+            Pool::Prog => &mut self.progs,
+        }
+    }
+
+    const fn get(&self, pool: Pool) -> usize {
+        match pool {
+            Pool::Agent => self.agents,
+            Pool::Prog => self.progs,
+        }
+    }
+
+    const fn is_idle(&self) -> bool {
+        self.agents == 0 && self.progs == 0
+    }
+}
+
+struct GraphSlots {
+    limits: ConcurrencyLimits,
+    running: Running,
+}
+
+struct Waiter {
+    seq: u64,
+    graph_id: String,
+    node_id: String,
+    pool: Pool,
+    priority: i32,
+    grant: oneshot::Sender<()>,
+}
+
+#[derive(Default)]
+struct State {
+    running: Running,
+    graphs: HashMap<String, GraphSlots>,
+    queue: Vec<Waiter>,
+    next_seq: u64,
+}
+
+struct Inner {
+    limits: ConcurrencyLimits,
+    state: Mutex<State>,
+}
+
+impl Inner {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Grant slots to waiters while any fit, best priority first.
+    fn pump(&self, state: &mut State) {
+        loop {
+            let best = state
+                .queue
+                .iter()
+                .enumerate()
+                .filter(|(_, w)| {
+                    let fits = |limit: Option<usize>, running: usize| limit.is_none_or(|max| running < max);
+                    let graph = &state.graphs[&w.graph_id];
+                    fits(self.limits.get(w.pool), state.running.get(w.pool))
+                        && fits(graph.limits.get(w.pool), graph.running.get(w.pool))
+                })
+                .max_by(|(_, a), (_, b)| a.priority.cmp(&b.priority).then(b.seq.cmp(&a.seq)))
+                .map(|(i, _)| i);
+            let Some(i) = best else { return };
+
+            let waiter = state.queue.swap_remove(i);
+            *state.running.slot(waiter.pool) += 1;
+            if let Some(graph) = state.graphs.get_mut(&waiter.graph_id) {
+                *graph.running.slot(waiter.pool) += 1;
+            }
+            // A dropped receiver means the permit is being dropped too; its
+            // Drop sees the waiter is gone and returns the slot.
+            let _ = waiter.grant.send(());
+        }
+    }
+}
+
+/// Hands out concurrency slots to graph nodes. Cheap to clone; clones share
+/// the same pools.
+#[derive(Clone)]
+pub struct Scheduler {
+    inner: Arc<Inner>,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new(ConcurrencyLimits::default())
+    }
+}
+
+impl Scheduler {
+    /// A scheduler with the given server-wide limits.
+    pub fn new(limits: ConcurrencyLimits) -> Self {
+        Self {
+            inner: Arc::new(Inner { limits, state: Mutex::new(State::default()) }),
+        }
+    }
+
+    /// Server-wide limits.
+    pub fn limits(&self) -> ConcurrencyLimits {
+        self.inner.limits
+    }
+
+    /// Wait for a slot in `pool` for `node_id` of `graph_id`.
+    ///
+    /// `graph_limits` are the graph's own caps, applied on top of the
+    /// server-wide ones. The slot is held until the returned permit is
+    /// dropped; dropping the future while it waits leaves the queue.
+    pub async fn acquire(
+        &self,
+        graph_id: &str,
+        node_id: &str,
+        pool: Pool,
+        priority: i32,
+        graph_limits: ConcurrencyLimits,
+    ) -> SchedulerPermit {
+        let (grant, granted) = oneshot::channel();
+        let permit = {
+            let mut state = self.inner.lock();
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state
+                .graphs
+                .entry(graph_id.to_string())
+                .and_modify(|g| g.limits = graph_limits)
+                .or_insert(GraphSlots { limits: graph_limits, running: Running::default() });
+            state.queue.push(Waiter {
+                seq,
+                graph_id: graph_id.to_string(),
+                node_id: node_id.to_string(),
+                pool,
+                priority,
+                grant,
+            });
+            self.inner.pump(&mut state);
+            SchedulerPermit { inner: self.inner.clone(), seq, graph_id: graph_id.to_string(), pool }
+        };
+        let _ = granted.await;
+        permit
+    }
+
+    /// Node ids of `graph_id` waiting for a slot.
+    pub fn queued(&self, graph_id: &str) -> Vec<String> {
+        self.inner
+            .lock()
+            .queue
+            .iter()
+            .filter(|w| w.graph_id == graph_id)
+            .map(|w| w.node_id.clone())
+            .collect()
+    }
+}
+
+/// A node's claim on a slot. Dropping it frees the slot (or, if it was
+/// still waiting, leaves the queue).
+pub struct SchedulerPermit {
+    inner: Arc<Inner>,
+    seq: u64,
+    graph_id: String,
+    pool: Pool,
+}
+
+impl Drop for SchedulerPermit {
+    fn drop(&mut self) {
+        let mut state = self.inner.lock();
+        if let Some(i) = state.queue.iter().position(|w| w.seq == self.seq) {
+            state.queue.swap_remove(i);
+        } else {
+            *state.running.slot(self.pool) -= 1;
+            if let Some(graph) = state.graphs.get_mut(&self.graph_id) {
+                *graph.running.slot(self.pool) -= 1;
+            }
+        }
+        let graph_id = self.graph_id.as_str();
+        let graph_idle = state.graphs.get(graph_id).is_some_and(|g| g.running.is_idle())
+            && !state.queue.iter().any(|w| w.graph_id == graph_id);
+        if graph_idle {
+            state.graphs.remove(graph_id);
+        }
+        self.inner.pump(&mut state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const UNLIMITED: ConcurrencyLimits = ConcurrencyLimits { max_agents: None, max_progs: None };
+
+    /// Whether `fut` is still pending after giving the runtime a moment.
+    async fn still_waiting<F: std::future::Future>(fut: F) -> bool {
+        tokio::time::timeout(Duration::from_millis(50), fut).await.is_err()
+    }
+
+    #[tokio::test]
+    async fn test_server_limit_is_per_pool() {
+        let scheduler = Scheduler::new(ConcurrencyLimits { max_agents: Some(1), max_progs: Some(1) });
+        let agent = scheduler.acquire("g", "a1", Pool::Agent, 0, UNLIMITED).await;
+        // The prog pool is separate
+        let _prog = scheduler.acquire("g", "p1", Pool::Prog, 0, UNLIMITED).await;
+        assert!(still_waiting(scheduler.acquire("other", "a2", Pool::Agent, 0, UNLIMITED)).await);
+        // Abandoned waiters leave the queue
+        assert!(scheduler.queued("other").is_empty());
+
+        drop(agent);
+        let _a2 = scheduler.acquire("other", "a2", Pool::Agent, 0, UNLIMITED).await;
+    }
+
+    #[tokio::test]
+    async fn test_graph_limit_only_caps_that_graph() {
+        let scheduler = Scheduler::default();
+        let one = ConcurrencyLimits { max_agents: Some(1), max_progs: None };
+        let _a1 = scheduler.acquire("g1", "a1", Pool::Agent, 0, one).await;
+        assert!(still_waiting(scheduler.acquire("g1", "a2", Pool::Agent, 0, one)).await);
+        let _b1 = scheduler.acquire("g2", "b1", Pool::Agent, 0, one).await;
+        let _p1 = scheduler.acquire("g1", "p1", Pool::Prog, 0, one).await;
+    }
+
+    #[tokio::test]
+    async fn test_waiters_are_granted_by_priority_then_arrival() {
+        let scheduler = Scheduler::new(ConcurrencyLimits { max_agents: Some(1), max_progs: None });
+        let running = scheduler.acquire("g", "first", Pool::Agent, 0, UNLIMITED).await;
+
+        // The first poll enqueues a waiter, so polling in order fixes arrival order
+        let mut low = Box::pin(scheduler.acquire("g", "low", Pool::Agent, 0, UNLIMITED));
+        let mut high = Box::pin(scheduler.acquire("g", "high", Pool::Agent, 5, UNLIMITED));
+        let mut low_2 = Box::pin(scheduler.acquire("g", "low-2", Pool::Agent, 0, UNLIMITED));
+        for waiter in [&mut low, &mut high, &mut low_2] {
+            assert!(futures::poll!(waiter.as_mut()).is_pending());
+        }
+        let mut queued = scheduler.queued("g");
+        queued.sort();
+        assert_eq!(queued, ["high", "low", "low-2"]);
+
+        drop(running);
+        let high = high.await;
+        assert!(futures::poll!(low.as_mut()).is_pending());
+        drop(high);
+        let low = low.await;
+        assert!(futures::poll!(low_2.as_mut()).is_pending());
+        drop(low);
+        let _low_2 = low_2.await;
+    }
+
+    #[test]
+    fn test_pool_for_spec() {
+        let task = |data: Value| NodeSpec::Task { data, handle: None, timeout_secs: None };
+        assert_eq!(
+            Pool::for_spec(&task(serde_json::json!({"orcha_type": "task", "task": "x", "priority": 3}))),
+            Some((Pool::Agent, 3))
+        );
+        assert_eq!(
+            Pool::for_spec(&task(serde_json::json!({"orcha_type": "validate", "command": "true"}))),
+            Some((Pool::Prog, 0))
+        );
+        assert_eq!(Pool::for_spec(&task(serde_json::json!({"orcha_type": "review", "prompt": "ok?"}))), None);
+    }
+
+    #[test]
+    fn test_limits_from_run_config() {
+        let config = serde_json::json!({"max_agents": 2, "max_progs": 0});
+        assert_eq!(
+            ConcurrencyLimits::from_run_config(&config),
+            ConcurrencyLimits { max_agents: Some(2), max_progs: None }
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use super::types::{SessionId, SessionInfo, SessionState};
+use super::types::{GraphTemplate, GraphTemplateSummary, OrchaEdgeDef, OrchaNodeDef, SessionId, SessionInfo, SessionState};
 use crate::activations::storage::init_sqlite_pool;
 use crate::activation_db_path_from_module;
 use sqlx::{sqlite::SqlitePool, Row};
@@ -146,6 +146,26 @@ impl OrchaStorage {
             .await
             .map_err(|e| format!("Failed to create state index: {e}"))?;
 
+        // Saved ticket graphs, one row per (name, version)
+        sqlx::query(
+            r"
+            CREATE TABLE IF NOT EXISTS orcha_graph_templates (
+                name TEXT NOT NULL,
+                version INTEGER NOT NULL,
+                description TEXT,
+                parameters TEXT NOT NULL,
+                source TEXT NOT NULL,
+                nodes TEXT NOT NULL,
+                edges TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                PRIMARY KEY (name, version)
+            )
+            ",
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to create orcha_graph_templates table: {e}"))?;
+
         Ok(())
     }
 
@@ -501,6 +521,119 @@ impl OrchaStorage {
         Ok((active as u32, completed as u32, failed as u32))
     }
 
+    // ═══════════════════════════════════════════════════════════════════════
+    // Graph Templates
+    // ═══════════════════════════════════════════════════════════════════════
+
+    /// Save a compiled graph as the next version of template `name`.
+    ///
+    /// Returns the version assigned (1 for a new name).
+    pub async fn save_template(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        parameters: &[String],
+        source: &str,
+        nodes: &[OrchaNodeDef],
+        edges: &[OrchaEdgeDef],
+    ) -> Result<u32, String> {
+        let to_json = |v: serde_json::Result<String>| v.map_err(|e| format!("Failed to serialize template: {e}"));
+        let parameters_json = to_json(serde_json::to_string(parameters))?;
+        let nodes_json = to_json(serde_json::to_string(nodes))?;
+        let edges_json = to_json(serde_json::to_string(edges))?;
+
+        let version: i64 = sqlx::query_scalar(
+            r"
+            INSERT INTO orcha_graph_templates
+                (name, version, description, parameters, source, nodes, edges, created_at)
+            SELECT ?, COALESCE(MAX(version), 0) + 1, ?, ?, ?, ?, ?, ?
+            FROM orcha_graph_templates WHERE name = ?
+            RETURNING version
+            ",
+        )
+        .bind(name)
+        .bind(description)
+        .bind(&parameters_json)
+        .bind(source)
+        .bind(&nodes_json)
+        .bind(&edges_json)
+        .bind(chrono::Utc::now().timestamp())
+        .bind(name)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to save template: {e}"))?;
+
+        Ok(version as u32)
+    }
+
+    /// Get a template by name — the latest version unless `version` is given.
+    pub async fn get_template(&self, name: &str, version: Option<u32>) -> Result<GraphTemplate, String> {
+        let row = sqlx::query(
+            r"
+            SELECT * FROM orcha_graph_templates
+            WHERE name = ? AND (? IS NULL OR version = ?)
+            ORDER BY version DESC LIMIT 1
+            ",
+        )
+        .bind(name)
+        .bind(version)
+        .bind(version)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch template: {e}"))?;
+
+        let Some(row) = row else {
+            return Err(match version {
+                Some(v) => format!("Template not found: {name} v{v}"),
+                None => format!("Template not found: {name}"),
+            });
+        };
+
+        let parameters: String = row.get("parameters");
+        let nodes: String = row.get("nodes");
+        let edges: String = row.get("edges");
+        let parse = |e: serde_json::Error| format!("Failed to parse template {name}: {e}");
+        Ok(GraphTemplate {
+            name: row.get("name"),
+            version: row.get::<i64, _>("version") as u32,
+            description: row.get("description"),
+            parameters: serde_json::from_str(&parameters).map_err(parse)?,
+            source: row.get("source"),
+            nodes: serde_json::from_str(&nodes).map_err(parse)?,
+            edges: serde_json::from_str(&edges).map_err(parse)?,
+            created_at: row.get("created_at"),
+        })
+    }
+
+    /// List every saved template version, newest first within each name.
+    pub async fn list_templates(&self) -> Result<Vec<GraphTemplateSummary>, String> {
+        let rows = sqlx::query(
+            r"
+            SELECT name, version, description, parameters, json_array_length(nodes) AS node_count, created_at
+            FROM orcha_graph_templates
+            ORDER BY name, version DESC
+            ",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to list templates: {e}"))?;
+
+        rows.into_iter()
+            .map(|row| {
+                let parameters: String = row.get("parameters");
+                Ok(GraphTemplateSummary {
+                    name: row.get("name"),
+                    version: row.get::<i64, _>("version") as u32,
+                    description: row.get("description"),
+                    parameters: serde_json::from_str(&parameters)
+                        .map_err(|e| format!("Failed to parse template parameters: {e}"))?,
+                    node_count: row.get::<i64, _>("node_count") as usize,
+                    created_at: row.get("created_at"),
+                })
+            })
+            .collect()
+    }
+
     /// Helper: Convert row to `AgentInfo`
     fn row_to_agent(&self, row: sqlx::sqlite::SqliteRow) -> Result<super::types::AgentInfo, String> {
         let state_type: String = row.get("state_type");
@@ -0,0 +1,183 @@
+//! Graph templates — compiled ticket graphs with `{{placeholders}}`.
+//!
+//! A template is stored as the `CompiledGraph` that `ticket_compiler` produced,
+//! with placeholders left in the node text. Instantiating it renders every text
+//! field through mustache before the nodes are added via `GraphRuntime`.
+//!
+//! Values are substituted verbatim: the text ends up in prompts and shell
+//! commands, so mustache's HTML escaping is switched off by rewriting plain
+//! `{{name}}` tags to their `{{{name}}}` form before compiling.
+
+use super::types::{OrchaNodeDef, OrchaNodeSpec};
+use regex::{Captures, Regex};
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+/// Mustache tag: optional third brace, optional sigil, name.
+const TAG_PATTERN: &str = r"\{\{(\{)?\s*([&#^/!>]?)\s*([A-Za-z0-9_.\-]+)\s*\}\}(\})?";
+
+fn tag_regex() -> Regex {
+    Regex::new(TAG_PATTERN).expect("template tag pattern is valid")
+}
+
+/// Top-level parameter names used as values in `text` (sections and comments excluded).
+fn text_parameters(re: &Regex, text: &str, out: &mut BTreeSet<String>) {
+    for caps in re.captures_iter(text) {
+        let sigil = caps.get(2).map_or("", |m| m.as_str());
+        let name = &caps[3];
+        if !matches!(sigil, "" | "&") || name.starts_with('.') {
+            continue;
+        }
+        let root = name.split('.').next().unwrap_or(name);
+        out.insert(root.to_string());
+    }
+}
+
+/// The text fields of a node spec that are rendered as templates.
+fn spec_texts(spec: &OrchaNodeSpec) -> Vec<&str> {
+    match spec {
+        OrchaNodeSpec::Task { task, .. }
+        | OrchaNodeSpec::Synthesize { task, .. }
+        | OrchaNodeSpec::Plan { task } => vec![task],
+        OrchaNodeSpec::Validate { command, cwd, .. } => {
+            std::iter::once(command.as_str()).chain(cwd.as_deref()).collect()
+        }
+        OrchaNodeSpec::Review { prompt } => vec![prompt],
+        OrchaNodeSpec::Gather { .. } | OrchaNodeSpec::Loop { .. } => Vec::new(),
+    }
+}
+
+/// Sorted placeholder names referenced anywhere in `nodes`.
+pub(super) fn parameters(nodes: &[OrchaNodeDef]) -> Vec<String> {
+    let re = tag_regex();
+    let mut names = BTreeSet::new();
+    for node in nodes {
+        for text in spec_texts(&node.spec) {
+            text_parameters(&re, text, &mut names);
+        }
+    }
+    names.into_iter().collect()
+}
+
+/// Render a single template string against `params` without HTML escaping.
+pub(super) fn render_text(text: &str, params: &Value) -> Result<String, String> {
+    let unescaped = tag_regex().replace_all(text, |caps: &Captures| {
+        let plain = caps.get(1).is_none() && caps.get(4).is_none() && caps[2].is_empty();
+        if plain {
+            format!("{{{{{{{}}}}}}}", &caps[3])
+        } else {
+            caps[0].to_string()
+        }
+    });
+    mustache::compile_str(&unescaped)
+        .map_err(|e| format!("Invalid template: {e}"))?
+        .render_to_string(params)
+        .map_err(|e| format!("Failed to render template: {e}"))
+}
+
+fn render_spec(spec: &OrchaNodeSpec, params: &Value) -> Result<OrchaNodeSpec, String> {
+    let render = |s: &str| render_text(s, params);
+    Ok(match spec {
+        OrchaNodeSpec::Task { task, max_retries } => OrchaNodeSpec::Task {
+            task: render(task)?,
+            max_retries: *max_retries,
+        },
+        OrchaNodeSpec::Synthesize { task, max_retries } => OrchaNodeSpec::Synthesize {
+            task: render(task)?,
+            max_retries: *max_retries,
+        },
+        OrchaNodeSpec::Validate { command, cwd, max_retries } => OrchaNodeSpec::Validate {
+            command: render(command)?,
+            cwd: cwd.as_deref().map(render).transpose()?,
+            max_retries: *max_retries,
+        },
+        OrchaNodeSpec::Review { prompt } => OrchaNodeSpec::Review { prompt: render(prompt)? },
+        OrchaNodeSpec::Plan { task } => OrchaNodeSpec::Plan { task: render(task)? },
+        OrchaNodeSpec::Gather { .. } | OrchaNodeSpec::Loop { .. } => spec.clone(),
+    })
+}
+
+/// Render every node of a template.
+///
+/// `params` must be a JSON object supplying each name in `parameters(nodes)`;
+/// missing names are reported together rather than rendered as empty strings.
+pub(super) fn render(nodes: &[OrchaNodeDef], params: &Value) -> Result<Vec<OrchaNodeDef>, String> {
+    let Some(provided) = params.as_object() else {
+        return Err("Template params must be a JSON object".to_string());
+    };
+    let missing: Vec<String> = parameters(nodes)
+        .into_iter()
+        .filter(|name| !provided.contains_key(name))
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("Missing template parameters: {}", missing.join(", ")));
+    }
+
+    nodes
+        .iter()
+        .map(|node| {
+            Ok(OrchaNodeDef {
+                spec: render_spec(&node.spec, params)
+                    .map_err(|e| format!("Node '{}': {e}", node.id))?,
+                ..node.clone()
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activations::orcha::ticket_compiler::compile_tickets;
+    use serde_json::json;
+
+    const TEMPLATE: &str = "\
+# BUILD [prog]
+cd {{working_directory}} && cargo build -p {{crate}}
+
+# FIX [agent]
+blocked_by: [BUILD]
+validate: cargo test -p {{crate}}
+
+Fix {{{issue}}} in {{crate}}{{#strict}} without new warnings{{/strict}}.
+";
+
+    #[test]
+    fn test_parameters_collects_value_tags() {
+        let compiled = compile_tickets(TEMPLATE).unwrap();
+        assert_eq!(parameters(&compiled.nodes), vec!["crate", "issue", "working_directory"]);
+    }
+
+    #[test]
+    fn test_render_substitutes_without_escaping() {
+        let compiled = compile_tickets(TEMPLATE).unwrap();
+        let params = json!({
+            "working_directory": "/src/app",
+            "crate": "core",
+            "issue": "<Foo> & \"bar\"",
+            "strict": true,
+        });
+        let rendered = render(&compiled.nodes, &params).unwrap();
+
+        let spec = |id: &str| &rendered.iter().find(|n| n.id == id).unwrap().spec;
+        match spec("BUILD") {
+            OrchaNodeSpec::Validate { command, .. } => {
+                assert_eq!(command.trim(), "cd /src/app && cargo build -p core");
+            }
+            other => panic!("unexpected spec {other:?}"),
+        }
+        match spec("FIX") {
+            OrchaNodeSpec::Task { task, .. } => {
+                assert!(task.contains("Fix <Foo> & \"bar\" in core without new warnings."), "{task}");
+            }
+            other => panic!("unexpected spec {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_render_reports_missing_parameters() {
+        let compiled = compile_tickets(TEMPLATE).unwrap();
+        let err = render(&compiled.nodes, &json!({ "crate": "core" })).unwrap_err();
+        assert_eq!(err, "Missing template parameters: issue, working_directory");
+    }
+}
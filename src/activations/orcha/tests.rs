@@ -619,6 +619,54 @@ fn test_create_session_request_defaults() {
     assert!(!req.multi_agent);
 }
 
+// ═══════════════════════════════════════════════════════════════════════════
+// Graph Template Tests
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[tokio::test]
+async fn test_template_versions() {
+    let storage = create_test_storage().await;
+    let nodes = vec![OrchaNodeDef::new(
+        "A",
+        OrchaNodeSpec::Task { task: "Work in {{working_directory}}".to_string(), max_retries: None },
+    )];
+    let params = vec!["working_directory".to_string()];
+
+    let v1 = storage
+        .save_template("nightly", Some("first"), &params, "# A [agent]", &nodes, &[])
+        .await
+        .expect("Failed to save template");
+    let v2 = storage
+        .save_template("nightly", None, &params, "# A [agent]", &nodes, &[])
+        .await
+        .expect("Failed to save template");
+    storage
+        .save_template("other", None, &[], "", &[], &[])
+        .await
+        .expect("Failed to save template");
+    assert_eq!((v1, v2), (1, 2));
+
+    let latest = storage.get_template("nightly", None).await.expect("Failed to get template");
+    assert_eq!(latest.version, 2);
+    assert_eq!(latest.parameters, params);
+    assert_eq!(latest.nodes.len(), 1);
+
+    let first = storage.get_template("nightly", Some(1)).await.expect("Failed to get template");
+    assert_eq!(first.description.as_deref(), Some("first"));
+    assert!(storage.get_template("nightly", Some(3)).await.is_err());
+
+    let listed = storage.list_templates().await.expect("Failed to list templates");
+    let versions: Vec<(String, u32, usize)> = listed
+        .into_iter()
+        .map(|t| (t.name, t.version, t.node_count))
+        .collect();
+    assert_eq!(versions, vec![
+        ("nightly".to_string(), 2, 1),
+        ("nightly".to_string(), 1, 1),
+        ("other".to_string(), 1, 0),
+    ]);
+}
+
 // ═══════════════════════════════════════════════════════════════════════════
 // Concurrency Tests
 // ═══════════════════════════════════════════════════════════════════════════
@@ -1,5 +1,6 @@
-use super::types::{OrchaEdgeDef, OrchaNodeDef, OrchaNodeSpec};
-use std::collections::HashMap;
+use super::agents::AgentTarget;
+use super::types::{DiagnosticSeverity, OrchaEdgeDef, OrchaNodeDef, OrchaNodeSpec, TicketDiagnostic};
+use std::collections::{HashMap, HashSet};
 
 // ─── Public API ───────────────────────────────────────────────────────────────
 
@@ -8,6 +9,141 @@ pub struct CompiledGraph {
     pub edges: Vec<OrchaEdgeDef>,
 }
 
+impl CompiledGraph {
+    /// Node ids in an order that respects every edge (document order among
+    /// nodes that are ready together).
+    pub fn topological_order(&self) -> Vec<String> {
+        let mut pending: HashMap<&str, usize> = self.nodes.iter().map(|n| (n.id.as_str(), 0)).collect();
+        for edge in &self.edges {
+            if let Some(count) = pending.get_mut(edge.to.as_str()) {
+                *count += 1;
+            }
+        }
+        let mut order: Vec<String> = Vec::with_capacity(self.nodes.len());
+        let mut done: HashSet<&str> = HashSet::new();
+        while order.len() < self.nodes.len() {
+            let Some(next) = self
+                .nodes
+                .iter()
+                .find(|n| !done.contains(n.id.as_str()) && pending[n.id.as_str()] == 0)
+            else {
+                break;
+            };
+            done.insert(&next.id);
+            order.push(next.id.clone());
+            for edge in self.edges.iter().filter(|e| e.from == next.id) {
+                if let Some(count) = pending.get_mut(edge.to.as_str()) {
+                    *count -= 1;
+                }
+            }
+        }
+        order
+    }
+
+    /// Turn `max_retries` into bounded `Loop` nodes, so every retry is a
+    /// persisted pass of the graph rather than a loop inside the runner.
+    ///
+    /// - A validate node whose only input is an agent node that feeds nothing
+    ///   else closes a loop back to that agent: a failed check re-runs the
+    ///   agent, with the failure among its inputs, and then the check. It
+    ///   runs up to `max_retries` extra rounds (default 3), the larger of the
+    ///   two nodes' values if both set one.
+    /// - Any other task, synthesize or validate node with `max_retries` above
+    ///   0 loops back to itself for that many extra passes.
+    ///
+    /// Each loop is named `<id>-retry` and added after the other nodes. It takes
+    /// over its node's outgoing edges, so dependents wait until the retries are
+    /// settled. `max_retries` is cleared on every node once it's been lowered.
+    ///
+    /// Fails, changing nothing, if a loop id is already taken.
+    pub fn lower_retries(&mut self) -> Result<(), String> {
+        let spec_of = |id: &str| self.nodes.iter().find(|n| n.id == id).map(|n| &n.spec);
+        let mut loops: Vec<(String, String, u8)> = Vec::new();
+        let mut covered: HashSet<&str> = HashSet::new();
+        for node in &self.nodes {
+            let OrchaNodeSpec::Validate { max_retries, .. } = node.spec else { continue };
+            let inbound: Vec<&OrchaEdgeDef> = self.edges.iter().filter(|e| e.to == node.id).collect();
+            let agent = match inbound.as_slice() {
+                [edge] if edge.predicate.is_none() && self.edges.iter().filter(|e| e.from == edge.from).count() == 1 => {
+                    match spec_of(&edge.from) {
+                        Some(OrchaNodeSpec::Task { max_retries: agent_retries, .. }
+                            | OrchaNodeSpec::Synthesize { max_retries: agent_retries, .. }) => {
+                            Some((edge.from.as_str(), *agent_retries))
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+            if let Some((agent_id, agent_retries)) = agent {
+                covered.insert(agent_id);
+                let rounds = max_retries.unwrap_or(DEFAULT_FIXUP_ROUNDS).max(agent_retries.unwrap_or(0));
+                if rounds > 0 {
+                    loops.push((node.id.clone(), agent_id.to_string(), rounds));
+                }
+            } else if let Some(rounds) = max_retries.filter(|n| *n > 0) {
+                loops.push((node.id.clone(), node.id.clone(), rounds));
+            }
+        }
+        for node in &self.nodes {
+            let (OrchaNodeSpec::Task { max_retries: Some(rounds), .. }
+                | OrchaNodeSpec::Synthesize { max_retries: Some(rounds), .. }) = node.spec
+            else {
+                continue;
+            };
+            if rounds > 0 && !covered.contains(node.id.as_str()) {
+                loops.push((node.id.clone(), node.id.clone(), rounds));
+            }
+        }
+
+        for (anchor, _, _) in &loops {
+            let id = retry_id(anchor);
+            if self.nodes.iter().any(|n| n.id == id) {
+                return Err(format!("Node id '{id}' clashes with the retry loop generated for '{anchor}'"));
+            }
+        }
+
+        for (anchor, target, rounds) in loops {
+            let id = retry_id(&anchor);
+            for edge in self.edges.iter_mut().filter(|e| e.from == anchor) {
+                edge.from.clone_from(&id);
+            }
+            self.edges.push(OrchaEdgeDef { from: anchor.clone(), to: id.clone(), predicate: None });
+            let (priority, tags) = self
+                .nodes
+                .iter()
+                .find(|n| n.id == anchor)
+                .map(|n| (n.priority, n.tags.clone()))
+                .unwrap_or_default();
+            self.nodes.push(OrchaNodeDef {
+                priority,
+                tags,
+                ..OrchaNodeDef::new(
+                    id,
+                    OrchaNodeSpec::Loop { target, max_iterations: u32::from(rounds) + 1, repeat_if: None },
+                )
+            });
+        }
+        for node in &mut self.nodes {
+            if let OrchaNodeSpec::Task { max_retries, .. }
+            | OrchaNodeSpec::Synthesize { max_retries, .. }
+            | OrchaNodeSpec::Validate { max_retries, .. } = &mut node.spec
+            {
+                *max_retries = None;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Everything `lint_tickets` found in a ticket document.
+pub struct TicketLint {
+    /// Errors and warnings, in document order
+    pub diagnostics: Vec<TicketDiagnostic>,
+    /// The compiled graph, unless there are errors
+    pub graph: Option<CompiledGraph>,
+}
+
 /// Compile a Markdown plan document into a graph definition.
 ///
 /// # Ticket format
@@ -38,23 +174,117 @@ pub struct CompiledGraph {
 /// - `[agent]` — Claude runs the full body as a task prompt
 /// - `[agent/synthesize]` — like agent, prepends prior-work context from upstream tokens
 /// - `[prog]` — the body (minus metadata lines) is a shell command; exit 0 = pass
+/// - `[review]` — the body is shown to a human, who approves or denies
+/// - `[planner]` — an agent writes a ticket document from the body, which then runs
 ///
 /// # Body metadata (parsed and stripped from the prompt)
 ///
-/// - `blocked_by: [dep1, dep2]` — dependency list; also accepts `blocked_by: dep1, dep2`
+/// - `blocked_by: [dep1, dep2]` — dependency list; also accepts `blocked_by: dep1, dep2`.
+///   Every id must be a ticket in the document, and the dependencies must not form a cycle.
 /// - `unlocks: [...]` — informational only, ignored by the compiler
 /// - `validate: <shell command>` — auto-generates a sibling `<ID>-validate` node
+/// - `priority: <integer>` — scheduling priority when concurrency slots are
+///   scarce; higher runs first, default 0. Shared by the validate sibling.
+/// - `model: <target>` — agent backend for this ticket instead of the graph's
+///   model: `opus`, `sonnet`, `haiku`, `cone:<model id>` or `cmd:<program>`.
+///   Agent and planner tickets only; an unknown target is an error.
+/// - `max_retries: <0-255>` — retries for the ticket's node; for a ticket with
+///   `validate:`, the number of fix-up rounds instead (default 3), each one
+///   re-running the ticket with the failed check's output and then the check.
+///   Compiled into an `<ID>-retry` loop node (see `CompiledGraph::lower_retries`).
+///   Not for review or planner tickets.
+/// - `timeout: <duration>` — fail the ticket's node if it runs longer than
+///   `90`, `90s`, `15m` or `2h`.
+/// - `cwd: <directory>` — where the ticket's shell commands run: the `[prog]`
+///   body and the `validate:` command.
+/// - `tags: [a, b]` — free-form labels stored with the ticket's nodes.
 ///
 /// # Validate sibling rewriting
 ///
 /// If `UX-4 [agent]` has `validate: cargo test`, the compiler creates:
 /// - `UX-4` — Task node
 /// - `UX-4-validate` — Validate node running `cargo test`
-/// - Any ticket with `blocked_by: [UX-4]` is rewritten to depend on `UX-4-validate`
+/// - `UX-4-validate-retry` — Loop node re-running `UX-4` and the check while it fails
+/// - Any ticket with `blocked_by: [UX-4]` is rewritten to depend on the loop
 ///   so downstream work only starts after validation passes.
+///
+/// On failure, the error lists every error diagnostic `lint_tickets` reports.
 pub fn compile_tickets(input: &str) -> Result<CompiledGraph, String> {
-    let sections = parse_sections(input);
-    build_graph(sections)
+    let lint = lint_tickets(input);
+    lint.graph.ok_or_else(|| {
+        lint.diagnostics
+            .iter()
+            .filter(|d| d.severity == DiagnosticSeverity::Error)
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ")
+    })
+}
+
+/// Check a ticket document without building anything: every problem is
+/// collected (rather than stopping at the first), each with its location.
+pub fn lint_tickets(input: &str) -> TicketLint {
+    let mut diagnostics = Diagnostics::default();
+    let tickets: Vec<ParsedTicket> = parse_sections(input)
+        .into_iter()
+        .map(|section| parse_section_body(section, &mut diagnostics))
+        .collect();
+    check_ids(&tickets, &mut diagnostics);
+    check_dependencies(&tickets, &mut diagnostics);
+    let graph = build_graph(&tickets, &mut diagnostics);
+
+    let mut diagnostics = diagnostics.0;
+    diagnostics.sort_by_key(|d| (d.line, d.column));
+    let failed = diagnostics.iter().any(|d| d.severity == DiagnosticSeverity::Error);
+    TicketLint { diagnostics, graph: if failed { None } else { Some(graph) } }
+}
+
+// ─── Diagnostics ──────────────────────────────────────────────────────────────
+
+/// A location in the document: 1-based line, 1-based character columns.
+#[derive(Debug, Clone, Copy)]
+struct Span {
+    line: usize,
+    column: usize,
+    end_column: usize,
+}
+
+impl Span {
+    /// The span of `part`, which must be a subslice of `line`.
+    fn of(line_no: usize, line: &str, part: &str) -> Self {
+        let start = (part.as_ptr() as usize).saturating_sub(line.as_ptr() as usize).min(line.len());
+        let column = line[..start].chars().count() + 1;
+        Self { line: line_no, column, end_column: column + part.chars().count() }
+    }
+
+    /// The whole of `line`, minus surrounding whitespace.
+    fn line(line_no: usize, line: &str) -> Self {
+        Self::of(line_no, line, line.trim())
+    }
+}
+
+#[derive(Default)]
+struct Diagnostics(Vec<TicketDiagnostic>);
+
+impl Diagnostics {
+    fn push(&mut self, severity: DiagnosticSeverity, ticket_id: &str, span: Span, message: String) {
+        self.0.push(TicketDiagnostic {
+            severity,
+            ticket_id: Some(ticket_id.to_string()),
+            line: span.line,
+            column: span.column,
+            end_column: span.end_column,
+            message,
+        });
+    }
+
+    fn error(&mut self, ticket_id: &str, span: Span, message: impl Into<String>) {
+        self.push(DiagnosticSeverity::Error, ticket_id, span, message.into());
+    }
+
+    fn warning(&mut self, ticket_id: &str, span: Span, message: impl Into<String>) {
+        self.push(DiagnosticSeverity::Warning, ticket_id, span, message.into());
+    }
 }
 
 // ─── Section parsing ──────────────────────────────────────────────────────────
@@ -62,7 +292,10 @@ pub fn compile_tickets(input: &str) -> Result<CompiledGraph, String> {
 struct RawSection {
     id: String,
     type_tag: String,
-    body_lines: Vec<String>,
+    id_span: Span,
+    type_span: Span,
+    /// Body lines with their 1-based line numbers
+    body_lines: Vec<(usize, String)>,
 }
 
 /// Split the document into per-ticket sections.
@@ -73,32 +306,32 @@ struct RawSection {
 /// Lines before the first ticket heading are preamble and are discarded.
 fn parse_sections(input: &str) -> Vec<RawSection> {
     let mut sections: Vec<RawSection> = Vec::new();
-    let mut current: Option<(String, String, Vec<String>)> = None;
 
-    for line in input.lines() {
+    for (index, line) in input.lines().enumerate() {
+        let line_no = index + 1;
         if let Some((id, type_tag)) = try_parse_ticket_heading(line) {
-            if let Some((prev_id, prev_type, lines)) = current.take() {
-                sections.push(RawSection { id: prev_id, type_tag: prev_type, body_lines: lines });
-            }
-            current = Some((id, type_tag, Vec::new()));
-        } else if let Some((_, _, ref mut lines)) = current {
-            lines.push(line.to_string());
+            sections.push(RawSection {
+                id: id.to_string(),
+                type_tag: type_tag.to_string(),
+                id_span: Span::of(line_no, line, id),
+                type_span: Span::of(line_no, line, type_tag),
+                body_lines: Vec::new(),
+            });
+        } else if let Some(section) = sections.last_mut() {
+            section.body_lines.push((line_no, line.to_string()));
         }
         // else: preamble — skip
     }
-    if let Some((id, type_tag, lines)) = current {
-        sections.push(RawSection { id, type_tag, body_lines: lines });
-    }
     sections
 }
 
-/// Try to parse `# ID: Title [type]` → `Some((id, type_tag))`.
+/// Try to parse `# ID: Title [type]` → `Some((id, type_tag))`, as slices of `line`.
 ///
 /// Rules:
 /// - Must start with exactly `# ` (not `## `)
 /// - Must contain `[type]` somewhere on the line
 /// - ID is the first token before `:` or `[`, must be non-empty and contain no spaces
-fn try_parse_ticket_heading(line: &str) -> Option<(String, String)> {
+fn try_parse_ticket_heading(line: &str) -> Option<(&str, &str)> {
     // Exactly "# " — not "## " or deeper
     let rest = line.strip_prefix("# ")?;
     if rest.starts_with('#') {
@@ -110,19 +343,14 @@ fn try_parse_ticket_heading(line: &str) -> Option<(String, String)> {
     let bracket_open = rest.find('[')?;
     let after_open = &rest[bracket_open + 1..];
     let bracket_close = after_open.find(']')?;
-    let type_tag = after_open[..bracket_close].trim().to_string();
+    let type_tag = after_open[..bracket_close].trim();
     if type_tag.is_empty() {
         return None;
     }
 
     // ID: everything before the first '[' or ':', must be a single token (no spaces)
     let before_bracket = rest[..bracket_open].trim();
-    let id = before_bracket
-        .split(':')
-        .next()
-        .unwrap_or(before_bracket)
-        .trim()
-        .to_string();
+    let id = before_bracket.split(':').next().unwrap_or(before_bracket).trim();
 
     if id.is_empty() || id.contains(' ') {
         return None;
@@ -131,182 +359,400 @@ fn try_parse_ticket_heading(line: &str) -> Option<(String, String)> {
     Some((id, type_tag))
 }
 
-// ─── Graph building ───────────────────────────────────────────────────────────
+// ─── Body parsing ─────────────────────────────────────────────────────────────
 
 struct ParsedTicket {
     id: String,
     type_tag: String,
-    deps: Vec<String>,
+    id_span: Span,
+    type_span: Span,
+    /// `blocked_by` ids with their locations
+    deps: Vec<(String, Span)>,
     /// Task prompt for agent types (body minus metadata lines)
     task: Option<String>,
     /// Shell command for prog types (body minus metadata lines)
     command: Option<String>,
     /// Inline validate command (generates a sibling validate node)
     validate: Option<String>,
+    /// Scheduling priority (applies to the validate sibling too)
+    priority: Option<i32>,
+    /// Agent backend override (agent types only)
+    model: Option<String>,
+    /// Retries (applies to the validate sibling too)
+    max_retries: Option<u8>,
+    timeout_secs: Option<u64>,
+    /// Working directory for the ticket's shell commands
+    cwd: Option<String>,
+    tags: Vec<String>,
+    /// Where each metadata key was (last) set, for diagnostics that depend on the type
+    fields: HashMap<&'static str, Span>,
 }
 
-fn build_graph(sections: Vec<RawSection>) -> Result<CompiledGraph, String> {
-    let parsed: Vec<ParsedTicket> = sections
-        .into_iter()
-        .map(parse_section_body)
-        .collect::<Result<_, _>>()?;
+/// Metadata keys recognised at the start of a body line. `blocked-by` is an
+/// alias for `blocked_by`.
+const METADATA_KEYS: &[&str] =
+    &["blocked_by", "blocked-by", "unlocks", "validate", "priority", "model", "max_retries", "timeout", "cwd", "tags"];
+
+fn parse_section_body(section: RawSection, diagnostics: &mut Diagnostics) -> ParsedTicket {
+    let RawSection { id, type_tag, id_span, type_span, body_lines } = section;
+    let mut ticket = ParsedTicket {
+        id,
+        type_tag,
+        id_span,
+        type_span,
+        deps: Vec::new(),
+        task: None,
+        command: None,
+        validate: None,
+        priority: None,
+        model: None,
+        max_retries: None,
+        timeout_secs: None,
+        cwd: None,
+        tags: Vec::new(),
+        fields: HashMap::new(),
+    };
+    let mut prose_lines: Vec<&str> = Vec::new();
 
-    // completion_id maps a ticket id to the id of the last node in its chain.
-    // If a ticket has a validate sibling, anything blocked_by that ticket
-    // must wait for the validate sibling instead.
-    let mut completion_id: HashMap<String, String> = HashMap::new();
-    for t in &parsed {
-        let effective = if t.validate.is_some() {
-            format!("{}-validate", t.id)
-        } else {
-            t.id.clone()
-        };
-        completion_id.insert(t.id.clone(), effective);
-    }
+    for (line_no, line) in &body_lines {
+        let (line_no, line) = (*line_no, line.as_str());
+        let trimmed = line.trim();
 
-    let mut nodes: Vec<OrchaNodeDef> = Vec::new();
-    let mut edges: Vec<OrchaEdgeDef> = Vec::new();
+        // Skip comment lines
+        if trimmed.starts_with("<!--") || trimmed.starts_with("//") {
+            continue;
+        }
 
-    for t in &parsed {
-        // Primary node
-        let spec = match t.type_tag.as_str() {
-            "agent" => {
-                let task = t.task.clone().ok_or_else(|| {
-                    format!("Ticket '{}' [agent] has no body text", t.id)
-                })?;
-                OrchaNodeSpec::Task { task, max_retries: None }
-            }
-            "agent/synthesize" => {
-                let task = t.task.clone().ok_or_else(|| {
-                    format!("Ticket '{}' [agent/synthesize] has no body text", t.id)
-                })?;
-                OrchaNodeSpec::Synthesize { task, max_retries: None }
-            }
-            "prog" => {
-                let command = t.command.clone().ok_or_else(|| {
-                    format!("Ticket '{}' [prog] has no body text", t.id)
-                })?;
-                OrchaNodeSpec::Validate { command, cwd: None, max_retries: None }
-            }
-            "review" => {
-                let prompt = t.task.clone().ok_or_else(|| {
-                    format!("Ticket '{}' [review] has no body text", t.id)
-                })?;
-                OrchaNodeSpec::Review { prompt }
-            }
-            "planner" => {
-                let task = t.task.clone().ok_or_else(|| {
-                    format!("Ticket '{}' [planner] has no body text", t.id)
-                })?;
-                OrchaNodeSpec::Plan { task }
+        let Some((key, value)) = METADATA_KEYS
+            .iter()
+            .find_map(|key| trimmed.strip_prefix(key)?.strip_prefix(':').map(|v| (*key, v.trim())))
+        else {
+            prose_lines.push(line);
+            continue;
+        };
+        let key = if key == "blocked-by" { "blocked_by" } else { key };
+        let key_span = Span::line(line_no, line);
+        let value_span = Span::of(line_no, line, value);
+        if key != "unlocks" && ticket.fields.insert(key, key_span).is_some() {
+            diagnostics.warning(&ticket.id, key_span, format!("'{key}:' is set more than once; the last one wins"));
+        }
+        let id = ticket.id.as_str();
+
+        match key {
+            // blocked_by: [dep1, dep2]  or  blocked_by: dep1, dep2
+            "blocked_by" => {
+                ticket.deps = list_items(value)
+                    .map(|dep| (dep.to_string(), Span::of(line_no, line, dep)))
+                    .collect();
             }
-            other => {
-                return Err(format!(
-                    "Unknown ticket type [{}] in ticket '{}'",
-                    other, t.id
-                ))
+            // validate: <command>  (single line)
+            "validate" if value.is_empty() => {
+                diagnostics.warning(id, key_span, "'validate:' has no command and is ignored");
             }
-        };
-        nodes.push(OrchaNodeDef { id: t.id.clone(), spec });
-
-        // Validate sibling node
-        if let Some(ref cmd) = t.validate {
-            nodes.push(OrchaNodeDef {
-                id: format!("{}-validate", t.id),
-                spec: OrchaNodeSpec::Validate { command: cmd.clone(), cwd: None, max_retries: None },
-            });
-            // Edge: ticket → validate sibling
-            edges.push(OrchaEdgeDef {
-                from: t.id.clone(),
-                to: format!("{}-validate", t.id),
-            });
+            "validate" => ticket.validate = Some(value.to_string()),
+            "priority" => match value.parse() {
+                Ok(priority) => ticket.priority = Some(priority),
+                Err(_) => diagnostics.error(id, value_span, format!("Invalid priority '{value}' (expected an integer)")),
+            },
+            "model" => match AgentTarget::parse(value) {
+                Ok(target) => ticket.model = Some(target.to_string()),
+                Err(e) => diagnostics.error(id, value_span, e),
+            },
+            "max_retries" => match value.parse() {
+                Ok(retries) => ticket.max_retries = Some(retries),
+                Err(_) => diagnostics.error(
+                    id,
+                    value_span,
+                    format!("Invalid max_retries '{value}' (expected an integer from 0 to 255)"),
+                ),
+            },
+            "timeout" => match parse_timeout(value) {
+                Some(secs) => ticket.timeout_secs = Some(secs),
+                None => diagnostics.error(
+                    id,
+                    value_span,
+                    format!("Invalid timeout '{value}' (expected seconds, or a number with s, m or h)"),
+                ),
+            },
+            "cwd" if value.is_empty() => diagnostics.error(id, key_span, "'cwd:' needs a directory"),
+            "cwd" => ticket.cwd = Some(value.to_string()),
+            "tags" => ticket.tags = list_items(value).map(str::to_string).collect(),
+            // unlocks: — informational only, discard
+            _ => {}
         }
+    }
 
-        // Dependency edges.
-        // If the dep is a ticket in this document AND has a validate sibling,
-        // rewrite to point at the sibling.  Otherwise pass the id through as-is
-        // (allows referencing externally-built lattice nodes).
-        for dep in &t.deps {
-            let effective_dep = completion_id
-                .get(dep)
-                .cloned()
-                .unwrap_or_else(|| dep.clone());
-            edges.push(OrchaEdgeDef { from: effective_dep, to: t.id.clone() });
-        }
+    // Trim leading/trailing blank lines, preserve internal structure
+    let start = prose_lines.iter().position(|l| !l.trim().is_empty()).unwrap_or(prose_lines.len());
+    let end = prose_lines
+        .iter()
+        .rposition(|l| !l.trim().is_empty())
+        .map_or(0, |i| i + 1);
+    let body = if start < end { Some(prose_lines[start..end].join("\n")) } else { None };
+
+    if ticket.type_tag == "prog" {
+        ticket.command = body;
+    } else {
+        ticket.task = body;
     }
+    ticket
+}
 
-    Ok(CompiledGraph { nodes, edges })
+/// Items of `[a, b]` or `a, b`, as slices of `value`.
+fn list_items(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
 }
 
-// Returns `Result` despite never erroring today so that future validation
-// (e.g. rejecting malformed blocked_by lines) can fail without churning the
-// `.map(parse_section_body).collect::<Result<_, _>>()` call site.
-#[allow(clippy::unnecessary_wraps)]
-fn parse_section_body(section: RawSection) -> Result<ParsedTicket, String> {
-    let RawSection { id, type_tag, body_lines } = section;
+/// `90`, `90s`, `15m` or `2h` → seconds. Zero is rejected.
+fn parse_timeout(value: &str) -> Option<u64> {
+    let unit_at = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let scale = match value[unit_at..].trim() {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        _ => return None,
+    };
+    value[..unit_at].parse::<u64>().ok().filter(|n| *n > 0)?.checked_mul(scale)
+}
 
-    let mut deps: Vec<String> = Vec::new();
-    let mut validate: Option<String> = None;
-    let mut prose_lines: Vec<String> = Vec::new();
+// ─── Checks ───────────────────────────────────────────────────────────────────
 
-    for line in &body_lines {
-        let trimmed = line.trim();
+fn validate_id(id: &str) -> String {
+    format!("{id}-validate")
+}
 
-        // Skip comment lines
-        if trimmed.starts_with("<!--") || trimmed.starts_with("//") {
-            continue;
+/// Fix-up rounds a validate node runs when neither it nor its agent sets `max_retries`.
+const DEFAULT_FIXUP_ROUNDS: u8 = 3;
+
+fn retry_id(id: &str) -> String {
+    format!("{id}-retry")
+}
+
+/// Ticket ids must be unique, including against generated `-validate`
+/// siblings and `-retry` loops.
+fn check_ids(tickets: &[ParsedTicket], diagnostics: &mut Diagnostics) {
+    let mut seen: HashMap<&str, &ParsedTicket> = HashMap::new();
+    for t in tickets {
+        if let Some(first) = seen.get(t.id.as_str()) {
+            diagnostics.error(
+                &t.id,
+                t.id_span,
+                format!("Duplicate ticket id '{}' (first defined on line {})", t.id, first.id_span.line),
+            );
+        } else {
+            seen.insert(&t.id, t);
+        }
+    }
+    for t in tickets.iter().filter(|t| t.validate.is_some()) {
+        if let Some(clash) = seen.get(validate_id(&t.id).as_str()) {
+            diagnostics.error(
+                &clash.id,
+                clash.id_span,
+                format!("Ticket id '{}' clashes with the validate node generated for '{}'", clash.id, t.id),
+            );
+        }
+    }
+    for t in tickets {
+        let looped = if t.validate.is_some() { validate_id(&t.id) } else { t.id.clone() };
+        let retries = t.validate.is_some() || t.max_retries.is_some_and(|n| n > 0);
+        if let Some(clash) = seen.get(retry_id(&looped).as_str()).filter(|_| retries) {
+            diagnostics.error(
+                &clash.id,
+                clash.id_span,
+                format!("Ticket id '{}' clashes with the retry loop generated for '{looped}'", clash.id),
+            );
         }
+    }
+}
 
-        // blocked_by: [dep1, dep2]  or  blocked_by: dep1, dep2
-        if let Some(rest) = trimmed
-            .strip_prefix("blocked_by:")
-            .or_else(|| trimmed.strip_prefix("blocked-by:"))
-        {
-            let list = rest.trim().trim_start_matches('[').trim_end_matches(']');
-            deps = list
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect();
-            continue;
+/// Every `blocked_by` id must name a ticket (or its `-validate` sibling), and
+/// the dependencies must be acyclic. Each cycle is reported once, at the
+/// `blocked_by` entry of its first ticket in document order.
+fn check_dependencies(tickets: &[ParsedTicket], diagnostics: &mut Diagnostics) {
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for (i, t) in tickets.iter().enumerate() {
+        index.entry(t.id.clone()).or_insert(i);
+        if t.validate.is_some() {
+            index.entry(validate_id(&t.id)).or_insert(i);
         }
+    }
 
-        // validate: <command>  (single line)
-        if let Some(cmd) = trimmed.strip_prefix("validate:") {
-            let cmd = cmd.trim().to_string();
-            if !cmd.is_empty() {
-                validate = Some(cmd);
+    // deps[i] = (ticket index, span of the blocked_by entry)
+    let mut deps: Vec<Vec<(usize, Span)>> = vec![Vec::new(); tickets.len()];
+    for (i, t) in tickets.iter().enumerate() {
+        for (dep, span) in &t.deps {
+            match index.get(dep) {
+                Some(&j) => deps[i].push((j, *span)),
+                None => diagnostics.error(&t.id, *span, format!("blocked_by references unknown ticket '{dep}'")),
             }
-            continue;
         }
+    }
 
-        // unlocks: — informational only, discard
-        if trimmed.starts_with("unlocks:") {
+    // Peel off tickets whose dependencies are all resolved; what's left is
+    // on a cycle or downstream of one.
+    let mut resolved = vec![false; tickets.len()];
+    loop {
+        let ready: Vec<usize> = (0..tickets.len())
+            .filter(|&i| !resolved[i] && deps[i].iter().all(|(j, _)| resolved[*j]))
+            .collect();
+        if ready.is_empty() {
+            break;
+        }
+        for i in ready {
+            resolved[i] = true;
+        }
+    }
+
+    // Walk unresolved dependencies from each leftover ticket until one repeats.
+    let mut visited = vec![false; tickets.len()];
+    for start in 0..tickets.len() {
+        if resolved[start] || visited[start] {
             continue;
         }
+        let mut path: Vec<usize> = Vec::new();
+        let mut current = start;
+        let cycle_at = loop {
+            if visited[current] {
+                break path.iter().position(|&i| i == current);
+            }
+            visited[current] = true;
+            path.push(current);
+            match deps[current].iter().find(|(j, _)| !resolved[*j]) {
+                Some(&(next, _)) => current = next,
+                None => break None,
+            }
+        };
+        let Some(pos) = cycle_at else { continue };
+        let cycle = &path[pos..];
+        let first = cycle.iter().position(|&i| i == *cycle.iter().min().unwrap_or(&current)).unwrap_or(0);
+        let (head, next) = (cycle[first], cycle[(first + 1) % cycle.len()]);
+        let span = deps[head].iter().find(|(j, _)| *j == next).map_or(tickets[head].id_span, |(_, span)| *span);
+        let names: Vec<&str> = cycle[first..]
+            .iter()
+            .chain(&cycle[..first])
+            .chain(std::iter::once(&head))
+            .map(|&i| tickets[i].id.as_str())
+            .collect();
+        diagnostics.error(
+            &tickets[head].id,
+            span,
+            format!("Dependency cycle: {} (each ticket is blocked by the next)", names.join(" → ")),
+        );
+    }
+}
 
-        prose_lines.push(line.clone());
+// ─── Graph building ───────────────────────────────────────────────────────────
+
+fn build_graph(tickets: &[ParsedTicket], diagnostics: &mut Diagnostics) -> CompiledGraph {
+    // completion_id maps a ticket id to the id of the last node in its chain.
+    // If a ticket has a validate sibling, anything blocked_by that ticket
+    // must wait for the validate sibling instead.
+    let mut completion_id: HashMap<&str, String> = HashMap::new();
+    for t in tickets {
+        let effective = if t.validate.is_some() { validate_id(&t.id) } else { t.id.clone() };
+        completion_id.entry(&t.id).or_insert(effective);
     }
 
-    // Trim leading/trailing blank lines, preserve internal structure
-    let start = prose_lines.iter().position(|l| !l.trim().is_empty()).unwrap_or(prose_lines.len());
-    let end = prose_lines
-        .iter()
-        .rposition(|l| !l.trim().is_empty())
-        .map_or(0, |i| i + 1);
-    let body = if start < end { prose_lines[start..end].join("\n") } else { String::new() };
+    let mut nodes: Vec<OrchaNodeDef> = Vec::new();
+    let mut edges: Vec<OrchaEdgeDef> = Vec::new();
+
+    for t in tickets {
+        let Some(spec) = ticket_spec(t, diagnostics) else { continue };
+        check_fields(t, &spec, diagnostics);
+
+        nodes.push(OrchaNodeDef {
+            id: t.id.clone(),
+            spec,
+            priority: t.priority,
+            model: t.model.clone(),
+            timeout_secs: t.timeout_secs,
+            tags: t.tags.clone(),
+        });
+
+        // Validate sibling node
+        if let Some(ref cmd) = t.validate {
+            nodes.push(OrchaNodeDef {
+                id: validate_id(&t.id),
+                spec: OrchaNodeSpec::Validate { command: cmd.clone(), cwd: t.cwd.clone(), max_retries: t.max_retries },
+                priority: t.priority,
+                model: None,
+                timeout_secs: None,
+                tags: t.tags.clone(),
+            });
+            // Edge: ticket → validate sibling
+            edges.push(OrchaEdgeDef { from: t.id.clone(), to: validate_id(&t.id), predicate: None });
+        }
 
-    let (task, command) = match type_tag.as_str() {
-        "prog" => (None, if body.is_empty() { None } else { Some(body) }),
-        _ => (if body.is_empty() { None } else { Some(body) }, None),
+        // Dependency edges. If the dep is a ticket with a validate sibling,
+        // rewrite to point at the sibling.
+        for (dep, _) in &t.deps {
+            let effective_dep = completion_id.get(dep.as_str()).cloned().unwrap_or_else(|| dep.clone());
+            edges.push(OrchaEdgeDef { from: effective_dep, to: t.id.clone(), predicate: None });
+        }
+    }
+
+    let mut graph = CompiledGraph { nodes, edges };
+    // A loop id clash has already been reported by `check_ids`
+    let _ = graph.lower_retries();
+    graph
+}
+
+/// The primary node spec for a ticket, or `None` (with an error) if its type
+/// is unknown or it has no body.
+fn ticket_spec(t: &ParsedTicket, diagnostics: &mut Diagnostics) -> Option<OrchaNodeSpec> {
+    let known = ["agent", "agent/synthesize", "prog", "review", "planner"];
+    if !known.contains(&t.type_tag.as_str()) {
+        diagnostics.error(
+            &t.id,
+            t.type_span,
+            format!("Unknown ticket type [{}] (expected one of: {})", t.type_tag, known.join(", ")),
+        );
+        return None;
+    }
+    let Some(body) = t.task.clone().or_else(|| t.command.clone()) else {
+        diagnostics.error(&t.id, t.id_span, format!("Ticket '{}' [{}] has no body text", t.id, t.type_tag));
+        return None;
     };
+    Some(match t.type_tag.as_str() {
+        "agent" => OrchaNodeSpec::Task { task: body, max_retries: t.max_retries },
+        "agent/synthesize" => OrchaNodeSpec::Synthesize { task: body, max_retries: t.max_retries },
+        "prog" => OrchaNodeSpec::Validate { command: body, cwd: t.cwd.clone(), max_retries: t.max_retries },
+        "review" => OrchaNodeSpec::Review { prompt: body },
+        _ => OrchaNodeSpec::Plan { task: body },
+    })
+}
 
-    Ok(ParsedTicket { id, type_tag, deps, task, command, validate })
+/// Metadata that doesn't apply to the ticket's type.
+fn check_fields(t: &ParsedTicket, spec: &OrchaNodeSpec, diagnostics: &mut Diagnostics) {
+    let runs_agent = matches!(
+        spec,
+        OrchaNodeSpec::Task { .. } | OrchaNodeSpec::Synthesize { .. } | OrchaNodeSpec::Plan { .. }
+    );
+    if let Some(&span) = t.fields.get("model").filter(|_| !runs_agent) {
+        diagnostics.error(&t.id, span, format!("Ticket '{}' [{}] runs no agent, so it can't set a model", t.id, t.type_tag));
+    }
+    let retries = !matches!(spec, OrchaNodeSpec::Review { .. } | OrchaNodeSpec::Plan { .. }) || t.validate.is_some();
+    if let Some(&span) = t.fields.get("max_retries").filter(|_| !retries) {
+        diagnostics.error(&t.id, span, format!("[{}] tickets don't retry, so max_retries has no effect", t.type_tag));
+    }
+    let runs_command = matches!(spec, OrchaNodeSpec::Validate { .. }) || t.validate.is_some();
+    if let Some(&span) = t.fields.get("cwd").filter(|_| !runs_command) {
+        diagnostics.warning(
+            &t.id,
+            span,
+            "'cwd:' only applies to [prog] commands and validate: siblings; it has no effect here",
+        );
+    }
 }
 
 impl PartialEq for OrchaEdgeDef {
     fn eq(&self, other: &Self) -> bool {
-        self.from == other.from && self.to == other.to
+        self.from == other.from && self.to == other.to && self.predicate == other.predicate
     }
 }
 
@@ -364,11 +810,13 @@ blocked_by: []
 validate: cargo test -- feature_tests
 ";
         let g = compile_tickets(input).unwrap();
-        assert_eq!(g.nodes.len(), 2);
+        assert_eq!(g.nodes.len(), 3);
         assert!(g.nodes.iter().any(|n| n.id == "T01"));
         assert!(g.nodes.iter().any(|n| n.id == "T01-validate"));
-        assert_eq!(g.edges.len(), 1);
-        assert_eq!(g.edges[0], OrchaEdgeDef { from: "T01".into(), to: "T01-validate".into() });
+        assert_eq!(g.edges, [
+            OrchaEdgeDef { from: "T01".into(), to: "T01-validate".into(), predicate: None },
+            OrchaEdgeDef { from: "T01-validate".into(), to: "T01-validate-retry".into(), predicate: None },
+        ]);
     }
 
     #[test]
@@ -387,16 +835,18 @@ Do the second thing.
 blocked_by: [T01]
 ";
         let g = compile_tickets(input).unwrap();
-        // T01, T01-validate, T02
-        assert_eq!(g.nodes.len(), 3);
+        // T01, T01-validate, T02, T01-validate-retry
+        assert_eq!(g.nodes.len(), 4);
 
         let edge_pairs: Vec<(&str, &str)> =
             g.edges.iter().map(|e| (e.from.as_str(), e.to.as_str())).collect();
 
         // T01 → T01-validate (validate sibling edge)
         assert!(edge_pairs.contains(&("T01", "T01-validate")));
-        // T02 blocked_by T01 → rewritten to depend on T01-validate
-        assert!(edge_pairs.contains(&("T01-validate", "T02")));
+        // T02 blocked_by T01 → rewritten to depend on T01-validate, whose
+        // retry loop then takes the edge over
+        assert!(edge_pairs.contains(&("T01-validate-retry", "T02")));
+        assert!(!edge_pairs.contains(&("T01-validate", "T02")));
         // NOT T01 → T02 directly
         assert!(!edge_pairs.contains(&("T01", "T02")));
     }
@@ -404,24 +854,32 @@ blocked_by: [T01]
     #[test]
     fn test_prog_ticket() {
         let input = "\
+# T01 [agent]
+Write it.
+
 # validate-build [prog]
 
 blocked_by: [T01]
 cargo build --release 2>&1 | grep -c '^error' | xargs test 0 -eq
 ";
         let g = compile_tickets(input).unwrap();
-        assert_eq!(g.nodes.len(), 1);
-        match &g.nodes[0].spec {
+        // The check loops back to the agent it checks
+        assert_eq!(g.nodes.len(), 3);
+        match &g.nodes[1].spec {
             OrchaNodeSpec::Validate { command, .. } => {
                 assert!(command.contains("cargo build"));
             }
             _ => panic!("wrong spec"),
         }
+        assert!(matches!(&g.nodes[2].spec, OrchaNodeSpec::Loop { target, .. } if target == "T01"));
     }
 
     #[test]
     fn test_subsections_become_prose() {
         let input = "\
+# UX-2: Prepare [agent]
+Get ready.
+
 # UX-4: Move ir.json [agent]
 
 blocked_by: [UX-2]
@@ -437,8 +895,8 @@ The ir.json file is written into the wrong place.
 - ir.json lives in cache
 ";
         let g = compile_tickets(input).unwrap();
-        assert_eq!(g.nodes.len(), 1);
-        match &g.nodes[0].spec {
+        assert_eq!(g.nodes.len(), 2);
+        match &g.nodes[1].spec {
             OrchaNodeSpec::Task { task, .. } => {
                 assert!(task.contains("## Problem"));
                 assert!(task.contains("## Acceptance Criteria"));
@@ -486,4 +944,229 @@ blocked_by: [A, B]
         assert!(edge_pairs.contains(&("A", "C")));
         assert!(edge_pairs.contains(&("B", "C")));
     }
+
+    #[test]
+    fn test_priority_applies_to_validate_sibling() {
+        let input = "\
+# A [agent]
+priority: 10
+validate: cargo test
+
+Urgent work.
+
+# B [agent]
+Regular work.
+";
+        let g = compile_tickets(input).unwrap();
+        let priority = |id: &str| g.nodes.iter().find(|n| n.id == id).unwrap().priority;
+        assert_eq!(priority("A"), Some(10));
+        assert_eq!(priority("A-validate"), Some(10));
+        assert_eq!(priority("B"), None);
+        match &g.nodes[0].spec {
+            OrchaNodeSpec::Task { task, .. } => assert_eq!(task, "Urgent work."),
+            _ => panic!("wrong spec"),
+        }
+
+        let err = compile_tickets("# C [agent]\npriority: high\nWork.\n").err().unwrap();
+        assert!(err.contains("Invalid priority 'high'"), "{err}");
+    }
+
+    #[test]
+    fn test_model_override() {
+        let input = "\
+# A [agent]
+model: cone:gpt-4o-mini
+validate: cargo test
+
+Cheap work.
+
+# B [agent]
+Regular work.
+";
+        let g = compile_tickets(input).unwrap();
+        let model = |id: &str| g.nodes.iter().find(|n| n.id == id).unwrap().model.clone();
+        assert_eq!(model("A").as_deref(), Some("cone:gpt-4o-mini"));
+        assert_eq!(model("A-validate"), None);
+        assert_eq!(model("B"), None);
+        match &g.nodes[0].spec {
+            OrchaNodeSpec::Task { task, .. } => assert_eq!(task, "Cheap work."),
+            _ => panic!("wrong spec"),
+        }
+
+        let err = compile_tickets("# C [agent]\nmodel: sonet\nWork.\n").err().unwrap();
+        assert!(err.contains("Unknown model 'sonet'"), "{err}");
+        let err = compile_tickets("# D [prog]\nmodel: opus\ntrue\n").err().unwrap();
+        assert!(err.contains("can't set a model"), "{err}");
+    }
+
+    #[test]
+    fn test_ticket_fields() {
+        let input = "\
+# A [agent]
+max_retries: 2
+timeout: 15m
+cwd: crates/core
+tags: [backend, urgent]
+validate: cargo test
+
+Work.
+
+# B [prog]
+timeout: 90
+cwd: /tmp
+true
+";
+        let g = compile_tickets(input).unwrap();
+        let node = |id: &str| g.nodes.iter().find(|n| n.id == id).unwrap();
+        assert!(matches!(node("A").spec, OrchaNodeSpec::Task { max_retries: None, .. }));
+        assert_eq!(node("A").timeout_secs, Some(900));
+        assert_eq!(node("A").tags, vec!["backend", "urgent"]);
+        match &node("A-validate").spec {
+            OrchaNodeSpec::Validate { cwd, max_retries, .. } => {
+                assert_eq!(cwd.as_deref(), Some("crates/core"));
+                assert_eq!(*max_retries, None);
+            }
+            _ => panic!("wrong spec"),
+        }
+        assert_eq!(node("A-validate").timeout_secs, None);
+        match &node("A-validate-retry").spec {
+            OrchaNodeSpec::Loop { target, max_iterations, .. } => {
+                assert_eq!(target, "A");
+                assert_eq!(*max_iterations, 3);
+            }
+            _ => panic!("wrong spec"),
+        }
+        assert_eq!(node("A-validate-retry").tags, vec!["backend", "urgent"]);
+        match &node("B").spec {
+            OrchaNodeSpec::Validate { command, cwd, .. } => {
+                assert_eq!(command, "true");
+                assert_eq!(cwd.as_deref(), Some("/tmp"));
+            }
+            _ => panic!("wrong spec"),
+        }
+        assert_eq!(node("B").timeout_secs, Some(90));
+    }
+
+    #[test]
+    fn test_retries_become_loop_nodes() {
+        let input = "\
+# A [agent]
+max_retries: 2
+Work.
+
+# B [agent]
+blocked_by: [A]
+validate: cargo test
+max_retries: 0
+More work.
+
+# C [agent/synthesize]
+blocked_by: [A, B]
+Sum up.
+";
+        let g = compile_tickets(input).unwrap();
+        let ids: Vec<&str> = g.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, ["A", "B", "B-validate", "C", "A-retry"]);
+        match &g.nodes[4].spec {
+            OrchaNodeSpec::Loop { target, max_iterations, repeat_if } => {
+                assert_eq!(target, "A");
+                assert_eq!(*max_iterations, 3);
+                assert!(repeat_if.is_none());
+            }
+            _ => panic!("wrong spec"),
+        }
+        let edge_pairs: Vec<(&str, &str)> = g.edges.iter().map(|e| (e.from.as_str(), e.to.as_str())).collect();
+        assert_eq!(edge_pairs, [
+            ("B", "B-validate"),
+            ("A-retry", "B"),
+            ("A-retry", "C"),
+            ("B-validate", "C"),
+            ("A", "A-retry"),
+        ]);
+
+        let err = compile_tickets("# A [agent]\nvalidate: true\nWork.\n\n# A-validate-retry [agent]\nWork.\n")
+            .err()
+            .unwrap();
+        assert!(err.contains("clashes with the retry loop generated for 'A-validate'"), "{err}");
+    }
+
+    #[test]
+    fn test_lint_collects_all_diagnostics_with_spans() {
+        let input = "\
+# A [agent]
+timeout: soon
+blocked_by: [B, Z]
+Work.
+
+# B [agent]
+blocked_by: A
+cwd: src
+Work.
+
+# C [robot]
+Work.
+";
+        let lint = lint_tickets(input);
+        assert!(lint.graph.is_none());
+        let found: Vec<(DiagnosticSeverity, usize, usize, &str)> = lint
+            .diagnostics
+            .iter()
+            .map(|d| (d.severity, d.line, d.column, d.message.as_str()))
+            .collect();
+        assert_eq!(found.len(), 5, "{found:?}");
+        assert_eq!((found[0].1, found[0].2), (2, 10));
+        assert!(found[0].3.starts_with("Invalid timeout 'soon'"));
+        assert_eq!((found[1].1, found[1].2), (3, 14));
+        assert!(found[1].3.starts_with("Dependency cycle: A → B → A"));
+        assert_eq!((found[2].1, found[2].2), (3, 17));
+        assert_eq!(found[2].3, "blocked_by references unknown ticket 'Z'");
+        assert_eq!(found[3].0, DiagnosticSeverity::Warning);
+        assert_eq!(found[3].1, 8);
+        assert_eq!((found[4].1, found[4].2), (11, 6));
+        assert!(found[4].3.starts_with("Unknown ticket type [robot]"));
+
+        let err = compile_tickets(input).err().unwrap();
+        assert!(err.starts_with("line 2:10: Invalid timeout"), "{err}");
+        assert!(!err.contains("cwd"), "warnings are not compile errors: {err}");
+    }
+
+    #[test]
+    fn test_duplicate_ids_and_sibling_clash() {
+        let input = "\
+# A [agent]
+validate: true
+Work.
+
+# A-validate [agent]
+Work.
+
+# A [agent]
+Again.
+";
+        let lint = lint_tickets(input);
+        let messages: Vec<&str> = lint.diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages.len(), 2, "{messages:?}");
+        assert!(messages[0].contains("clashes with the validate node generated for 'A'"));
+        assert_eq!(messages[1], "Duplicate ticket id 'A' (first defined on line 1)");
+    }
+
+    #[test]
+    fn test_topological_order() {
+        let input = "\
+# C [agent]
+blocked_by: [B]
+Third.
+
+# A [agent]
+validate: true
+First.
+
+# B [agent]
+blocked_by: [A]
+Second.
+";
+        let lint = lint_tickets(input);
+        assert!(lint.diagnostics.is_empty());
+        assert_eq!(lint.graph.unwrap().topological_order(), vec!["A", "A-validate", "A-validate-retry", "B", "C"]);
+    }
 }
@@ -1,4 +1,4 @@
-pub use crate::activations::lattice::GatherStrategy;
+pub use crate::activations::lattice::{EdgePredicate, GatherStrategy, RenderFormat, RenderResult};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -38,9 +38,9 @@ impl From<String> for OrchaError {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "orcha_type", rename_all = "snake_case")]
 pub enum OrchaNodeKind {
-    Task { task: String, #[serde(default)] max_retries: Option<u8> },
-    Synthesize { task: String, #[serde(default)] max_retries: Option<u8> },
-    Validate { command: String, cwd: Option<String>, #[serde(default)] max_retries: Option<u8> },
+    Task { task: String },
+    Synthesize { task: String },
+    Validate { command: String, cwd: Option<String> },
     Review { prompt: String },
     Plan { task: String },
 }
@@ -275,6 +275,10 @@ pub struct ApproveRequest {
     pub approval_id: String,
     /// Optional message explaining approval decision
     pub message: Option<String>,
+    /// Replacement tool input to run instead of the requested one (a JSON
+    /// object, or a string holding one)
+    #[serde(default)]
+    pub updated_input: Option<serde_json::Value>,
 }
 
 /// Request to deny a pending request
@@ -531,6 +535,12 @@ pub enum OrchaEvent {
         graph_id: String,
     },
 
+    /// A failed graph was resumed via `retry_graph`; `reset_node_ids` will run again
+    GraphResumed {
+        graph_id: String,
+        reset_node_ids: Vec<String>,
+    },
+
     /// A node is ready and has been dispatched for execution
     NodeStarted {
         node_id: String,
@@ -563,7 +573,7 @@ pub enum OrchaEvent {
         percentage: Option<u32>,
     },
 
-    /// A validate node is retrying after a failed validation attempt
+    /// A retry loop is running its body again from `node_id` after a failed attempt
     Retrying {
         node_id: String,
         ticket_id: Option<String>,
@@ -584,6 +594,26 @@ pub enum OrchaEvent {
         graph_id: String,
     },
 
+    /// An agent run finished and reported what it spent
+    NodeUsage {
+        node_id: String,
+        ticket_id: Option<String>,
+        usage: Usage,
+    },
+
+    /// Spend across the graph tree crossed the run's `max_cost_usd` /
+    /// `max_tokens`; remaining nodes are cancelled and the graph fails
+    BudgetExceeded {
+        /// The graph whose run config set the budget
+        graph_id: String,
+        /// The node whose agent run crossed it
+        node_id: String,
+        ticket_id: Option<String>,
+        spent: Usage,
+        max_cost_usd: Option<f64>,
+        max_tokens: Option<u64>,
+    },
+
     /// A pending approval request is waiting for a human decision
     ApprovalPending {
         approval_id: String,
@@ -594,6 +624,50 @@ pub enum OrchaEvent {
     },
 }
 
+// ═══════════════════════════════════════════════════════════════════════════
+// Usage
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Tokens and dollars spent by agent runs. Backends that don't report a
+/// figure (cone models have no price, commands may lack token counts) add 0.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Usage {
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub cost_usd: f64,
+}
+
+impl Usage {
+    /// Read the `usage` an agent reported on completion; `None` when it
+    /// carries none of the fields.
+    pub fn from_agent(usage: &serde_json::Value) -> Option<Self> {
+        let field = |key: &str| usage.get(key).filter(|v| !v.is_null());
+        if ["input_tokens", "output_tokens", "cost_usd"].iter().all(|k| field(k).is_none()) {
+            return None;
+        }
+        Some(Self {
+            input_tokens: field("input_tokens").and_then(serde_json::Value::as_u64).unwrap_or(0),
+            output_tokens: field("output_tokens").and_then(serde_json::Value::as_u64).unwrap_or(0),
+            cost_usd: field("cost_usd").and_then(serde_json::Value::as_f64).unwrap_or(0.0),
+        })
+    }
+
+    pub const fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+impl std::ops::AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cost_usd += other.cost_usd;
+    }
+}
+
 // ═══════════════════════════════════════════════════════════════════════════
 // Validation Types
 // ═══════════════════════════════════════════════════════════════════════════
@@ -785,20 +859,157 @@ pub enum OrchaNodeSpec {
     Gather { strategy: GatherStrategy },
     Review { prompt: String },
     Plan { task: String },
+    /// Bounded loop back to `target` (an earlier `OrchaNodeDef.id`) — see lattice `NodeSpec::Loop`.
+    Loop {
+        target: String,
+        max_iterations: u32,
+        #[serde(default)]
+        repeat_if: Option<EdgePredicate>,
+    },
 }
 
 /// One node in an inline graph definition.
 /// `id` is a caller-supplied stable label used in `OrchaEdgeDef`.
+///
+/// `priority` orders nodes waiting for a concurrency slot: higher goes first,
+/// default 0. `timeout_secs` fails the node if it runs longer; `tags` are
+/// stored with the node for callers to group and filter by.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct OrchaNodeDef {
     pub id: String,
     pub spec: OrchaNodeSpec,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i32>,
+    /// Agent backend for this node, overriding the graph's `model`
+    /// (`opus`, `cone:<model id>`, `cmd:<program>`, …)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl OrchaNodeDef {
+    /// A node with no priority, model override, timeout or tags.
+    pub fn new(id: impl Into<String>, spec: OrchaNodeSpec) -> Self {
+        Self { id: id.into(), spec, priority: None, model: None, timeout_secs: None, tags: Vec::new() }
+    }
 }
 
 /// One edge in an inline graph definition.
 /// `from`/`to` reference OrchaNodeDef.id values.
+///
+/// `predicate`, if set, routes the `from` node's output only when it holds —
+/// e.g. `{"op":"ne","path":"/exit_code","value":0}`.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct OrchaEdgeDef {
     pub from: String,
     pub to: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub predicate: Option<EdgePredicate>,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Ticket Diagnostics
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticSeverity {
+    /// The document can't be compiled
+    Error,
+    /// Compiles, but probably not as intended
+    Warning,
+}
+
+/// A problem found in a ticket document, located by 1-based line and
+/// character columns (`end_column` is exclusive).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct TicketDiagnostic {
+    pub severity: DiagnosticSeverity,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ticket_id: Option<String>,
+    pub line: usize,
+    pub column: usize,
+    pub end_column: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for TicketDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LintTicketsResult {
+    /// The document compiles to `nodes` + `edges` (what `run_tickets` would
+    /// build); `order` lists the node ids in a valid execution order.
+    /// `diagnostics` holds any warnings.
+    Ok {
+        diagnostics: Vec<TicketDiagnostic>,
+        nodes: Vec<OrchaNodeDef>,
+        edges: Vec<OrchaEdgeDef>,
+        order: Vec<String>,
+    },
+    /// The document has errors; nothing would be built
+    Invalid { diagnostics: Vec<TicketDiagnostic> },
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Graph Templates
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// A compiled ticket graph saved for re-use.
+///
+/// Node text (task prompts, validate commands and cwds, review prompts) may
+/// contain `{{placeholders}}`, filled in by `run_template`. `model` and
+/// `working_directory` are always available to placeholders.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GraphTemplate {
+    pub name: String,
+    /// Starts at 1; every save under the same name adds a new version.
+    pub version: u32,
+    pub description: Option<String>,
+    /// Placeholder names referenced by the template, sorted.
+    pub parameters: Vec<String>,
+    /// The ticket markdown the template was compiled from.
+    pub source: String,
+    pub nodes: Vec<OrchaNodeDef>,
+    pub edges: Vec<OrchaEdgeDef>,
+    pub created_at: i64,
+}
+
+/// Listing entry for a saved template version.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GraphTemplateSummary {
+    pub name: String,
+    pub version: u32,
+    pub description: Option<String>,
+    pub parameters: Vec<String>,
+    pub node_count: usize,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SaveTemplateResult {
+    Ok { name: String, version: u32, parameters: Vec<String> },
+    Err { message: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GetTemplateResult {
+    Ok { template: GraphTemplate },
+    Err { message: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ListTemplatesResult {
+    Ok { templates: Vec<GraphTemplateSummary> },
+    Err { message: String },
 }
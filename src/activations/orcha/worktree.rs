@@ -0,0 +1,391 @@
+//! Per-ticket git worktree isolation for ticket graphs.
+//!
+//! With isolation on, every ticket's agent (and its `-validate` sibling) runs
+//! in its own worktree on its own branch, so parallel tickets can't clobber
+//! each other's edits. When the ticket's last node completes, the branch is
+//! merged (or rebased and fast-forwarded) back into the original checkout.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// pm node-log seq for the worktree record, ahead of the prompt at seq 0.
+pub(super) const WORKTREE_LOG_SEQ: i64 = -1;
+/// pm node-log seq for the integration result, after everything the node logged.
+pub(super) const INTEGRATE_LOG_SEQ: i64 = i64::MAX;
+
+/// Integrations into a checkout are serialized so two tickets finishing
+/// together don't race on its index and HEAD.
+static INTEGRATION_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// How a ticket branch is brought back into the original checkout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Integration {
+    /// `git merge --no-ff` the ticket branch
+    Merge,
+    /// Rebase the ticket branch onto the checkout's HEAD, then fast-forward
+    Rebase,
+}
+
+impl Integration {
+    /// Parse the `isolation` run parameter: `worktree` (merge back),
+    /// `worktree-rebase`, or `none` / absent for no isolation.
+    pub(super) fn parse(isolation: Option<&str>) -> Result<Option<Self>, String> {
+        match isolation {
+            None | Some("none") => Ok(None),
+            Some("worktree") => Ok(Some(Self::Merge)),
+            Some("worktree-rebase") => Ok(Some(Self::Rebase)),
+            Some(other) => Err(format!(
+                "Unknown isolation mode '{other}' (expected none, worktree or worktree-rebase)"
+            )),
+        }
+    }
+
+    /// The `isolation` value this mode is stored as in `_plexus_run_config`.
+    pub(super) const fn as_str(self) -> &'static str {
+        match self {
+            Self::Merge => "worktree",
+            Self::Rebase => "worktree-rebase",
+        }
+    }
+
+    /// Read the mode back from a graph's `_plexus_run_config`.
+    pub(super) fn from_run_config(run_config: &Value) -> Option<Self> {
+        Self::parse(run_config.get("isolation").and_then(Value::as_str))
+            .ok()
+            .flatten()
+    }
+}
+
+/// Which ticket worktree a node runs in, and whether it is the ticket's last
+/// node (so its completion integrates the branch).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct TicketIsolation {
+    pub(super) integration: Integration,
+    pub(super) ticket_id: String,
+    pub(super) integrate: bool,
+}
+
+impl TicketIsolation {
+    /// Isolation for `node_id`, or `None` for nodes that aren't tickets.
+    ///
+    /// A `<ticket>-validate` sibling shares its ticket's worktree and does the
+    /// integration; a ticket without a sibling integrates itself.
+    pub(super) fn for_node(
+        integration: Integration,
+        node_to_ticket: &HashMap<String, String>,
+        node_id: &str,
+    ) -> Option<Self> {
+        let ticket_id = node_to_ticket.get(node_id)?;
+        let is_ticket = |id: &str| node_to_ticket.values().any(|t| t == id);
+
+        if let Some(base) = ticket_id.strip_suffix("-validate").filter(|base| is_ticket(base)) {
+            return Some(Self {
+                integration,
+                ticket_id: base.to_string(),
+                integrate: true,
+            });
+        }
+        Some(Self {
+            integration,
+            ticket_id: ticket_id.clone(),
+            integrate: !is_ticket(&format!("{ticket_id}-validate")),
+        })
+    }
+}
+
+/// Result of bringing a ticket branch back into the original checkout.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(super) enum IntegrateOutcome {
+    /// The branch landed; `commit` is the checkout's new HEAD
+    Integrated { commit: String },
+    /// The ticket changed nothing
+    Unchanged,
+    /// Git couldn't integrate the branch; the worktree and branch are kept
+    Conflict { message: String },
+}
+
+/// A ticket's worktree: where it lives, its branch, and the checkout it
+/// came from.
+#[derive(Debug, Clone)]
+pub(super) struct TicketWorktree {
+    /// Top level of the original checkout
+    repo: PathBuf,
+    /// Top level of the worktree
+    path: PathBuf,
+    /// Where the agent runs: the worktree counterpart of the requested
+    /// working directory
+    pub(super) workdir: PathBuf,
+    pub(super) branch: String,
+}
+
+impl TicketWorktree {
+    /// Create (or, on resume, reuse) the worktree for `ticket_id`.
+    ///
+    /// Worktrees live under the repository's git dir, so they never show up
+    /// as untracked files in the checkout. The branch starts at the
+    /// checkout's current HEAD.
+    pub(super) async fn prepare(
+        working_directory: &str,
+        graph_id: &str,
+        ticket_id: &str,
+    ) -> Result<Self, String> {
+        let wd = Path::new(working_directory);
+        let repo = PathBuf::from(
+            git(wd, &["rev-parse", "--show-toplevel"])
+                .await
+                .map_err(|e| format!("Worktree isolation needs a git checkout at '{working_directory}': {e}"))?,
+        );
+        let git_dir = PathBuf::from(git(wd, &["rev-parse", "--path-format=absolute", "--git-common-dir"]).await?);
+
+        let ticket = sanitize(ticket_id);
+        let graph_short: String = graph_id.chars().take(8).collect();
+        let path = git_dir.join("orcha-worktrees").join(sanitize(graph_id)).join(&ticket);
+        let branch = format!("orcha/{graph_short}/{ticket}");
+
+        if !path.join(".git").exists() {
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .map_err(|e| format!("Failed to create '{}': {e}", parent.display()))?;
+            }
+            let path_str = path.to_string_lossy();
+            let branch_exists = git(&repo, &["rev-parse", "--verify", "--quiet", &format!("refs/heads/{branch}")])
+                .await
+                .is_ok();
+            if branch_exists {
+                git(&repo, &["worktree", "add", &path_str, &branch]).await?;
+            } else {
+                git(&repo, &["worktree", "add", "-b", &branch, &path_str, "HEAD"]).await?;
+            }
+        }
+
+        let relative = std::fs::canonicalize(wd)
+            .ok()
+            .and_then(|wd| wd.strip_prefix(&repo).ok().map(Path::to_path_buf))
+            .unwrap_or_default();
+        let workdir = path.join(relative);
+
+        Ok(Self { repo, path, workdir, branch })
+    }
+
+    pub(super) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(super) fn workdir_string(&self) -> String {
+        self.workdir.to_string_lossy().into_owned()
+    }
+
+    /// Commit whatever the ticket left in its worktree and integrate the
+    /// branch into the original checkout.
+    ///
+    /// On success the worktree and branch are removed. On conflict git is
+    /// restored to where it was and both are kept for a human to resolve.
+    pub(super) async fn integrate(&self, integration: Integration, message: &str) -> Result<IntegrateOutcome, String> {
+        git(&self.path, &["add", "-A"]).await?;
+        if git(&self.path, &["diff", "--cached", "--quiet"]).await.is_err() {
+            commit(&self.path, message).await?;
+        }
+
+        let _guard = INTEGRATION_LOCK.lock().await;
+
+        let range = format!("HEAD..{}", self.branch);
+        let ahead = git(&self.repo, &["rev-list", "--count", &range]).await?;
+        if ahead == "0" {
+            self.remove().await;
+            return Ok(IntegrateOutcome::Unchanged);
+        }
+
+        let conflict = match integration {
+            Integration::Merge => {
+                let merge_message = format!("Merge {}: {message}", self.branch);
+                match git_commit_cmd(&self.repo, &["merge", "--no-ff", "-m", &merge_message, &self.branch]).await {
+                    Ok(_) => None,
+                    Err(e) => {
+                        let _ = git(&self.repo, &["merge", "--abort"]).await;
+                        Some(e)
+                    }
+                }
+            }
+            Integration::Rebase => {
+                let head = git(&self.repo, &["rev-parse", "HEAD"]).await?;
+                match git_commit_cmd(&self.path, &["rebase", &head]).await {
+                    Err(e) => {
+                        let _ = git(&self.path, &["rebase", "--abort"]).await;
+                        Some(e)
+                    }
+                    Ok(_) => git(&self.repo, &["merge", "--ff-only", &self.branch]).await.err(),
+                }
+            }
+        };
+        if let Some(message) = conflict {
+            return Ok(IntegrateOutcome::Conflict { message });
+        }
+
+        let commit = git(&self.repo, &["rev-parse", "HEAD"]).await?;
+        self.remove().await;
+        Ok(IntegrateOutcome::Integrated { commit })
+    }
+
+    /// Best-effort removal of the worktree and its (now integrated) branch.
+    async fn remove(&self) {
+        let path = self.path.to_string_lossy();
+        if let Err(e) = git(&self.repo, &["worktree", "remove", "--force", &path]).await {
+            tracing::warn!("Failed to remove worktree {}: {}", path, e);
+        }
+        if let Err(e) = git(&self.repo, &["branch", "-D", &self.branch]).await {
+            tracing::warn!("Failed to delete branch {}: {}", self.branch, e);
+        }
+    }
+}
+
+/// Keep ids usable as path and ref components.
+fn sanitize(id: &str) -> String {
+    id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '-' })
+        .collect()
+}
+
+/// Run `git -C dir args…`, returning trimmed stdout, or stdout + stderr on failure.
+async fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    run_git(dir, &[], args).await
+}
+
+/// Like `git`, for commands that create commits. Falls back to a placeholder
+/// identity when the repository has none configured, instead of failing.
+async fn git_commit_cmd(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let identity: &[&str] = if git(dir, &["config", "user.email"]).await.is_ok() {
+        &[]
+    } else {
+        &["-c", "user.name=orcha", "-c", "user.email=orcha@localhost"]
+    };
+    run_git(dir, identity, args).await
+}
+
+async fn commit(dir: &Path, message: &str) -> Result<String, String> {
+    git_commit_cmd(dir, &["commit", "--no-verify", "-m", message]).await
+}
+
+async fn run_git(dir: &Path, config: &[&str], args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(config)
+        .args(args)
+        .env("GIT_EDITOR", "true")
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run git: {e}"))?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if output.status.success() {
+        Ok(stdout)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(format!("git {} failed: {}", args.join(" "), format!("{stdout}\n{stderr}").trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn init_repo() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for args in [
+            &["init", "-q", "-b", "main"][..],
+            &["config", "user.email", "test@example.com"],
+            &["config", "user.name", "Test"],
+        ] {
+            git(dir.path(), args).await.unwrap();
+        }
+        std::fs::write(dir.path().join("shared.txt"), "base\n").unwrap();
+        git(dir.path(), &["add", "-A"]).await.unwrap();
+        git(dir.path(), &["commit", "-q", "-m", "init"]).await.unwrap();
+        dir
+    }
+
+    fn wd(dir: &tempfile::TempDir) -> String {
+        dir.path().to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_ticket_isolation_for_node() {
+        let node_to_ticket: HashMap<String, String> = [
+            ("n1", "A"),
+            ("n2", "A-validate"),
+            ("n3", "B"),
+            ("n4", "solo-validate"),
+        ]
+        .into_iter()
+        .map(|(n, t)| (n.to_string(), t.to_string()))
+        .collect();
+        let iso = |node| TicketIsolation::for_node(Integration::Merge, &node_to_ticket, node)
+            .map(|i| (i.ticket_id, i.integrate));
+
+        assert_eq!(iso("n1"), Some(("A".to_string(), false)));
+        assert_eq!(iso("n2"), Some(("A".to_string(), true)));
+        assert_eq!(iso("n3"), Some(("B".to_string(), true)));
+        // No "solo" ticket, so this is an ordinary ticket that happens to end in -validate
+        assert_eq!(iso("n4"), Some(("solo-validate".to_string(), true)));
+        assert_eq!(iso("unmapped"), None);
+    }
+
+    #[test]
+    fn test_parse_isolation() {
+        assert_eq!(Integration::parse(None), Ok(None));
+        assert_eq!(Integration::parse(Some("worktree")), Ok(Some(Integration::Merge)));
+        assert_eq!(Integration::parse(Some("worktree-rebase")), Ok(Some(Integration::Rebase)));
+        assert!(Integration::parse(Some("container")).is_err());
+        let config = serde_json::json!({ "isolation": Integration::Rebase.as_str() });
+        assert_eq!(Integration::from_run_config(&config), Some(Integration::Rebase));
+    }
+
+    #[tokio::test]
+    async fn test_parallel_tickets_merge_back() {
+        let repo = init_repo().await;
+        let a = TicketWorktree::prepare(&wd(&repo), "graph-1234", "A").await.unwrap();
+        let b = TicketWorktree::prepare(&wd(&repo), "graph-1234", "B").await.unwrap();
+        assert_ne!(a.workdir, b.workdir);
+        assert_eq!(b.branch, "orcha/graph-12/B");
+
+        std::fs::write(a.workdir.join("a.txt"), "from A\n").unwrap();
+        std::fs::write(b.workdir.join("b.txt"), "from B\n").unwrap();
+        // Nothing leaks into the checkout until integration
+        assert!(!repo.path().join("a.txt").exists());
+
+        assert!(matches!(a.integrate(Integration::Merge, "A").await, Ok(IntegrateOutcome::Integrated { .. })));
+        assert!(matches!(b.integrate(Integration::Rebase, "B").await, Ok(IntegrateOutcome::Integrated { .. })));
+        assert!(repo.path().join("a.txt").exists());
+        assert!(repo.path().join("b.txt").exists());
+        assert!(!a.path().exists());
+        assert!(git(repo.path(), &["status", "--porcelain"]).await.unwrap().is_empty());
+
+        let c = TicketWorktree::prepare(&wd(&repo), "graph-1234", "C").await.unwrap();
+        assert_eq!(c.integrate(Integration::Merge, "C").await, Ok(IntegrateOutcome::Unchanged));
+    }
+
+    #[tokio::test]
+    async fn test_conflict_keeps_checkout_and_branch() {
+        let repo = init_repo().await;
+        let a = TicketWorktree::prepare(&wd(&repo), "g", "A").await.unwrap();
+        let b = TicketWorktree::prepare(&wd(&repo), "g", "B").await.unwrap();
+        std::fs::write(a.workdir.join("shared.txt"), "A's version\n").unwrap();
+        std::fs::write(b.workdir.join("shared.txt"), "B's version\n").unwrap();
+
+        a.integrate(Integration::Merge, "A").await.unwrap();
+        let outcome = b.integrate(Integration::Merge, "B").await.unwrap();
+        assert!(matches!(&outcome, IntegrateOutcome::Conflict { message } if message.contains("shared.txt")), "{outcome:?}");
+
+        assert_eq!(std::fs::read_to_string(repo.path().join("shared.txt")).unwrap(), "A's version\n");
+        assert!(git(repo.path(), &["status", "--porcelain"]).await.unwrap().is_empty());
+        assert!(b.path().exists());
+
+        // Resuming reuses the kept worktree
+        let again = TicketWorktree::prepare(&wd(&repo), "g", "B").await.unwrap();
+        assert_eq!(again.path(), b.path());
+    }
+}
@@ -5,7 +5,7 @@
 use std::sync::{Arc, Weak};
 
 use crate::activations::arbor::{Arbor, ArborConfig};
-use crate::activations::bash::Bash;
+use crate::activations::bash::{Bash, BashExecutor, ExecPolicy};
 #[cfg(feature = "chaos")]
 use crate::activations::chaos::Chaos;
 use crate::activations::claudecode::{ClaudeCode, ClaudeCodeStorage, ClaudeCodeStorageConfig};
@@ -18,7 +18,7 @@ use crate::activations::lattice::{Lattice, LatticeStorageConfig};
 use crate::activations::changelog::{Changelog, ChangelogStorageConfig};
 use crate::activations::mustache::{Mustache, MustacheStorageConfig};
 use crate::activations::orcha::pm::{Pm, PmStorage, PmStorageConfig};
-use crate::activations::orcha::{GraphRuntime, Orcha, OrchaStorage, OrchaStorageConfig};
+use crate::activations::orcha::{ConcurrencyLimits, GraphRuntime, Orcha, OrchaStorage, OrchaStorageConfig, Scheduler};
 use crate::activations::solar::Solar;
 use crate::plexus::DynamicHub;
 // use plexus_jsexec::{JsExec, JsExecConfig};  // temporarily disabled - needs API updates
@@ -107,6 +107,13 @@ pub async fn build_plexus_rpc() -> Arc<DynamicHub> {
         .await
         .expect("Failed to initialize Lattice storage");
 
+    // Load the exec policy shared by bash and orcha validate nodes. A missing
+    // file means no restrictions; an invalid one is fatal rather than ignored.
+    let exec_policy = Arc::new(
+        ExecPolicy::load(&ExecPolicy::default_path())
+            .expect("Failed to load exec policy")
+    );
+
     // Initialize Registry for backend discovery
     let registry = Registry::with_defaults()
         .await
@@ -126,8 +133,16 @@ pub async fn build_plexus_rpc() -> Arc<DynamicHub> {
         claudecode.inject_parent(weak_hub.clone());
 
         // Initialize Orcha with dependencies (needs to be inside closure to access claudecode)
-        let graph_runtime = Arc::new(GraphRuntime::new(lattice.storage()));
-        let pm = Arc::new(Pm::new(pm_storage.clone(), lattice.storage()));
+        // One scheduler for every graph run, so the server-wide caps
+        // (PLEXUS_ORCHA_MAX_AGENTS / PLEXUS_ORCHA_MAX_PROGS) hold across graphs.
+        let scheduler = Scheduler::new(ConcurrencyLimits::from_env());
+        let graph_runtime = Arc::new(
+            GraphRuntime::new(lattice.storage())
+                .with_executor(BashExecutor::with_policy(exec_policy.clone()))
+                .with_scheduler(scheduler.clone())
+                .with_model_registry(cone.llm_registry().clone()),
+        );
+        let pm = Arc::new(Pm::new(pm_storage.clone(), lattice.storage()).with_scheduler(scheduler));
         let orcha: Orcha<Weak<DynamicHub>> = Orcha::new(
             orcha_storage.clone(),
             Arc::new(claudecode.clone()),
@@ -144,7 +159,7 @@ pub async fn build_plexus_rpc() -> Arc<DynamicHub> {
         let hub = DynamicHub::new("substrate")
             .register(Health::new())
             .register(Echo::new())
-            .register(Bash::new());
+            .register(Bash::with_policy(exec_policy.clone()));
 
         // Chaos activation is feature-gated — off by default because it pulls
         // in libc + narrow unsafe signal primitives.
@@ -36,7 +36,7 @@ use rmcp::{
 };
 
 /// Default session cleanup age: 30 days
-pub const DEFAULT_SESSION_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+pub const DEFAULT_SESSION_MAX_AGE: Duration = Duration::from_hours(30 * 24);
 
 /// Configuration for `SQLite` session storage
 #[derive(Debug, Clone)]